@@ -0,0 +1,198 @@
+// Benchmarks of the core paths: extraction parsing, transform fan-out & sink serialization.
+//
+// cargo bench --features bench --bench core
+//
+// The mean time of each stage, over every pipe benchmarked, is written next to criterion's
+// reports, to target/criterion/stages.json.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use pipe_io::sink::RawSink;
+use pipe_io::{counters, pipeline, sink, Fork, Outcome, Pipe, Sink, Source};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Row {
+    symbol: String,
+    date: String,
+    open: f64,
+    close: f64,
+    volume: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(transparent)]
+struct Rows(Vec<Row>);
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Volume(u64);
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Closes(Vec<f64>);
+
+pipeline! {
+    Rows -> Rows {
+        async fn transform(&self, rows: Rows) -> pipe_io::Result<Rows> {
+            Ok(rows)
+        }
+    }
+
+    Rows -> Volume {
+        async fn transform(&self, rows: Rows) -> pipe_io::Result<Volume> {
+            Ok(Volume(rows.0.iter().map(|row| row.volume).sum()))
+        }
+    }
+
+    Rows -> Closes {
+        async fn transform(&self, rows: Rows) -> pipe_io::Result<Closes> {
+            Ok(Closes(rows.0.iter().map(|row| row.close).collect()))
+        }
+    }
+}
+
+// loads nothing; so only the pipe's own work is measured
+struct Discard;
+
+impl<O: ?Sized + Sync> Sink<O> for Discard {
+    async fn load(&self, _: &O) -> pipe_io::Result<Outcome> {
+        Ok(Outcome::Done)
+    }
+}
+
+impl RawSink for Discard {
+    async fn load_bytes(&self, _: &[u8]) -> pipe_io::Result<()> {
+        Ok(())
+    }
+}
+
+fn rows(n: usize) -> Vec<Row> {
+    (0..n)
+        .map(|i| Row {
+            symbol: format!("SYM{}", i % 100),
+            date: format!("2024-01-{:02}", i % 28 + 1),
+            open: i as f64 * 1.5,
+            close: i as f64 * 1.5 + 0.25,
+            volume: i as u64 * 1000,
+        })
+        .collect()
+}
+
+fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Runtime::new().expect("tokio runtime")
+}
+
+fn extraction(c: &mut Criterion) {
+    let runtime = runtime();
+    let mut group = c.benchmark_group("extract");
+    for n in [100, 10_000] {
+        let path = std::env::temp_dir().join(format!("pipe-io-bench-{n}.json"));
+        let json = serde_json::to_string(&rows(n)).unwrap();
+        std::fs::write(&path, &json).unwrap();
+        let path = path.to_str().unwrap();
+        group.throughput(Throughput::Bytes(json.len() as u64));
+
+        // through `run()`, so the extract stage is counted; transform & load do nothing
+        let typed = Pipe::<Rows, Rows>::builder()
+            .source(Source::endpoint(path))
+            .sink(Discard)
+            .build()
+            .unwrap();
+        group.bench_function(BenchmarkId::new("typed", n), |b| {
+            b.to_async(&runtime)
+                .iter(|| async { typed.run().await.unwrap() })
+        });
+        let dynamic = Pipe::<Value, Value>::builder()
+            .source(Source::endpoint(path))
+            .sink(Discard)
+            .build()
+            .unwrap();
+        group.bench_function(BenchmarkId::new("value", n), |b| {
+            b.to_async(&runtime)
+                .iter(|| async { dynamic.run().await.unwrap() })
+        });
+    }
+    group.finish();
+}
+
+fn fan_out(c: &mut Criterion) {
+    let runtime = runtime();
+    let mut group = c.benchmark_group("fork");
+    for n in [100, 10_000] {
+        let input = Rows(rows(n));
+        group.throughput(Throughput::Elements(n as u64));
+        let branch = || Pipe::<Rows, Rows>::builder().sink(Discard).build();
+        let fork = Fork::new()
+            .branch(branch().unwrap())
+            .branch(
+                Pipe::<Rows, Volume>::builder()
+                    .sink(Discard)
+                    .build()
+                    .unwrap(),
+            )
+            .branch(
+                Pipe::<Rows, Closes>::builder()
+                    .sink(Discard)
+                    .build()
+                    .unwrap(),
+            );
+        group.bench_with_input(BenchmarkId::new("3 branches", n), &input, |b, input| {
+            b.to_async(&runtime)
+                .iter(|| async { fork.run_input(input.clone()).await.unwrap() })
+        });
+    }
+    group.finish();
+}
+
+fn serialization(c: &mut Criterion) {
+    let runtime = runtime();
+    let mut group = c.benchmark_group("load");
+    for n in [100, 10_000] {
+        let output = rows(n);
+        group.throughput(Throughput::Elements(n as u64));
+
+        let file =
+            sink::File::new(std::env::temp_dir().join(format!("pipe-io-bench-out-{n}.json")));
+        group.bench_with_input(BenchmarkId::new("file", n), &output, |b, output| {
+            b.to_async(&runtime)
+                .iter(|| async { file.load(output).await.unwrap() })
+        });
+        let compact = Discard.with_serializer(|rows: &Vec<Row>| serde_json::to_vec(rows).unwrap());
+        group.bench_with_input(BenchmarkId::new("serialized", n), &output, |b, output| {
+            b.to_async(&runtime)
+                .iter(|| async { compact.load(output).await.unwrap() })
+        });
+        let batched = sink::Batched::new(Discard, 1000);
+        group.bench_with_input(BenchmarkId::new("batched", n), &output, |b, output| {
+            b.to_async(&runtime)
+                .iter(|| async { batched.load(output).await.unwrap() })
+        });
+    }
+    group.finish();
+}
+
+// the mean time of each stage, over every pipe benchmarked; see the top of the file
+fn stages(_: &mut Criterion) {
+    let counters = counters::snapshot();
+    let stages: serde_json::Map<String, Value> = [
+        ("extract", counters.extract),
+        ("transform", counters.transform),
+        ("load", counters.load),
+    ]
+    .into_iter()
+    .map(|(stage, counter)| {
+        let stats = serde_json::json!({
+            "attempts": counter.attempts(),
+            "mean_nanos": counter.mean().as_nanos() as u64,
+        });
+        (stage.to_string(), stats)
+    })
+    .collect();
+    let dir = std::env::var("CARGO_TARGET_DIR").unwrap_or_else(|_| "target".into());
+    let dir = std::path::Path::new(&dir).join("criterion");
+    std::fs::create_dir_all(&dir).unwrap();
+    let stages = serde_json::to_vec_pretty(&stages).unwrap();
+    std::fs::write(dir.join("stages.json"), stages).unwrap();
+}
+
+criterion_group!(benches, extraction, fan_out, serialization, stages);
+criterion_main!(benches);
@@ -42,7 +42,7 @@ fn main() -> anyhow::Result<()> {
         .values(user)
         .get_result::<(i32, String, String)>(&mut conn)
         .expect("failed saving new user");
-    let _ = delete_user(1);
+    delete_user(1);
     let _ = down(&mut conn);
 
     Ok(())
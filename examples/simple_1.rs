@@ -66,7 +66,7 @@ pipeline! {
             Ok(Reformatted {
                 first_name: input.first_name,
                 last_name: input.last_name,
-                thoughts: thoughts,
+                thoughts,
             })
         }
     }
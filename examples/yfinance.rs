@@ -1,10 +1,12 @@
-use anyhow::Result;
-use chrono::DateTime;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap as Map;
 use std::time::{SystemTime, UNIX_EPOCH};
 use std::vec::Vec;
 use pipe_io::core::*;
+use pipe_io::columns::{self, Column};
+use pipe_io::summary;
+use pipe_io::time;
+use pipe_io::Endpoint;
 
 #[derive(Deserialize, Debug)]
 struct RawPrice {
@@ -29,7 +31,7 @@ pipeline! {
     
     RawPrice -> Price {
         async fn extract(&self, init: &str) -> pipe_io::Result<RawPrice> {
-            let data = serde_json::from_str(&init)?;
+            let data = serde_json::from_str(init)?;
             Ok(data)
         }
 
@@ -37,28 +39,29 @@ pipeline! {
             let base = &data.chart.result[0];
             let price = &base.indicators.quote[0];
             let adjclose = &base.indicators.adjclose[0].adjclose;
-            let dates = &base.date;
-            let price_set = price
-                .open
-                .iter()
-                .zip(price.high.iter())
-                .zip(price.low.iter())
-                .zip(price.close.iter())
-                .zip(price.volume.iter())
-                .zip(adjclose.iter())
-                .zip(dates.iter())
-                .map(
-                    |((((((open, high), low), close), volume), adj_close), date)| PriceRow {
-                        date: date.clone(),
-                        open: *open,
-                        high: *high,
-                        low: *low,
-                        close: *close,
-                        adj_close: *adj_close,
-                        volume: *volume,
-                    },
-                )
-                .collect::<Vec<_>>();       
+            // the trading days, as the pipe's timezone policy has them
+            let dates = &time::timezone().dates(&base.timestamp, base.meta.gmtoffset)?;
+            // any price may be missing; a row is only kept if all of its prices are there
+            let price_set = columns::zip((
+                Column::new(&price.open),
+                Column::new(&price.high),
+                Column::new(&price.low),
+                Column::new(&price.close),
+                Column::new(&price.volume).or(0),
+                Column::new(adjclose),
+                Column::dense(dates),
+            ))?
+            .into_iter()
+            .map(|(open, high, low, close, volume, adj_close, date)| PriceRow {
+                date: date.to_string(),
+                open,
+                high,
+                low,
+                close,
+                adj_close,
+                volume,
+            })
+            .collect::<Vec<_>>();
             Ok(Price(price_set))
         }
     }
@@ -71,46 +74,16 @@ struct Chart {
 
 #[derive(Deserialize, Debug)]
 struct ChartResult {
-    // meta: Meta,
-
-    #[serde(rename = "timestamp", deserialize_with = "de_timestamps")]
-    date: Vec<String>,
+    meta: Meta,
+    timestamp: Vec<i64>,
     indicators: Indicators,
 }
 
-// CIK code can either be a 10-digit string, or shortened number; de_cik handles both
-fn de_timestamps<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
-where
-    D: serde::Deserializer<'de>,
-{
-    // general deserialisation, followed by match statement (depending on type found)
-    let value: serde_json::Value = Deserialize::deserialize(deserializer)?;
-    match value {
-        serde_json::Value::Array(vec) => {
-            let dates = vec
-                .iter()
-                .map(|timestamp| {
-                    let ts: serde_json::Value = Deserialize::deserialize(timestamp).unwrap();
-                    match ts {
-                        serde_json::Value::Number(num) => {
-                            if let Some(number) = num.as_i64() {
-                                let dt = DateTime::from_timestamp(number, 0)
-                                    .expect("invalid timestamp - value should be of type i64");
-                                dt.date_naive().to_string()
-                            } else {
-                                panic!("ERROR! Timestamp array element did not cast as type: i64")
-                            }
-                        }
-                        _ => panic!("ERROR! Timestamp array element is not of type: Number"),
-                    }
-                })
-                .collect::<Vec<_>>();
-            Ok(dates)
-        }
-        _ => Err(serde::de::Error::custom(
-            "ERROR! Expected an array of timestamps of type: i64",
-        )),
-    }
+#[derive(Deserialize, Debug)]
+struct Meta {
+    // seconds east of UTC of the exchange's local time
+    #[serde(default)]
+    gmtoffset: i32,
 }
 
 // #[derive(Deserialize, Debug)]
@@ -129,16 +102,16 @@ struct Indicators {
 
 #[derive(Deserialize, Debug)]
 struct Quote {
-    open: Vec<f64>,
-    high: Vec<f64>,
-    close: Vec<f64>,
-    low: Vec<f64>,
-    volume: Vec<u64>,
+    open: Vec<Option<f64>>,
+    high: Vec<Option<f64>>,
+    close: Vec<Option<f64>>,
+    low: Vec<Option<f64>>,
+    volume: Vec<Option<u64>>,
 }
 
 #[derive(Deserialize, Debug)]
 struct AdjClose {
-    adjclose: Vec<f64>,
+    adjclose: Vec<Option<f64>>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -160,7 +133,8 @@ async fn main() {
                     "meta": {
                         "currency": "USD",
                         "symbol": "NVDA",
-                        "exchangeName": "NMS"
+                        "exchangeName": "NMS",
+                        "gmtoffset": -14400
                     },
                     "timestamp": [
                         1710862018,
@@ -227,7 +201,15 @@ async fn main() {
         .as_secs()
         .to_string();
     let metrics = "quarterlyNetIncome,annualNetIncome,quarterlyTotalRevenue,annualTotalRevenue,quarterlyDilutedEPS,annualDilutedEPS,quarterlyTotalDebt,annualTotalDebt";
-    let url = format!("https://query2.finance.yahoo.com/ws/fundamentals-timeseries/v1/finance/timeseries/{ticker}?symbol={ticker}&type={metrics}&period1=1483142400&period2={time_in_unix}");
+    let url = Endpoint::new(
+        "https://query2.finance.yahoo.com/ws/fundamentals-timeseries/v1/finance/timeseries/{ticker}",
+    )
+    .var("ticker", ticker)
+    .param("symbol", ticker)
+    .param("type", metrics)
+    .param_iter([("period1", "1483142400"), ("period2", &time_in_unix)])
+    .url()
+    .unwrap();
     println!("{url:#?}");
     let response = reqwest::get(url).await.unwrap().text().await.unwrap();
     let ts: Timeseries = serde_json::from_str(&response).unwrap();
@@ -244,7 +226,7 @@ async fn main() {
                         date: x.as_of_date.clone(),
                         currency: x.currency_code.clone(),
                         period: x.period_type.clone(),
-                        raw: x.reported_value.raw.clone(),
+                        raw: x.reported_value.raw,
                         fmt: x.reported_value.fmt.clone(),
                     })
                     .collect::<Vec<Fundamentals>>();
@@ -257,7 +239,7 @@ async fn main() {
                         date: x.as_of_date.clone(),
                         currency: x.currency_code.clone(),
                         period: x.period_type.clone(),
-                        raw: x.reported_value.raw.clone(),
+                        raw: x.reported_value.raw,
                         fmt: x.reported_value.fmt.clone(),
                     })
                     .collect::<Vec<Fundamentals>>();
@@ -272,7 +254,7 @@ async fn main() {
                         date: x.as_of_date.clone(),
                         currency: x.currency_code.clone(),
                         period: x.period_type.clone(),
-                        raw: x.reported_value.raw.clone(),
+                        raw: x.reported_value.raw,
                         fmt: x.reported_value.fmt.clone(),
                     })
                     .collect::<Vec<Fundamentals>>();
@@ -287,7 +269,7 @@ async fn main() {
                         date: x.as_of_date.clone(),
                         currency: x.currency_code.clone(),
                         period: x.period_type.clone(),
-                        raw: x.reported_value.raw.clone(),
+                        raw: x.reported_value.raw,
                         fmt: x.reported_value.fmt.clone(),
                     })
                     .collect::<Vec<Fundamentals>>();
@@ -302,7 +284,7 @@ async fn main() {
                         date: x.as_of_date.clone(),
                         currency: x.currency_code.clone(),
                         period: x.period_type.clone(),
-                        raw: x.reported_value.raw.clone(),
+                        raw: x.reported_value.raw,
                         fmt: x.reported_value.fmt.clone(),
                     })
                     .collect::<Vec<Fundamentals>>();
@@ -315,7 +297,7 @@ async fn main() {
                         date: x.as_of_date.clone(),
                         currency: x.currency_code.clone(),
                         period: x.period_type.clone(),
-                        raw: x.reported_value.raw.clone(),
+                        raw: x.reported_value.raw,
                         fmt: x.reported_value.fmt.clone(),
                     })
                     .collect::<Vec<Fundamentals>>();
@@ -330,7 +312,7 @@ async fn main() {
                         date: x.as_of_date.clone(),
                         currency: x.currency_code.clone(),
                         period: x.period_type.clone(),
-                        raw: x.reported_value.raw.clone(),
+                        raw: x.reported_value.raw,
                         fmt: x.reported_value.fmt.clone(),
                     })
                     .collect::<Vec<Fundamentals>>();
@@ -343,7 +325,7 @@ async fn main() {
                         date: x.as_of_date.clone(),
                         currency: x.currency_code.clone(),
                         period: x.period_type.clone(),
-                        raw: x.reported_value.raw.clone(),
+                        raw: x.reported_value.raw,
                         fmt: x.reported_value.fmt.clone(),
                     })
                     .collect::<Vec<Fundamentals>>();
@@ -355,11 +337,11 @@ async fn main() {
     let dataset = Output {
         // currency: currency.to_string(),
         // exchange: exchange_name.to_string(),
-        price: price,
+        price,
         fundamentals: fdmt.clone(),
     };
     
-    println!("{:#?}", &dataset);
+    println!("{}", summary::of(&dataset).unwrap());
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -1,7 +1,22 @@
+// Invalid input is a compile error, spanned where it was written; never a panic
+#![deny(
+    clippy::unwrap_used,
+    clippy::expect_used,
+    clippy::panic,
+    clippy::todo,
+    clippy::unimplemented,
+    clippy::unreachable
+)]
+
 use proc_macro::TokenStream;
-use quote::quote;
+use proc_macro2::TokenTree;
+use quote::{quote, quote_spanned};
 use syn::parse::{Parse, ParseStream, Result};
-use syn::{braced, parse_macro_input, Attribute, Block, Stmt, Token, Type};
+use syn::spanned::Spanned;
+use syn::{
+    braced, parse_macro_input, Attribute, Block, Data, DeriveInput, Fields, Ident, Item, LitStr,
+    Meta, Stmt, Token, Type,
+};
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////////////
 // pipeline! { ... }
@@ -10,18 +25,50 @@ use syn::{braced, parse_macro_input, Attribute, Block, Stmt, Token, Type};
 #[proc_macro]
 pub fn pipeline(input: TokenStream) -> TokenStream {
     let args = parse_macro_input!(input as Args);
+    let pipes = match merge(args.args).and_then(validate_all) {
+        Ok(pipes) => pipes,
+        Err(error) => return error.to_compile_error().into(),
+    };
     let mut quotes = vec![];
 
     // for each Pipe defined, collect each TokenStream into a vector
-    for arg in args.args {
+    for arg in pipes {
         let type1 = &arg.type_one; // match Type::Path (MyStruct) or Type::Group (Vec<MyStruct>)
         let type2 = &arg.type_two; // match Type::Path (MyStruct) or Type::Group (Vec<MyStruct>)
-        let stmts = &arg.stmts;
+        let stmts = &arg.stmts.into_iter().map(with_metadata).collect::<Vec<_>>();
+
+        // without a custom `extract()`, use the `fetch()` & `decode()` that respect the pipe's
+        // configuration, unless the block defines its own
+        let custom = defines(stmts, "extract");
+        let fetch = (!custom && !defines(stmts, "fetch")).then(|| {
+            quote! {
+                async fn fetch(&self, path: &str) -> pipe_io::Result<pipe_io::Bytes> {
+                    self.fetch_default(path).await
+                }
+            }
+        });
+        let decode = (!custom && !defines(stmts, "decode")).then(|| {
+            quote! {
+                fn decode(&self, bytes: pipe_io::Bytes) -> pipe_io::Result<#type1> {
+                    self.decode_default(bytes)
+                }
+            }
+        });
+
+        let extracts_by_fetch = custom.then(|| {
+            quote! {
+                fn extracts_by_fetch(&self) -> bool {
+                    false
+                }
+            }
+        });
+
         quotes.push(quote! {
-            impl pipe_io::Input for #type1 {}
-            impl pipe_io::Output for #type2 {}
             impl pipe_io::ETL<#type1, #type2> for pipe_io::Pipe<#type1, #type2>
             {
+                #fetch
+                #decode
+                #extracts_by_fetch
                 #(#stmts)*
             }
         })
@@ -31,8 +78,181 @@ pub fn pipeline(input: TokenStream) -> TokenStream {
     quote! { #(#quotes)* }.into()
 }
 
+// Merge `extend I -> O { ... }` blocks into the first `I -> O`, rejecting any other duplicates;
+// rustc would otherwise report them as confusingly overlapping impls.
+fn merge(args: Vec<Arg>) -> Result<Vec<Arg>> {
+    let mut pipes: Vec<Arg> = vec![];
+    for arg in args {
+        let pair = pair(&arg);
+        let first = pipes.iter_mut().find(|pipe| self::pair(pipe) == pair);
+        match (first, arg.extend) {
+            (None, false) => pipes.push(arg),
+            (None, true) => {
+                return Err(syn::Error::new_spanned(
+                    arg.spanned(),
+                    format!("there is no `{pair}` pipeline to extend; remove `extend`"),
+                ))
+            }
+            (Some(_), false) => {
+                return Err(syn::Error::new_spanned(
+                    arg.spanned(),
+                    format!(
+                        "duplicate pipeline `{pair}`; use `extend {pair} {{ ... }}` to add to the first definition"
+                    ),
+                ))
+            }
+            (Some(first), true) => {
+                for stmt in &arg.stmts {
+                    if let Stmt::Item(Item::Fn(func)) = stmt {
+                        if defines(&first.stmts, &func.sig.ident.to_string()) {
+                            return Err(syn::Error::new_spanned(
+                                &func.sig,
+                                format!("`{}` is already defined for `{pair}`", func.sig.ident),
+                            ));
+                        }
+                    }
+                }
+                first.stmts.extend(arg.stmts);
+            }
+        }
+    }
+    Ok(pipes)
+}
+
+// The methods of `ETL`, and how many arguments each takes after `&self`.
+const METHODS: [(&str, usize); 8] = [
+    ("extract", 1),
+    ("fetch", 1),
+    ("decode", 1),
+    ("transform", 1),
+    ("load", 3),
+    ("extran", 1),
+    ("etl", 3),
+    ("lineage", 0),
+];
+
+// Validate every pipeline, reporting all the invalid ones at once.
+fn validate_all(pipes: Vec<Arg>) -> Result<Vec<Arg>> {
+    let errors = pipes.iter().filter_map(|pipe| validate(pipe).err());
+    match errors.reduce(|mut all, error| {
+        all.combine(error);
+        all
+    }) {
+        Some(errors) => Err(errors),
+        None => Ok(pipes),
+    }
+}
+
+// Check a (merged) pipeline against the `ETL` trait, so mistakes are reported at the offending
+// tokens; rustc would otherwise report them as unsatisfied or mismatched trait items.
+fn validate(arg: &Arg) -> Result<()> {
+    let pair = pair(arg);
+    if !defines(&arg.stmts, "transform") {
+        return Err(syn::Error::new_spanned(
+            arg.spanned(),
+            format!("transform() must be defined for {pair}"),
+        ));
+    }
+    for stmt in &arg.stmts {
+        let Stmt::Item(Item::Fn(func)) = stmt else {
+            continue;
+        };
+        let sig = &func.sig;
+        let name = sig.ident.to_string();
+        let Some((_, arity)) = METHODS.iter().find(|(method, _)| *method == name) else {
+            let methods = METHODS.map(|(method, _)| format!("`{method}`")).join(", ");
+            return Err(syn::Error::new_spanned(
+                &sig.ident,
+                format!("`{name}` is not a method of `ETL`; expected one of {methods}"),
+            ));
+        };
+        let receiver = match sig.inputs.first() {
+            Some(syn::FnArg::Receiver(receiver)) => {
+                receiver.reference.is_some() && receiver.mutability.is_none()
+            }
+            _ => false,
+        };
+        if !receiver {
+            return Err(syn::Error::new_spanned(
+                &sig.inputs,
+                format!("`{name}` must take `&self`, as the first argument"),
+            ));
+        }
+        // `transform()` may also take the source's metadata; see `with_metadata()`
+        let metadata = name == "transform" && sig.inputs.len() == 3;
+        if metadata && sig.asyncness.is_none() {
+            return Err(syn::Error::new_spanned(
+                sig,
+                "`transform` must be an `async fn` to take the source's metadata",
+            ));
+        }
+        if sig.inputs.len() - 1 != *arity && !metadata {
+            return Err(syn::Error::new_spanned(
+                &sig.inputs,
+                format!(
+                    "`{name}` takes {arity} argument{} after `&self`, not {}",
+                    if *arity == 1 { "" } else { "s" },
+                    sig.inputs.len() - 1
+                ),
+            ));
+        }
+        if sig.asyncness.is_none() && matches!(sig.output, syn::ReturnType::Default) {
+            return Err(syn::Error::new_spanned(
+                sig,
+                format!("`{name}` must be an `async fn`, or return an `impl Future`"),
+            ));
+        }
+    }
+    Ok(())
+}
+
+// A `transform(&self, input, metadata: &Metadata)`, as the `ETL` method it is: without the
+// argument, which is bound to the pipe's `metadata()` instead.
+fn with_metadata(stmt: Stmt) -> Stmt {
+    let Stmt::Item(Item::Fn(mut func)) = stmt else {
+        return stmt;
+    };
+    if func.sig.ident != "transform" || func.sig.inputs.len() != 3 {
+        return Stmt::Item(Item::Fn(func));
+    }
+    if let Some(syn::FnArg::Typed(arg)) = func.sig.inputs.pop().map(|pair| pair.into_value()) {
+        let (pat, ty) = (&arg.pat, &arg.ty);
+        let block = &func.block;
+        // hygienic, so it can't shadow the input
+        let metadata = Ident::new("metadata", proc_macro2::Span::mixed_site());
+        func.block = Box::new(syn::parse_quote!({
+            let #metadata = self.metadata();
+            let #pat: #ty = &#metadata;
+            #block
+        }));
+    }
+    // without the trailing comma
+    let inputs = std::mem::take(&mut func.sig.inputs);
+    func.sig.inputs = inputs.into_iter().collect();
+    Stmt::Item(Item::Fn(func))
+}
+
+// `I -> O`, normalised, for comparing & reporting pipelines
+fn pair(arg: &Arg) -> String {
+    let (type1, type2) = (&arg.type_one, &arg.type_two);
+    quote!(#type1 -> #type2).to_string()
+}
+
+// does the block define a function called `name`?
+fn defines(stmts: &[Stmt], name: &str) -> bool {
+    stmts.iter().any(|stmt| match stmt {
+        Stmt::Item(Item::Fn(func)) => func.sig.ident == name,
+        _ => false,
+    })
+}
+
+mod kw {
+    syn::custom_keyword!(extend);
+}
+
 // a single `pipe` input
 struct Arg {
+    extend: bool,
     type_one: Type,
     type_two: Type,
     stmts: Vec<Stmt>,
@@ -42,6 +262,7 @@ impl Parse for Arg {
     fn parse(input: ParseStream) -> Result<Self> {
         // `@ Input -> Output`
         // input.parse::<Token![@]>()?;
+        let extend = input.parse::<Option<kw::extend>>()?.is_some();
         let type_one = input.parse()?;
         input.parse::<Token![->]>()?;
         let type_two = input.parse()?;
@@ -52,6 +273,7 @@ impl Parse for Arg {
         let _inner_brace_attrs = brace_content.call(Attribute::parse_inner)?;
         let stmts = brace_content.call(Block::parse_within)?;
         Ok(Arg {
+            extend,
             type_one,
             type_two,
             stmts,
@@ -59,6 +281,14 @@ impl Parse for Arg {
     }
 }
 
+impl Arg {
+    // the tokens an error about this pipeline should point at
+    fn spanned(&self) -> proc_macro2::TokenStream {
+        let (type1, type2) = (&self.type_one, &self.type_two);
+        quote!(#type1 -> #type2)
+    }
+}
+
 // collect all the `pipe`s together in a vector
 struct Args {
     args: Vec<Arg>,
@@ -97,9 +327,221 @@ impl Parse for PipeArg {
         let type_one: Type = input.parse()?;
         input.parse::<Token![->]>()?;
         let type_two: Type = input.parse()?;
-        Ok(PipeArg {
-            type_one,
-            type_two,
+        Ok(PipeArg { type_one, type_two })
+    }
+}
+////////////////////////////////////////////////////////////////////////////////////////////////////////////
+// #[derive(Transform)]
+////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// #[derive(Transform)]
+// #[etl(input = RawPrice)]
+// struct Meta {
+//     #[etl(from = "chart.result[0].meta.currency")]
+//     currency: String,
+// }
+//
+// == `impl ETL<RawPrice, Meta> for Pipe<RawPrice, Meta>`, where `transform()` clones each field from its path,
+//    and `lineage()` declares it
+#[proc_macro_derive(Transform, attributes(etl))]
+pub fn transform(input: TokenStream) -> TokenStream {
+    let derive = parse_macro_input!(input as DeriveInput);
+    match derive_transform(&derive) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn derive_transform(derive: &DeriveInput) -> Result<proc_macro2::TokenStream> {
+    let output = &derive.ident;
+
+    // `#[etl(input = Type)]` on the struct
+    let mut input_type: Option<Type> = None;
+    for attr in derive.attrs.iter().filter(|a| a.path().is_ident("etl")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("input") {
+                input_type = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `input = Type`"))
+            }
+        })?;
+    }
+    let input_type = input_type.ok_or_else(|| {
+        syn::Error::new_spanned(
+            output,
+            "missing `#[etl(input = Type)]` for #[derive(Transform)]",
+        )
+    })?;
+
+    let fields = match &derive.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    output,
+                    "#[derive(Transform)] needs named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                output,
+                "#[derive(Transform)] only supports structs",
+            ))
+        }
+    };
+
+    // each field is plucked from `#[etl(from = "path")]`, or from the input field of the same name
+    let mut inits = vec![];
+    let mut lineage = vec![];
+    for field in fields {
+        let ident = field.ident.as_ref().ok_or_else(|| {
+            syn::Error::new_spanned(field, "#[derive(Transform)] needs named fields")
+        })?;
+        let mut from: Option<LitStr> = None;
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("etl")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("from") {
+                    from = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `from = \"path\"`"))
+                }
+            })?;
+        }
+        let from = from.unwrap_or_else(|| LitStr::new(&ident.to_string(), ident.span()));
+        let access = field_access(&from)?;
+        inits.push(quote! { #ident: ::core::clone::Clone::clone(&(input #access)) });
+        let name = ident.to_string();
+        lineage.push(quote! { .field(#name, [#from]) });
+    }
+
+    Ok(quote! {
+        impl pipe_io::ETL<#input_type, #output> for pipe_io::Pipe<#input_type, #output> {
+            async fn extract(&self, path: &str) -> pipe_io::Result<#input_type> {
+                self.extract_default(path).await
+            }
+
+            async fn transform(&self, input: #input_type) -> pipe_io::Result<#output> {
+                Ok(#output {
+                    #(#inits,)*
+                })
+            }
+
+            fn lineage(&self) -> pipe_io::lineage::Lineage {
+                pipe_io::lineage::Lineage::new() #(#lineage)*
+            }
+        }
+    })
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////////////
+// #[derive(Tabular)]
+////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// #[derive(Serialize, Tabular)]
+// struct Price {
+//     symbol: String,
+//     close: Option<f64>,
+// }
+//
+// == `impl Tabular for Price`, bounded on every serialized field being a `Scalar`, so a nested field
+//    fails to compile where it's declared
+#[proc_macro_derive(Tabular)]
+pub fn tabular(input: TokenStream) -> TokenStream {
+    let derive = parse_macro_input!(input as DeriveInput);
+    match derive_tabular(&derive) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn derive_tabular(derive: &DeriveInput) -> Result<proc_macro2::TokenStream> {
+    let output = &derive.ident;
+    let fields = match &derive.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                output,
+                "#[derive(Tabular)] only supports structs",
+            ))
+        }
+    };
+
+    // fields serde skips aren't columns
+    let bounds = fields
+        .iter()
+        .filter(|field| !field.attrs.iter().any(skipped))
+        .map(|field| {
+            let ty = &field.ty;
+            quote_spanned! { ty.span()=> #ty: pipe_io::tabular::Scalar }
+        });
+    let (impl_generics, type_generics, where_clause) = derive.generics.split_for_impl();
+    let predicates = where_clause
+        .into_iter()
+        .flat_map(|clause| &clause.predicates);
+    Ok(quote! {
+        impl #impl_generics pipe_io::tabular::Tabular for #output #type_generics
+        where
+            #(#predicates,)*
+            #(#bounds,)*
+            Self: pipe_io::tabular::Serialize,
+        {
+        }
+    })
+}
+
+// `#[serde(skip)]` or `#[serde(skip_serializing)]`
+fn skipped(attr: &Attribute) -> bool {
+    let Meta::List(list) = &attr.meta else {
+        return false;
+    };
+    list.path.is_ident("serde")
+        && list.tokens.clone().into_iter().any(|token| {
+            matches!(token, TokenTree::Ident(ident) if ident == "skip" || ident == "skip_serializing")
         })
+}
+
+// "chart.result[0].meta" -> `.chart.result.get(0).ok_or_else(..)?.meta`
+fn field_access(path: &LitStr) -> Result<proc_macro2::TokenStream> {
+    let text = path.value();
+    let invalid = || {
+        syn::Error::new_spanned(
+            path,
+            format!("invalid path `{text}`; expected e.g. \"a.b[0].c\""),
+        )
+    };
+    let mut tokens = proc_macro2::TokenStream::new();
+    let mut walked = String::new();
+
+    for segment in text.split('.') {
+        let (name, mut rest) = segment
+            .split_once('[')
+            .map_or((segment, ""), |(n, r)| (n, r));
+        if !name.is_empty() {
+            let ident = syn::parse_str::<Ident>(name).map_err(|_| invalid())?;
+            tokens.extend(quote! { .#ident });
+            walked.push_str(if walked.is_empty() { "" } else { "." });
+            walked.push_str(name);
+        } else if walked.is_empty() && rest.is_empty() {
+            return Err(invalid());
+        }
+
+        // any number of `[index]`s
+        while !rest.is_empty() {
+            let (index, tail) = rest.split_once(']').ok_or_else(invalid)?;
+            let index: usize = index.parse().map_err(|_| invalid())?;
+            walked.push_str(&format!("[{index}]"));
+            let missing = walked.clone();
+            tokens.extend(quote! {
+                .get(#index).ok_or_else(|| pipe_io::Error::Missing(#missing.to_string()))?
+            });
+            rest = match tail {
+                "" => "",
+                _ => tail.strip_prefix('[').ok_or_else(invalid)?,
+            };
+        }
     }
-}
\ No newline at end of file
+    Ok(tokens)
+}
@@ -0,0 +1,132 @@
+use super::clock::{self, Clock};
+use super::Error;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde_json::Value;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// An archive of every run's loaded output, as gzipped NDJSON, alongside a pipe's main sink.
+///
+/// Each run writes one file, partitioned by its (UTC) start date:
+/// `<dir>/2024/06/01/run-<uuid>.ndjson.gz`, named by the [run's id](crate::clock::run_id).
+/// The file is written as `run-<uuid>.ndjson.gz.partial`, and only renamed once the run has
+/// finished; a run that fails leaves no archive behind.
+/// An output that serializes to a JSON array is written one element per line; anything else,
+/// one output per line.
+///
+/// ```rust,ignore
+/// let pipe = Pipe::<I, O>::builder()
+///     .sink(sink)
+///     .archive(Archive::new("archive"))
+///     .build()?;
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Archive {
+    pub dir: PathBuf,
+    pub level: u32,
+}
+
+impl Archive {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Archive {
+            dir: dir.into(),
+            level: Compression::default().level(),
+        }
+    }
+
+    /// The gzip compression level, from 0 (none) to 9 (best). Defaults to 6.
+    pub fn level(mut self, level: u32) -> Self {
+        self.level = level.min(9);
+        self
+    }
+
+    /// Start archiving a new run, dated by `clock`; named by the id of the run in progress, or,
+    /// outside of one, a new id from `clock`.
+    pub fn start(&self, clock: &dyn Clock) -> Result<ArchiveRun, Error> {
+        let now = clock.now();
+        let dir = ["%Y", "%m", "%d"]
+            .iter()
+            .fold(self.dir.clone(), |dir, part| {
+                dir.join(now.format(part).to_string())
+            });
+        std::fs::create_dir_all(&dir)?;
+        let id = clock::run_id().unwrap_or_else(|| clock::new_run_id(clock));
+        let path = dir.join(format!("{id}.ndjson.gz"));
+
+        let partial = partial(&path);
+        let file = std::fs::File::create(&partial)?;
+        let encoder = GzEncoder::new(BufWriter::new(file), Compression::new(self.level));
+        Ok(ArchiveRun {
+            path,
+            encoder: Some(encoder),
+        })
+    }
+}
+
+/// The archive file of a single run; see [`Archive::start()`].
+///
+/// The file is published, as a complete gzip stream, once [`finish()`] is called; a run dropped
+/// before then removes its partial file.
+///
+/// [`finish()`]: ArchiveRun::finish
+pub struct ArchiveRun {
+    path: PathBuf,
+    // `None` once finished
+    encoder: Option<GzEncoder<BufWriter<std::fs::File>>>,
+}
+
+// the file a run is written to, until it's finished
+fn partial(path: &Path) -> PathBuf {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    PathBuf::from(partial)
+}
+
+impl ArchiveRun {
+    /// Where the archive is published, once finished.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append `output` to the archive.
+    pub fn write<O>(&mut self, output: &O) -> Result<(), Error>
+    where
+        O: serde::Serialize + ?Sized,
+    {
+        match serde_json::to_value(output)? {
+            Value::Array(records) => records.iter().try_for_each(|record| self.line(record))?,
+            value => self.line(&value)?,
+        }
+        Ok(())
+    }
+
+    fn line(&mut self, value: &Value) -> Result<(), Error> {
+        if let Some(encoder) = &mut self.encoder {
+            serde_json::to_writer(&mut *encoder, value)?;
+            encoder.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    /// Write the gzip trailer, flush the archive to disk, and publish it at [`path()`].
+    ///
+    /// [`path()`]: ArchiveRun::path
+    pub fn finish(mut self) -> Result<PathBuf, Error> {
+        if let Some(encoder) = self.encoder.take() {
+            encoder.finish()?.flush()?;
+            std::fs::rename(partial(&self.path), &self.path)?;
+        }
+        Ok(std::mem::take(&mut self.path))
+    }
+}
+
+impl Drop for ArchiveRun {
+    fn drop(&mut self) {
+        // not finished, e.g., the run failed
+        if let Some(encoder) = self.encoder.take() {
+            drop(encoder);
+            let _ = std::fs::remove_file(partial(&self.path));
+        }
+    }
+}
@@ -0,0 +1,208 @@
+use super::pool::{self, Permit};
+use super::{default, Error, Input};
+use futures::stream::{BoxStream, StreamExt};
+use std::collections::VecDeque;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Decode the elements of a top-level JSON array one at a time, as they are read from `reader`.
+///
+/// Only one element is held in memory at a time, so arrays much larger than memory can be
+/// streamed into a pipe, without converting them to NDJSON first:
+///
+/// ```rust,ignore
+/// let prices = array::from_file::<Price>("prices.json").await?;
+/// let pipe = Pipe::<Price, Row>::builder().source(Source::stream(prices)).sink(sink).build()?;
+/// pipe.run().await?;
+/// ```
+pub fn from_reader<I, R>(reader: R) -> BoxStream<'static, Result<I, Error>>
+where
+    I: Input + 'static,
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    elements(Chunks::Reader(Box::new(reader)))
+}
+
+/// The elements of the JSON array in the file at `path`; see [`from_reader()`].
+pub async fn from_file<I>(path: &str) -> Result<BoxStream<'static, Result<I, Error>>, Error>
+where
+    I: Input + 'static,
+{
+    let file = tokio::fs::File::open(path).await?;
+    Ok(from_reader(file))
+}
+
+/// The elements of the JSON array in the body of a GET to `url`; see [`from_reader()`].
+pub async fn from_url<I>(url: &str) -> Result<BoxStream<'static, Result<I, Error>>, Error>
+where
+    I: Input + 'static,
+{
+    let pool = pool::global();
+    let permit = pool.acquire(url).await?;
+    let response = pool
+        .client(url)?
+        .get(url)
+        .header("User-Agent", "example@example.com")
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(elements(Chunks::Response {
+        response,
+        _permit: permit,
+    }))
+}
+
+// where the bytes come from
+enum Chunks {
+    Reader(Box<dyn AsyncRead + Unpin + Send>),
+    // with the host's permit, held until the body is read
+    Response {
+        response: reqwest::Response,
+        _permit: Permit,
+    },
+}
+
+impl Chunks {
+    async fn next(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        match self {
+            Chunks::Reader(reader) => {
+                let mut buf = vec![0; 8 * 1024];
+                let n = reader.read(&mut buf).await?;
+                buf.truncate(n);
+                Ok((n > 0).then_some(buf))
+            }
+            Chunks::Response { response, .. } => {
+                Ok(response.chunk().await?.map(|bytes| bytes.to_vec()))
+            }
+        }
+    }
+}
+
+struct Elements {
+    chunks: Chunks,
+    splitter: Splitter,
+    // complete elements, not yet decoded
+    pending: VecDeque<Vec<u8>>,
+    done: bool,
+}
+
+fn elements<I>(chunks: Chunks) -> BoxStream<'static, Result<I, Error>>
+where
+    I: Input + 'static,
+{
+    let state = Elements {
+        chunks,
+        splitter: Splitter::default(),
+        pending: VecDeque::new(),
+        done: false,
+    };
+    futures::stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(element) = state.pending.pop_front() {
+                let item = default::decode::<I>(&element);
+                return Some((item, state));
+            }
+            if state.done {
+                return None;
+            }
+            let result = match state.chunks.next().await {
+                Ok(Some(chunk)) => state.splitter.push(&chunk, &mut state.pending),
+                Ok(None) => {
+                    state.done = true;
+                    state.splitter.finish()
+                }
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                // the rest of the array can't be trusted
+                state.done = true;
+                state.pending.clear();
+                return Some((Err(e), state));
+            }
+        }
+    })
+    .boxed()
+}
+
+#[derive(Debug, Default, PartialEq)]
+enum Position {
+    #[default]
+    Start,
+    Array,
+    End,
+}
+
+// Splits a JSON array into the bytes of its elements, without decoding them.
+#[derive(Default)]
+struct Splitter {
+    position: Position,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    element: Vec<u8>,
+    count: usize,
+}
+
+impl Splitter {
+    fn push(&mut self, bytes: &[u8], elements: &mut VecDeque<Vec<u8>>) -> Result<(), Error> {
+        for &byte in bytes {
+            match self.position {
+                Position::Start => match byte {
+                    b'[' => self.position = Position::Array,
+                    _ if byte.is_ascii_whitespace() => {}
+                    _ => return Err(invalid("expected a JSON array")),
+                },
+                Position::End if !byte.is_ascii_whitespace() => {
+                    return Err(invalid("trailing characters after the JSON array"))
+                }
+                Position::End => {}
+                Position::Array if self.in_string => {
+                    self.element.push(byte);
+                    match (self.escaped, byte) {
+                        (true, _) => self.escaped = false,
+                        (false, b'\\') => self.escaped = true,
+                        (false, b'"') => self.in_string = false,
+                        _ => {}
+                    }
+                }
+                Position::Array => match byte {
+                    b',' | b']' if self.depth == 0 => {
+                        let element = std::mem::take(&mut self.element);
+                        let blank = element.iter().all(u8::is_ascii_whitespace);
+                        match (blank, byte) {
+                            (true, b']') if self.count == 0 => {} // `[]`
+                            (true, _) => return Err(invalid("missing JSON array element")),
+                            (false, _) => {
+                                elements.push_back(element);
+                                self.count += 1;
+                            }
+                        }
+                        if byte == b']' {
+                            self.position = Position::End;
+                        }
+                    }
+                    _ => {
+                        match byte {
+                            b'{' | b'[' => self.depth += 1,
+                            b'}' | b']' => self.depth = self.depth.saturating_sub(1),
+                            b'"' => self.in_string = true,
+                            _ => {}
+                        }
+                        self.element.push(byte);
+                    }
+                },
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&self) -> Result<(), Error> {
+        match self.position {
+            Position::End => Ok(()),
+            _ => Err(invalid("unexpected end of the JSON array")),
+        }
+    }
+}
+
+fn invalid(msg: &str) -> Error {
+    Error::JSON(<serde_json::Error as serde::de::Error>::custom(msg))
+}
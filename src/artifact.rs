@@ -0,0 +1,195 @@
+//! Sidecar files of a run, e.g., a summary JSON or an NDJSON of the records a transform rejected,
+//! written alongside the main output rather than loaded with it.
+//!
+//! A stage emits them with [`Pipe::artifact()`] (or [`emit()`], where there's no pipe to hand),
+//! and the pipe's [`Artifacts`] routes each, by name, to its location once the run has finished:
+//!
+//! ```rust,ignore
+//! async fn transform(&self, input: RawPrices) -> pipe_io::Result<Vec<Price>> {
+//!     let (prices, rejected): (Vec<_>, Vec<_>) = input.rows.into_iter().partition(valid);
+//!     self.artifact(Artifact::ndjson("rejected", &rejected)?);
+//!     self.artifact(Artifact::json("summary", &json!({ "rejected": rejected.len() }))?);
+//!     Ok(prices.into_iter().map(Price::from).collect())
+//! }
+//!
+//! let pipe = Pipe::<RawPrices, Vec<Price>>::builder()
+//!     .sink(sink)
+//!     .artifacts(Artifacts::new("out/artifacts").route("rejected", "out/rejected.ndjson"))
+//!     .build()?;
+//! ```
+//!
+//! They're written whether or not the run succeeded, e.g., for the records rejected before a load
+//! failed. Where each was written is in the run's [`EtlReport::artifacts`]; one that can't be
+//! written is a warning, rather than an error, as the run has already loaded (or failed).
+//!
+//! [`Pipe::artifact()`]: crate::Pipe::artifact
+//! [`EtlReport::artifacts`]: crate::EtlReport::artifacts
+
+use super::warning::{self, Warning};
+use super::{fs, Error, EtlReport};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+
+tokio::task_local! {
+    // the artifacts of the run in progress
+    static ARTIFACTS: RefCell<Vec<Artifact>>;
+}
+
+/// A named sidecar file of a run, already serialized.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Artifact {
+    pub name: String,
+    pub bytes: Vec<u8>,
+}
+
+impl Artifact {
+    pub fn new(name: impl Into<String>, bytes: impl Into<Vec<u8>>) -> Self {
+        Artifact {
+            name: name.into(),
+            bytes: bytes.into(),
+        }
+    }
+
+    /// `value`, as pretty-printed JSON.
+    pub fn json<T: Serialize + ?Sized>(name: impl Into<String>, value: &T) -> Result<Self, Error> {
+        Ok(Self::new(name, serde_json::to_vec_pretty(value)?))
+    }
+
+    /// `records`, as NDJSON: one per line.
+    pub fn ndjson<T: Serialize>(
+        name: impl Into<String>,
+        records: impl IntoIterator<Item = T>,
+    ) -> Result<Self, Error> {
+        let mut bytes = vec![];
+        for record in records {
+            serde_json::to_writer(&mut bytes, &record)?;
+            bytes.push(b'\n');
+        }
+        Ok(Self::new(name, bytes))
+    }
+}
+
+/// Where a pipe writes its runs' artifacts: each to `<dir>/<name>`, unless routed elsewhere.
+///
+/// Artifacts of the same name emitted in one run are written as one file, in the order they were
+/// emitted; so NDJSON emitted per streamed input adds up, but JSON should be emitted once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Artifacts {
+    pub dir: PathBuf,
+    pub routes: BTreeMap<String, PathBuf>,
+}
+
+impl Artifacts {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Artifacts {
+            dir: dir.into(),
+            routes: BTreeMap::new(),
+        }
+    }
+
+    /// Write the artifact named `name` to `path`, rather than into the directory.
+    pub fn route(mut self, name: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        self.routes.insert(name.into(), path.into());
+        self
+    }
+
+    /// Where the artifact named `name` is written.
+    pub fn path(&self, name: &str) -> PathBuf {
+        match self.routes.get(name) {
+            Some(path) => path.clone(),
+            None => self.dir.join(name),
+        }
+    }
+
+    // Write `artifacts`, merged by name, returning where each went; one that can't be written is
+    // left out, with a warning.
+    fn write(&self, artifacts: Vec<Artifact>) -> Vec<Written> {
+        let mut merged: Vec<Artifact> = vec![];
+        for artifact in artifacts {
+            match merged.iter_mut().find(|other| other.name == artifact.name) {
+                Some(other) => other.bytes.extend(artifact.bytes),
+                None => merged.push(artifact),
+            }
+        }
+        let mut written = vec![];
+        for artifact in merged {
+            let path = self.path(&artifact.name);
+            match write(&path, &artifact.bytes) {
+                Ok(()) => written.push(Written {
+                    name: artifact.name,
+                    path,
+                    bytes: artifact.bytes.len() as u64,
+                }),
+                Err(e) => warning::warn(Warning::other(format!(
+                    "artifact `{}` couldn't be written to {}: {e}",
+                    artifact.name,
+                    path.display()
+                ))),
+            }
+        }
+        written
+    }
+}
+
+// Write `bytes` to `path`, creating its directory if need be.
+fn write(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent().filter(|parent| *parent != Path::new("")) {
+        std::fs::create_dir_all(parent)?;
+    }
+    fs::write_atomic(path, bytes)
+}
+
+/// An artifact a run wrote; see [`EtlReport::artifacts`].
+///
+/// [`EtlReport::artifacts`]: crate::EtlReport::artifacts
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Written {
+    pub name: String,
+    pub path: PathBuf,
+    pub bytes: u64,
+}
+
+/// Add `artifact` to the run in progress; outside of a run, it's dropped, with a [`Warning`].
+pub fn emit(artifact: Artifact) {
+    let mut artifact = Some(artifact);
+    let _ = ARTIFACTS.try_with(|artifacts| artifacts.borrow_mut().extend(artifact.take()));
+    if let Some(artifact) = artifact {
+        warning::warn(Warning::other(format!(
+            "artifact `{}` dropped, as it was emitted outside of a run",
+            artifact.name
+        )));
+    }
+}
+
+// Run `run`, writing the artifacts emitted meanwhile once it has finished, whether or not it
+// succeeded; with nowhere to write them (or an error writing one), they're dropped with a
+// warning, rather than failing the run. Call within `warning::collect()`.
+pub(crate) async fn collect<E>(
+    artifacts: Option<&Artifacts>,
+    run: impl Future<Output = Result<EtlReport, E>>,
+) -> Result<EtlReport, E> {
+    ARTIFACTS
+        .scope(RefCell::new(vec![]), async {
+            let result = run.await;
+            let emitted = ARTIFACTS.with(|emitted| emitted.take());
+            let written = match artifacts {
+                Some(artifacts) => artifacts.write(emitted),
+                None => {
+                    for artifact in emitted {
+                        warning::warn(Warning::other(format!(
+                            "artifact `{}` dropped, as the pipe has no artifacts location",
+                            artifact.name
+                        )));
+                    }
+                    vec![]
+                }
+            };
+            let mut report = result?;
+            report.artifacts.extend(written);
+            Ok(report)
+        })
+        .await
+}
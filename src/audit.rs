@@ -0,0 +1,122 @@
+use super::clock::{self, Clock};
+use super::sink::{content_hash, Sink};
+use super::{Error, Outcome};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+
+/// What was loaded, where from, by whom, and when; one per load through an [`Audited`] sink.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AuditRecord {
+    /// Who ran the load; see [`Audited::user()`].
+    pub user: String,
+    /// The destination loaded to; see [`Audited::name()`].
+    pub sink: String,
+    /// The input source, if one was given; see [`Audited::source()`].
+    pub source: Option<String>,
+    /// RFC 3339 timestamps, in UTC.
+    pub started_at: String,
+    pub finished_at: String,
+    /// The number of records in the output: the length of a JSON array, or else 1.
+    pub records: usize,
+    /// A stable hash of the output, as serialized to JSON.
+    pub content_hash: String,
+    /// Why the load failed, if it did.
+    pub error: Option<String>,
+}
+
+/// Wraps a sink, recording an [`AuditRecord`] to a separate audit store for every load.
+///
+/// Failed loads are audited too. A load only succeeds once its audit record has been stored,
+/// so nothing is ever loaded without a trace.
+///
+/// ```rust,ignore
+/// let sink = Audited::new(
+///     sink::Postgres::new(conn, "prices"),
+///     sink::Postgres::new(conn, "audit_log"),
+/// )
+/// .name("prices")
+/// .source(url);
+/// ```
+pub struct Audited<S, A> {
+    sink: S,
+    store: A,
+    user: String,
+    name: String,
+    source: Option<String>,
+    clock: Arc<dyn Clock>,
+}
+
+impl<S, A> Audited<S, A>
+where
+    A: Sink<AuditRecord>,
+{
+    /// Audit loads to `sink` in `store`; the user defaults to `$USER`, and the name to the sink's type.
+    pub fn new(sink: S, store: A) -> Self {
+        let user = std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .unwrap_or_else(|_| "unknown".into());
+        Audited {
+            sink,
+            store,
+            user,
+            name: std::any::type_name::<S>().to_string(),
+            source: None,
+            clock: clock::system(),
+        }
+    }
+
+    /// Who to record as running the loads, e.g., a service account.
+    pub fn user(mut self, user: impl Into<String>) -> Self {
+        self.user = user.into();
+        self
+    }
+
+    /// How to record the destination, e.g., a table name.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Timestamp the records with `clock`, instead of the system clock.
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// The input source to record, e.g., the pipe's endpoint.
+    pub fn source(mut self, source: impl Into<String>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+}
+
+impl<O, S, A> Sink<O> for Audited<S, A>
+where
+    O: Serialize + Sync + ?Sized,
+    S: Sink<O>,
+    A: Sink<AuditRecord>,
+{
+    async fn load(&self, output: &O) -> Result<Outcome, Error> {
+        let started_at = self.clock.now().to_rfc3339();
+        let json = serde_json::to_vec(output)?;
+        let records = match serde_json::from_slice::<Value>(&json)? {
+            Value::Array(records) => records.len(),
+            _ => 1,
+        };
+
+        let result = self.sink.load(output).await;
+        let record = AuditRecord {
+            user: self.user.clone(),
+            sink: self.name.clone(),
+            source: self.source.clone(),
+            started_at,
+            finished_at: self.clock.now().to_rfc3339(),
+            records,
+            content_hash: content_hash(&[&json]),
+            error: result.as_ref().err().map(Error::to_string),
+        };
+        self.store.load(&record).await?;
+        result
+    }
+}
@@ -0,0 +1,185 @@
+//! Avro encoding of output records, for consumers that require Avro rather than JSON: to files,
+//! as object container files, with [`AvroFile`]; or to Kafka (with the `kafka` feature), framed
+//! with the id of the schema, as registered in a [`SchemaRegistry`]; see [`KafkaSink::avro()`].
+//!
+//! The schema is derived from the record type, with `#[derive(apache_avro::AvroSchema)]`, or
+//! supplied as JSON:
+//!
+//! ```rust,ignore
+//! #[derive(Serialize, AvroSchema)]
+//! struct Price {
+//!     ticker: String,
+//!     close: f64,
+//! }
+//!
+//! let sink = AvroFile::new("prices.avro", Avro::of::<Price>());
+//! let sink = KafkaSink::new("localhost:9092", "prices")?
+//!     .avro(Avro::parse(SCHEMA)?, SchemaRegistry::new("http://localhost:8081"));
+//! ```
+//!
+//! Records are serialized with serde, then checked against the schema; a record that doesn't
+//! match it fails the load with [`Error::Avro`], before anything is written or sent.
+//!
+//! [`KafkaSink::avro()`]: crate::kafka::KafkaSink::avro
+
+use super::sink::Sink;
+use super::{fs, Error, Outcome};
+use apache_avro::writer::datum::GenericDatumWriter;
+use apache_avro::{AvroSchema, Schema, Writer};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// The schema records are encoded with; clones share it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Avro {
+    schema: Arc<Schema>,
+}
+
+impl Avro {
+    pub fn new(schema: Schema) -> Self {
+        Avro {
+            schema: Arc::new(schema),
+        }
+    }
+
+    /// The schema in the JSON text `schema`.
+    pub fn parse(schema: &str) -> Result<Self, Error> {
+        Ok(Self::new(Schema::parse_str(schema)?))
+    }
+
+    /// The schema derived from `T`.
+    pub fn of<T: AvroSchema>() -> Self {
+        Self::new(T::get_schema())
+    }
+
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    /// `records`, as an object container file: the schema, then the records.
+    pub fn container<T: Serialize>(&self, records: &[T]) -> Result<Vec<u8>, Error> {
+        let mut writer = Writer::new(&self.schema, vec![])?;
+        for record in records {
+            writer.append_ser(record)?;
+        }
+        Ok(writer.into_inner()?)
+    }
+
+    /// `record`, as a bare datum, without the schema.
+    pub fn datum<T: Serialize>(&self, record: &T) -> Result<Vec<u8>, Error> {
+        Ok(GenericDatumWriter::builder(&self.schema)
+            .build()?
+            .write_ser_to_vec(record)?)
+    }
+
+    /// `record`, in the wire format of a schema registry: a 0 byte, the 4-byte (big-endian) id
+    /// of the schema, then the datum.
+    pub fn framed<T: Serialize>(&self, schema_id: u32, record: &T) -> Result<Vec<u8>, Error> {
+        let mut framed = vec![0];
+        framed.extend(schema_id.to_be_bytes());
+        framed.extend(self.datum(record)?);
+        Ok(framed)
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+// registry
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// A Confluent-compatible schema registry, at `url`, that schemas are registered with to get their
+/// ids; each subject's id is only requested once, then remembered.
+pub struct SchemaRegistry {
+    url: String,
+    client: reqwest::Client,
+    credentials: Option<(String, String)>,
+    ids: Mutex<HashMap<String, u32>>,
+}
+
+impl SchemaRegistry {
+    pub fn new(url: impl Into<String>) -> Self {
+        SchemaRegistry {
+            url: url.into().trim_end_matches('/').to_string(),
+            client: reqwest::Client::new(),
+            credentials: None,
+            ids: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Authenticate with an API key & secret, as HTTP basic auth.
+    pub fn basic_auth(mut self, key: impl Into<String>, secret: impl Into<String>) -> Self {
+        self.credentials = Some((key.into(), secret.into()));
+        self
+    }
+
+    /// The id of `schema` under `subject` (e.g., `prices-value`); registering it as a new version
+    /// if the registry doesn't have it yet.
+    ///
+    /// Fails with [`Error::HTTP`] if the registry rejects it, e.g., as incompatible with the
+    /// subject's earlier versions.
+    pub async fn id(&self, subject: &str, schema: &Schema) -> Result<u32, Error> {
+        if let Some(id) = self
+            .ids
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(subject)
+        {
+            return Ok(*id);
+        }
+
+        #[derive(serde::Deserialize)]
+        struct Registered {
+            id: u32,
+        }
+        let mut request = self
+            .client
+            .post(format!("{}/subjects/{subject}/versions", self.url))
+            .header("Content-Type", "application/vnd.schemaregistry.v1+json")
+            .json(&serde_json::json!({ "schema": serde_json::to_string(schema)? }));
+        if let Some((key, secret)) = &self.credentials {
+            request = request.basic_auth(key, Some(secret));
+        }
+        let registered: Registered = request.send().await?.error_for_status()?.json().await?;
+
+        self.ids
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(subject.to_string(), registered.id);
+        Ok(registered.id)
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+// file
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Writes an array output to an Avro object container file, replacing any previous contents;
+/// see [`fs::write_atomic()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct AvroFile {
+    pub path: PathBuf,
+    pub avro: Avro,
+}
+
+impl AvroFile {
+    pub fn new(path: impl Into<PathBuf>, avro: Avro) -> Self {
+        AvroFile {
+            path: path.into(),
+            avro,
+        }
+    }
+}
+
+impl<T> Sink<Vec<T>> for AvroFile
+where
+    T: Serialize + Sync,
+{
+    async fn load(&self, output: &Vec<T>) -> Result<Outcome, Error> {
+        let bytes = self.avro.container(output)?;
+        fs::write_atomic(&self.path, &bytes)?;
+        Ok(Outcome::File {
+            bytes: bytes.len() as u64,
+        })
+    }
+}
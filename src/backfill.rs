@@ -0,0 +1,205 @@
+use super::fs;
+use super::observer::Event;
+use super::report::{Loaded, Skip};
+use super::{Endpoint, Error, EtlReport, Input, Output, Pipe, ETL};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{Datelike, Days, Months, NaiveDate};
+use std::path::PathBuf;
+
+/// How a backfill's date range is split into extractions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chunk {
+    Day,
+    /// 7 days at a time, from the start of the range.
+    Week,
+    /// Calendar months; the first & last chunks may be partial.
+    Month,
+}
+
+/// Extracts a date range from an endpoint in chunks, e.g., to load years of history from an API
+/// that only serves a month per request.
+///
+/// The endpoint's `{start}` & `{end}` placeholders are filled in with the first & last date of
+/// each chunk (inclusive). Chunks are extracted in order, through the pipe, so its retries, rate
+/// limit & observer apply to every one.
+///
+/// ```rust,ignore
+/// let backfill = Backfill::new(
+///     Endpoint::new("https://example.com/prices/{ticker}/{start}/{end}").var("ticker", "NVDA"),
+///     NaiveDate::from_ymd_opt(2020, 1, 1).unwrap(),
+///     NaiveDate::from_ymd_opt(2023, 12, 31).unwrap(),
+/// )
+/// .chunk(Chunk::Month)
+/// .checkpoint("nvda.backfill");
+/// backfill.run(&pipe).await?;
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backfill {
+    endpoint: Endpoint,
+    start: NaiveDate,
+    end: NaiveDate,
+    chunk: Chunk,
+    format: String,
+    checkpoint: Option<PathBuf>,
+}
+
+impl Backfill {
+    /// Backfill `endpoint` from `start` to `end`, inclusive.
+    pub fn new(endpoint: Endpoint, start: NaiveDate, end: NaiveDate) -> Self {
+        Backfill {
+            endpoint,
+            start,
+            end,
+            chunk: Chunk::Day,
+            format: "%Y-%m-%d".into(),
+            checkpoint: None,
+        }
+    }
+
+    /// How much of the range to extract at once. Defaults to [`Chunk::Day`].
+    pub fn chunk(mut self, chunk: Chunk) -> Self {
+        self.chunk = chunk;
+        self
+    }
+
+    /// How dates are written into the endpoint, as a `chrono` format string. Defaults to `%Y-%m-%d`.
+    pub fn format(mut self, format: impl Into<String>) -> Self {
+        self.format = format.into();
+        self
+    }
+
+    /// Record the last loaded chunk in the file at `path`, so that a failed (or interrupted)
+    /// backfill resumes after it when run again, instead of starting over.
+    pub fn checkpoint(mut self, path: impl Into<PathBuf>) -> Self {
+        self.checkpoint = Some(path.into());
+        self
+    }
+
+    /// The first & last date of every chunk, in order.
+    pub fn chunks(&self) -> Vec<(NaiveDate, NaiveDate)> {
+        let mut chunks = vec![];
+        let mut start = self.start;
+        while start <= self.end {
+            let next = match self.chunk {
+                Chunk::Day => start.checked_add_days(Days::new(1)),
+                Chunk::Week => start.checked_add_days(Days::new(7)),
+                Chunk::Month => start
+                    .with_day(1)
+                    .and_then(|first| first.checked_add_months(Months::new(1))),
+            };
+            let end = next
+                .and_then(|next| next.pred_opt())
+                .map_or(self.end, |last| last.min(self.end));
+            chunks.push((start, end));
+            match next {
+                Some(next) => start = next,
+                None => break,
+            }
+        }
+        chunks
+    }
+
+    /// Extract, transform & load each chunk separately; stopping at the first failure.
+    ///
+    /// Returns what the sink reported for every chunk loaded; none for those already checkpointed.
+    pub async fn run<I, O>(&self, pipe: &Pipe<I, O>) -> Result<EtlReport, Error>
+    where
+        I: Input,
+        O: Output,
+        Pipe<I, O>: ETL<I, O>,
+    {
+        pipe.collect(async {
+            let pending = self.pending()?;
+            pipe.check_quota(pending.len())?;
+            let mut report = EtlReport::new();
+            pipe.replay_stage(&mut report).await?;
+            for (end, url) in pending {
+                pipe.notify(Event::Started { source: &url });
+                match pipe.extran_changed(&url).await? {
+                    Some(changed) => {
+                        let output = pipe.enrich_stage(changed.output).await?;
+                        report.push(pipe.load_stage(&output).await?);
+                        pipe.loaded(&url, changed.digest)?;
+                    }
+                    None => report.push(Loaded::skipped(Skip::Unchanged)),
+                }
+                self.save(end)?;
+                pipe.notify(Event::Finished);
+            }
+            Ok(report)
+        })
+        .await
+    }
+
+    /// Extract & transform every chunk, then load them all together, as one output.
+    ///
+    /// Nothing is loaded if any chunk fails, so a checkpoint only ever moves past the whole range.
+    pub async fn run_merged<I, T>(&self, pipe: &Pipe<I, Vec<T>>) -> Result<EtlReport, Error>
+    where
+        I: Input,
+        Vec<T>: Output,
+        Pipe<I, Vec<T>>: ETL<I, Vec<T>>,
+    {
+        pipe.collect(async {
+            let pending = self.pending()?;
+            pipe.check_quota(pending.len())?;
+            let mut report = EtlReport::new();
+            pipe.replay_stage(&mut report).await?;
+            let Some((last, _)) = pending.last().cloned() else {
+                return Ok(report);
+            };
+            let source = format!("{} .. {}", pending[0].1, pending[pending.len() - 1].1);
+            pipe.notify(Event::Started { source: &source });
+            let mut merged = vec![];
+            for (_, url) in &pending {
+                merged.extend(pipe.extran_cached(url).await?);
+            }
+            let merged = pipe.enrich_stage(merged).await?;
+            report.push(pipe.load_stage(&merged).await?);
+            self.save(last)?;
+            pipe.notify(Event::Finished);
+            Ok(report)
+        })
+        .await
+    }
+
+    // The last date & URL of every chunk after the checkpoint, if any.
+    fn pending(&self) -> Result<Vec<(NaiveDate, String)>, Error> {
+        if StrftimeItems::new(&self.format).any(|item| item == Item::Error) {
+            return Err(Error::Config(format!(
+                "invalid backfill date format {:?}",
+                self.format
+            )));
+        }
+        let done = match &self.checkpoint {
+            Some(path) if path.exists() => {
+                let saved = std::fs::read_to_string(path)?;
+                let date = NaiveDate::parse_from_str(saved.trim(), "%Y-%m-%d").map_err(|e| {
+                    Error::Config(format!("invalid backfill checkpoint {path:?}: {e}"))
+                })?;
+                Some(date)
+            }
+            _ => None,
+        };
+        self.chunks()
+            .into_iter()
+            .filter(|(_, end)| done.is_none_or(|done| *end > done))
+            .map(|(start, end)| {
+                let url = self
+                    .endpoint
+                    .clone()
+                    .var("start", start.format(&self.format))
+                    .var("end", end.format(&self.format))
+                    .url()?;
+                Ok((end, url.to_string()))
+            })
+            .collect()
+    }
+
+    fn save(&self, end: NaiveDate) -> Result<(), Error> {
+        if let Some(path) = &self.checkpoint {
+            fs::write_atomic(path, end.format("%Y-%m-%d").to_string().as_bytes())?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,587 @@
+//! A sink streaming rows into a BigQuery table, through the `tabledata.insertAll` API.
+//!
+//! ```rust,ignore
+//! let sink = BigQuery::new("my-project", "markets", "prices").create_missing();
+//! let pipe = Pipe::<RawPrice, Vec<Price>>::builder().source(source).sink(sink).build()?;
+//! ```
+//!
+//! Each record of the output is one row; they're sent in batches (of 500 rows, and under the API's
+//! 10 MB per request), each row with an `insertId` derived from the run id (or the journaled
+//! output's key, see [`journal`](crate::journal)) and its place in the output, so BigQuery
+//! discards the rows of a retried request it already has. Requests refused for quota, rate
+//! limits or a failing backend are retried with backoff; a row BigQuery rejects fails the load
+//! (with [`Error::Upstream`], naming the row), and nothing of its batch is inserted. The batches
+//! before it stay inserted, though; a retried load sends them again with the same `insertId`s,
+//! so BigQuery discards them rather than inserting them twice.
+//!
+//! Requests are authenticated with a token from the metadata server, as on GCE, GKE & Cloud Run;
+//! or with one given to [`BigQuery::token()`], e.g., from `gcloud auth print-access-token`.
+//!
+//! A table's [`Schema`] can be inferred from an output, e.g., to create the table before the
+//! first load; see [`Schema::infer()`].
+
+use super::clock::{self, Clock};
+use super::endpoint::encode_segment;
+use super::retry::RetryPolicy;
+use super::sink::{content_hash, Sink};
+use super::{journal, Error, Outcome};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, OnceCell};
+
+const API: &str = "https://bigquery.googleapis.com/bigquery/v2";
+const METADATA: &str =
+    "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token";
+// insertAll refuses requests over 10 MB; leave room for the envelope
+const MAX_BYTES: usize = 9 * 1024 * 1024;
+// the error reasons of requests (or rows) that may succeed when retried
+const RETRYABLE: [&str; 5] = [
+    "backendError",
+    "internalError",
+    "quotaExceeded",
+    "rateLimitExceeded",
+    "timeout",
+];
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+// sink
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Streams every record of the output into a BigQuery table; see [`bigquery`](self).
+#[derive(Debug)]
+pub struct BigQuery {
+    pub project: String,
+    pub dataset: String,
+    pub table: String,
+    /// The id insert ids derive from, if given one; otherwise, that of the
+    /// [run in progress](crate::clock::run_id), or outside of one, the sink's own.
+    pub run_id: Option<String>,
+    // the id insert ids derive from outside of a run, without a `run_id`
+    own_id: String,
+    endpoint: String,
+    auth: Auth,
+    batch: usize,
+    retry: RetryPolicy,
+    ignore_unknown: bool,
+    create_missing: bool,
+    created: OnceCell<()>,
+    client: reqwest::Client,
+}
+
+#[derive(Debug)]
+enum Auth {
+    Token(String),
+    // fetched from the metadata server, & kept until it's about to expire
+    Metadata(Mutex<Option<(String, Instant)>>),
+}
+
+// why an insert failed; `Retry` if it may succeed when sent again
+enum Failure {
+    Retry(Error),
+    Fatal(Error),
+}
+
+impl BigQuery {
+    /// Stream into `project.dataset.table`, with insert ids derived from the id of each run;
+    /// outside of one, from a fresh id of its own, from the system [`Clock`].
+    pub fn new(project: &str, dataset: &str, table: &str) -> Self {
+        BigQuery {
+            project: project.to_string(),
+            dataset: dataset.to_string(),
+            table: table.to_string(),
+            run_id: None,
+            own_id: clock::System.uuid().to_string(),
+            endpoint: API.to_string(),
+            auth: Auth::Metadata(Mutex::new(None)),
+            batch: 500,
+            retry: RetryPolicy::new(5).backoff(Duration::from_secs(1), Duration::from_secs(32)),
+            ignore_unknown: false,
+            create_missing: false,
+            created: OnceCell::new(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Authenticate with `token`, rather than one from the metadata server.
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.auth = Auth::Token(token.into());
+        self
+    }
+
+    /// Send requests to `endpoint` rather than the BigQuery API, e.g., an emulator's.
+    pub fn endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        self.endpoint = endpoint.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Derive insert ids from `run_id` instead, in every run; re-running with the same id
+    /// replays the same ids, so BigQuery can discard the rows it already has (for a minute or so).
+    pub fn run_id(mut self, run_id: impl Into<String>) -> Self {
+        self.run_id = Some(run_id.into());
+        self
+    }
+
+    /// Send at most `rows` rows per request. Defaults to 500, as BigQuery recommends.
+    pub fn batch(mut self, rows: usize) -> Self {
+        self.batch = rows.max(1);
+        self
+    }
+
+    /// How to retry requests refused for quota, rate limits, or a failing backend. Defaults to 5
+    /// attempts, backing off from 1 second to 32.
+    pub fn retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Drop the fields of a row that aren't in the table's schema, rather than rejecting it.
+    pub fn ignore_unknown(mut self) -> Self {
+        self.ignore_unknown = true;
+        self
+    }
+
+    /// Create the table on the first load, if it doesn't exist, with the schema inferred from
+    /// that load's rows; see [`Schema::infer()`].
+    pub fn create_missing(mut self) -> Self {
+        self.create_missing = true;
+        self
+    }
+
+    /// Create the table with `schema`; `false` if it already exists.
+    pub async fn create_table(&self, schema: &Schema) -> Result<bool, Error> {
+        let url = format!("{}/tables", self.dataset_url());
+        let body = json!({
+            "tableReference": {
+                "projectId": self.project,
+                "datasetId": self.dataset,
+                "tableId": self.table,
+            },
+            "schema": schema,
+        });
+        let response = self
+            .client
+            .post(url)
+            .bearer_auth(self.bearer().await?)
+            .json(&body)
+            .send()
+            .await?;
+        match response.status().as_u16() {
+            409 => Ok(false),
+            _ => {
+                response.error_for_status()?;
+                Ok(true)
+            }
+        }
+    }
+
+    // The URL of the dataset, with its & the project's names percent-encoded.
+    fn dataset_url(&self) -> String {
+        format!(
+            "{}/projects/{}/datasets/{}",
+            self.endpoint,
+            encode_segment(&self.project),
+            encode_segment(&self.dataset)
+        )
+    }
+
+    // A valid access token.
+    async fn bearer(&self) -> Result<String, Error> {
+        let cached = match &self.auth {
+            Auth::Token(token) => return Ok(token.clone()),
+            Auth::Metadata(cached) => cached,
+        };
+        let mut cached = cached.lock().await;
+        if let Some((token, expires)) = &*cached {
+            if Instant::now() + Duration::from_secs(60) < *expires {
+                return Ok(token.clone());
+            }
+        }
+        #[derive(Deserialize)]
+        struct Token {
+            access_token: String,
+            expires_in: u64,
+        }
+        let issued = Instant::now();
+        let token: Token = self
+            .client
+            .get(METADATA)
+            .header("Metadata-Flavor", "Google")
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let expires = issued + Duration::from_secs(token.expires_in);
+        *cached = Some((token.access_token.clone(), expires));
+        Ok(token.access_token)
+    }
+
+    // Forget the token from the metadata server, e.g., after it's refused.
+    async fn invalidate(&self) {
+        if let Auth::Metadata(cached) = &self.auth {
+            *cached.lock().await = None;
+        }
+    }
+
+    // One insertAll request, of every row in `body`.
+    async fn insert(&self, body: &[u8]) -> Result<(), Failure> {
+        let url = format!(
+            "{}/tables/{}/insertAll",
+            self.dataset_url(),
+            encode_segment(&self.table)
+        );
+        let bearer = self.bearer().await.map_err(Failure::Fatal)?;
+        let response = self
+            .client
+            .post(url)
+            .bearer_auth(bearer)
+            .header("Content-Type", "application/json")
+            .body(body.to_vec())
+            .send()
+            .await
+            .map_err(|e| Failure::Retry(e.into()))?;
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| Failure::Retry(e.into()))?;
+        let reply: Value = serde_json::from_str(&text).unwrap_or_default();
+
+        if !status.is_success() {
+            let error = Error::Upstream(format!("{status}: {}", message(&reply, &text)));
+            let reasons = reply["error"]["errors"]
+                .as_array()
+                .map(|errors| reasons(errors))
+                .unwrap_or_default();
+            return Err(match status.as_u16() {
+                401 if matches!(self.auth, Auth::Metadata(_)) => {
+                    self.invalidate().await;
+                    Failure::Retry(error)
+                }
+                429 | 500 | 502 | 503 | 504 => Failure::Retry(error),
+                _ if !reasons.is_empty() && reasons.iter().all(|r| RETRYABLE.contains(r)) => {
+                    Failure::Retry(error)
+                }
+                _ => Failure::Fatal(error),
+            });
+        }
+
+        let Some(rows) = reply["insertErrors"]
+            .as_array()
+            .filter(|rows| !rows.is_empty())
+        else {
+            return Ok(());
+        };
+        let mut retryable = true;
+        let mut rejected = vec![];
+        for row in rows {
+            let errors = row["errors"]
+                .as_array()
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            // "stopped" rows were fine, but not inserted because of another row
+            let reasons: Vec<&str> = reasons(errors)
+                .into_iter()
+                .filter(|reason| *reason != "stopped")
+                .collect();
+            if reasons.is_empty() {
+                continue;
+            }
+            retryable &= reasons.iter().all(|reason| RETRYABLE.contains(reason));
+            let messages: Vec<&str> = errors
+                .iter()
+                .filter(|error| error["reason"] != "stopped")
+                .filter_map(|error| error["message"].as_str())
+                .collect();
+            rejected.push(format!("row {}: {}", row["index"], messages.join("; ")));
+        }
+        let error = Error::Upstream(format!("rows rejected: {}", rejected.join(", ")));
+        Err(match retryable {
+            true => Failure::Retry(error),
+            false => Failure::Fatal(error),
+        })
+    }
+}
+
+impl<O> Sink<O> for BigQuery
+where
+    O: Serialize + Sync + ?Sized,
+{
+    async fn load(&self, output: &O) -> Result<Outcome, Error> {
+        let rows = match serde_json::to_value(output)? {
+            Value::Array(rows) => rows,
+            row => vec![row],
+        };
+        let rows = rows
+            .into_iter()
+            .enumerate()
+            .map(|(i, row)| match row {
+                Value::Object(row) => Ok(row),
+                _ => Err(Error::Config(format!(
+                    "BigQuery rows must be objects; record {i} isn't"
+                ))),
+            })
+            .collect::<Result<Vec<Map<String, Value>>, Error>>()?;
+        if rows.is_empty() {
+            return Ok(Outcome::Streamed { rows: 0 });
+        }
+        if self.create_missing {
+            self.created
+                .get_or_try_init(|| async {
+                    self.create_table(&Schema::infer(&rows)?).await.map(|_| ())
+                })
+                .await?;
+        }
+
+        // the ids of this output's rows are derived from one key
+        let key = match journal::idempotency_key() {
+            Some(key) => key,
+            None => {
+                let payload = serde_json::to_vec(&rows)?;
+                let run_id = self.run_id.clone().or_else(clock::run_id);
+                let run_id = run_id.as_deref().unwrap_or(&self.own_id);
+                content_hash(&[run_id.as_bytes(), &[0], &payload])
+            }
+        };
+        for body in self.batches(&key, &rows)? {
+            let mut attempt = 1;
+            loop {
+                match self.insert(&body).await {
+                    Ok(()) => break,
+                    Err(Failure::Retry(e)) if attempt < self.retry.max_attempts => {
+                        tokio::time::sleep(self.retry.delay_after(attempt, &e)).await;
+                        attempt += 1;
+                    }
+                    Err(Failure::Retry(e) | Failure::Fatal(e)) => return Err(e),
+                }
+            }
+        }
+        Ok(Outcome::Streamed {
+            rows: rows.len() as u64,
+        })
+    }
+}
+
+impl BigQuery {
+    // The insertAll request bodies for `rows`; each of at most `batch` rows, & under `MAX_BYTES`.
+    fn batches(&self, key: &str, rows: &[Map<String, Value>]) -> Result<Vec<Vec<u8>>, Error> {
+        let body = |rows: &[Value]| {
+            serde_json::to_vec(&json!({
+                "ignoreUnknownValues": self.ignore_unknown,
+                "rows": rows,
+            }))
+        };
+        let mut bodies = vec![];
+        let mut batch = vec![];
+        let mut bytes = 0;
+        for (i, row) in rows.iter().enumerate() {
+            let insert_id = content_hash(&[key.as_bytes(), &[0], i.to_string().as_bytes()]);
+            let row = json!({ "insertId": insert_id, "json": row });
+            let len = serde_json::to_vec(&row)?.len() + 1;
+            if !batch.is_empty() && (batch.len() == self.batch || bytes + len > MAX_BYTES) {
+                bodies.push(body(&batch)?);
+                batch.clear();
+                bytes = 0;
+            }
+            bytes += len;
+            batch.push(row);
+        }
+        if !batch.is_empty() {
+            bodies.push(body(&batch)?);
+        }
+        Ok(bodies)
+    }
+}
+
+// the `reason` of each error
+fn reasons(errors: &[Value]) -> Vec<&str> {
+    errors
+        .iter()
+        .filter_map(|error| error["reason"].as_str())
+        .collect()
+}
+
+// the message of an error reply, or its text
+fn message<'a>(reply: &'a Value, text: &'a str) -> &'a str {
+    reply["error"]["message"].as_str().unwrap_or(text)
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+// schema
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// The schema of a BigQuery table, as the API takes it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Schema {
+    pub fields: Vec<Field>,
+}
+
+/// One column of a [`Schema`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Field {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: Type,
+    pub mode: Mode,
+    /// The fields of a [`Type::Record`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<Field>,
+}
+
+/// The type of a [`Field`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Type {
+    String,
+    Int64,
+    Float64,
+    Bool,
+    /// An RFC 3339 date & time, e.g., `2024-06-01T09:30:00Z`.
+    Timestamp,
+    /// A calendar date, e.g., `2024-06-01`.
+    Date,
+    /// A nested object, of [`Field::fields`].
+    Record,
+    /// Any JSON value.
+    Json,
+}
+
+/// Whether a [`Field`] may be missing, or holds an array.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Mode {
+    Nullable,
+    Required,
+    Repeated,
+}
+
+impl Schema {
+    /// The schema of the records of `output` (an array of objects, or one object), as serialized
+    /// to JSON: numbers are `INT64` (or `FLOAT64`, if any has a fraction), strings are `TIMESTAMP`
+    /// or `DATE` if every one parses as such, objects are `RECORD`s, and arrays are `REPEATED`.
+    ///
+    /// Every field is `NULLABLE`, so records may omit it; a field whose values don't share a type
+    /// (e.g., numbers & strings, or arrays & not) is `JSON`, as is one that's only ever `null`.
+    /// Fields (and those of records) are in alphabetical order.
+    pub fn infer<T: Serialize + ?Sized>(output: &T) -> Result<Schema, Error> {
+        let records = match serde_json::to_value(output)? {
+            Value::Array(records) => records,
+            record => vec![record],
+        };
+        let mut columns = Columns::default();
+        for record in &records {
+            match record {
+                Value::Object(record) => columns.observe(record),
+                _ => return Err(Error::Config("only objects have a schema".into())),
+            }
+        }
+        Ok(Schema {
+            fields: columns.finish(),
+        })
+    }
+}
+
+// the columns seen so far, by name
+#[derive(Default)]
+struct Columns(BTreeMap<String, Column>);
+
+#[derive(Default)]
+struct Column {
+    kind: Option<Type>,
+    scalar: bool,
+    repeated: bool,
+    fields: Columns,
+}
+
+impl Columns {
+    fn observe(&mut self, record: &Map<String, Value>) {
+        for (name, value) in record {
+            self.0.entry(name.clone()).or_default().observe(value);
+        }
+    }
+
+    fn finish(self) -> Vec<Field> {
+        self.0
+            .into_iter()
+            .map(|(name, column)| column.finish(name))
+            .collect()
+    }
+}
+
+impl Column {
+    fn observe(&mut self, value: &Value) {
+        match value {
+            Value::Null => {}
+            Value::Array(elements) => {
+                self.repeated = true;
+                for element in elements {
+                    match element {
+                        Value::Null => {}
+                        Value::Array(_) => self.kind = Some(Type::Json),
+                        element => self.element(element),
+                    }
+                }
+            }
+            value => {
+                self.scalar = true;
+                self.element(value);
+            }
+        }
+    }
+
+    fn element(&mut self, value: &Value) {
+        let kind = match value {
+            Value::Bool(_) => Type::Bool,
+            Value::Number(n) if n.is_i64() || n.is_u64() => Type::Int64,
+            Value::Number(_) => Type::Float64,
+            Value::String(s) if chrono::DateTime::parse_from_rfc3339(s).is_ok() => Type::Timestamp,
+            Value::String(s) if chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").is_ok() => {
+                Type::Date
+            }
+            Value::String(_) => Type::String,
+            Value::Object(record) => {
+                self.fields.observe(record);
+                Type::Record
+            }
+            Value::Null | Value::Array(_) => Type::Json,
+        };
+        self.kind = Some(match (self.kind, kind) {
+            (None, kind) => kind,
+            (Some(seen), kind) if seen == kind => kind,
+            (Some(Type::Int64), Type::Float64) | (Some(Type::Float64), Type::Int64) => {
+                Type::Float64
+            }
+            (Some(Type::String | Type::Timestamp | Type::Date), Type::String)
+            | (Some(Type::String), Type::Timestamp | Type::Date)
+            | (Some(Type::Timestamp), Type::Date)
+            | (Some(Type::Date), Type::Timestamp) => Type::String,
+            _ => Type::Json,
+        });
+    }
+
+    fn finish(self, name: String) -> Field {
+        let fields = self.fields.finish();
+        let kind = match self.kind {
+            _ if self.scalar && self.repeated => None,
+            // BigQuery has no empty records
+            Some(Type::Record) if fields.is_empty() => None,
+            kind => kind,
+        };
+        let (kind, mode) = match kind {
+            None | Some(Type::Json) => (Type::Json, Mode::Nullable),
+            Some(kind) if self.repeated => (kind, Mode::Repeated),
+            Some(kind) => (kind, Mode::Nullable),
+        };
+        Field {
+            name,
+            kind,
+            mode,
+            fields: match kind {
+                Type::Record => fields,
+                _ => vec![],
+            },
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use super::Error;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+/// On-disk cache of transformed outputs, keyed by source.
+///
+/// While a cached output is younger than `ttl`, running the pipe again skips extraction
+/// and transformation entirely, and goes straight to loading.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cache {
+    /// Directory the cached outputs are written to; created if missing.
+    pub dir: PathBuf,
+    /// How long a cached output stays fresh.
+    pub ttl: Duration,
+}
+
+impl Cache {
+    pub fn new(dir: impl Into<PathBuf>, ttl: Duration) -> Self {
+        Cache {
+            dir: dir.into(),
+            ttl,
+        }
+    }
+
+    /// File that holds the cached output for `key`.
+    pub fn path(&self, key: &str) -> PathBuf {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        self.dir.join(format!("{:016x}.json", hasher.finish()))
+    }
+
+    /// Read a fresh cached output for `key`, if there is one.
+    ///
+    /// Stale, missing or unreadable entries are all treated as a miss.
+    pub fn get<O>(&self, key: &str) -> Option<O>
+    where
+        O: serde::de::DeserializeOwned,
+    {
+        let path = self.path(key);
+        let modified = std::fs::metadata(&path).ok()?.modified().ok()?;
+        let age = SystemTime::now()
+            .duration_since(modified)
+            .unwrap_or_default();
+        if age > self.ttl {
+            return None;
+        }
+        let file = std::fs::File::open(path).ok()?;
+        serde_json::from_reader(std::io::BufReader::new(file)).ok()
+    }
+
+    /// Write `output` to the cache under `key`.
+    pub fn put<O>(&self, key: &str, output: &O) -> Result<(), Error>
+    where
+        O: serde::Serialize,
+    {
+        std::fs::create_dir_all(&self.dir)?;
+        let file = std::fs::File::create(self.path(key))?;
+        serde_json::to_writer(std::io::BufWriter::new(file), output)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+//! A machine-readable inventory of the pipelines of a [`Runner`]: what each reads, writes, and
+//! how often; e.g., to document the data flows of a service, or check them into a data catalog.
+//!
+//! ```rust,ignore
+//! let runner = Runner::new()
+//!     .pipe("prices", prices, Duration::from_secs(60 * 60))
+//!     .pipe("holidays", holidays, Duration::from_secs(24 * 60 * 60));
+//!
+//! std::fs::write("catalog.json", serde_json::to_vec_pretty(&runner.catalog())?)?;
+//! // {"pipelines": [{"name": "prices", "input": "app::RawPrice", "output": "alloc::vec::Vec<app::Price>",
+//! //   "source": "https://example.com/prices.json", "sink": "pipe_io::sink::Postgres", "every_ms": 3600000}, ...]}
+//! ```
+//!
+//! With [`lineage`](crate::lineage) declared, each pipeline also lists where its output fields come
+//! from; and [`Catalog::lineage()`] is the graph of them all.
+//!
+//! Types are named as by [`std::any::type_name()`]: fully qualified, but not guaranteed stable
+//! across compiler versions.
+//!
+//! [`Runner`]: crate::Runner
+
+use super::lineage::{Edge, Lineage};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Every pipeline of a runner, in the order they were added; see [`Runner::catalog()`].
+///
+/// [`Runner::catalog()`]: crate::Runner::catalog
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Catalog {
+    pub pipelines: Vec<Entry>,
+}
+
+/// One pipeline of a [`Catalog`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub name: String,
+    #[serde(flatten)]
+    pub pipe: Description,
+    /// How often the pipeline runs.
+    pub every_ms: u64,
+}
+
+/// What a pipe reads & writes; see [`DynPipeline::describe()`].
+///
+/// [`DynPipeline::describe()`]: crate::DynPipeline::describe
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Description {
+    /// The input type.
+    pub input: String,
+    /// The output type.
+    pub output: String,
+    /// The configured source: its endpoint, `<inline>` or `<stream>`; see [`Source::describe()`].
+    /// A URL's password & secret query parameters are redacted; see [`wire::redact_url()`].
+    ///
+    /// [`Source::describe()`]: crate::Source::describe
+    /// [`wire::redact_url()`]: crate::wire::redact_url
+    pub source: Option<String>,
+    /// The type of the configured sink.
+    pub sink: Option<String>,
+    /// Where the output's fields come from, as far as declared.
+    #[serde(default, skip_serializing_if = "Lineage::is_empty")]
+    pub lineage: Lineage,
+}
+
+impl Catalog {
+    /// The lineage graph of every pipeline: an edge from each input path to each output field
+    /// derived from it, pipeline by pipeline.
+    pub fn lineage(&self) -> Vec<Edge> {
+        let mut edges = vec![];
+        for entry in &self.pipelines {
+            for derivation in &entry.pipe.lineage.fields {
+                let from = match derivation.from.as_slice() {
+                    [] => vec![None],
+                    from => from.iter().cloned().map(Some).collect(),
+                };
+                edges.extend(from.into_iter().map(|from| Edge {
+                    pipeline: entry.name.clone(),
+                    from,
+                    field: derivation.field.clone(),
+                    note: derivation.note.clone(),
+                }));
+            }
+        }
+        edges
+    }
+}
+
+impl Entry {
+    pub fn every(&self) -> Duration {
+        Duration::from_millis(self.every_ms)
+    }
+}
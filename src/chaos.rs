@@ -0,0 +1,175 @@
+//! Failures injected at chosen points of a run, so the retry, checkpoint & policy logic of a
+//! pipeline can be tested deterministically; without a flaky server, or a database to take down.
+//!
+//! For tests only: enable the `chaos` feature in `[dev-dependencies]`.
+//!
+//! ```rust,ignore
+//! // the 1st extraction fails as if served a 500, and the 3rd batch loaded times out
+//! let chaos = Chaos::new().fail(Stage::Extract, 1, Fault::Status(500));
+//! let pipe = Pipe::<I, Vec<O>>::builder()
+//!     .retry(RetryPolicy::new(3))
+//!     .chaos(chaos.clone())
+//!     .sink(sink::Batched::new(Faulty::new(sink).fail(3, Fault::Timeout), 100))
+//!     .build()?;
+//!
+//! let result = pipe.run().await; // fails, with the 3rd batch's range
+//! assert_eq!(chaos.attempts(Stage::Extract), 2);
+//! ```
+
+use super::observer::Stage;
+use super::sink::Sink;
+use super::{Error, Outcome};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// What an injected failure looks like.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fault {
+    /// Fail as a response with this HTTP status would, as [`Error::Injected`].
+    Status(u16),
+    /// Fail as the stage's timeout would, as [`Error::Timeout`]; without waiting for it.
+    Timeout,
+    /// Wait this long first, then carry on; e.g., to run into a real timeout.
+    Delay(Duration),
+    /// Fail with this message, as [`Error::Injected`].
+    Error(String),
+}
+
+/// Faults to inject into the stages of a pipe, by attempt; see [`PipeBuilder::chaos()`].
+///
+/// Attempts are counted per stage (from 1) over the life of the plan, retries included; clones
+/// share the same counts.
+///
+/// [`PipeBuilder::chaos()`]: crate::PipeBuilder::chaos
+#[derive(Debug, Clone, Default)]
+pub struct Chaos {
+    faults: HashMap<(Stage, u32), Fault>,
+    attempts: Arc<Mutex<HashMap<Stage, u32>>>,
+}
+
+impl Chaos {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inject `fault` into attempt `attempt` (from 1) of `stage`.
+    pub fn fail(mut self, stage: Stage, attempt: u32, fault: Fault) -> Self {
+        self.faults.insert((stage, attempt), fault);
+        self
+    }
+
+    /// How many times `stage` has been attempted.
+    pub fn attempts(&self, stage: Stage) -> u32 {
+        let attempts = self
+            .attempts
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        attempts.get(&stage).copied().unwrap_or(0)
+    }
+
+    // Count an attempt of `stage`, and run `fut` for it; unless a fault is injected instead.
+    pub(crate) async fn attempt<T>(
+        &self,
+        stage: Stage,
+        timeout: Option<Duration>,
+        fut: impl Future<Output = Result<T, Error>>,
+    ) -> Result<T, Error> {
+        let attempt = {
+            let mut attempts = self
+                .attempts
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let attempt = attempts.entry(stage).or_insert(0);
+            *attempt += 1;
+            *attempt
+        };
+        inject(
+            self.faults.get(&(stage, attempt)),
+            stage,
+            attempt,
+            timeout,
+            fut,
+        )
+        .await
+    }
+}
+
+/// Wraps a sink, injecting faults into its loads, by call; e.g., into the 3rd batch of a
+/// [`Batched`] sink.
+///
+/// [`Batched`]: crate::sink::Batched
+pub struct Faulty<S> {
+    sink: S,
+    faults: HashMap<u32, Fault>,
+    loads: AtomicU32,
+}
+
+impl<S> Faulty<S> {
+    pub fn new(sink: S) -> Self {
+        Faulty {
+            sink,
+            faults: HashMap::new(),
+            loads: AtomicU32::new(0),
+        }
+    }
+
+    /// Inject `fault` into load `load` (from 1).
+    pub fn fail(mut self, load: u32, fault: Fault) -> Self {
+        self.faults.insert(load, fault);
+        self
+    }
+
+    /// How many loads have been attempted.
+    pub fn loads(&self) -> u32 {
+        self.loads.load(Ordering::SeqCst)
+    }
+}
+
+impl<O, S> Sink<O> for Faulty<S>
+where
+    O: Sync + ?Sized,
+    S: Sink<O>,
+{
+    async fn load(&self, output: &O) -> Result<Outcome, Error> {
+        let load = self.loads.fetch_add(1, Ordering::SeqCst) + 1;
+        inject(
+            self.faults.get(&load),
+            Stage::Load,
+            load,
+            None,
+            self.sink.load(output),
+        )
+        .await
+    }
+}
+
+// Run `fut`, unless `fault` fails it first.
+async fn inject<T>(
+    fault: Option<&Fault>,
+    stage: Stage,
+    attempt: u32,
+    timeout: Option<Duration>,
+    fut: impl Future<Output = Result<T, Error>>,
+) -> Result<T, Error> {
+    let error = match fault {
+        None => return fut.await,
+        Some(Fault::Delay(delay)) => {
+            tokio::time::sleep(*delay).await;
+            return fut.await;
+        }
+        Some(Fault::Status(status)) => {
+            Error::Injected(format!("HTTP {status}, on {stage} attempt {attempt}"))
+        }
+        Some(Fault::Timeout) => Error::Timeout {
+            stage,
+            timeout: timeout.unwrap_or_default(),
+        },
+        Some(Fault::Error(message)) => {
+            Error::Injected(format!("{message}, on {stage} attempt {attempt}"))
+        }
+    };
+    Err(error)
+}
@@ -0,0 +1,185 @@
+//! A command-line interface to the pipelines of a [`Runner`], so every binary built on this crate
+//! takes the same arguments:
+//!
+//! ```rust,ignore
+//! #[tokio::main(flavor = "current_thread")]
+//! async fn main() -> std::process::ExitCode {
+//!     let runner = Runner::new()
+//!         .pipe("prices", prices, Duration::from_secs(3600))
+//!         .pipe("holidays", holidays, Duration::from_secs(86400));
+//!     pipe_io::cli::run_cli(runner).await
+//! }
+//! ```
+//!
+//! ```text
+//! etl                                       # every enabled pipeline, once
+//! etl prices                                # just `prices`
+//! etl prices --source backfill/2024.json    # `prices`, from another file or URL
+//! etl prices --sink prices.json             # `prices`, written to a JSON file instead
+//! etl prices --dry-run                      # `prices`' output, printed rather than loaded
+//! etl --config runner.json                  # with a runner config; see `config`
+//! ```
+//!
+//! Reports & dry-run outputs are printed to stdout as JSON; errors to stderr, with a failing exit
+//! code. Only plain runs of a pipeline are recorded in its status & history; runs with another
+//! source or sink, and dry runs, aren't its real runs.
+
+use super::error::{Context, Errors};
+use super::sink::{self, Sink};
+use super::{Error, EtlReport, Runner};
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use serde::Serialize;
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// The arguments [`run_cli()`] takes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Args {
+    /// The pipeline to run, by its name in the runner; every enabled pipeline if `None`.
+    pub pipeline: Option<String>,
+    /// The file path or URL to extract from, rather than the pipeline's source.
+    pub source: Option<String>,
+    /// The JSON file to write the output to, rather than the pipeline's sink.
+    pub sink: Option<PathBuf>,
+    /// Print the output, rather than loading it.
+    pub dry_run: bool,
+    /// The runner config; see [`Runner::config()`].
+    pub config: Option<PathBuf>,
+}
+
+impl Args {
+    /// Parse `args`, the first being the binary's name, as from [`std::env::args_os()`].
+    ///
+    /// Returns [`Error::Config`] on invalid arguments; e.g., `--source` without a pipeline.
+    pub fn parse_from<I, T>(args: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let matches = command()
+            .try_get_matches_from(args)
+            .map_err(|e| Error::Config(e.to_string()))?;
+        Ok(Self::from_matches(&matches))
+    }
+
+    fn from_matches(matches: &ArgMatches) -> Self {
+        Args {
+            pipeline: matches.get_one::<String>("pipeline").cloned(),
+            source: matches.get_one::<String>("source").cloned(),
+            sink: matches.get_one::<PathBuf>("sink").cloned(),
+            dry_run: matches.get_flag("dry-run"),
+            config: matches.get_one::<PathBuf>("config").cloned(),
+        }
+    }
+}
+
+fn command() -> Command {
+    Command::new("etl")
+        .about("Run the pipelines of an ETL runner once")
+        .arg(Arg::new("pipeline").help("The pipeline to run; every enabled pipeline if omitted"))
+        .arg(
+            Arg::new("source")
+                .long("source")
+                .value_name("PATH")
+                .requires("pipeline")
+                .help("Extract from this file or URL, rather than the pipeline's source"),
+        )
+        .arg(
+            Arg::new("sink")
+                .long("sink")
+                .value_name("FILE")
+                .value_parser(clap::value_parser!(PathBuf))
+                .requires("pipeline")
+                .help("Write the output to this JSON file, rather than the pipeline's sink"),
+        )
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .action(ArgAction::SetTrue)
+                .requires("pipeline")
+                .conflicts_with("sink")
+                .help("Print the output, rather than loading it"),
+        )
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .value_name("FILE")
+                .value_parser(clap::value_parser!(PathBuf))
+                .help("The runner config to apply"),
+        )
+}
+
+/// Parse the process' arguments, then [`execute()`] them on `runner`; printing any errors, and
+/// returning the exit code for `main()` to return.
+///
+/// `--help` prints the usage, and exits straight away.
+pub async fn run_cli(runner: Runner) -> ExitCode {
+    let args = Args::from_matches(&command().get_matches());
+    match execute(runner, &args, &mut std::io::stdout()).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(errors) => {
+            for failure in &errors {
+                match failure.context {
+                    Context::None => eprintln!("error: {}", failure.error),
+                    ref context => eprintln!("error: {context}: {}", failure.error),
+                }
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Run `args` on `runner`, printing the report (or, for a dry run, the output) to `out`.
+///
+/// Every failure is returned; tagged with its pipeline, when every pipeline is run.
+pub async fn execute(mut runner: Runner, args: &Args, out: &mut impl Write) -> Result<(), Errors> {
+    if let Some(config) = &args.config {
+        runner = runner.config(config);
+    }
+    let Some(name) = &args.pipeline else {
+        return runner.run_once().await;
+    };
+    execute_one(&runner, name, args, out)
+        .await
+        .map_err(|error| {
+            let mut errors = Errors::new();
+            errors.push(Context::Pipeline(name.clone()), error);
+            errors
+        })
+}
+
+async fn execute_one(
+    runner: &Runner,
+    name: &str,
+    args: &Args,
+    out: &mut impl Write,
+) -> Result<(), Error> {
+    let pipeline = || {
+        runner
+            .pipeline(name)
+            .ok_or_else(|| Error::Config(format!("no pipeline named `{name}`")))
+    };
+    let source = args.source.as_deref();
+    match (&args.sink, source) {
+        _ if args.dry_run => print(out, &pipeline()?.preview(source).await?),
+        (Some(path), _) => {
+            let output = pipeline()?.preview(source).await?;
+            let outcome = sink::File::new(path).load(&output).await?;
+            let report = EtlReport {
+                outcomes: vec![outcome],
+                ..EtlReport::new()
+            };
+            print(out, &report)
+        }
+        (None, Some(source)) => print(out, &pipeline()?.run_from(source).await?),
+        (None, None) => print(out, &runner.run_named(name).await?),
+    }
+}
+
+fn print(out: &mut impl Write, value: &impl Serialize) -> Result<(), Error> {
+    serde_json::to_writer_pretty(&mut *out, value)?;
+    writeln!(out)?;
+    Ok(())
+}
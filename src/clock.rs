@@ -0,0 +1,137 @@
+use chrono::{DateTime, Utc};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Where the crate gets the current time, and new run ids, from.
+///
+/// Everything that stamps its output (archives, audit records, HTTP idempotency keys) takes a
+/// clock; swap the [`System`] clock for a [`Fixed`] one to make those outputs deterministic,
+/// e.g., for snapshot tests.
+pub trait Clock: Send + Sync {
+    /// The current time, in UTC.
+    fn now(&self) -> DateTime<Utc>;
+
+    /// A new, unique id, e.g., for a run.
+    fn uuid(&self) -> Uuid;
+}
+
+/// The real time, and random (v4) ids.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct System;
+
+impl Clock for System {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn uuid(&self) -> Uuid {
+        Uuid::new_v4()
+    }
+}
+
+/// The system clock, shared; the default wherever a clock can be configured.
+pub fn system() -> Arc<dyn Clock> {
+    Arc::new(System)
+}
+
+tokio::task_local! {
+    // the clock of the pipe whose stage is running
+    static CURRENT: Arc<dyn Clock>;
+}
+
+/// The clock of the pipe whose stage is running, e.g., for a sink to date a response against;
+/// outside of one, the [`System`] clock.
+pub fn current() -> Arc<dyn Clock> {
+    CURRENT.try_with(Arc::clone).unwrap_or_else(|_| system())
+}
+
+// Run `fut` (a pipe's stage) with `clock` as the current one; see `current()`.
+pub(crate) async fn scoped<T>(clock: Arc<dyn Clock>, fut: impl Future<Output = T>) -> T {
+    CURRENT.scope(clock, fut).await
+}
+
+tokio::task_local! {
+    // the id of the run in progress
+    static RUN: String;
+}
+
+/// The id of the pipe run in progress, `run-<uuid>`, e.g., for a sink to key its writes by;
+/// outside of one, `None`.
+///
+/// It's the one id of the run throughout: its [archive](crate::archive) & [dump](crate::dump)
+/// are named by it, an [`Http`](crate::sink::Http) sink's idempotency keys derive from it, and a
+/// [`Runner`](crate::Runner)'s history records it.
+pub fn run_id() -> Option<String> {
+    RUN.try_with(String::clone).ok()
+}
+
+// A new run id, from `clock`.
+pub(crate) fn new_run_id(clock: &dyn Clock) -> String {
+    format!("run-{}", clock.uuid())
+}
+
+// Run `run` (a pipe's) as the run `id`; unless it's within a run already, e.g., a runner's, whose
+// id it keeps.
+pub(crate) async fn identified<T>(id: impl FnOnce() -> String, run: impl Future<Output = T>) -> T {
+    match run_id() {
+        Some(_) => run.await,
+        None => RUN.scope(id(), run).await,
+    }
+}
+
+/// A clock for tests: the time only moves when told to, and ids count up from 1.
+///
+/// ```rust,ignore
+/// let clock = Arc::new(Fixed::new("2024-06-01T00:00:00Z".parse()?).step(Duration::from_secs(1)));
+/// let pipe = Pipe::<I, O>::builder().clock(clock.clone()).archive(Archive::new(dir)).build()?;
+/// ```
+#[derive(Debug)]
+pub struct Fixed {
+    now: Mutex<DateTime<Utc>>,
+    step: Duration,
+    ids: AtomicU64,
+}
+
+impl Fixed {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Fixed {
+            now: Mutex::new(now),
+            step: Duration::ZERO,
+            ids: AtomicU64::new(0),
+        }
+    }
+
+    /// Move the time on by `step` after every reading of it. Defaults to zero.
+    pub fn step(mut self, step: Duration) -> Self {
+        self.step = step;
+        self
+    }
+
+    /// Move the time on by `by`.
+    pub fn advance(&self, by: Duration) {
+        let mut now = self
+            .now
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        *now += by;
+    }
+}
+
+impl Clock for Fixed {
+    fn now(&self) -> DateTime<Utc> {
+        let mut now = self
+            .now
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let reading = *now;
+        *now += self.step;
+        reading
+    }
+
+    fn uuid(&self) -> Uuid {
+        Uuid::from_u128(self.ids.fetch_add(1, Ordering::SeqCst) as u128 + 1)
+    }
+}
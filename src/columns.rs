@@ -0,0 +1,157 @@
+use super::Error;
+
+/// What to do with a `null` element of a column.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Nulls<T> {
+    /// Drop the whole row.
+    Skip,
+    /// Use the column's last non-null value; the row is dropped if there hasn't been one yet.
+    FillForward,
+    /// Use this value instead.
+    Default(T),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Values<'a, T> {
+    Sparse(&'a [Option<T>]),
+    Dense(&'a [T]),
+}
+
+/// One column of a table, with its null policy; see [`zip()`].
+#[derive(Debug, Clone)]
+pub struct Column<'a, T> {
+    values: Values<'a, T>,
+    nulls: Nulls<T>,
+    last: Option<T>,
+}
+
+impl<'a, T> Column<'a, T>
+where
+    T: Clone,
+{
+    /// A column that may contain nulls; rows with a null here are skipped, unless told otherwise.
+    pub fn new(values: &'a [Option<T>]) -> Self {
+        Column {
+            values: Values::Sparse(values),
+            nulls: Nulls::Skip,
+            last: None,
+        }
+    }
+
+    /// A column without nulls, e.g., timestamps.
+    pub fn dense(values: &'a [T]) -> Self {
+        Column {
+            values: Values::Dense(values),
+            nulls: Nulls::Skip,
+            last: None,
+        }
+    }
+
+    pub fn nulls(mut self, nulls: Nulls<T>) -> Self {
+        self.nulls = nulls;
+        self
+    }
+
+    /// Fill nulls with the last value before them; see [`Nulls::FillForward`].
+    pub fn fill_forward(self) -> Self {
+        self.nulls(Nulls::FillForward)
+    }
+
+    /// Replace nulls with `value`; see [`Nulls::Default`].
+    pub fn or(self, value: T) -> Self {
+        self.nulls(Nulls::Default(value))
+    }
+
+    pub fn len(&self) -> usize {
+        match self.values {
+            Values::Sparse(values) => values.len(),
+            Values::Dense(values) => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // The value of row `i` under the null policy, or `None` if the row is to be skipped.
+    fn resolve(&mut self, i: usize) -> Option<T> {
+        let value = match self.values {
+            Values::Sparse(values) => values[i].as_ref(),
+            Values::Dense(values) => Some(&values[i]),
+        };
+        match (value, &self.nulls) {
+            (Some(value), Nulls::FillForward) => {
+                self.last = Some(value.clone());
+                self.last.clone()
+            }
+            (Some(value), _) => Some(value.clone()),
+            (None, Nulls::Skip) => None,
+            (None, Nulls::FillForward) => self.last.clone(),
+            (None, Nulls::Default(value)) => Some(value.clone()),
+        }
+    }
+}
+
+/// A tuple of [`Column`]s that can be zipped into rows; implemented for 2 to 8 columns.
+pub trait Zip {
+    type Row;
+
+    fn zip(self) -> Result<Vec<Self::Row>, Error>;
+}
+
+/// Zip parallel columns into rows (tuples), applying each column's null policy.
+///
+/// For "column-oriented" JSON, like Yahoo Finance's, which sends a table as one array per field,
+/// where any element may be `null`: `{ "open": [866.0, null, 123.0], "volume": [100, 200, null], ... }`
+///
+/// ```rust,ignore
+/// let rows = columns::zip((
+///     Column::new(&quote.open).fill_forward(),
+///     Column::new(&quote.volume).or(0),
+///     Column::dense(&dates),
+/// ))?
+/// .into_iter()
+/// .map(|(open, volume, date)| PriceRow { open, volume, date })
+/// .collect::<Vec<_>>();
+/// ```
+///
+/// Returns [`Error::Verification`] if the columns aren't all the same length, rather than
+/// silently cutting the rows short.
+pub fn zip<Z: Zip>(columns: Z) -> Result<Vec<Z::Row>, Error> {
+    columns.zip()
+}
+
+macro_rules! zip_tuple {
+    ($($column:ident: $t:ident),+) => {
+        impl<'a, $($t: Clone),+> Zip for ($(Column<'a, $t>,)+) {
+            type Row = ($($t,)+);
+
+            fn zip(self) -> Result<Vec<Self::Row>, Error> {
+                let ($(mut $column,)+) = self;
+                let lens = [$($column.len()),+];
+                if lens.iter().any(|len| *len != lens[0]) {
+                    return Err(Error::Verification(format!(
+                        "columns have different lengths: {lens:?}"
+                    )));
+                }
+
+                let mut rows = Vec::with_capacity(lens[0]);
+                for i in 0..lens[0] {
+                    // every column is resolved, so that fill-forward values stay current on skipped rows
+                    if let ($(Some($column),)+) = ($($column.resolve(i),)+) {
+                        rows.push(($($column,)+));
+                    }
+                }
+                Ok(rows)
+            }
+        }
+    };
+}
+
+zip_tuple!(a: A, b: B);
+zip_tuple!(a: A, b: B, c: C);
+zip_tuple!(a: A, b: B, c: C, d: D);
+zip_tuple!(a: A, b: B, c: C, d: D, e: E);
+zip_tuple!(a: A, b: B, c: C, d: D, e: E, f: F);
+zip_tuple!(a: A, b: B, c: C, d: D, e: E, f: F, g: G);
+zip_tuple!(a: A, b: B, c: C, d: D, e: E, f: F, g: G, h: H);
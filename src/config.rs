@@ -0,0 +1,213 @@
+use super::error::{Context, Errors};
+use super::runner::Overlap;
+use super::{Error, Input, Output, PipeBuilder, RateLimit, RetryPolicy};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+/// The environment variable naming the profile to use; see [`Profiles::from_env()`].
+pub const PROFILE_VAR: &str = "PIPE_IO_PROFILE";
+
+/// Named configurations of one pipeline (e.g., `dev`, `staging` & `prod`), each layered over a
+/// shared default; so one pipeline definition can run against different endpoints, sinks & limits.
+///
+/// The configuration type `T` is the pipeline's own; profiles are JSON, like:
+///
+/// ```json
+/// {
+///     "default": { "endpoint": "https://example.com/prices.json", "limits": { "retries": 3 } },
+///     "profiles": {
+///         "dev": { "conn": "host=localhost user=postgres password=password" },
+///         "prod": { "conn": "host=db.internal user=etl", "limits": { "requests_per_second": 2 } }
+///     }
+/// }
+/// ```
+///
+/// ```rust,ignore
+/// #[derive(Deserialize)]
+/// struct Config {
+///     endpoint: String,
+///     conn: String,
+///     #[serde(default)]
+///     limits: Limits,
+/// }
+///
+/// let profiles = Profiles::from_file("prices.json")?;
+/// profiles.validate::<Config>()?;
+/// let config: Config = profiles.from_env()?; // e.g., PIPE_IO_PROFILE=prod
+/// let pipe = config
+///     .limits
+///     .apply(Pipe::<I, O>::builder())
+///     .source(Source::endpoint(config.endpoint))
+///     .sink(sink::Postgres::new(config.conn, "prices"))
+///     .build()?;
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Profiles {
+    default: Map<String, Value>,
+    profiles: BTreeMap<String, Map<String, Value>>,
+}
+
+impl Profiles {
+    /// Profiles from a JSON object, with an optional `default` object and a `profiles` object of them.
+    pub fn from_value(value: Value) -> Result<Self, Error> {
+        #[derive(Deserialize)]
+        struct Raw {
+            #[serde(default)]
+            default: Map<String, Value>,
+            profiles: BTreeMap<String, Map<String, Value>>,
+        }
+        let raw: Raw = serde_json::from_value(value)
+            .map_err(|e| Error::Config(format!("invalid profiles: {e}")))?;
+        Ok(Profiles {
+            default: raw.default,
+            profiles: raw.profiles,
+        })
+    }
+
+    /// Profiles from a JSON file; see [`from_value()`].
+    ///
+    /// [`from_value()`]: Profiles::from_value
+    pub fn from_file(path: &str) -> Result<Self, Error> {
+        let text = std::fs::read_to_string(path)?;
+        Self::from_value(serde_json::from_str(&text)?)
+    }
+
+    /// The names of the profiles, in order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.profiles.keys().map(String::as_str)
+    }
+
+    /// The configuration of profile `name`: its settings, merged over the defaults.
+    ///
+    /// Objects are merged key by key, at every depth; anything else in the profile replaces the default.
+    /// Returns [`Error::Config`] for an unknown profile, or one missing a setting `T` requires.
+    pub fn get<T: DeserializeOwned>(&self, name: &str) -> Result<T, Error> {
+        let profile = self
+            .profiles
+            .get(name)
+            .ok_or_else(|| Error::Config(format!("no profile named `{name}`")))?;
+        let mut config = Value::Object(self.default.clone());
+        merge(&mut config, Value::Object(profile.clone()));
+        serde_json::from_value(config)
+            .map_err(|e| Error::Config(format!("profile `{name}` is incomplete: {e}")))
+    }
+
+    /// The configuration of the profile named by the `PIPE_IO_PROFILE` environment variable.
+    pub fn from_env<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        let name = std::env::var(PROFILE_VAR)
+            .map_err(|_| Error::Config(format!("{PROFILE_VAR} is not set")))?;
+        self.get(&name)
+    }
+
+    /// Check that every profile is fully specified, i.e., that each one makes a `T`; before
+    /// deploying, say, rather than finding out when `prod` first runs.
+    pub fn validate<T: DeserializeOwned>(&self) -> Result<(), Errors> {
+        let mut errors = Errors::new();
+        for name in self.names() {
+            if let Err(e) = self.get::<T>(name) {
+                errors.push(Context::None, e);
+            }
+        }
+        errors.into_result()
+    }
+}
+
+// `overlay`'s objects merged into `base`'s, recursively
+fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                merge(base.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// The limits of a pipe, as configured per profile; all optional.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct Limits {
+    /// The most attempts per stage; see [`RetryPolicy`].
+    pub retries: Option<u32>,
+    /// See [`RateLimit::per_second()`].
+    pub requests_per_second: Option<u32>,
+    /// The timeout of each stage attempt, in seconds.
+    pub timeout_secs: Option<u64>,
+}
+
+impl Limits {
+    /// Configure `builder` with these limits.
+    pub fn apply<I, O>(&self, mut builder: PipeBuilder<I, O>) -> PipeBuilder<I, O>
+    where
+        I: Input,
+        O: Output,
+    {
+        if let Some(retries) = self.retries {
+            builder = builder.retry(RetryPolicy::new(retries));
+        }
+        if let Some(requests) = self.requests_per_second {
+            builder = builder.rate_limit(RateLimit::per_second(requests));
+        }
+        if let Some(timeout) = self.timeout_secs {
+            builder = builder.timeout(Duration::from_secs(timeout));
+        }
+        builder
+    }
+}
+
+/// The settings of a [`Runner`]'s pipelines, by name, that can change while it runs; see
+/// [`Runner::config()`]. Pipelines it doesn't name keep the settings they were added with.
+///
+/// ```json
+/// {
+///     "pipelines": {
+///         "prices": { "every_secs": 600, "requests_per_second": 2, "priority": 10 },
+///         "holidays": { "enabled": false, "overlap": "skip" }
+///     }
+/// }
+/// ```
+///
+/// [`Runner`]: crate::Runner
+/// [`Runner::config()`]: crate::Runner::config
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RunnerConfig {
+    #[serde(default)]
+    pub pipelines: BTreeMap<String, PipelineConfig>,
+}
+
+/// The settings of one pipeline of a [`RunnerConfig`]; all optional.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PipelineConfig {
+    /// Whether the pipeline runs at all; `true` if not set.
+    pub enabled: Option<bool>,
+    /// How often the pipeline runs, in seconds.
+    pub every_secs: Option<u64>,
+    /// See [`RateLimit::per_second()`]; the pipe must have been built with a rate limit, which it
+    /// goes back to if not set.
+    pub requests_per_second: Option<u32>,
+    /// See [`Runner::priority()`](crate::Runner::priority); over the runner's own.
+    pub priority: Option<i32>,
+    /// See [`Runner::overlap()`](crate::Runner::overlap); over the runner's own.
+    pub overlap: Option<Overlap>,
+}
+
+impl RunnerConfig {
+    /// A configuration from JSON text.
+    pub fn from_json(text: &str) -> Result<Self, Error> {
+        serde_json::from_str(text).map_err(|e| Error::Config(format!("invalid runner config: {e}")))
+    }
+
+    /// A configuration from a JSON file; see [`from_json()`].
+    ///
+    /// [`from_json()`]: RunnerConfig::from_json
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+}
@@ -0,0 +1,128 @@
+//! Counts of the stage attempts made by every pipe in the process, and the time spent in them;
+//! for benchmarks to attribute a regression to a stage, and tests to assert on the work done.
+//!
+//! ```rust,ignore
+//! counters::reset();
+//! pipe.run().await?;
+//! let counters = counters::snapshot();
+//! assert_eq!(counters.extract.completed, 1);
+//! assert!(counters.load.mean() < Duration::from_millis(10));
+//! ```
+//!
+//! The counters are global, so they also count the pipes of any other tests running at the same time.
+
+use super::observer::Stage;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// The attempts at one stage.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Counter {
+    pub completed: u64,
+    pub failed: u64,
+    /// The time spent in every attempt, completed or failed.
+    pub elapsed: Duration,
+}
+
+impl Counter {
+    /// Every attempt, completed or failed.
+    pub fn attempts(&self) -> u64 {
+        self.completed + self.failed
+    }
+
+    /// The mean time of an attempt.
+    pub fn mean(&self) -> Duration {
+        match self.attempts() {
+            0 => Duration::ZERO,
+            // `Duration / u32` would truncate the attempts past `u32::MAX`
+            attempts => {
+                let nanos = self.elapsed.as_nanos() / u128::from(attempts);
+                Duration::from_nanos(u64::try_from(nanos).unwrap_or(u64::MAX))
+            }
+        }
+    }
+}
+
+/// The counters of every stage, as of a [`snapshot()`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Counters {
+    pub extract: Counter,
+    pub transform: Counter,
+    pub enrich: Counter,
+    pub load: Counter,
+    /// Attempts retried, over every stage.
+    pub retries: u64,
+}
+
+struct Atomic {
+    completed: AtomicU64,
+    failed: AtomicU64,
+    nanos: AtomicU64,
+}
+
+impl Atomic {
+    const fn new() -> Self {
+        Atomic {
+            completed: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+            nanos: AtomicU64::new(0),
+        }
+    }
+
+    fn load(&self) -> Counter {
+        Counter {
+            completed: self.completed.load(Ordering::Relaxed),
+            failed: self.failed.load(Ordering::Relaxed),
+            elapsed: Duration::from_nanos(self.nanos.load(Ordering::Relaxed)),
+        }
+    }
+
+    fn reset(&self) {
+        self.completed.store(0, Ordering::Relaxed);
+        self.failed.store(0, Ordering::Relaxed);
+        self.nanos.store(0, Ordering::Relaxed);
+    }
+}
+
+static STAGES: [Atomic; 4] = [Atomic::new(), Atomic::new(), Atomic::new(), Atomic::new()];
+static RETRIES: AtomicU64 = AtomicU64::new(0);
+
+fn stage(stage: Stage) -> &'static Atomic {
+    match stage {
+        Stage::Extract => &STAGES[0],
+        Stage::Transform => &STAGES[1],
+        Stage::Enrich => &STAGES[2],
+        Stage::Load => &STAGES[3],
+    }
+}
+
+/// The counters as they are now.
+pub fn snapshot() -> Counters {
+    Counters {
+        extract: stage(Stage::Extract).load(),
+        transform: stage(Stage::Transform).load(),
+        enrich: stage(Stage::Enrich).load(),
+        load: stage(Stage::Load).load(),
+        retries: RETRIES.load(Ordering::Relaxed),
+    }
+}
+
+/// Zero every counter.
+pub fn reset() {
+    STAGES.iter().for_each(Atomic::reset);
+    RETRIES.store(0, Ordering::Relaxed);
+}
+
+pub(crate) fn attempted(of: Stage, ok: bool, elapsed: Duration) {
+    let counter = stage(of);
+    match ok {
+        true => counter.completed.fetch_add(1, Ordering::Relaxed),
+        false => counter.failed.fetch_add(1, Ordering::Relaxed),
+    };
+    let nanos = u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX);
+    counter.nanos.fetch_add(nanos, Ordering::Relaxed);
+}
+
+pub(crate) fn retried() {
+    RETRIES.fetch_add(1, Ordering::Relaxed);
+}
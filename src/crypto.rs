@@ -0,0 +1,194 @@
+//! Encryption of sensitive fields before they're loaded, so they never land in a store in
+//! plaintext; with AES-256-GCM, under a key given directly or fetched from a KMS.
+//!
+//! ```rust,ignore
+//! let sink = Encrypted::new(sink::Postgres::new(conn, "customers"), Key::kms(|| async {
+//!     kms.data_key("customers").await
+//! }))
+//! .field("email")?
+//! .field("address.street")?;
+//!
+//! // on read-back, with the same key
+//! let cipher = Cipher::new(&key);
+//! cipher.decrypt_fields(&mut row, &[Path::parse("email")?, Path::parse("address.street")?])?;
+//! ```
+//!
+//! Each field's JSON value is encrypted as a whole, with a fresh nonce, and replaced by a string
+//! `enc:v1:<base64 of nonce & ciphertext>`; so encrypted columns need a text type. Fields that are
+//! missing (or `null`) are left as they are.
+
+use super::dynamic::Path;
+use super::sink::Sink;
+use super::{Error, Outcome};
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use futures::future::BoxFuture;
+use serde::Serialize;
+use serde_json::Value;
+use std::future::Future;
+use std::sync::Arc;
+
+const PREFIX: &str = "enc:v1:";
+
+/// AES-256-GCM, under one 256-bit key.
+#[derive(Clone)]
+pub struct Cipher {
+    aes: Aes256Gcm,
+}
+
+impl std::fmt::Debug for Cipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Cipher { .. }")
+    }
+}
+
+impl Cipher {
+    pub fn new(key: &[u8; 32]) -> Self {
+        Cipher {
+            aes: Aes256Gcm::new(key.into()),
+        }
+    }
+
+    /// `value`, encrypted as `enc:v1:<base64>`.
+    pub fn encrypt(&self, value: &Value) -> Result<String, Error> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let plaintext = serde_json::to_vec(value)?;
+        let ciphertext = self
+            .aes
+            .encrypt(&nonce, plaintext.as_slice())
+            .map_err(|_| Error::Crypto("encryption failed".into()))?;
+        let mut bytes = nonce.to_vec();
+        bytes.extend(ciphertext);
+        Ok(format!("{PREFIX}{}", STANDARD.encode(bytes)))
+    }
+
+    /// The value encrypted by [`encrypt()`]; failing for a different key, or a tampered field.
+    ///
+    /// [`encrypt()`]: Cipher::encrypt
+    pub fn decrypt(&self, encrypted: &str) -> Result<Value, Error> {
+        let invalid = || Error::Crypto(format!("not an encrypted field: {encrypted:?}"));
+        let encoded = encrypted.strip_prefix(PREFIX).ok_or_else(invalid)?;
+        let bytes = STANDARD.decode(encoded).map_err(|_| invalid())?;
+        if bytes.len() < 12 {
+            return Err(invalid());
+        }
+        let (nonce, ciphertext) = bytes.split_at(12);
+        let plaintext = self
+            .aes
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| Error::Crypto("decryption failed; wrong key, or tampered data".into()))?;
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+
+    /// Encrypt each of `fields` of `record`, in place.
+    pub fn encrypt_fields(&self, record: &mut Value, fields: &[Path]) -> Result<(), Error> {
+        for field in fields {
+            match field.get(record) {
+                None | Some(Value::Null) => {}
+                Some(value) => {
+                    let encrypted = self.encrypt(value)?;
+                    field.set(record, Value::String(encrypted))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Decrypt each of `fields` of `record`, in place; e.g., of a row read back from the store.
+    pub fn decrypt_fields(&self, record: &mut Value, fields: &[Path]) -> Result<(), Error> {
+        for field in fields {
+            if let Some(Value::String(encrypted)) = field.get(record) {
+                let value = self.decrypt(encrypted)?;
+                field.set(record, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+type Kms = Arc<dyn Fn() -> BoxFuture<'static, Result<[u8; 32], Error>> + Send + Sync>;
+
+/// Where an [`Encrypted`] sink gets its key.
+#[derive(Clone)]
+pub enum Key {
+    Static(Box<Cipher>),
+    /// Fetched for every load, e.g., a data key from a KMS; so a rotated key takes effect on the next load.
+    Kms(Kms),
+}
+
+impl Key {
+    /// A fixed 256-bit key.
+    pub fn new(key: &[u8; 32]) -> Self {
+        Key::Static(Box::new(Cipher::new(key)))
+    }
+
+    /// A key fetched by `fetch`, before every load.
+    pub fn kms<F, Fut>(fetch: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<[u8; 32], Error>> + Send + 'static,
+    {
+        Key::Kms(Arc::new(move || Box::pin(fetch())))
+    }
+
+    /// The cipher to encrypt with now.
+    pub async fn cipher(&self) -> Result<Cipher, Error> {
+        match self {
+            Key::Static(cipher) => Ok((**cipher).clone()),
+            Key::Kms(fetch) => Ok(Cipher::new(&fetch().await?)),
+        }
+    }
+}
+
+/// Wraps a sink, encrypting configured fields of every record before it's loaded; see the
+/// [module docs](self).
+///
+/// Each element of an array output is a record of its own.
+pub struct Encrypted<S> {
+    sink: S,
+    key: Key,
+    fields: Vec<Path>,
+}
+
+impl<S> Encrypted<S> {
+    pub fn new(sink: S, key: Key) -> Self {
+        Encrypted {
+            sink,
+            key,
+            fields: vec![],
+        }
+    }
+
+    /// Encrypt the field at `path`, e.g., `email` or `address.street`.
+    pub fn field(mut self, path: &str) -> Result<Self, Error> {
+        self.fields.push(Path::parse(path)?);
+        Ok(self)
+    }
+
+    /// The fields encrypted, e.g., to decrypt them again with [`Cipher::decrypt_fields()`].
+    pub fn fields(&self) -> &[Path] {
+        &self.fields
+    }
+}
+
+impl<O, S> Sink<O> for Encrypted<S>
+where
+    O: Serialize + Sync + ?Sized,
+    S: Sink<Value>,
+{
+    async fn load(&self, output: &O) -> Result<Outcome, Error> {
+        let cipher = self.key.cipher().await?;
+        let mut output = serde_json::to_value(output)?;
+        match &mut output {
+            Value::Array(records) => {
+                for record in records {
+                    cipher.encrypt_fields(record, &self.fields)?;
+                }
+            }
+            record => cipher.encrypt_fields(record, &self.fields)?,
+        }
+        self.sink.load(&output).await
+    }
+}
@@ -0,0 +1,101 @@
+//! A status endpoint for a [`Runner`], so scheduled pipelines can be checked on without grepping
+//! their logs.
+//!
+//! - `GET /status` --- the [`Status`] of every pipeline, as JSON.
+//! - `GET /status/{name}` --- the status of one pipeline; 404 if there's none by that name.
+//! - `GET /` --- the same, as a minimal HTML page.
+//!
+//! ```rust,ignore
+//! let listener = tokio::net::TcpListener::bind("0.0.0.0:8080").await?;
+//! tokio::try_join!(runner.run(), runner.serve_dashboard(listener))?;
+//! ```
+
+use super::runner::{Runner, Status, Statuses};
+use super::Error;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::Html;
+use axum::routing::get;
+use axum::{Json, Router};
+use tokio::net::TcpListener;
+
+impl Runner {
+    /// The dashboard's routes, over this runner's statuses; e.g., to nest in an app of your own.
+    pub fn dashboard(&self) -> Router {
+        router(self.statuses())
+    }
+
+    /// Serve the dashboard on `listener`; only returns on an I/O error.
+    pub async fn serve_dashboard(&self, listener: TcpListener) -> Result<(), Error> {
+        axum::serve(listener, self.dashboard()).await?;
+        Ok(())
+    }
+}
+
+/// The dashboard's routes, over `statuses`.
+pub fn router(statuses: Statuses) -> Router {
+    Router::new()
+        .route("/", get(page))
+        .route("/status", get(all))
+        .route("/status/:name", get(one))
+        .with_state(statuses)
+}
+
+async fn all(State(statuses): State<Statuses>) -> Json<Vec<Status>> {
+    Json(statuses.snapshot())
+}
+
+async fn one(
+    State(statuses): State<Statuses>,
+    Path(name): Path<String>,
+) -> Result<Json<Status>, StatusCode> {
+    statuses.get(&name).map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn page(State(statuses): State<Statuses>) -> Html<String> {
+    let mut rows = String::new();
+    for status in statuses.snapshot() {
+        let (last_run, duration) = match &status.last_run {
+            Some(run) => (
+                format!(
+                    "{} ({})",
+                    run.at.to_rfc3339(),
+                    if run.ok { "ok" } else { "failed" }
+                ),
+                format!("{:?}", run.duration),
+            ),
+            None => ("never".into(), "-".into()),
+        };
+        let error = status
+            .recent_errors
+            .back()
+            .map_or(String::new(), |error| escape(&error.message));
+        rows.push_str(&format!(
+            "<tr><td>{}</td><td>{:?}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            escape(&status.name),
+            status.every,
+            if status.running { "running" } else { "idle" },
+            status.runs,
+            status.failures,
+            status.skipped,
+            status.delayed,
+            status.cancelled,
+            last_run,
+            duration,
+            error,
+        ));
+    }
+    Html(format!(
+        "<!DOCTYPE html><html><head><title>pipe-io</title></head><body><table>\
+         <tr><th>pipeline</th><th>every</th><th>state</th><th>runs</th><th>failures</th>\
+         <th>skipped</th><th>delayed</th><th>cancelled</th>\
+         <th>last run</th><th>duration</th><th>latest error</th></tr>{rows}</table></body></html>"
+    ))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
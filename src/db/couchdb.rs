@@ -1,3 +1,4 @@
+use crate::{retry, Error, WireLog};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 
@@ -7,67 +8,327 @@ struct CouchDocument {
     _rev: String,
 }
 
+/// A document written by [`upsert_doc()`].
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct CouchOutcome {
+    pub id: String,
+    /// The new Revision ID (_rev).
+    pub rev: String,
+    /// Whether the document was created, rather than updated.
+    #[serde(default)]
+    pub created: bool,
+}
+
 /// Deploys a [`reqwest Client`].
 ///
 /// [`reqwest Client`]: (https://docs.rs/reqwest/latest/reqwest/struct.Client.html)
 ///
 /// Initially, the client sends a GET request to the database and awaits the response.
-/// One of two responses will elicit further actions (any other response will panic):
+/// One of two responses will elicit further actions (any other response is an error):
 ///
 /// - `Status Code: OK`; the file alreadys exists, so we update it by retrieving the Revision ID (_rev) and PUTing
-/// the file up with this new ID.
+///   the file up with this new ID.
 /// - Status: NOT_FOUND; the file does not exist, so we then PUT the document with an empty Revision ID.
 ///
 /// See the [`CouchDB Documentation`]  for more details.
 ///
 /// [`CouchDB Documentation`]: (https://docs.couchdb.org/en/stable/intro/index.html)
-pub async fn insert_doc<T>(data: &T, conn: &str, doc_id: &str)
+pub async fn insert_doc<T>(data: &T, conn: &str, doc_id: &str) -> Result<(), Error>
+where
+    T: serde::Serialize + serde::de::DeserializeOwned,
+{
+    insert_doc_logged(data, conn, doc_id, None).await
+}
+
+/// [`insert_doc()`], logging each request & response with `wire_log`.
+///
+/// A write that conflicts with a concurrent one is retried a few times; see [`upsert_doc()`].
+pub async fn insert_doc_logged<T>(
+    data: &T,
+    conn: &str,
+    doc_id: &str,
+    wire_log: Option<&WireLog>,
+) -> Result<(), Error>
 where
     T: serde::Serialize + serde::de::DeserializeOwned,
 {
-    // check if the document already exists with a GET request
-    let conn = format!("{conn}/{doc_id}");
+    upsert_doc(data, conn, doc_id, 3, wire_log).await?;
+    Ok(())
+}
+
+/// Creates or updates a document, PUTing it with its current Revision ID (_rev), if it has one.
+///
+/// If another writer updates the document in between, CouchDB rejects the PUT with `409 Conflict`;
+/// the latest revision is then fetched again, and the PUT retried, up to `retries` times, before
+/// giving up with [`Error::Conflict`]. So a concurrent update is never silently overwritten with
+/// a stale revision, nor lost.
+///
+/// Returns the document's id & new revision, as CouchDB acknowledged them.
+pub async fn upsert_doc<T>(
+    data: &T,
+    conn: &str,
+    doc_id: &str,
+    retries: u32,
+    wire_log: Option<&WireLog>,
+) -> Result<CouchOutcome, Error>
+where
+    T: serde::Serialize + ?Sized,
+{
+    let url = format!("{conn}/{doc_id}");
     let client = reqwest::Client::new();
-    let response = client
-        .get(conn.clone())
+    let mut doc = json!(data);
+    for _ in 0..=retries {
+        // check if the document already exists with a GET request
+        let response = send(client.get(&url), wire_log).await?;
+        let created = match response.status() {
+            // "if the file already exists ...", PUT it up with the current Revision ID
+            reqwest::StatusCode::OK => {
+                let text = response.text().await?;
+                if let Some(wire_log) = wire_log {
+                    wire_log.body(&text);
+                }
+                let current: CouchDocument = serde_json::from_str(&text)?;
+                doc["_rev"] = json!(current._rev);
+                false
+            }
+            // "if the file does not exist ...", PUT it up without one
+            reqwest::StatusCode::NOT_FOUND => {
+                if let Some(doc) = doc.as_object_mut() {
+                    doc.remove("_rev");
+                }
+                true
+            }
+            _ => {
+                checked(response)?;
+                false
+            }
+        };
+
+        let response = send(client.put(&url).json(&doc), wire_log).await?;
+        if response.status() != reqwest::StatusCode::CONFLICT {
+            let text = checked(response)?.text().await?;
+            if let Some(wire_log) = wire_log {
+                wire_log.body(&text);
+            }
+            let outcome: CouchOutcome = serde_json::from_str(&text)?;
+            return Ok(CouchOutcome { created, ..outcome });
+        }
+    }
+    Err(Error::Conflict {
+        doc_id: doc_id.to_string(),
+        attempts: retries + 1,
+    })
+}
+
+/// How an output is laid out in CouchDB.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Layout {
+    /// One document, with [`upsert_doc()`].
+    #[default]
+    Single,
+    /// An array of records split across documents of up to `records` each, with a manifest
+    /// linking them; see [`upsert_chunked()`].
+    Chunked { records: usize },
+}
+
+/// The manifest of a document split into chunks by [`upsert_chunked()`].
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    /// The ids of the chunk documents, in order.
+    pub chunks: Vec<String>,
+    /// The records over every chunk.
+    pub records: usize,
+}
+
+/// The id of chunk `index` (from 0) of `doc_id`, e.g., `prices-0001` for the first.
+pub fn chunk_id(doc_id: &str, index: usize) -> String {
+    format!("{doc_id}-{:04}", index + 1)
+}
+
+/// Splits an array of records across documents of up to `size` records each, as
+/// `{ "records": [...] }`, with a [`Manifest`] document at `doc_id` listing them; so no single
+/// document grows past CouchDB's practical size limits (a few MB), however long the output is.
+///
+/// Each chunk is written with [`upsert_doc()`], then the manifest last; so readers following the
+/// manifest (e.g., with [`read_chunked()`]) never see chunks that haven't been written yet.
+/// Chunks left over from a previous, longer load are then deleted.
+///
+/// Returns the chunks written, then the manifest.
+pub async fn upsert_chunked<T>(
+    data: &T,
+    conn: &str,
+    doc_id: &str,
+    size: usize,
+    retries: u32,
+    wire_log: Option<&WireLog>,
+) -> Result<Vec<CouchOutcome>, Error>
+where
+    T: serde::Serialize + ?Sized,
+{
+    let records = match json!(data) {
+        serde_json::Value::Array(records) => records,
+        _ => {
+            return Err(Error::Config(format!(
+                "cannot split `{doc_id}` into chunks; it isn't an array of records"
+            )))
+        }
+    };
+    let previous = previous_chunks(conn, doc_id, wire_log).await?;
+
+    let mut outcomes = vec![];
+    let mut manifest = Manifest {
+        chunks: vec![],
+        records: records.len(),
+    };
+    for (i, chunk) in records.chunks(size.max(1)).enumerate() {
+        let id = chunk_id(doc_id, i);
+        let doc = json!({ "records": chunk });
+        outcomes.push(upsert_doc(&doc, conn, &id, retries, wire_log).await?);
+        manifest.chunks.push(id);
+    }
+    outcomes.push(upsert_doc(&manifest, conn, doc_id, retries, wire_log).await?);
+
+    for stale in previous.iter().filter(|id| !manifest.chunks.contains(id)) {
+        delete_doc(conn, stale).await?;
+    }
+    Ok(outcomes)
+}
+
+// the chunks of the document at `doc_id`, if it's a manifest
+async fn previous_chunks(
+    conn: &str,
+    doc_id: &str,
+    wire_log: Option<&WireLog>,
+) -> Result<Vec<String>, Error> {
+    let response = send(
+        reqwest::Client::new().get(format!("{conn}/{doc_id}")),
+        wire_log,
+    )
+    .await?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(vec![]);
+    }
+    let text = checked(response)?.text().await?;
+    if let Some(wire_log) = wire_log {
+        wire_log.body(&text);
+    }
+    Ok(serde_json::from_str::<Manifest>(&text)
+        .map(|manifest| manifest.chunks)
+        .unwrap_or_default())
+}
+
+/// Reads back the records of a document written by [`upsert_chunked()`], in order.
+pub async fn read_chunked(conn: &str, doc_id: &str) -> Result<Vec<serde_json::Value>, Error> {
+    #[derive(Deserialize)]
+    struct Chunk {
+        records: Vec<serde_json::Value>,
+    }
+
+    let manifest: Manifest = reqwest::Client::new()
+        .get(format!("{conn}/{doc_id}"))
         .send()
-        .await
-        .expect("failed to retrieve GET response");
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let mut records = Vec::with_capacity(manifest.records);
+    for id in &manifest.chunks {
+        let chunk: Chunk = reqwest::Client::new()
+            .get(format!("{conn}/{id}"))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        records.extend(chunk.records);
+    }
+    Ok(records)
+}
 
+// send a request, logging it and its response when a `wire_log` is given
+async fn send(
+    request: reqwest::RequestBuilder,
+    wire_log: Option<&WireLog>,
+) -> reqwest::Result<reqwest::Response> {
+    let (client, request) = request.build_split();
+    let request = request?;
+    if let Some(wire_log) = wire_log {
+        wire_log.request(&request);
+    }
+    let response = client.execute(request).await?;
+    if let Some(wire_log) = wire_log {
+        wire_log.response(&response);
+    }
+    Ok(response)
+}
+
+// `response`, unless it failed; `429 Too Many Requests` (or `503` with a `Retry-After`) is
+// CouchDB pushing back, retried after the delay it asks for
+fn checked(response: reqwest::Response) -> Result<reqwest::Response, Error> {
+    let status = response.status();
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(retry::retry_after);
+    let pushed_back = status == reqwest::StatusCode::TOO_MANY_REQUESTS
+        || (status == reqwest::StatusCode::SERVICE_UNAVAILABLE && retry_after.is_some());
+    if pushed_back {
+        return Err(Error::Backpressure {
+            message: format!("CouchDB answered {status} for {}", response.url()),
+            retry_after,
+        });
+    }
+    Ok(response.error_for_status()?)
+}
+
+/// Retrieves the current Revision ID (_rev) of a document, or `None` if it doesn't exist.
+pub async fn get_rev(conn: &str, doc_id: &str) -> Result<Option<String>, Error> {
+    let response = reqwest::Client::new()
+        .get(format!("{conn}/{doc_id}"))
+        .send()
+        .await?;
     match response.status() {
-        // "if the file already exists ..."
-        reqwest::StatusCode::OK => {
-            // retrieve current Revision ID
-            let text = response
-                .text()
-                .await
-                .expect("failed to translate response to text");
-            let current_file: CouchDocument = serde_json::from_str(&text)
-                .expect("failed to read current revision to serde schema");
-
-            // PUT the file up with current Revision ID
-            let mut updated_file = json!(data);
-            updated_file["_rev"] = json!(current_file._rev);
-            let _second_response = client
-                .put(conn)
-                .json(&updated_file)
-                .send()
-                .await
-                .expect("failed to retrieve PUT response");
+        reqwest::StatusCode::NOT_FOUND => Ok(None),
+        _ => {
+            let doc: CouchDocument = checked(response)?.json().await?;
+            Ok(Some(doc._rev))
         }
+    }
+}
 
-        // "if the file does not exist ..."
-        reqwest::StatusCode::NOT_FOUND => {
-            // PUT the new file up, requiring no Revision ID (where we use an empty string)
-            let new_file = json!(data);
-            let _second_response = client
-                .put(conn)
-                .json(&new_file)
-                .send()
-                .await
-                .expect("failed to retrieve PUT response");
-        }
+/// Copies document `from` over document `to` (creating `to` if it doesn't exist), with CouchDB's `COPY` method.
+///
+/// The copy is a single write, so readers of `to` see either the old or the new document, never a mix.
+/// Returns `to`'s new revision.
+pub async fn copy_doc(conn: &str, from: &str, to: &str) -> Result<CouchOutcome, Error> {
+    let existing = get_rev(conn, to).await?;
+    let destination = match &existing {
+        Some(rev) => format!("{to}?rev={rev}"),
+        None => to.to_string(),
+    };
+    let copy = reqwest::Method::from_bytes(b"COPY")
+        .map_err(|e| Error::Invariant(format!("the COPY method: {e}")))?;
+    let response = reqwest::Client::new()
+        .request(copy, format!("{conn}/{from}"))
+        .header("Destination", destination)
+        .send()
+        .await?;
+    let outcome: CouchOutcome = serde_json::from_str(&checked(response)?.text().await?)?;
+    Ok(CouchOutcome {
+        created: existing.is_none(),
+        ..outcome
+    })
+}
 
-        _ => eprintln!("unexpected status code found - expected `OK` or `NOT_FOUND`"),
+/// Deletes a document, if it exists.
+pub async fn delete_doc(conn: &str, doc_id: &str) -> Result<(), Error> {
+    if let Some(rev) = get_rev(conn, doc_id).await? {
+        let response = reqwest::Client::new()
+            .delete(format!("{conn}/{doc_id}?rev={rev}"))
+            .send()
+            .await?;
+        checked(response)?;
     }
+    Ok(())
 }
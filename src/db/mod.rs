@@ -1,3 +1,4 @@
 pub mod couchdb;
 pub mod postgresql;
 pub mod scylladb;
+pub mod types;
@@ -1,7 +1,831 @@
-// pub async fn insert_doc<D, T>(data: &D, conn: &str, target: T)
-// where
-//     D: serde::Serialize + serde::de::DeserializeOwned,
-//     T: diesel::Table,
-// {
-//     
-// }
+use super::types::{Conversions, Kind};
+use crate::Error;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// What to do when an inserted row collides with an existing row on the natural key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnConflict {
+    /// `ON CONFLICT (key) DO NOTHING`; keep the existing row.
+    Nothing,
+    /// `ON CONFLICT (key) DO UPDATE SET ...`; overwrite every non-key column with the new values.
+    Update,
+}
+
+/// A natural key (one or more columns with a unique constraint), and the action to take on collision.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub key: Vec<String>,
+    pub action: OnConflict,
+}
+
+impl Conflict {
+    pub fn new<K>(key: K, action: OnConflict) -> Self
+    where
+        K: IntoIterator,
+        K::Item: Into<String>,
+    {
+        Conflict {
+            key: key.into_iter().map(Into::into).collect(),
+            action,
+        }
+    }
+}
+
+/// What to do when the output's fields no longer match the table's columns; see [`reconcile()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnDrift {
+    /// Fail the load with [`Error::Drift`], before anything is inserted.
+    Fail,
+    /// `ALTER TABLE ... ADD COLUMN` for every new field, as a nullable column.
+    AddColumns,
+    /// Insert only the fields the table has columns for.
+    Ignore,
+}
+
+/// How an output differs from the table it's loaded to; see [`drift()`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Drift {
+    pub table: String,
+    /// Fields without a column.
+    pub added: Vec<String>,
+    /// Fields with values the column's type can't hold.
+    pub changed: Vec<Change>,
+}
+
+/// A field whose values no longer fit its column; e.g., a whole number that became a decimal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub column: String,
+    /// The column's type, as named by `information_schema`, e.g., `bigint`.
+    pub column_type: String,
+    pub kind: Kind,
+}
+
+impl Drift {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl std::fmt::Display for Drift {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "`{}`", self.table)?;
+        if !self.added.is_empty() {
+            write!(f, "; new fields: {}", self.added.join(", "))?;
+        }
+        for change in &self.changed {
+            write!(
+                f,
+                "; `{}` is {}, but has {:?} values",
+                change.column, change.column_type, change.kind
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// What loading an output would write to a table, estimated before it's loaded; see [`estimate()`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Estimate {
+    pub table: String,
+    /// Rows to insert (or update).
+    pub rows: u64,
+    /// The size of the rows, as the JSON sent to the server.
+    pub bytes: u64,
+    /// The table's rows beforehand, by the planner's statistics; 0 for a new (or unanalyzed) table.
+    pub existing_rows: u64,
+    /// The indexes on the table, each written to for every row.
+    pub indexes: u64,
+}
+
+impl Estimate {
+    /// The index entries the load writes, a row per index; a rough measure of its write cost
+    /// beyond the rows themselves.
+    pub fn index_entries(&self) -> u64 {
+        self.rows.saturating_mul(self.indexes)
+    }
+}
+
+impl std::fmt::Display for Estimate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} rows ({} bytes) into `{}` of ~{} rows, through {} indexes",
+            self.rows, self.bytes, self.table, self.existing_rows, self.indexes
+        )
+    }
+}
+
+/// Estimates what inserting `data` into `table` would write, without writing anything.
+///
+/// The table's size & indexes are read from the catalog (`pg_class` & `pg_index`) rather than
+/// counted, so it's cheap even for a large table; a table that doesn't exist yet has neither.
+pub async fn estimate<T>(data: &T, conn: &str, table: &str) -> Result<Estimate, Error>
+where
+    T: serde::Serialize + ?Sized,
+{
+    let rows = rows(data)?;
+    let bytes = serde_json::to_vec(&rows)?.len() as u64;
+    let client = pooled(conn).await?;
+    let row = client
+        .query_one(
+            "SELECT greatest(coalesce(max(c.reltuples), 0), 0)::BIGINT, count(i.indexrelid) \
+             FROM pg_class c LEFT JOIN pg_index i ON i.indrelid = c.oid \
+             WHERE c.oid = to_regclass($1)",
+            &[&quote_table(table)],
+        )
+        .await?;
+    Ok(Estimate {
+        table: table.to_string(),
+        rows: rows.len() as u64,
+        bytes,
+        existing_rows: row.try_get::<_, i64>(0)? as u64,
+        indexes: row.try_get::<_, i64>(1)? as u64,
+    })
+}
+
+type Confirm = Arc<dyn Fn(&Estimate) -> bool + Send + Sync>;
+
+/// A guardrail for loads over a size, e.g., a full reload into a production table by mistake:
+/// each load is [estimated](estimate()) first, and one over any threshold is refused with
+/// [`Error::Preflight`], unless the [`confirm()`](Preflight::confirm) callback lets it through.
+///
+/// ```rust,ignore
+/// let sink = sink::Postgres::new(conn, "prices").preflight(
+///     Preflight::new()
+///         .max_rows(100_000)
+///         .confirm(|estimate| ask(&format!("load {estimate}?"))),
+/// );
+/// ```
+#[derive(Clone, Default)]
+pub struct Preflight {
+    pub max_rows: Option<u64>,
+    pub max_bytes: Option<u64>,
+    /// See [`Estimate::index_entries()`].
+    pub max_index_entries: Option<u64>,
+    confirm: Option<Confirm>,
+}
+
+impl Preflight {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_rows(mut self, rows: u64) -> Self {
+        self.max_rows = Some(rows);
+        self
+    }
+
+    pub fn max_bytes(mut self, bytes: u64) -> Self {
+        self.max_bytes = Some(bytes);
+        self
+    }
+
+    pub fn max_index_entries(mut self, entries: u64) -> Self {
+        self.max_index_entries = Some(entries);
+        self
+    }
+
+    /// Ask `confirm` whether a load over a threshold goes ahead, rather than refusing it; e.g., a
+    /// prompt, or a check for an approved change.
+    pub fn confirm<F>(mut self, confirm: F) -> Self
+    where
+        F: Fn(&Estimate) -> bool + Send + Sync + 'static,
+    {
+        self.confirm = Some(Arc::new(confirm));
+        self
+    }
+
+    /// The thresholds `estimate` is over, by name.
+    pub fn exceeded(&self, estimate: &Estimate) -> Vec<&'static str> {
+        [
+            ("rows", self.max_rows, estimate.rows),
+            ("bytes", self.max_bytes, estimate.bytes),
+            (
+                "index entries",
+                self.max_index_entries,
+                estimate.index_entries(),
+            ),
+        ]
+        .into_iter()
+        .filter(|(_, max, value)| max.is_some_and(|max| *value > max))
+        .map(|(name, _, _)| name)
+        .collect()
+    }
+
+    /// Whether a load of `estimate` goes ahead: if it's under every threshold, or confirmed (with a
+    /// warning, for the run's report); otherwise it fails with [`Error::Preflight`].
+    pub fn check(&self, estimate: Estimate) -> Result<(), Error> {
+        let exceeded = self.exceeded(&estimate);
+        if exceeded.is_empty() {
+            return Ok(());
+        }
+        match &self.confirm {
+            Some(confirm) if confirm(&estimate) => {
+                crate::warn(crate::Warning::other(format!(
+                    "{estimate}, over the preflight's {}, was confirmed",
+                    exceeded.join(" & ")
+                )));
+                Ok(())
+            }
+            _ => Err(Error::Preflight(estimate)),
+        }
+    }
+}
+
+impl std::fmt::Debug for Preflight {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Preflight")
+            .field("max_rows", &self.max_rows)
+            .field("max_bytes", &self.max_bytes)
+            .field("max_index_entries", &self.max_index_entries)
+            .field("confirm", &self.confirm.is_some())
+            .finish()
+    }
+}
+
+impl PartialEq for Preflight {
+    fn eq(&self, other: &Self) -> bool {
+        let confirm = match (&self.confirm, &other.confirm) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        };
+        confirm
+            && (self.max_rows, self.max_bytes, self.max_index_entries)
+                == (other.max_rows, other.max_bytes, other.max_index_entries)
+    }
+}
+
+/// Rows written by a load; see [`insert_doc()`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PgOutcome {
+    /// Rows inserted or updated; rows skipped with [`OnConflict::Nothing`] aren't counted.
+    pub rows_affected: u64,
+}
+
+/// Connects to PostgreSQL with a [`tokio-postgres`] connection string, e.g.,
+/// `host=localhost user=postgres password=password`.
+///
+/// [`tokio-postgres`]: (https://docs.rs/tokio-postgres/latest/tokio_postgres/config/struct.Config.html)
+pub async fn connect(conn: &str) -> Result<tokio_postgres::Client, Error> {
+    let (client, connection) = tokio_postgres::connect(conn, tokio_postgres::NoTls).await?;
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            eprintln!("postgres connection error: {e}");
+        }
+    });
+    Ok(client)
+}
+
+// the most idle connections kept, per database
+const MAX_IDLE: usize = 8;
+
+type Idle = HashMap<String, Vec<Arc<tokio_postgres::Client>>>;
+
+static IDLE: std::sync::OnceLock<std::sync::Mutex<Idle>> = std::sync::OnceLock::new();
+
+/// A connection to `conn` from the pool shared by every sink (& health check) of the process,
+/// connecting if there's no idle one; returned to the pool once dropped.
+///
+/// Closed connections, e.g., after the database restarted, are never handed out: they're dropped,
+/// and replaced by a new connection, so a sink reconnects on its next load.
+pub async fn pooled(conn: &str) -> Result<Pooled, Error> {
+    let idle = {
+        let mut pool = IDLE
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let clients = pool.entry(conn.to_string()).or_default();
+        clients.retain(|client| !client.is_closed());
+        clients.pop()
+    };
+    let client = match idle {
+        Some(client) => client,
+        None => Arc::new(connect(conn).await?),
+    };
+    Ok(Pooled {
+        conn: conn.to_string(),
+        client,
+    })
+}
+
+/// A pooled connection; see [`pooled()`].
+pub struct Pooled {
+    conn: String,
+    client: Arc<tokio_postgres::Client>,
+}
+
+impl std::ops::Deref for Pooled {
+    type Target = tokio_postgres::Client;
+
+    fn deref(&self) -> &Self::Target {
+        &self.client
+    }
+}
+
+impl Drop for Pooled {
+    fn drop(&mut self) {
+        if self.client.is_closed() {
+            return;
+        }
+        let mut pool = IDLE
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let clients = pool.entry(std::mem::take(&mut self.conn)).or_default();
+        if clients.len() < MAX_IDLE {
+            clients.push(self.client.clone());
+        }
+    }
+}
+
+/// Inserts `data` into `table`, returning the number of rows affected.
+///
+/// `data` must serialize to a JSON object (one row) or an array of objects (many rows); each object key
+/// is a column name. Rows are expanded server-side with `json_populate_recordset`, so the column types
+/// are taken from the table itself.
+///
+/// With a [`Conflict`], rows colliding on the natural key are either skipped or updated, which makes
+/// repeated loads of the same data idempotent. The key columns need a unique constraint (or index).
+pub async fn insert_doc<T>(
+    data: &T,
+    conn: &str,
+    table: &str,
+    conflict: Option<&Conflict>,
+) -> Result<u64, Error>
+where
+    T: serde::Serialize + ?Sized,
+{
+    insert_columns(data, conn, table, None, conflict).await
+}
+
+/// [`insert_doc()`], inserting only `columns` (e.g., from [`reconcile()`]) rather than every field.
+pub async fn insert_columns<T>(
+    data: &T,
+    conn: &str,
+    table: &str,
+    columns: Option<&[String]>,
+    conflict: Option<&Conflict>,
+) -> Result<u64, Error>
+where
+    T: serde::Serialize + ?Sized,
+{
+    let rows = rows(data)?;
+    if rows.is_empty() {
+        return Ok(0);
+    }
+
+    let columns = match columns {
+        Some(columns) => columns.to_vec(),
+        None => self::columns(&rows),
+    };
+    let client = pooled(conn).await?;
+    insert_rows(&client, &rows, table, &columns, conflict).await
+}
+
+// insert `columns` of `rows`, on `client`
+async fn insert_rows(
+    client: &tokio_postgres::Client,
+    rows: &[Value],
+    table: &str,
+    columns: &[String],
+    conflict: Option<&Conflict>,
+) -> Result<u64, Error> {
+    let query = insert_query(table, columns, conflict);
+    let json = serde_json::to_string(rows)?;
+    let affected = client.execute(&query, &[&json]).await?;
+    Ok(affected)
+}
+
+// `data` as rows: a JSON object is one row, an array of objects many
+fn rows<T>(data: &T) -> Result<Vec<Value>, Error>
+where
+    T: serde::Serialize + ?Sized,
+{
+    match serde_json::to_value(data)? {
+        Value::Array(rows) => Ok(rows),
+        row @ Value::Object(_) => Ok(vec![row]),
+        _ => Err(Error::Other(anyhow::anyhow!(
+            "postgres rows must serialize to a JSON object or an array of objects"
+        ))),
+    }
+}
+
+/// Creates `table`, unless it already exists, with a column for every key of `data`; the column
+/// types are inferred from the values with `types`.
+pub async fn create_table<T>(
+    data: &T,
+    conn: &str,
+    table: &str,
+    types: &Conversions,
+) -> Result<(), Error>
+where
+    T: serde::Serialize + ?Sized,
+{
+    let rows = rows(data)?;
+    if rows.is_empty() {
+        return Ok(());
+    }
+    let client = pooled(conn).await?;
+    client
+        .batch_execute(&create_table_query(table, &rows, types))
+        .await?;
+    Ok(())
+}
+
+/// Builds the `CREATE TABLE IF NOT EXISTS` statement used by [`create_table()`].
+pub fn create_table_query(table: &str, rows: &[Value], types: &Conversions) -> String {
+    let cols = types
+        .infer(rows)
+        .iter()
+        .map(|(column, sql)| format!("{} {sql}", quote_ident(column)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("CREATE TABLE IF NOT EXISTS {} ({cols})", quote_table(table))
+}
+
+/// Compares `data` with the columns of `table`, and deals with any [`Drift`] as told; returning
+/// the columns to insert, for [`insert_columns()`].
+///
+/// New columns get their types from `types`. A field whose values the column can't hold fails
+/// with [`Error::Drift`] whatever `on_drift` is, rather than with an opaque error from the insert.
+/// A table that doesn't exist yet has no drift.
+pub async fn reconcile<T>(
+    data: &T,
+    conn: &str,
+    table: &str,
+    on_drift: OnDrift,
+    types: &Conversions,
+) -> Result<Vec<String>, Error>
+where
+    T: serde::Serialize + ?Sized,
+{
+    let rows = rows(data)?;
+    let existing = table_columns(conn, table).await?;
+    let drift = drift(table, &rows, &existing);
+    if existing.is_empty() || drift.is_empty() {
+        return Ok(columns(&rows));
+    }
+    if !drift.changed.is_empty() || on_drift == OnDrift::Fail {
+        return Err(Error::Drift(drift));
+    }
+
+    match on_drift {
+        OnDrift::AddColumns => {
+            let client = pooled(conn).await?;
+            for column in &drift.added {
+                let values = rows.iter().filter_map(|row| row.get(column));
+                client
+                    .batch_execute(&format!(
+                        "ALTER TABLE {} ADD COLUMN IF NOT EXISTS {} {}",
+                        quote_table(table),
+                        quote_ident(column),
+                        types.column_type(column, values)
+                    ))
+                    .await?;
+            }
+            Ok(columns(&rows))
+        }
+        _ => Ok(columns(&rows)
+            .into_iter()
+            .filter(|column| !drift.added.contains(column))
+            .collect()),
+    }
+}
+
+/// The `(column, type)`s of `table`, in order; empty if there's no such table.
+pub async fn table_columns(conn: &str, table: &str) -> Result<Vec<(String, String)>, Error> {
+    let (schema, name) = match table.rsplit_once('.') {
+        Some((schema, name)) => (Some(schema), name),
+        None => (None, table),
+    };
+    let client = pooled(conn).await?;
+    let rows = client
+        .query(
+            "SELECT column_name::text, data_type::text FROM information_schema.columns \
+             WHERE table_schema = COALESCE($1::text, current_schema()) AND table_name = $2 \
+             ORDER BY ordinal_position",
+            &[&schema, &name],
+        )
+        .await?;
+    rows.iter()
+        .map(|row| Ok((row.try_get(0)?, row.try_get(1)?)))
+        .collect()
+}
+
+/// How `rows` differ from the `existing` `(column, type)`s of `table`.
+pub fn drift(table: &str, rows: &[Value], existing: &[(String, String)]) -> Drift {
+    let mut drift = Drift {
+        table: table.to_string(),
+        ..Drift::default()
+    };
+    for column in columns(rows) {
+        let Some((_, column_type)) = existing.iter().find(|(name, _)| *name == column) else {
+            drift.added.push(column);
+            continue;
+        };
+        let kinds = rows
+            .iter()
+            .filter_map(|row| row.get(&column))
+            .filter_map(Kind::of);
+        if let Some(kind) = kinds.into_iter().find(|kind| !holds(column_type, *kind)) {
+            drift.changed.push(Change {
+                column,
+                column_type: column_type.clone(),
+                kind,
+            });
+        }
+    }
+    drift
+}
+
+// Whether a column of `data_type` (as named by `information_schema`) can be populated from JSON
+// values of `kind`; types this doesn't know of are given the benefit of the doubt.
+fn holds(data_type: &str, kind: Kind) -> bool {
+    const INTEGERS: &[&str] = &["smallint", "integer", "bigint"];
+    const NUMBERS: &[&str] = &["numeric", "real", "double precision"];
+    match kind {
+        Kind::Bool => data_type == "boolean",
+        Kind::Integer => INTEGERS.contains(&data_type) || NUMBERS.contains(&data_type),
+        Kind::Float => NUMBERS.contains(&data_type),
+        // strings are parsed by the column's type, e.g., dates; but a number that became a
+        // string is more likely drift than a number in quotes
+        Kind::String => {
+            data_type != "boolean"
+                && !INTEGERS.contains(&data_type)
+                && !NUMBERS.contains(&data_type)
+        }
+        Kind::Array => matches!(data_type, "json" | "jsonb" | "ARRAY"),
+        Kind::Object => matches!(data_type, "json" | "jsonb"),
+    }
+}
+
+/// The union of the object keys over all `rows`, in order of first appearance.
+pub fn columns(rows: &[Value]) -> Vec<String> {
+    let mut columns: Vec<String> = vec![];
+    for row in rows.iter().filter_map(Value::as_object) {
+        for key in row.keys() {
+            if !columns.contains(key) {
+                columns.push(key.clone());
+            }
+        }
+    }
+    columns
+}
+
+/// Builds the `INSERT` statement used by [`insert_doc()`]; the rows are bound as JSON text to `$1`.
+pub fn insert_query(table: &str, columns: &[String], conflict: Option<&Conflict>) -> String {
+    let cols = columns
+        .iter()
+        .map(|c| quote_ident(c))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut query = format!(
+        "INSERT INTO {table} ({cols}) SELECT {cols} FROM json_populate_recordset(NULL::{table}, $1::text::json)",
+        table = quote_table(table),
+    );
+
+    if let Some(conflict) = conflict {
+        let key = conflict
+            .key
+            .iter()
+            .map(|c| quote_ident(c))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let updates = columns
+            .iter()
+            .filter(|c| !conflict.key.contains(c))
+            .map(|c| format!("{col} = EXCLUDED.{col}", col = quote_ident(c)))
+            .collect::<Vec<_>>();
+        match conflict.action {
+            // with nothing but key columns, there is nothing to update
+            OnConflict::Update if !updates.is_empty() => query.push_str(&format!(
+                " ON CONFLICT ({key}) DO UPDATE SET {}",
+                updates.join(", ")
+            )),
+            _ => query.push_str(&format!(" ON CONFLICT ({key}) DO NOTHING")),
+        }
+    }
+    query
+}
+
+/// Builds the statements of a dimension lookup (see [`Dimension`]), with the natural key bound to `$1`:
+/// the `SELECT` of its surrogate key, and the `INSERT` of a missing one, returning the new surrogate key
+/// (or nothing, if a concurrent load inserted it first).
+///
+/// [`Dimension`]: crate::enrich::Dimension
+pub fn dimension_queries(table: &str, natural: &str, surrogate: &str) -> (String, String) {
+    let (table, natural, surrogate) = (
+        quote_table(table),
+        quote_ident(natural),
+        quote_ident(surrogate),
+    );
+    (
+        format!("SELECT {surrogate}::BIGINT FROM {table} WHERE {natural} = $1"),
+        format!(
+            "INSERT INTO {table} ({natural}) VALUES ($1) ON CONFLICT ({natural}) DO NOTHING RETURNING {surrogate}::BIGINT"
+        ),
+    )
+}
+
+/// Double-quotes an identifier, escaping any embedded quotes.
+pub fn quote_ident(ident: &str) -> String {
+    format!("\"{}\"", ident.replace('"', "\"\""))
+}
+
+// `schema.table` is quoted part by part
+pub(crate) fn quote_table(table: &str) -> String {
+    table
+        .split('.')
+        .map(quote_ident)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Creates an empty (or emptied) copy of `table`'s definition, named `staging`.
+pub async fn create_like(conn: &str, table: &str, staging: &str) -> Result<(), Error> {
+    let client = pooled(conn).await?;
+    client
+        .batch_execute(&format!(
+            "DROP TABLE IF EXISTS {staging}; CREATE TABLE {staging} (LIKE {table} INCLUDING ALL)",
+            staging = quote_table(staging),
+            table = quote_table(table),
+        ))
+        .await?;
+    Ok(())
+}
+
+/// Counts the rows of `table`.
+pub async fn count_rows(conn: &str, table: &str) -> Result<u64, Error> {
+    let client = pooled(conn).await?;
+    let row = client
+        .query_one(&format!("SELECT count(*) FROM {}", quote_table(table)), &[])
+        .await?;
+    Ok(row.try_get::<_, i64>(0)? as u64)
+}
+
+/// Replaces `table` with `staging` in a single transaction, dropping the previous `table`.
+///
+/// Both tables must live in the same schema.
+pub async fn swap_table(conn: &str, table: &str, staging: &str) -> Result<(), Error> {
+    let client = connect(conn).await?;
+    let retired = format!("{}__retired", bare_name(table));
+    client
+        .batch_execute(&format!(
+            "BEGIN; \
+             ALTER TABLE {table} RENAME TO {retired}; \
+             ALTER TABLE {staging} RENAME TO {name}; \
+             DROP TABLE {old}; \
+             COMMIT",
+            table = quote_table(table),
+            retired = quote_ident(&retired),
+            staging = quote_table(staging),
+            name = quote_ident(bare_name(table)),
+            old = quote_table(&with_name(table, &retired)),
+        ))
+        .await?;
+    Ok(())
+}
+
+/// Drops `table`, if it exists.
+pub async fn drop_table(conn: &str, table: &str) -> Result<(), Error> {
+    let client = pooled(conn).await?;
+    client
+        .batch_execute(&format!("DROP TABLE IF EXISTS {}", quote_table(table)))
+        .await?;
+    Ok(())
+}
+
+// `schema.table` -> `table`
+fn bare_name(table: &str) -> &str {
+    table.rsplit('.').next().unwrap_or(table)
+}
+
+// `schema.table` -> `schema.name`
+fn with_name(table: &str, name: &str) -> String {
+    match table.rsplit_once('.') {
+        Some((schema, _)) => format!("{schema}.{name}"),
+        None => name.to_string(),
+    }
+}
+
+/// One transaction, on one connection, shared by several [`Postgres`] sinks; e.g., by the related
+/// pipelines of a [`Runner`], so their tables are committed together, or not at all. See
+/// [`Runner::run_in_transaction()`].
+///
+/// ```rust,ignore
+/// let transaction = Transaction::new(conn);
+/// let runner = Runner::new()
+///     .pipe("orders", orders(sink::Postgres::new(conn, "orders").transaction(&transaction)), day)
+///     .pipe("lines", lines(sink::Postgres::new(conn, "order_lines").transaction(&transaction)), day);
+///
+/// runner.run_in_transaction(&transaction).await?;
+/// ```
+///
+/// While the transaction is open, the sinks' rows are inserted through it. Only the inserts are:
+/// tables are created, and drift dealt with, on connections of their own, which commit straight
+/// away. While it isn't, the sinks load as usual.
+///
+/// Clones share the same transaction.
+///
+/// [`Postgres`]: crate::sink::Postgres
+/// [`Runner`]: crate::Runner
+/// [`Runner::run_in_transaction()`]: crate::Runner::run_in_transaction
+#[derive(Clone)]
+pub struct Transaction {
+    conn: String,
+    client: Arc<Mutex<Option<tokio_postgres::Client>>>,
+}
+
+impl Transaction {
+    /// A transaction on the database at `conn`; the sinks enlisted in it must load to the same.
+    pub fn new(conn: impl Into<String>) -> Self {
+        Transaction {
+            conn: conn.into(),
+            client: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn conn(&self) -> &str {
+        &self.conn
+    }
+
+    /// Connect, and open the transaction. Returns [`Error::Config`] if it's already open.
+    pub async fn begin(&self) -> Result<(), Error> {
+        let mut client = self.client.lock().await;
+        if client.is_some() {
+            return Err(Error::Config("the transaction is already open".into()));
+        }
+        let connected = connect(&self.conn).await?;
+        connected.batch_execute("BEGIN").await?;
+        *client = Some(connected);
+        Ok(())
+    }
+
+    /// Commit everything inserted since [`begin()`](Transaction::begin), and close the transaction.
+    pub async fn commit(&self) -> Result<(), Error> {
+        self.end("COMMIT").await
+    }
+
+    /// Discard everything inserted since [`begin()`](Transaction::begin), and close the transaction.
+    pub async fn rollback(&self) -> Result<(), Error> {
+        self.end("ROLLBACK").await
+    }
+
+    pub async fn is_open(&self) -> bool {
+        self.client.lock().await.is_some()
+    }
+
+    async fn end(&self, statement: &str) -> Result<(), Error> {
+        let client = self.client.lock().await.take();
+        match client {
+            Some(client) => Ok(client.batch_execute(statement).await?),
+            None => Err(Error::Config("the transaction isn't open".into())),
+        }
+    }
+
+    // Insert `columns` of `data` into `table` through the transaction, if it's open; `None` if not.
+    pub(crate) async fn insert<T>(
+        &self,
+        data: &T,
+        table: &str,
+        columns: Option<&[String]>,
+        conflict: Option<&Conflict>,
+    ) -> Result<Option<u64>, Error>
+    where
+        T: serde::Serialize + ?Sized,
+    {
+        let client = self.client.lock().await;
+        let Some(client) = client.as_ref() else {
+            return Ok(None);
+        };
+        let rows = rows(data)?;
+        if rows.is_empty() {
+            return Ok(Some(0));
+        }
+        let columns = match columns {
+            Some(columns) => columns.to_vec(),
+            None => self::columns(&rows),
+        };
+        let affected = insert_rows(client, &rows, table, &columns, conflict).await?;
+        Ok(Some(affected))
+    }
+}
+
+impl std::fmt::Debug for Transaction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Transaction")
+            .field("conn", &self.conn)
+            .finish_non_exhaustive()
+    }
+}
+
+// the same transaction, not merely one on the same database
+impl PartialEq for Transaction {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.client, &other.client)
+    }
+}
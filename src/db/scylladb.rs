@@ -1 +1,231 @@
+use super::postgresql::{quote_ident, quote_table};
+use super::types::Conversions;
+use crate::Error;
+use futures::{StreamExt, TryStreamExt};
+use scylla::batch::{Batch, BatchType};
+use scylla::frame::response::result::{ColumnType, CqlValue};
+use scylla::frame::value::{CqlDate, CqlTimestamp};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
 
+/// Builds a `CREATE TABLE IF NOT EXISTS` statement for `rows`, with a column for every object key;
+/// the column types are inferred from the values with `types` (see [`Conversions::cql()`]).
+///
+/// CQL tables need a primary key, so the `primary_key` columns must be among the keys of `rows`.
+pub fn create_table_query(
+    table: &str,
+    rows: &[Value],
+    types: &Conversions,
+    primary_key: &[&str],
+) -> String {
+    let cols = types
+        .infer(rows)
+        .iter()
+        .map(|(column, cql)| format!("{} {cql}", quote_ident(column)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let key = primary_key
+        .iter()
+        .map(|c| quote_ident(c))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "CREATE TABLE IF NOT EXISTS {} ({cols}, PRIMARY KEY ({key}))",
+        quote_table(table)
+    )
+}
+
+/// Connects to a ScyllaDB cluster through `nodes`, a comma-separated list of `host:port`s.
+pub async fn connect(nodes: &str) -> Result<scylla::Session, Error> {
+    let session = scylla::SessionBuilder::new()
+        .known_nodes(nodes.split(',').map(str::trim))
+        .build()
+        .await
+        .map_err(|e| Error::Other(e.into()))?;
+    Ok(session)
+}
+
+/// Groups `rows` by the values of their `partition_key` columns, keeping the order of first appearance.
+pub fn partition(rows: Vec<Value>, partition_key: &[&str]) -> Vec<Vec<Value>> {
+    let mut partitions: Vec<Vec<Value>> = vec![];
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for row in rows {
+        let key = partition_key
+            .iter()
+            .map(|column| row.get(column).unwrap_or(&Value::Null).to_string())
+            .collect::<Vec<_>>()
+            .join("\u{1f}");
+        match index.get(&key) {
+            Some(&i) => partitions[i].push(row),
+            None => {
+                index.insert(key, partitions.len());
+                partitions.push(vec![row]);
+            }
+        }
+    }
+    partitions
+}
+
+/// Builds the `INSERT` statement used by [`insert_batched()`], of `columns` (the partition key's
+/// first): the partition key columns are bound as themselves, so the driver can route each batch
+/// straight to the partition's replicas; every other column is bound as JSON, with `fromJson()`.
+pub fn insert_query(table: &str, columns: &[String], partition_key: &[&str]) -> String {
+    let names = columns
+        .iter()
+        .map(|column| quote_ident(column))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let markers = columns
+        .iter()
+        .map(|column| match partition_key.contains(&column.as_str()) {
+            true => "?",
+            false => "fromJson(?)",
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "INSERT INTO {} ({names}) VALUES ({markers})",
+        quote_table(table)
+    )
+}
+
+/// A partition key's `value`, bound as its column's `typ`: text, integers, floats, booleans,
+/// UUIDs, timestamps (RFC 3339, or milliseconds since the epoch) and dates (`YYYY-MM-DD`).
+pub fn cql_value(value: &Value, typ: &ColumnType) -> Result<CqlValue, Error> {
+    let unsupported = || {
+        Error::Config(format!(
+            "a partition key of type {typ:?} can't be bound from {value}"
+        ))
+    };
+    let int = || value.as_i64().ok_or_else(unsupported);
+    Ok(match (typ, value) {
+        (ColumnType::Ascii, Value::String(s)) => CqlValue::Ascii(s.clone()),
+        (ColumnType::Text, Value::String(s)) => CqlValue::Text(s.clone()),
+        (ColumnType::Boolean, Value::Bool(b)) => CqlValue::Boolean(*b),
+        (ColumnType::TinyInt, _) => {
+            CqlValue::TinyInt(int()?.try_into().map_err(|_| unsupported())?)
+        }
+        (ColumnType::SmallInt, _) => {
+            CqlValue::SmallInt(int()?.try_into().map_err(|_| unsupported())?)
+        }
+        (ColumnType::Int, _) => CqlValue::Int(int()?.try_into().map_err(|_| unsupported())?),
+        (ColumnType::BigInt, _) => CqlValue::BigInt(int()?),
+        (ColumnType::Float, Value::Number(n)) => {
+            CqlValue::Float(n.as_f64().ok_or_else(unsupported)? as f32)
+        }
+        (ColumnType::Double, Value::Number(n)) => {
+            CqlValue::Double(n.as_f64().ok_or_else(unsupported)?)
+        }
+        (ColumnType::Uuid, Value::String(s)) => {
+            CqlValue::Uuid(s.parse().map_err(|_| unsupported())?)
+        }
+        (ColumnType::Timeuuid, Value::String(s)) => {
+            CqlValue::Timeuuid(s.parse().map_err(|_| unsupported())?)
+        }
+        (ColumnType::Timestamp, Value::String(s)) => {
+            let at = chrono::DateTime::parse_from_rfc3339(s).map_err(|_| unsupported())?;
+            CqlValue::Timestamp(CqlTimestamp(at.timestamp_millis()))
+        }
+        (ColumnType::Timestamp, _) => CqlValue::Timestamp(CqlTimestamp(int()?)),
+        (ColumnType::Date, Value::String(s)) => {
+            let date =
+                chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").map_err(|_| unsupported())?;
+            // days since 2^31 days before the epoch
+            let days = date
+                .signed_duration_since(chrono::NaiveDate::default())
+                .num_days()
+                + (1 << 31);
+            CqlValue::Date(CqlDate(days.try_into().map_err(|_| unsupported())?))
+        }
+        _ => return Err(unsupported()),
+    })
+}
+
+/// Inserts `data` (a JSON object, or an array of objects) into `table`, returning the number of rows.
+///
+/// Rows are grouped by their partition key, and each partition's rows are inserted in unlogged
+/// batches of up to `batch_size`; so no batch ever spans partitions, which would make a single
+/// coordinator fan the writes out across the cluster. Each batch is sent to a replica of its
+/// partition (token-aware; see [`insert_query()`]), with up to `parallelism` in flight at once.
+///
+/// A column missing from some rows is written as `null` to those, as with `INSERT JSON`.
+pub async fn insert_batched<T>(
+    data: &T,
+    session: &scylla::Session,
+    table: &str,
+    partition_key: &[&str],
+    batch_size: usize,
+    parallelism: usize,
+) -> Result<u64, Error>
+where
+    T: serde::Serialize + ?Sized,
+{
+    let rows = match serde_json::to_value(data)? {
+        Value::Array(rows) => rows,
+        row => vec![row],
+    };
+    let count = rows.len() as u64;
+    if rows.is_empty() {
+        return Ok(0);
+    }
+    // the partition key, then every other column of any row
+    let others: BTreeSet<&String> = rows
+        .iter()
+        .filter_map(Value::as_object)
+        .flat_map(|row| row.keys())
+        .filter(|column| !partition_key.contains(&column.as_str()))
+        .collect();
+    let columns: Vec<String> = partition_key
+        .iter()
+        .map(|column| column.to_string())
+        .chain(others.into_iter().cloned())
+        .collect();
+    let insert = session
+        .prepare(insert_query(table, &columns, partition_key))
+        .await?;
+    let types: Vec<ColumnType> = insert
+        .get_prepared_metadata()
+        .col_specs
+        .iter()
+        .map(|spec| spec.typ.clone())
+        .collect();
+
+    let mut batches = vec![];
+    for partition in partition(rows, partition_key) {
+        for chunk in partition.chunks(batch_size.max(1)) {
+            let mut batch = Batch::new(BatchType::Unlogged);
+            let mut values = Vec::with_capacity(chunk.len());
+            for row in chunk {
+                batch.append_statement(insert.clone());
+                values.push(bind(row, &columns, partition_key.len(), &types)?);
+            }
+            batches.push((batch, values));
+        }
+    }
+
+    futures::stream::iter(batches)
+        .map(|(batch, values)| async move { session.batch(&batch, values).await })
+        .buffer_unordered(parallelism.max(1))
+        .try_collect::<Vec<_>>()
+        .await?;
+    Ok(count)
+}
+
+// the values of `row` for `columns`, of which the first `keys` are the partition key
+fn bind(
+    row: &Value,
+    columns: &[String],
+    keys: usize,
+    types: &[ColumnType],
+) -> Result<Vec<CqlValue>, Error> {
+    columns
+        .iter()
+        .zip(types)
+        .enumerate()
+        .map(|(i, (column, typ))| match (i < keys, row.get(column)) {
+            (true, Some(value)) if !value.is_null() => cql_value(value, typ),
+            (true, _) => Err(Error::Missing(column.clone())),
+            (false, value) => Ok(CqlValue::Text(value.unwrap_or(&Value::Null).to_string())),
+        })
+        .collect()
+}
@@ -0,0 +1,142 @@
+use super::postgresql;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// The kinds of JSON value a column's type is inferred from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Kind {
+    Bool,
+    Integer,
+    Float,
+    String,
+    Array,
+    Object,
+}
+
+impl Kind {
+    /// The kind of `value`, or `None` for `null`, which fits any column.
+    pub fn of(value: &Value) -> Option<Kind> {
+        match value {
+            Value::Null => None,
+            Value::Bool(_) => Some(Kind::Bool),
+            Value::Number(n) if n.is_i64() || n.is_u64() => Some(Kind::Integer),
+            Value::Number(_) => Some(Kind::Float),
+            Value::String(_) => Some(Kind::String),
+            Value::Array(_) => Some(Kind::Array),
+            Value::Object(_) => Some(Kind::Object),
+        }
+    }
+}
+
+/// A registry of the column types used for JSON values, when creating tables from an output.
+///
+/// Starts from a dialect's defaults, which can be overridden by kind of value, or for a
+/// single column; e.g., dates serialize to JSON strings, so are `TEXT` unless told otherwise:
+///
+/// ```rust,ignore
+/// let types = Conversions::postgres()
+///     .kind(Kind::Float, "NUMERIC(18,6)")
+///     .field("date", "TIMESTAMPTZ");
+/// let sink = sink::Postgres::new(conn, "prices").create_table(types);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conversions {
+    kinds: HashMap<Kind, String>,
+    fields: HashMap<String, String>,
+    // for columns that are only ever null, or mix incompatible kinds
+    fallback: String,
+}
+
+impl Conversions {
+    /// PostgreSQL's types: `BOOLEAN`, `BIGINT`, `DOUBLE PRECISION`, `TEXT` & `JSONB`.
+    pub fn postgres() -> Self {
+        Self::new(
+            [
+                (Kind::Bool, "BOOLEAN"),
+                (Kind::Integer, "BIGINT"),
+                (Kind::Float, "DOUBLE PRECISION"),
+                (Kind::String, "TEXT"),
+                (Kind::Array, "JSONB"),
+                (Kind::Object, "JSONB"),
+            ],
+            "JSONB",
+        )
+    }
+
+    /// CQL's (i.e., ScyllaDB's) types: `boolean`, `bigint`, `double` & `text`, with arrays &
+    /// objects stored as JSON `text`.
+    pub fn cql() -> Self {
+        Self::new(
+            [
+                (Kind::Bool, "boolean"),
+                (Kind::Integer, "bigint"),
+                (Kind::Float, "double"),
+                (Kind::String, "text"),
+                (Kind::Array, "text"),
+                (Kind::Object, "text"),
+            ],
+            "text",
+        )
+    }
+
+    fn new(kinds: [(Kind, &str); 6], fallback: &str) -> Self {
+        Conversions {
+            kinds: kinds
+                .into_iter()
+                .map(|(kind, sql)| (kind, sql.to_string()))
+                .collect(),
+            fields: HashMap::new(),
+            fallback: fallback.to_string(),
+        }
+    }
+
+    /// Use `sql` for every column of `kind`.
+    pub fn kind(mut self, kind: Kind, sql: impl Into<String>) -> Self {
+        self.kinds.insert(kind, sql.into());
+        self
+    }
+
+    /// Use `sql` for the column `field`, whatever its values.
+    pub fn field(mut self, field: impl Into<String>, sql: impl Into<String>) -> Self {
+        self.fields.insert(field.into(), sql.into());
+        self
+    }
+
+    /// The type of the column `field`, holding `values`.
+    ///
+    /// Integers mixed with floats are floats; any other mix of kinds takes the fallback type.
+    pub fn column_type<'a>(
+        &self,
+        field: &str,
+        values: impl IntoIterator<Item = &'a Value>,
+    ) -> &str {
+        if let Some(sql) = self.fields.get(field) {
+            return sql;
+        }
+        let mut kind = None;
+        for next in values.into_iter().filter_map(Kind::of) {
+            kind = match (kind, next) {
+                (None, next) => Some(next),
+                (Some(kind), next) if kind == next => Some(kind),
+                (Some(Kind::Integer), Kind::Float) | (Some(Kind::Float), Kind::Integer) => {
+                    Some(Kind::Float)
+                }
+                _ => return &self.fallback,
+            };
+        }
+        kind.and_then(|kind| self.kinds.get(&kind))
+            .unwrap_or(&self.fallback)
+    }
+
+    /// A `(column, type)` for every column of `rows`, as listed by [`postgresql::columns()`].
+    pub fn infer(&self, rows: &[Value]) -> Vec<(String, String)> {
+        postgresql::columns(rows)
+            .into_iter()
+            .map(|column| {
+                let values = rows.iter().filter_map(|row| row.get(&column));
+                let sql = self.column_type(&column, values).to_string();
+                (column, sql)
+            })
+            .collect()
+    }
+}
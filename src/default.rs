@@ -1,4 +1,6 @@
+use super::pool;
 use super::{db::*, Error};
+use bytes::Bytes;
 
 /// Fetch data from some endpoint, also known as `path`);
 /// default implementation assumes `&str` input type, resembling either a File Path or a URL.
@@ -13,15 +15,53 @@ where
     }
 }
 
+/// Fetch the raw bytes of some endpoint: a GET request if `path` starts with `http`, or the
+/// file at `path` if not; see [`ETL::fetch()`].
+///
+/// [`ETL::fetch()`]: crate::ETL::fetch
+pub async fn fetch(path: &str) -> Result<Bytes, Error> {
+    if !path.starts_with("http") {
+        return Ok(tokio::fs::read(path).await?.into());
+    }
+    let pool = pool::global();
+    let _permit = pool.acquire(path).await?;
+    let response = pool
+        .client(path)?
+        .get(path)
+        .header("User-Agent", "example@example.com")
+        .send()
+        .await?;
+    body(response).await
+}
+
+// The body of `response`: text in a charset other than UTF-8 is converted to UTF-8 (as JSON
+// should be), and anything else is kept byte for byte.
+pub(crate) async fn body(response: reqwest::Response) -> Result<Bytes, Error> {
+    let charset = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| {
+            value
+                .split(';')
+                .find_map(|param| param.trim().strip_prefix("charset="))
+        })
+        .map(|charset| charset.trim_matches('"').to_ascii_lowercase());
+    match charset {
+        Some(charset) if charset != "utf-8" && charset != "utf8" => {
+            Ok(response.text().await?.into())
+        }
+        _ => Ok(response.bytes().await?),
+    }
+}
+
 /// Reads a JSON file and deserializes to some `I` type.
 pub async fn extract_file<I>(file_path: &str) -> Result<I, Error>
 where
     I: serde::de::DeserializeOwned + Send,
 {
-    let file = std::fs::File::open(file_path)?;
-    let reader = std::io::BufReader::new(file);
-    let data = serde_json::from_reader(reader)?;
-    Ok(data)
+    let json = std::fs::read(file_path)?;
+    decode(&json)
 }
 
 /// GET request a URL (with a client), deserializing the JSON response to some `I` type.
@@ -33,16 +73,62 @@ pub async fn extract_url<I>(url: &str) -> Result<I, Error>
 where
     I: serde::de::DeserializeOwned + Send,
 {
-    let client = reqwest::Client::new();
-    let response = client
+    let pool = pool::global();
+    let _permit = pool.acquire(url).await?;
+    let response = pool
+        .client(url)?
         .get(url)
         .header("User-Agent", "example@example.com")
         .send()
         .await?
         .text()
         .await?;
-    let data: I = serde_json::from_str(&response)?;
-    Ok(data)
+    decode(&response)
+}
+
+/// Deserialize JSON to some `I` type, as extracted.
+///
+/// Failures are [`Error::Decode`], locating the offending value by its path and a snippet of the
+/// JSON around it; rather than by a line & column, deep into a large payload.
+pub fn decode<I>(json: impl AsRef<[u8]>) -> Result<I, Error>
+where
+    I: serde::de::DeserializeOwned,
+{
+    let json = json.as_ref();
+    let mut deserializer = serde_json::Deserializer::from_slice(json);
+    let data = serde_path_to_error::deserialize(&mut deserializer)
+        .map_err(|e| (e.path().to_string(), e.into_inner()))
+        .and_then(|data| {
+            // nothing but whitespace after the value
+            deserializer.end().map_err(|e| (".".to_string(), e))?;
+            Ok(data)
+        });
+    data.map_err(|(path, source)| Error::Decode {
+        path,
+        snippet: snippet(json, source.line(), source.column()),
+        source,
+    })
+}
+
+// the JSON within `SNIPPET` bytes of `line` & `column` (both 1-based)
+fn snippet(json: &[u8], line: usize, column: usize) -> String {
+    const SNIPPET: usize = 40;
+    let line_start = json
+        .iter()
+        .enumerate()
+        .filter(|(_, byte)| **byte == b'\n')
+        .nth(line.saturating_sub(2))
+        .map_or(0, |(i, _)| i + 1);
+    let at = match line {
+        0 | 1 => column,
+        _ => line_start + column,
+    }
+    .min(json.len());
+    let start = at.saturating_sub(SNIPPET);
+    let end = (at + SNIPPET).min(json.len());
+    String::from_utf8_lossy(&json[start..end])
+        .trim()
+        .to_string()
 }
 
 /// Load to a database.
@@ -54,17 +140,15 @@ where
 /// The assumed workflow is: take a file/table and create/update it
 pub async fn load<O>(output: O, conn: &str, doc_id: &str) -> Result<(), Error>
 where
-    O: for<'a> serde::de::Deserialize<'a> + serde::Serialize + Send,
+    O: for<'a> serde::de::Deserialize<'a> + serde::Serialize + Send + Sync,
 {
-    let _ = load_couchdb(output, conn, doc_id).await;
-    Ok(())
+    load_couchdb(output, conn, doc_id).await
 }
 
 /// Loads document to CouchDB.
 pub async fn load_couchdb<O>(output: O, conn: &str, doc_id: &str) -> Result<(), Error>
 where
-    O: for<'a> serde::de::Deserialize<'a> + serde::Serialize + Send,
+    O: for<'a> serde::de::Deserialize<'a> + serde::Serialize + Send + Sync,
 {
-    couchdb::insert_doc::<O>(&output, conn, doc_id).await;
-    Ok(())
+    couchdb::insert_doc::<O>(&output, conn, doc_id).await
 }
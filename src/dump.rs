@@ -0,0 +1,258 @@
+//! Diagnostic dumps of failed runs: what failed, where, and on what, persisted as the run fails;
+//! so a failure at 3am under a scheduler can be investigated after the fact, rather than rerun.
+//!
+//! ```rust,ignore
+//! let pipe = Pipe::<RawPrice, Vec<Price>>::builder()
+//!     .source(Source::endpoint("https://example.com/prices.json"))
+//!     .sink(sink)
+//!     .dumps(Dumps::dir("/var/log/prices/dumps"))
+//!     .build()?;
+//! pipe.run().await?;
+//! // on failure: /var/log/prices/dumps/20240601T030000Z-run-<uuid>.json
+//! ```
+//!
+//! A [`Dump`] has the run's error & [id](crate::clock::run_id) (as its archive & history record
+//! have), the stage that failed, the head of the last payload fetched and the pipe's
+//! configuration. Secrets are redacted, as for the [`wire`](crate::wire) log: from URLs, the vars,
+//! the payload (if it's JSON), and the JSON fields quoted in the error.
+//!
+//! Dumps can be loaded to a [`Sink`] instead, e.g., to a bucket with [`sink::Http`]. The pipe's
+//! observer is told where each went, or why it couldn't be written, as an [`Event::Dumped`]; the
+//! run fails with its own error either way.
+//!
+//! [`Sink`]: crate::Sink
+//! [`sink::Http`]: crate::sink::Http
+//! [`Event::Dumped`]: crate::observer::Event::Dumped
+
+use super::clock::{self, Clock};
+use super::observer::{Event, Stage};
+use super::sink::{DynSink, Sink};
+use super::{fs, wire, Error, EtlReport};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// The most bytes of the payload kept in a dump, by default.
+pub const SNIPPET: usize = 4096;
+
+tokio::task_local! {
+    // what's known of the run in progress
+    static RUN: RefCell<Run>;
+}
+
+#[derive(Default)]
+struct Run {
+    payload: Option<Bytes>,
+    failed: Option<Stage>,
+}
+
+/// A failed run, as dumped; see [`dump`](self).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Dump {
+    pub run_id: String,
+    pub at: DateTime<Utc>,
+    /// The stage that failed last; `None` if the run failed outside of one, e.g., to archive.
+    pub stage: Option<Stage>,
+    pub error: String,
+    /// The head of the last payload fetched, if any; lossily, as UTF-8.
+    pub payload: Option<String>,
+    /// The pipe's configuration, by setting; only those set.
+    pub config: BTreeMap<String, String>,
+}
+
+/// Where a pipe dumps its failed runs; see [`dump`](self).
+#[derive(Clone)]
+pub struct Dumps {
+    pub dir: Option<PathBuf>,
+    sink: Option<Arc<dyn DynSink<Dump>>>,
+    /// The most bytes of the payload kept; [`SNIPPET`] by default.
+    pub snippet: usize,
+}
+
+impl Dumps {
+    /// Write each dump to `<dir>/<time>-<run id>.json`, e.g., `20240601T030000Z-run-<uuid>.json`.
+    pub fn dir(dir: impl Into<PathBuf>) -> Self {
+        Dumps {
+            dir: Some(dir.into()),
+            sink: None,
+            snippet: SNIPPET,
+        }
+    }
+
+    /// Load each dump to `sink`.
+    pub fn sink<S: Sink<Dump> + 'static>(sink: S) -> Self {
+        Dumps {
+            dir: None,
+            sink: Some(Arc::new(sink)),
+            snippet: SNIPPET,
+        }
+    }
+
+    /// Keep at most `bytes` of the payload.
+    pub fn snippet(mut self, bytes: usize) -> Self {
+        self.snippet = bytes;
+        self
+    }
+
+    // Persist `dump`, returning where it went.
+    async fn write(&self, dump: &Dump) -> Result<String, Error> {
+        if let Some(sink) = &self.sink {
+            sink.load_boxed(dump).await?;
+            return Ok(sink.type_name().to_string());
+        }
+        let dir = self.dir.as_deref().unwrap_or(Path::new(""));
+        let name = format!("{}-{}.json", dump.at.format("%Y%m%dT%H%M%SZ"), dump.run_id);
+        let path = dir.join(name);
+        std::fs::create_dir_all(dir)?;
+        fs::write_atomic(&path, &serde_json::to_vec_pretty(dump)?)?;
+        Ok(path.display().to_string())
+    }
+}
+
+impl fmt::Debug for Dumps {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Dumps")
+            .field("dir", &self.dir)
+            .field("sink", &self.sink.as_ref().map(|sink| sink.type_name()))
+            .field("snippet", &self.snippet)
+            .finish()
+    }
+}
+
+// Note the payload just fetched, in case the run fails; outside of a dumped run, a no-op.
+pub(crate) fn fetched(payload: &Bytes) {
+    let _ = RUN.try_with(|run| run.borrow_mut().payload = Some(payload.clone()));
+}
+
+// Note that `stage` failed; outside of a dumped run, a no-op.
+pub(crate) fn failed(stage: Stage) {
+    let _ = RUN.try_with(|run| run.borrow_mut().failed = Some(stage));
+}
+
+// Run `run`, dumping it to `dumps` if it fails, with the pipe's redacted `config`; and telling
+// `notify` where the dump went.
+pub(crate) async fn collect<E: From<Error> + fmt::Display>(
+    dumps: Option<&Dumps>,
+    clock: &dyn Clock,
+    config: impl FnOnce() -> BTreeMap<String, String>,
+    notify: impl Fn(Event),
+    run: impl Future<Output = Result<EtlReport, E>>,
+) -> Result<EtlReport, E> {
+    let Some(dumps) = dumps else {
+        return run.await;
+    };
+    RUN.scope(RefCell::new(Run::default()), async {
+        let error = match run.await {
+            Ok(report) => return Ok(report),
+            Err(error) => error,
+        };
+        let known = RUN.with(|run| run.take());
+        let dump = Dump {
+            run_id: clock::run_id().unwrap_or_else(|| clock::new_run_id(clock)),
+            at: clock.now(),
+            stage: known.failed,
+            error: redact_pairs(&redact_urls(&error.to_string())),
+            payload: known
+                .payload
+                .map(|payload| snippet(&payload, dumps.snippet)),
+            config: config(),
+        };
+        let written = dumps.write(&dump).await;
+        notify(Event::Dumped {
+            run_id: &dump.run_id,
+            to: written.as_deref(),
+        });
+        Err(error)
+    })
+    .await
+}
+
+// the head of `payload`, redacted if it's JSON
+fn snippet(payload: &[u8], bytes: usize) -> String {
+    let text = wire::redact_body(&String::from_utf8_lossy(payload));
+    if text.len() <= bytes {
+        return text;
+    }
+    let end = (0..=bytes)
+        .rev()
+        .find(|&end| text.is_char_boundary(end))
+        .unwrap_or(0);
+    format!("{}...", &text[..end])
+}
+
+// `text`, with any secrets redacted from the URLs in it, e.g., of a request that failed
+fn redact_urls(text: &str) -> String {
+    let words = text.split(' ').map(|word| {
+        let url = word.trim_matches(|c: char| matches!(c, '(' | ')' | '"' | '\'' | ','));
+        match reqwest::Url::parse(url) {
+            Ok(parsed) if parsed.has_host() => word.replace(url, &wire::redact_url(&parsed)),
+            _ => word.to_string(),
+        }
+    });
+    words.collect::<Vec<_>>().join(" ")
+}
+
+// `text`, with the string values of any secret JSON fields in it redacted, e.g., in a decode
+// error's snippet of its payload; `"token": "..."` as `"token": "***"`
+fn redact_pairs(text: &str) -> String {
+    let mut redacted = String::with_capacity(text.len());
+    // copied up to, and searched from
+    let (mut copied, mut from) = (0, 0);
+    while let Some(open) = quote(&text[from..]).map(|i| from + i) {
+        let Some(close) = quote(&text[open + 1..]).map(|i| open + 1 + i) else {
+            break;
+        };
+        let key = &text[open + 1..close];
+        from = close + 1;
+        let value = text[from..]
+            .trim_start()
+            .strip_prefix(':')
+            .map(str::trim_start)
+            .and_then(|value| value.strip_prefix('"'));
+        let Some(value) = value else {
+            continue;
+        };
+        let start = text.len() - value.len();
+        let Some(end) = quote(value).map(|i| start + i) else {
+            break;
+        };
+        if wire::is_secret(key) {
+            redacted.push_str(&text[copied..start]);
+            redacted.push_str("***");
+            copied = end;
+        }
+        from = end + 1;
+    }
+    redacted.push_str(&text[copied..]);
+    redacted
+}
+
+// the index of the first unescaped `"` of `text`
+fn quote(text: &str) -> Option<usize> {
+    let mut escaped = false;
+    text.char_indices().find_map(|(i, c)| match (c, escaped) {
+        ('"', false) => Some(i),
+        _ => {
+            escaped = c == '\\' && !escaped;
+            None
+        }
+    })
+}
+
+// `value` of the setting named `name`, redacted if its name is of a secret, e.g., a var `api_key`;
+// or if it's a URL, of any secrets in it
+pub(crate) fn redact(name: &str, value: String) -> String {
+    if wire::is_secret(name) {
+        return "***".into();
+    }
+    match reqwest::Url::parse(&value) {
+        Ok(url) if url.has_host() => wire::redact_url(&url),
+        _ => value,
+    }
+}
@@ -0,0 +1,392 @@
+//! Pipes on plain JSON, reshaped by rules given at runtime, e.g., from a config file; for inputs
+//! whose shape isn't known when the pipeline is compiled.
+//!
+//! Rules are JSON too, applied to each record in turn:
+//!
+//! ```json
+//! {
+//!     "records": "chart.result",
+//!     "rules": [
+//!         { "op": "filter", "field": "meta.currency", "cmp": "eq", "value": "USD" },
+//!         { "op": "map", "from": "meta.symbol", "to": "symbol" },
+//!         { "op": "rename", "from": "timestamp", "to": "dates" },
+//!         { "op": "set", "field": "source", "value": "yahoo" },
+//!         { "op": "select", "fields": ["symbol", "dates", "source"] }
+//!     ]
+//! }
+//! ```
+//!
+//! ```rust,ignore
+//! let pipe: DynamicPipe = Pipe::builder()
+//!     .source(Source::endpoint("https://example.com/chart.json"))
+//!     .rules(Rules::from_file("chart.rules.json")?)
+//!     .sink(sink::Postgres::new(conn, "charts"))
+//!     .build()?;
+//! pipe.run().await?;
+//! ```
+//!
+//! Fields are addressed by path: keys separated by `.`, with `[n]` for array elements, e.g.,
+//! `chart.result[0].meta.symbol`.
+
+use super::lineage::Lineage;
+use super::{Enrich, Error, Pipe, PipeBuilder};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+/// A [`Pipe`] from JSON to JSON, that loads its input as extracted, unless given [`Rules`];
+/// see [`PipeBuilder::rules()`].
+pub type DynamicPipe = Pipe<Value, Value>;
+
+/// The path to a field, e.g., `chart.result[0].meta.symbol`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Path {
+    path: String,
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Step {
+    Key(String),
+    Index(usize),
+}
+
+impl Path {
+    pub fn parse(path: &str) -> Result<Self, Error> {
+        let invalid = || Error::Config(format!("invalid field path: {path:?}"));
+        let mut steps = vec![];
+        for segment in path.split('.') {
+            let (key, mut indexes) = match segment.find('[') {
+                Some(i) => segment.split_at(i),
+                None => (segment, ""),
+            };
+            if segment.is_empty() {
+                return Err(invalid());
+            }
+            if !key.is_empty() {
+                steps.push(Step::Key(key.to_string()));
+            }
+            while !indexes.is_empty() {
+                let end = indexes.find(']').ok_or_else(invalid)?;
+                let index = indexes[1..end].parse().map_err(|_| invalid())?;
+                steps.push(Step::Index(index));
+                indexes = &indexes[end + 1..];
+                if !indexes.is_empty() && !indexes.starts_with('[') {
+                    return Err(invalid());
+                }
+            }
+        }
+        Ok(Path {
+            path: path.to_string(),
+            steps,
+        })
+    }
+
+    /// The field at this path in `value`, if any.
+    pub fn get<'a>(&self, value: &'a Value) -> Option<&'a Value> {
+        self.steps.iter().try_fold(value, |value, step| match step {
+            Step::Key(key) => value.get(key),
+            Step::Index(index) => value.get(index),
+        })
+    }
+
+    /// Remove the field at this path from `value`, if it's there.
+    pub fn take(&self, value: &mut Value) -> Option<Value> {
+        let (last, parents) = self.steps.split_last()?;
+        let parent = parents.iter().try_fold(value, |value, step| match step {
+            Step::Key(key) => value.get_mut(key),
+            Step::Index(index) => value.get_mut(index),
+        })?;
+        match (last, parent) {
+            (Step::Key(key), Value::Object(object)) => object.remove(key),
+            (Step::Index(index), Value::Array(array)) if *index < array.len() => {
+                Some(array.remove(*index))
+            }
+            _ => None,
+        }
+    }
+
+    /// Set the field at this path in `value` to `field`, adding any objects missing on the way.
+    ///
+    /// Returns [`Error::Config`] if the path runs through something other than an object (or, for
+    /// `[n]`, an array at least `n + 1` long).
+    pub fn set(&self, value: &mut Value, field: Value) -> Result<(), Error> {
+        let mismatch = || Error::Config(format!("cannot set {:?}", self.path));
+        let mut target = value;
+        for step in &self.steps {
+            target = match step {
+                Step::Key(key) => {
+                    if target.is_null() {
+                        *target = Value::Object(Map::new());
+                    }
+                    match target {
+                        Value::Object(object) => object.entry(key.clone()).or_insert(Value::Null),
+                        _ => return Err(mismatch()),
+                    }
+                }
+                Step::Index(index) => match target {
+                    Value::Array(array) if *index < array.len() => &mut array[*index],
+                    _ => return Err(mismatch()),
+                },
+            };
+        }
+        *target = field;
+        Ok(())
+    }
+}
+
+impl TryFrom<String> for Path {
+    type Error = Error;
+
+    fn try_from(path: String) -> Result<Self, Error> {
+        Path::parse(&path)
+    }
+}
+
+impl std::fmt::Display for Path {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.path)
+    }
+}
+
+/// How a [`Rule::Filter`] compares a field with its value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Cmp {
+    Eq,
+    Ne,
+    /// Numbers are compared as numbers, strings lexically; anything else never matches.
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    /// The field is present (and not `null`); the value is ignored.
+    Exists,
+    /// The field is absent (or `null`); the value is ignored.
+    Missing,
+}
+
+impl Cmp {
+    fn matches(self, field: Option<&Value>, value: &Value) -> bool {
+        let field = field.filter(|field| !field.is_null());
+        let order = || match (field?, value) {
+            (Value::Number(a), Value::Number(b)) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+            (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+            _ => None,
+        };
+        match self {
+            Cmp::Eq => field == Some(value),
+            Cmp::Ne => field != Some(value),
+            Cmp::Gt => order().is_some_and(|order| order.is_gt()),
+            Cmp::Gte => order().is_some_and(|order| order.is_ge()),
+            Cmp::Lt => order().is_some_and(|order| order.is_lt()),
+            Cmp::Lte => order().is_some_and(|order| order.is_le()),
+            Cmp::Exists => field.is_some(),
+            Cmp::Missing => field.is_none(),
+        }
+    }
+}
+
+/// One step of reshaping a record.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Rule {
+    /// Drop the record, unless its `field` compares to `value` by `cmp`.
+    Filter {
+        field: Path,
+        cmp: Cmp,
+        #[serde(default)]
+        value: Value,
+    },
+    /// Copy the field at `from` to `to`; or `null`, if there's none.
+    Map { from: Path, to: Path },
+    /// Move the field at `from` to `to`, if there's one.
+    Rename { from: Path, to: Path },
+    /// Set `field` to `value`.
+    Set { field: Path, value: Value },
+    /// Remove each of `fields`.
+    Drop { fields: Vec<Path> },
+    /// Keep only `fields`, keyed by their paths, e.g., `{ "meta.symbol": "NVDA" }`.
+    Select { fields: Vec<Path> },
+}
+
+impl Rule {
+    // the record, reshaped; or `None` if it's filtered out
+    fn apply(&self, mut record: Value) -> Result<Option<Value>, Error> {
+        match self {
+            Rule::Filter { field, cmp, value } => {
+                return Ok(cmp.matches(field.get(&record), value).then_some(record));
+            }
+            Rule::Map { from, to } => {
+                let field = from.get(&record).cloned().unwrap_or_default();
+                to.set(&mut record, field)?;
+            }
+            Rule::Rename { from, to } => {
+                if let Some(field) = from.take(&mut record) {
+                    to.set(&mut record, field)?;
+                }
+            }
+            Rule::Set { field, value } => field.set(&mut record, value.clone())?,
+            Rule::Drop { fields } => {
+                for field in fields {
+                    field.take(&mut record);
+                }
+            }
+            Rule::Select { fields } => {
+                let selected = fields
+                    .iter()
+                    .filter_map(|field| {
+                        let value = field.get(&record)?.clone();
+                        Some((field.to_string(), value))
+                    })
+                    .collect();
+                record = Value::Object(selected);
+            }
+        }
+        Ok(Some(record))
+    }
+}
+
+/// The [`Rule`]s of a [`DynamicPipe`], and where its records are.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct Rules {
+    /// The path to the array of records within the input. Without one, an array input is the
+    /// records, and anything else is a single record.
+    #[serde(default)]
+    pub records: Option<Path>,
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+}
+
+impl Rules {
+    pub fn new(rules: Vec<Rule>) -> Self {
+        Rules {
+            records: None,
+            rules,
+        }
+    }
+
+    /// Rules from a JSON object, like the one in the [module docs](crate::dynamic); paths are
+    /// checked as they're read.
+    pub fn from_value(value: Value) -> Result<Self, Error> {
+        serde_json::from_value(value).map_err(|e| Error::Config(format!("invalid rules: {e}")))
+    }
+
+    /// Rules from a JSON file; see [`from_value()`].
+    ///
+    /// [`from_value()`]: Rules::from_value
+    pub fn from_file(path: &str) -> Result<Self, Error> {
+        let text = std::fs::read_to_string(path)?;
+        Self::from_value(serde_json::from_str(&text)?)
+    }
+
+    /// Read the records, as a [`Path`] from the input.
+    pub fn records(mut self, path: &str) -> Result<Self, Error> {
+        self.records = Some(Path::parse(path)?);
+        Ok(self)
+    }
+
+    /// Apply the rules to every record of `input`, in order; always returning an array, of the
+    /// records that weren't filtered out.
+    pub fn apply(&self, input: Value) -> Result<Value, Error> {
+        let records = match &self.records {
+            Some(path) => match path.get(&input) {
+                Some(Value::Array(records)) => records.clone(),
+                _ => {
+                    return Err(Error::Config(format!(
+                        "no array of records at {:?}",
+                        path.to_string()
+                    )))
+                }
+            },
+            None => match input {
+                Value::Array(records) => records,
+                record => vec![record],
+            },
+        };
+
+        let mut output = Vec::with_capacity(records.len());
+        'records: for mut record in records {
+            for rule in &self.rules {
+                match rule.apply(record)? {
+                    Some(reshaped) => record = reshaped,
+                    None => continue 'records,
+                }
+            }
+            output.push(record);
+        }
+        Ok(Value::Array(output))
+    }
+
+    /// The fields the rules derive, and the paths (within each record) they're derived from;
+    /// fields passed through as they are aren't declared, unless `select`ed. See
+    /// [`lineage`](crate::lineage).
+    pub fn lineage(&self) -> Lineage {
+        // each derived field, in the order first derived, & its paths in the original record
+        let mut derived: Vec<(String, Vec<String>)> = vec![];
+        let sources = |derived: &[(String, Vec<String>)], path: &Path| {
+            let path = path.to_string();
+            match derived.iter().find(|(field, _)| *field == path) {
+                Some((_, from)) => from.clone(),
+                None => vec![path],
+            }
+        };
+        let derive = |derived: &mut Vec<(String, Vec<String>)>, field: &Path, from| {
+            let field = field.to_string();
+            derived.retain(|(derived, _)| *derived != field);
+            derived.push((field, from));
+        };
+        for rule in &self.rules {
+            match rule {
+                Rule::Filter { .. } => {}
+                Rule::Map { from, to } => {
+                    let from = sources(&derived, from);
+                    derive(&mut derived, to, from);
+                }
+                Rule::Rename { from, to } => {
+                    let sources = sources(&derived, from);
+                    derived.retain(|(field, _)| *field != from.to_string());
+                    derive(&mut derived, to, sources);
+                }
+                Rule::Set { field, .. } => derive(&mut derived, field, vec![]),
+                Rule::Drop { fields } => {
+                    let dropped: Vec<String> = fields.iter().map(Path::to_string).collect();
+                    derived.retain(|(field, _)| !dropped.contains(field));
+                }
+                Rule::Select { fields } => {
+                    let selected = fields
+                        .iter()
+                        .map(|field| (field.to_string(), sources(&derived, field)))
+                        .collect();
+                    derived = selected;
+                }
+            }
+        }
+        derived
+            .into_iter()
+            .fold(Lineage::new(), |lineage, (field, from)| {
+                lineage.field(&field, from)
+            })
+    }
+}
+
+// rules run as an enrichment, after the (passthrough) transform
+impl Enrich<Value> for Rules {
+    async fn enrich(&self, output: Value) -> Result<Value, Error> {
+        self.apply(output)
+    }
+}
+
+impl PipeBuilder<Value, Value> {
+    /// Reshape each output by `rules` before it's loaded; see [`dynamic`](crate::dynamic).
+    ///
+    /// The rules take the place of the pipe's [`enrich()`] stage, so the two can't be combined.
+    ///
+    /// The fields the rules derive are declared as the pipe's lineage; see [`Rules::lineage()`].
+    ///
+    /// [`enrich()`]: PipeBuilder::enrich
+    pub fn rules(self, rules: Rules) -> Self {
+        let lineage = rules.lineage();
+        self.enrich(rules).lineage(lineage)
+    }
+}
@@ -0,0 +1,121 @@
+use super::template::Vars;
+use super::{Error, Source};
+use reqwest::Url;
+
+/// A URL, built from a template and typed query parameters, with everything percent-encoded.
+///
+/// `{name}` placeholders in the template are filled in with [`var()`]; query parameters are
+/// appended, in order, with [`param()`] and [`param_iter()`].
+///
+/// ```rust,ignore
+/// let endpoint = Endpoint::new("https://query2.finance.yahoo.com/ws/fundamentals-timeseries/v1/finance/timeseries/{ticker}")
+///     .var("ticker", ticker)
+///     .param("symbol", ticker)
+///     .param("type", metrics.join(","))
+///     .param_iter([("period1", 1483142400), ("period2", now)]);
+/// let pipe = Pipe::<I, O>::builder().source(endpoint.try_into()?).build()?;
+/// ```
+///
+/// [`var()`]: Endpoint::var
+/// [`param()`]: Endpoint::param
+/// [`param_iter()`]: Endpoint::param_iter
+#[derive(Debug, Clone, PartialEq)]
+pub struct Endpoint {
+    template: String,
+    vars: Vec<(String, String)>,
+    params: Vec<(String, String)>,
+}
+
+impl Endpoint {
+    pub fn new(template: impl Into<String>) -> Self {
+        Endpoint {
+            template: template.into(),
+            vars: Vec::new(),
+            params: Vec::new(),
+        }
+    }
+
+    /// Fill in the `{name}` placeholder of the template with `value`, encoded as a path segment.
+    pub fn var(mut self, name: impl Into<String>, value: impl ToString) -> Self {
+        self.vars.push((name.into(), value.to_string()));
+        self
+    }
+
+    /// Fill in a placeholder per value of `vars`; see [`template`](crate::template).
+    pub fn vars(mut self, vars: &Vars) -> Self {
+        self.vars.extend(
+            vars.values
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone())),
+        );
+        self
+    }
+
+    /// Append the query parameter `key=value`.
+    pub fn param(mut self, key: impl Into<String>, value: impl ToString) -> Self {
+        self.params.push((key.into(), value.to_string()));
+        self
+    }
+
+    /// Append every `(key, value)` pair as a query parameter.
+    pub fn param_iter<K, V>(mut self, params: impl IntoIterator<Item = (K, V)>) -> Self
+    where
+        K: Into<String>,
+        V: ToString,
+    {
+        self.params.extend(
+            params
+                .into_iter()
+                .map(|(key, value)| (key.into(), value.to_string())),
+        );
+        self
+    }
+
+    /// The complete URL.
+    ///
+    /// Returns [`Error::Config`] if the URL is invalid, or a placeholder wasn't filled in.
+    pub fn url(&self) -> Result<Url, Error> {
+        let mut url = self.template.clone();
+        for (name, value) in &self.vars {
+            url = url.replace(&format!("{{{name}}}"), &encode_segment(value));
+        }
+        if let Some(start) = url.find('{') {
+            let end = url[start..]
+                .find('}')
+                .map_or(url.len(), |end| start + end + 1);
+            return Err(Error::Config(format!(
+                "endpoint placeholder {} was not filled in",
+                &url[start..end]
+            )));
+        }
+
+        let mut url = Url::parse(&url)
+            .map_err(|e| Error::Config(format!("invalid endpoint URL {url:?}: {e}")))?;
+        if !self.params.is_empty() {
+            url.query_pairs_mut().extend_pairs(&self.params);
+        }
+        Ok(url)
+    }
+}
+
+impl<I> TryFrom<Endpoint> for Source<I> {
+    type Error = Error;
+
+    fn try_from(endpoint: Endpoint) -> Result<Self, Error> {
+        Ok(Source::endpoint(endpoint.url()?))
+    }
+}
+
+// Percent-encode everything but the unreserved characters of RFC 3986.
+pub(crate) fn encode_segment(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
@@ -0,0 +1,287 @@
+use super::db::postgresql;
+use super::Error;
+use futures::future::BoxFuture;
+use futures::{StreamExt, TryStreamExt};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::sync::Mutex;
+
+/// A stage between transform & load, that adds to the output what the input didn't have.
+///
+/// Configured on a pipe with [`PipeBuilder::enrich()`]; most enrichments are per-record
+/// lookups, for which see [`Enricher`].
+///
+/// [`PipeBuilder::enrich()`]: crate::PipeBuilder::enrich
+pub trait Enrich<O>: Send + Sync {
+    /// Enrich the transformed `output`, ahead of loading it.
+    fn enrich(&self, output: O) -> impl Future<Output = Result<O, Error>> + Send;
+}
+
+// `Enrich` returns `impl Future`, so it cannot be boxed as-is; pipes store enrichers through this instead.
+pub(crate) trait DynEnrich<O>: Send + Sync {
+    fn enrich_boxed(&self, output: O) -> BoxFuture<'_, Result<O, Error>>;
+}
+
+impl<O, E> DynEnrich<O> for E
+where
+    O: Send + 'static,
+    E: Enrich<O>,
+{
+    fn enrich_boxed(&self, output: O) -> BoxFuture<'_, Result<O, Error>> {
+        Box::pin(self.enrich(output))
+    }
+}
+
+/// An async lookup of extra data for a record `T`, e.g., from an HTTP API or a dimension table.
+///
+/// The lookup owns whatever it needs to connect (a client, a connection string), so that
+/// [`transform()`] can stay a pure function of the input.
+///
+/// ```rust,ignore
+/// struct Sectors { conn: String }
+///
+/// impl Lookup<Row> for Sectors {
+///     type Key = String;
+///     type Value = String;
+///
+///     fn key(&self, row: &Row) -> String {
+///         row.ticker.clone()
+///     }
+///
+///     async fn fetch(&self, ticker: &String) -> pipe_io::Result<String> {
+///         let (client, connection) = tokio_postgres::connect(&self.conn, NoTls).await?;
+///         tokio::spawn(connection);
+///         let row = client.query_one("SELECT sector FROM sectors WHERE ticker = $1", &[ticker]).await?;
+///         Ok(row.try_get(0)?)
+///     }
+///
+///     fn apply(&self, row: &mut Row, sector: String) {
+///         row.sector = Some(sector);
+///     }
+/// }
+/// ```
+///
+/// [`transform()`]: crate::ETL::transform
+pub trait Lookup<T>: Send + Sync {
+    type Key: Eq + Hash + Clone + Send + Sync;
+    type Value: Clone + Send + Sync;
+
+    /// What to look up for `record`; records with equal keys share a single fetch.
+    fn key(&self, record: &T) -> Self::Key;
+
+    /// Fetch the value for `key`.
+    fn fetch(&self, key: &Self::Key) -> impl Future<Output = Result<Self::Value, Error>> + Send;
+
+    /// Add the fetched `value` to `record`.
+    fn apply(&self, record: &mut T, value: Self::Value);
+}
+
+/// Enriches every record of a `Vec<T>` output with a [`Lookup`].
+///
+/// Each distinct key is fetched once, with up to `concurrency` fetches in flight at a time;
+/// fetched values are cached for the life of the enricher, so later runs of the same pipe
+/// only fetch keys they haven't seen before.
+///
+/// ```rust,ignore
+/// let pipe = Pipe::<Raw, Vec<Row>>::builder()
+///     .enrich(Enricher::new(Sectors { conn }).concurrency(8))
+///     .sink(sink)
+///     .build()?;
+/// ```
+pub struct Enricher<L, T>
+where
+    L: Lookup<T>,
+{
+    lookup: L,
+    concurrency: usize,
+    cache: Mutex<HashMap<L::Key, L::Value>>,
+    record: PhantomData<fn(&mut T)>,
+}
+
+impl<L, T> Enricher<L, T>
+where
+    L: Lookup<T>,
+{
+    pub fn new(lookup: L) -> Self {
+        Enricher {
+            lookup,
+            concurrency: 4,
+            cache: Mutex::new(HashMap::new()),
+            record: PhantomData,
+        }
+    }
+
+    /// The most lookups to have in flight at once. Defaults to 4.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Forget every cached value.
+    pub fn clear(&self) {
+        self.cache
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clear();
+    }
+
+    async fn fetch(&self, key: L::Key) -> Result<(L::Key, L::Value), Error> {
+        let value = self.lookup.fetch(&key).await?;
+        Ok((key, value))
+    }
+}
+
+impl<L, T> Enrich<Vec<T>> for Enricher<L, T>
+where
+    L: Lookup<T>,
+    T: Send,
+{
+    async fn enrich(&self, mut output: Vec<T>) -> Result<Vec<T>, Error> {
+        let keys: Vec<L::Key> = output
+            .iter()
+            .map(|record| self.lookup.key(record))
+            .collect();
+
+        // only fetch what isn't cached yet, once per key
+        let missing: Vec<L::Key> = {
+            let cache = self
+                .cache
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let mut seen = HashSet::new();
+            keys.iter()
+                .filter(|key| !cache.contains_key(*key) && seen.insert(*key))
+                .cloned()
+                .collect()
+        };
+        let fetched: Vec<(L::Key, L::Value)> = futures::stream::iter(missing)
+            .map(|key| self.fetch(key))
+            .buffer_unordered(self.concurrency)
+            .try_collect()
+            .await?;
+
+        let mut cache = self
+            .cache
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        cache.extend(fetched);
+        for (record, key) in output.iter_mut().zip(keys) {
+            if let Some(value) = cache.get(&key) {
+                self.lookup.apply(record, value.clone());
+            }
+        }
+        Ok(output)
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+// dimension
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+type NaturalKey<T> = Box<dyn Fn(&T) -> String + Send + Sync>;
+type SurrogateKey<T> = Box<dyn Fn(&mut T, i64) + Send + Sync>;
+
+/// A [`Lookup`] of surrogate keys in a Postgres dimension table, by the natural keys of the
+/// output (e.g., `NVDA`); missing dimension rows are inserted on demand, so a fact table can be
+/// loaded in one pass.
+///
+/// The natural key column needs a unique constraint, and the surrogate key a default (e.g., an
+/// `IDENTITY` or `BIGSERIAL` column) of type `BIGINT`; every other column of a new dimension row
+/// gets its default. Lookups share the pooled connections of the Postgres sinks.
+///
+/// ```rust,ignore
+/// // CREATE TABLE dim_ticker (ticker_id BIGSERIAL PRIMARY KEY, symbol TEXT UNIQUE NOT NULL)
+/// let tickers = Dimension::new(
+///     conn,
+///     "dim_ticker",
+///     |row: &Price| row.symbol.clone(),
+///     |row, id| row.ticker_id = Some(id),
+/// )
+/// .columns("symbol", "ticker_id");
+/// let pipe = Pipe::<Raw, Vec<Price>>::builder()
+///     .enrich(Enricher::new(tickers))
+///     .sink(sink::Postgres::new(conn, "fact_prices"))
+///     .build()?;
+/// ```
+pub struct Dimension<T> {
+    conn: String,
+    table: String,
+    natural: String,
+    surrogate: String,
+    insert: bool,
+    key: NaturalKey<T>,
+    apply: SurrogateKey<T>,
+}
+
+impl<T> Dimension<T> {
+    /// Look up the natural `key` of every record in `table`, at `conn`, and `apply` its
+    /// surrogate key; by default, the columns are `name` & `id`.
+    pub fn new<K, A>(conn: impl Into<String>, table: impl Into<String>, key: K, apply: A) -> Self
+    where
+        K: Fn(&T) -> String + Send + Sync + 'static,
+        A: Fn(&mut T, i64) + Send + Sync + 'static,
+    {
+        Dimension {
+            conn: conn.into(),
+            table: table.into(),
+            natural: "name".into(),
+            surrogate: "id".into(),
+            insert: true,
+            key: Box::new(key),
+            apply: Box::new(apply),
+        }
+    }
+
+    /// The columns of the natural & surrogate keys.
+    pub fn columns(mut self, natural: impl Into<String>, surrogate: impl Into<String>) -> Self {
+        self.natural = natural.into();
+        self.surrogate = surrogate.into();
+        self
+    }
+
+    /// Whether to insert the dimension rows of unknown natural keys; if not, they fail the
+    /// lookup with [`Error::Missing`]. Defaults to `true`.
+    pub fn insert_missing(mut self, insert: bool) -> Self {
+        self.insert = insert;
+        self
+    }
+}
+
+impl<T> Lookup<T> for Dimension<T>
+where
+    T: Send + Sync,
+{
+    type Key = String;
+    type Value = i64;
+
+    fn key(&self, record: &T) -> String {
+        (self.key)(record)
+    }
+
+    async fn fetch(&self, key: &String) -> Result<i64, Error> {
+        let client = postgresql::pooled(&self.conn).await?;
+        let (select, insert) =
+            postgresql::dimension_queries(&self.table, &self.natural, &self.surrogate);
+        // e.g., an `INTEGER` surrogate key is an error, not a panic
+        if let Some(row) = client.query_opt(&select, &[key]).await? {
+            return Ok(row.try_get(0)?);
+        }
+        if !self.insert {
+            return Err(Error::Missing(format!(
+                "{}.{} = {key}",
+                self.table, self.natural
+            )));
+        }
+        match client.query_opt(&insert, &[key]).await? {
+            Some(row) => Ok(row.try_get(0)?),
+            // inserted concurrently, since the select
+            None => Ok(client.query_one(&select, &[key]).await?.try_get(0)?),
+        }
+    }
+
+    fn apply(&self, record: &mut T, id: i64) {
+        (self.apply)(record, id)
+    }
+}
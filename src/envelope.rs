@@ -0,0 +1,58 @@
+//! Unwrapping payloads from the envelopes many APIs wrap them in, e.g.,
+//! `{ "data": [...], "error": null }`; so `I` can be the data itself, rather than the envelope.
+//!
+//! ```rust,ignore
+//! let pipe = Pipe::<Vec<Quote>, Vec<Quote>>::builder()
+//!     .source(Source::endpoint("https://example.com/quotes"))
+//!     .with_envelope("data", "error")
+//!     .build()?;
+//! ```
+//!
+//! Before the payload is decoded to `I`, the error field is checked: anything but `null` (or no
+//! field at all) fails the extraction with [`Error::Upstream`]; then the data field is decoded
+//! instead of the whole payload. Both are [`Path`]s, e.g., `chart.result[0]`.
+//!
+//! Only used by the default [`decode()`](crate::ETL::decode) (and [`Pipe::decode_default()`]),
+//! of JSON payloads.
+//!
+//! [`Pipe::decode_default()`]: crate::Pipe::decode_default
+
+use super::dynamic::Path;
+use super::Error;
+use bytes::Bytes;
+use serde_json::Value;
+
+/// Where an envelope keeps its data & error; see [`envelope`](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Envelope {
+    pub data: Path,
+    pub error: Path,
+}
+
+impl Envelope {
+    /// Returns [`Error::Config`] if either path is invalid.
+    pub fn new(data: &str, error: &str) -> Result<Self, Error> {
+        Ok(Envelope {
+            data: Path::parse(data)?,
+            error: Path::parse(error)?,
+        })
+    }
+
+    /// The data in the JSON `payload`, as JSON.
+    ///
+    /// Returns [`Error::Upstream`] if the error field isn't `null`: its text if it's a string, or
+    /// the JSON otherwise; and [`Error::Missing`] if there's no data field.
+    pub fn unwrap(&self, payload: &[u8]) -> Result<Bytes, Error> {
+        let mut payload: Value = serde_json::from_slice(payload)?;
+        match self.error.get(&payload) {
+            None | Some(Value::Null) => {}
+            Some(Value::String(error)) => return Err(Error::Upstream(error.clone())),
+            Some(error) => return Err(Error::Upstream(error.to_string())),
+        }
+        let data = self
+            .data
+            .take(&mut payload)
+            .ok_or_else(|| Error::Missing(self.data.to_string()))?;
+        Ok(serde_json::to_vec(&data)?.into())
+    }
+}
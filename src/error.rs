@@ -3,7 +3,11 @@
 /// If a user adds further dependencies, they should redefine further objects within Error enum.
 ///
 /// If not: any undefined error will return an `anyhow::Error`, defined as `Other(Error)`.
+///
+/// New variants may be added, so matches on it need a wildcard arm. The variants of optional
+/// connectors exist whether or not their features are enabled, holding their errors' messages.
 #[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// reqwest
     #[error("could not fetch URL: {0}")]
@@ -17,7 +21,321 @@ pub enum Error {
     #[error("could not convert source to JSON: {0}")]
     JSON(#[from] serde_json::Error),
 
+    /// serde_json, decoding an extracted payload; with the path to the offending value
+    /// (e.g., `chart.result[0].meta.symbol`) and the text around it
+    #[error("could not decode source at `{path}`: {source}, near `{snippet}`")]
+    Decode {
+        path: String,
+        snippet: String,
+        source: serde_json::Error,
+    },
+
+    /// csv
+    #[error("could not read or write CSV: {0}")]
+    Csv(#[from] csv::Error),
+
+    /// tokio-postgres
+    #[error("postgres query failed: {0}")]
+    Postgres(#[from] tokio_postgres::Error),
+
+    /// scylla
+    #[error("scylla query failed: {0}")]
+    Scylla(#[from] scylla::transport::errors::QueryError),
+
+    /// apache-avro; a schema that can't be parsed, or a record that doesn't match it
+    #[error("avro error: {0}")]
+    Avro(String),
+
+    /// rdkafka
+    #[error("kafka error: {0}")]
+    Kafka(String),
+
+    /// rumqttc; a request the client couldn't queue, or a connection to the broker that kept
+    /// failing
+    #[error("mqtt error: {0}")]
+    Mqtt(String),
+
+    /// async-nats; connecting, publishing, or a message the stream didn't acknowledge
+    #[error("nats error: {0}")]
+    Nats(String),
+
+    /// lapin; connecting, publishing, or a message the broker nacked or couldn't route
+    #[error("rabbitmq error: {0}")]
+    RabbitMq(String),
+
+    /// lettre
+    #[error("smtp error: {0}")]
+    Smtp(String),
+
+    /// notify, watching a source file; see [`crate::watch`]
+    #[error("could not watch file: {0}")]
+    Watch(String),
+
+    /// a field couldn't be encrypted or decrypted, e.g., with the wrong key; see [`crate::crypto`]
+    #[error("field encryption failed: {0}")]
+    Crypto(String),
+
+    /// a failure injected for testing; see [`crate::chaos`]
+    #[error("injected failure: {0}")]
+    Injected(String),
+
+    /// invalid pipe configuration
+    #[error("invalid configuration: {0}")]
+    Config(String),
+
+    /// a stage attempt ran past the configured timeout
+    #[error("{stage} timed out after {timeout:?}")]
+    Timeout {
+        stage: crate::observer::Stage,
+        timeout: std::time::Duration,
+    },
+
+    /// an API's daily request budget doesn't cover the requests needed; see [`crate::quota`]
+    #[error("{needed} requests needed, but only {remaining} are left of the daily quota, until {resets_at}")]
+    QuotaExceeded {
+        needed: u32,
+        remaining: u32,
+        resets_at: chrono::DateTime<chrono::Utc>,
+    },
+
+    /// a staged load did not pass verification, so it was not published
+    #[error("verification failed: {0}")]
+    Verification(String),
+
+    /// a path plucked by a derived `transform()` (or an [`Envelope`]'s data) had no value, e.g.,
+    /// an out-of-bounds index
+    ///
+    /// [`Envelope`]: crate::envelope::Envelope
+    #[error("nothing found at `{0}`")]
+    Missing(String),
+
+    /// something the crate relies on didn't hold, e.g., a semaphore was closed; a bug, reported
+    /// as an error rather than a panic
+    #[error("internal error: {0}")]
+    Invariant(String),
+
+    /// the API reported an error in its response's envelope; see [`envelope`](crate::envelope)
+    #[error("the upstream API returned an error: {0}")]
+    Upstream(String),
+
+    /// a date or time couldn't be parsed, or is out of range; see [`crate::time`]
+    #[error("invalid date/time: {0}")]
+    Time(String),
+
+    /// the output's fields don't match the table loaded to; see [`postgresql::reconcile()`]
+    ///
+    /// [`postgresql::reconcile()`]: crate::db::postgresql::reconcile
+    #[error("schema drift in {0}")]
+    Drift(crate::db::postgresql::Drift),
+
+    /// a load was over its preflight's thresholds, and wasn't confirmed; see [`Preflight`]
+    ///
+    /// [`Preflight`]: crate::db::postgresql::Preflight
+    #[error("load refused by its preflight: {0}")]
+    Preflight(crate::db::postgresql::Estimate),
+
+    /// an output failed its quality checks, with the checks enforced; see [`Quality::enforced()`]
+    ///
+    /// [`Quality::enforced()`]: crate::quality::Quality::enforced
+    #[error("quality checks failed: {0}")]
+    Checks(crate::quality::Failed),
+
+    /// a CouchDB document kept conflicting with concurrent writes; see [`couchdb::upsert_doc()`]
+    ///
+    /// [`couchdb::upsert_doc()`]: crate::db::couchdb::upsert_doc
+    #[error("document `{doc_id}` was still in conflict after {attempts} attempts")]
+    Conflict { doc_id: String, attempts: u32 },
+
+    /// a sink pushed back on a load, e.g., CouchDB's `429 Too Many Requests`; retried after the
+    /// delay it asked for, if any; see [`Hint`](crate::retry::Hint)
+    #[error("the sink is overloaded: {message}")]
+    Backpressure {
+        message: String,
+        retry_after: Option<std::time::Duration>,
+    },
+
+    /// the sink's circuit breaker is open, after too many consecutive failures
+    #[error("the sink is unavailable; retry in {retry_in:?}")]
+    CircuitOpen { retry_in: std::time::Duration },
+
+    /// several failures at once, e.g., from a batched load
+    #[error("{0}")]
+    Many(Errors),
+
     /// undefined errors are umbrella'd under here
     #[error("{0}")]
     Other(#[from] anyhow::Error),
 }
+
+// the errors of optional connectors, by their messages; so the variants don't depend on features
+macro_rules! from_optional {
+    ($($feature:literal: $error:ty => $variant:ident),* $(,)?) => {$(
+        #[cfg(feature = $feature)]
+        impl From<$error> for Error {
+            fn from(error: $error) -> Self {
+                Error::$variant(error.to_string())
+            }
+        }
+    )*};
+}
+
+from_optional! {
+    "avro": apache_avro::Error => Avro,
+    "kafka": rdkafka::error::KafkaError => Kafka,
+    "mqtt": rumqttc::ClientError => Mqtt,
+    "smtp": lettre::transport::smtp::Error => Smtp,
+    "watch": notify::Error => Watch,
+}
+
+/// Where a failure happened, within an operation over many endpoints or records.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Context {
+    /// An endpoint (File Path or URL), e.g., one of the paths given to [`etl_many()`].
+    ///
+    /// [`etl_many()`]: crate::Pipe::etl_many
+    Endpoint(String),
+    /// A single record, by its index in the output.
+    Record(usize),
+    /// A range of records (`start..end`), e.g., one batch of a batched load.
+    Records { start: usize, end: usize },
+    /// A route of a [`Router`] sink, by its name.
+    ///
+    /// [`Router`]: crate::sink::Router
+    Route(String),
+    /// A pipeline, by its name in a [`Runner`].
+    ///
+    /// [`Runner`]: crate::Runner
+    Pipeline(String),
+    /// No particular location, e.g., an invalid pipe configuration.
+    None,
+}
+
+impl std::fmt::Display for Context {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Context::Endpoint(path) => write!(f, "{path}"),
+            Context::Record(index) => write!(f, "record {index}"),
+            Context::Records { start, end } => write!(f, "records {start}..{end}"),
+            Context::Route(name) => write!(f, "route `{name}`"),
+            Context::Pipeline(name) => write!(f, "pipeline `{name}`"),
+            Context::None => Ok(()),
+        }
+    }
+}
+
+/// One failure of an [`Errors`] collection, with its location.
+#[derive(Debug)]
+pub struct Failure {
+    pub context: Context,
+    pub error: Error,
+}
+
+/// Every failure of an operation over many endpoints or records, rather than just the first one.
+///
+/// Indexable & iterable, in the order the failures happened:
+///
+/// ```rust,ignore
+/// if let Err(errors) = pipe.etl_many(paths).await {
+///     for failure in &errors {
+///         eprintln!("{}: {}", failure.context, failure.error);
+///     }
+///     let first = &errors[0];
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct Errors(Vec<Failure>);
+
+impl Errors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, context: Context, error: Error) {
+        self.0.push(Failure { context, error });
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Failure> {
+        self.0.iter()
+    }
+
+    /// The failures of a given context.
+    pub fn at<'a>(&'a self, context: &'a Context) -> impl Iterator<Item = &'a Error> + 'a {
+        self.0
+            .iter()
+            .filter(move |f| &f.context == context)
+            .map(|f| &f.error)
+    }
+
+    /// `Ok(())` if there were no failures.
+    pub fn into_result(self) -> Result<(), Errors> {
+        match self.is_empty() {
+            true => Ok(()),
+            false => Err(self),
+        }
+    }
+}
+
+/// A single failure, of no particular location.
+impl From<Error> for Errors {
+    fn from(error: Error) -> Self {
+        let mut errors = Errors::new();
+        errors.push(Context::None, error);
+        errors
+    }
+}
+
+impl std::ops::Index<usize> for Errors {
+    type Output = Failure;
+
+    fn index(&self, index: usize) -> &Failure {
+        &self.0[index]
+    }
+}
+
+impl IntoIterator for Errors {
+    type Item = Failure;
+    type IntoIter = std::vec::IntoIter<Failure>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Errors {
+    type Item = &'a Failure;
+    type IntoIter = std::slice::Iter<'a, Failure>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl std::fmt::Display for Errors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} error(s)", self.0.len())?;
+        for failure in &self.0 {
+            match failure.context {
+                Context::None => write!(f, "; {}", failure.error)?,
+                _ => write!(f, "; {}: {}", failure.context, failure.error)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Errors {}
+
+impl From<Errors> for Error {
+    fn from(errors: Errors) -> Self {
+        Error::Many(errors)
+    }
+}
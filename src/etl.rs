@@ -1,7 +1,10 @@
-use super::{default, Error, Input, Output};
+use super::lineage::Lineage;
+use super::sample::Sample;
+use super::{default, Error, Input, Output, Pipe};
+use bytes::Bytes;
 use std::future::Future;
 
-pub trait ETL<I, O>
+pub trait ETL<I, O>: Sync
 where
     I: Input,
     O: Output,
@@ -10,33 +13,107 @@ where
     ///
     /// - ***path*** --- Path to the endpoint.
     ///
+    /// *The default implementation [`fetch()`]es the bytes at `path`, then [`decode()`]s them;
+    /// so either half can be overridden on its own.*
+    ///
+    /// [`fetch()`]: ETL::fetch
+    /// [`decode()`]: ETL::decode
+    fn extract(&self, path: &str) -> impl Future<Output = Result<I, Error>> + Send {
+        async {
+            let bytes = self.fetch(path).await?;
+            self.decode(bytes)
+        }
+    }
+
+    /// Fetch the raw bytes at some endpoint; the first half of [`extract()`].
+    ///
+    /// - ***path*** --- Path to the endpoint.
+    ///
     /// *The default implementation sends a GET request if `path`starts with `http`,
-    /// and then tries to open a file from `path` if not.*
-    fn extract(&self, path: &str) -> impl Future<Output = Result<I, Error>> {
-        async { default::extract(path).await }
+    /// and then tries to read a file from `path` if not.*
+    ///
+    /// [`extract()`]: ETL::extract
+    fn fetch(&self, path: &str) -> impl Future<Output = Result<Bytes, Error>> + Send {
+        async { default::fetch(path).await }
+    }
+
+    /// Decode fetched bytes to a value of input type `I`; the second half of [`extract()`].
+    ///
+    /// - ***bytes*** --- The fetched bytes.
+    ///
+    /// *The default implementation deserializes them as JSON; see [`default::decode()`].*
+    ///
+    /// [`extract()`]: ETL::extract
+    fn decode(&self, bytes: Bytes) -> Result<I, Error> {
+        default::decode(bytes)
     }
 
     /// Transform input type `I` to some output type `O`.
     ///
     /// - ***input*** --- The transformed data.
-    fn transform(&self, _input: I) -> impl Future<Output = Result<O, Error>>;
+    fn transform(&self, _input: I) -> impl Future<Output = Result<O, Error>> + Send;
 
     /// Load output type `O` to some Database.
     ///
     /// - ***output*** --- The transformed data.
     /// - ***conn*** --- Connection query string for connecting to the database.
     /// - ***doc_id*** --- Name/ID of document/table to update/create within the database.
-    fn load(&self, output: O, conn: &str, doc_id: &str) -> impl Future<Output = Result<(), Error>> {
+    fn load(
+        &self,
+        output: O,
+        conn: &str,
+        doc_id: &str,
+    ) -> impl Future<Output = Result<(), Error>> + Send {
         async { default::load(output, conn, doc_id).await }
     }
 
+    /// Whether [`extract()`] is [`fetch()`] then [`decode()`], as by default; a pipe that skips
+    /// unchanged payloads hashes the fetched bytes, so can't run one that isn't.
+    ///
+    /// *The default implementation is `true`; `pipeline!` implements it as `false` for a block
+    /// with its own `extract()`. Override it along with `extract()`.*
+    ///
+    /// [`extract()`]: ETL::extract
+    /// [`fetch()`]: ETL::fetch
+    /// [`decode()`]: ETL::decode
+    fn extracts_by_fetch(&self) -> bool {
+        true
+    }
+
+    /// Where the fields of the output come from, as declared by [`transform()`]; see
+    /// [`lineage`](crate::lineage).
+    ///
+    /// *The default implementation declares none; `#[derive(Transform)]` declares each field.*
+    ///
+    /// [`transform()`]: ETL::transform
+    fn lineage(&self) -> Lineage {
+        Lineage::default()
+    }
+
+    /// Load a sample of the output's records, e.g., its first `n`, with [`load()`]; to verify the
+    /// database end to end before loading all of it. See [`sample`](crate::sample).
+    ///
+    /// - ***sample*** --- The records to load; a number is the first that many.
+    ///
+    /// [`load()`]: ETL::load
+    fn load_sample(
+        &self,
+        output: &O,
+        sample: impl Into<Sample>,
+        conn: &str,
+        doc_id: &str,
+    ) -> impl Future<Output = Result<(), Error>> + Send {
+        let sampled = sample.into().of(output);
+        async move { self.load(sampled?, conn, doc_id).await }
+    }
+
     /// [`extract()`] & [`transform()`]
     ///
     /// Extract some value as type `I`, and then transform it to type `O`.
     ///
     /// [`extract()`]: crate::pipe::Pipe::extract
     /// [`transform()`]: crate::pipe::Pipe::transform
-    fn extran(&self, path: &str) -> impl Future<Output = Result<O, Error>> {
+    fn extran(&self, path: &str) -> impl Future<Output = Result<O, Error>> + Send {
         async {
             let input = self.extract(path).await?;
             self.transform(input).await
@@ -50,7 +127,12 @@ where
     /// [`extract()`]: crate::pipe::Pipe::extract
     /// [`transform()`]: crate::pipe::Pipe::transform
     /// [`load()`]: crate::pipe::Pipe::load
-    fn etl(&self, path: &str, conn: &str, doc_id: &str) -> impl Future<Output = Result<(), Error>> {
+    fn etl(
+        &self,
+        path: &str,
+        conn: &str,
+        doc_id: &str,
+    ) -> impl Future<Output = Result<(), Error>> + Send {
         async {
             let input = self.extract(path).await?;
             let output = self.transform(input).await?;
@@ -61,21 +143,105 @@ where
     /// Closure format of [`extract()`].
     ///
     /// [`extract()`]: crate::pipe::Pipe::extract
-    fn map_extract() {
-        unimplemented!()
-    }
+    #[deprecated(note = "never implemented; it does nothing")]
+    fn map_extract() {}
 
     /// Closure format of [`transform()`].
     ///
     /// [`transform()`]: crate::pipe::Pipe::transform
-    fn map_transform() {
-        unimplemented!()
-    }
+    #[deprecated(note = "never implemented; it does nothing")]
+    fn map_transform() {}
 
     /// Closure format of [`load()`].
     ///
     /// [`load()`]: crate::pipe::Pipe::load
-    fn map_load() {
-        unimplemented!()
+    #[deprecated(note = "never implemented; it does nothing")]
+    fn map_load() {}
+}
+
+/// ETL implemented on the input type itself, with static functions rather than methods;
+/// for one-off pipelines that need no [`Pipe`] configuration.
+///
+/// ```rust,ignore
+/// impl SelfPipe<Vec<Price>> for RawPrice {
+///     // using extract() & load() default impls
+///     async fn transform(data: RawPrice) -> Result<Vec<Price>, pipe_io::Error> {
+///         ...
+///     }
+/// }
+///
+/// RawPrice::etl(url, conn, "prices").await?;
+/// ```
+///
+/// Every `SelfPipe` is also an [`ETL`], through [`I::pipe()`]; so the same impl can be run with
+/// a pipe's configuration, e.g., `Pipe::<RawPrice, Vec<Price>>::builder()`.
+///
+/// [`I::pipe()`]: SelfPipe::pipe
+pub trait SelfPipe<O>: Input + Sized
+where
+    O: Output,
+{
+    /// Extract data from some endpoint (e.g., URL or File Path); see [`ETL::extract()`].
+    fn extract(path: &str) -> impl Future<Output = Result<Self, Error>> + Send {
+        async { default::extract(path).await }
+    }
+
+    /// Transform the input to output type `O`; see [`ETL::transform()`].
+    fn transform(input: Self) -> impl Future<Output = Result<O, Error>> + Send;
+
+    /// Load output type `O` to some Database; see [`ETL::load()`].
+    fn load(output: O, conn: &str, doc_id: &str) -> impl Future<Output = Result<(), Error>> + Send {
+        async { default::load(output, conn, doc_id).await }
+    }
+
+    /// [`extract()`] & [`transform()`]
+    ///
+    /// [`extract()`]: SelfPipe::extract
+    /// [`transform()`]: SelfPipe::transform
+    fn extran(path: &str) -> impl Future<Output = Result<O, Error>> + Send {
+        async {
+            let input = Self::extract(path).await?;
+            Self::transform(input).await
+        }
+    }
+
+    /// [`extract()`] & [`transform()`] & [`load()`]
+    ///
+    /// [`extract()`]: SelfPipe::extract
+    /// [`transform()`]: SelfPipe::transform
+    /// [`load()`]: SelfPipe::load
+    fn etl(path: &str, conn: &str, doc_id: &str) -> impl Future<Output = Result<(), Error>> + Send {
+        async {
+            let output = Self::extran(path).await?;
+            Self::load(output, conn, doc_id).await
+        }
+    }
+
+    /// A pipe running this impl; configure one with [`Pipe::builder()`] instead.
+    fn pipe() -> Pipe<Self, O> {
+        Pipe::new()
+    }
+}
+
+impl<I, O> ETL<I, O> for Pipe<I, O>
+where
+    I: SelfPipe<O>,
+    O: Output,
+{
+    fn extract(&self, path: &str) -> impl Future<Output = Result<I, Error>> + Send {
+        I::extract(path)
+    }
+
+    fn transform(&self, input: I) -> impl Future<Output = Result<O, Error>> + Send {
+        I::transform(input)
+    }
+
+    fn load(
+        &self,
+        output: O,
+        conn: &str,
+        doc_id: &str,
+    ) -> impl Future<Output = Result<(), Error>> + Send {
+        I::load(output, conn, doc_id)
     }
 }
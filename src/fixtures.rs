@@ -0,0 +1,285 @@
+//! Regression tests of a transform over many sample payloads: every input fixture is run through
+//! [`extran()`], and its output compared with a golden file, as pretty-printed JSON.
+//!
+//! ```text
+//! tests/fixtures/
+//!     prices/nvda.json          <- an input
+//!     prices/nvda.golden.json   <- its expected output
+//! ```
+//!
+//! ```rust,ignore
+//! #[tokio::test]
+//! async fn transforms_match_their_golden_files() {
+//!     Fixtures::new("tests/fixtures/prices")
+//!         .check(&Pipe::<RawPrice, Vec<Price>>::new())
+//!         .await
+//!         .assert();
+//! }
+//! ```
+//!
+//! A mismatch is shown as a line diff of the two; once the change is intended, run the tests with
+//! `PIPE_IO_BLESS=1` to write every output as its golden file instead (e.g., for new fixtures).
+//!
+//! [`extran()`]: crate::ETL::extran
+
+use super::{Error, Input, Output, Pipe, ETL};
+use serde_json::Value;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// The environment variable that turns on bless mode, unless it's empty or `0`.
+pub const BLESS: &str = "PIPE_IO_BLESS";
+
+const GOLDEN: &str = ".golden.json";
+// lines of unchanged context around each change in a diff
+const CONTEXT: usize = 3;
+
+/// The input fixtures under a directory; see [`fixtures`](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fixtures {
+    pub root: PathBuf,
+    /// Write the golden files, rather than compare with them; from [`BLESS`] by default.
+    pub bless: bool,
+}
+
+/// What became of one fixture.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    /// The output was its golden file.
+    Matched,
+    /// The output was written as its golden file.
+    Blessed,
+    /// There was no golden file to compare with.
+    Missing,
+    /// The output wasn't its golden file; with the line diff, from the golden file to it.
+    Differs(String),
+    /// The fixture couldn't be read, extracted or transformed.
+    Failed(String),
+}
+
+/// The verdict of every fixture, by path, in order; see [`Fixtures::check()`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Checked {
+    pub fixtures: Vec<(PathBuf, Verdict)>,
+}
+
+impl Fixtures {
+    /// The fixtures under `root` (e.g., `tests/fixtures`), recursively: every `.json` file that
+    /// isn't a golden file.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let bless = std::env::var(BLESS).is_ok_and(|bless| !bless.is_empty() && bless != "0");
+        Fixtures {
+            root: root.into(),
+            bless,
+        }
+    }
+
+    pub fn bless(mut self, bless: bool) -> Self {
+        self.bless = bless;
+        self
+    }
+
+    /// Every input fixture, sorted by path.
+    pub fn inputs(&self) -> Result<Vec<PathBuf>, Error> {
+        let mut inputs = vec![];
+        let mut dirs = vec![self.root.clone()];
+        while let Some(dir) = dirs.pop() {
+            for entry in std::fs::read_dir(&dir)? {
+                let path = entry?.path();
+                let name = path.to_string_lossy();
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if name.ends_with(".json") && !name.ends_with(GOLDEN) {
+                    inputs.push(path);
+                }
+            }
+        }
+        inputs.sort();
+        Ok(inputs)
+    }
+
+    /// The golden file of the fixture at `input`: `name.json`'s is `name.golden.json`.
+    pub fn golden(input: &Path) -> PathBuf {
+        input.with_extension("golden.json")
+    }
+
+    /// Run every fixture through `pipe`, comparing (or, blessing, writing) its golden file.
+    ///
+    /// A fixture that can't be run fails with its error, as its verdict; and a missing root fails
+    /// as the only fixture.
+    pub async fn check<I, O>(&self, pipe: &Pipe<I, O>) -> Checked
+    where
+        I: Input,
+        O: Output,
+        Pipe<I, O>: ETL<I, O>,
+    {
+        let inputs = match self.inputs() {
+            Ok(inputs) => inputs,
+            Err(e) => {
+                let verdict = Verdict::Failed(format!("no fixtures: {e}"));
+                return Checked {
+                    fixtures: vec![(self.root.clone(), verdict)],
+                };
+            }
+        };
+        let mut fixtures = vec![];
+        for input in inputs {
+            let verdict = match self.verdict(pipe, &input).await {
+                Ok(verdict) => verdict,
+                Err(e) => Verdict::Failed(e.to_string()),
+            };
+            fixtures.push((input, verdict));
+        }
+        Checked { fixtures }
+    }
+
+    async fn verdict<I, O>(&self, pipe: &Pipe<I, O>, input: &Path) -> Result<Verdict, Error>
+    where
+        I: Input,
+        O: Output,
+        Pipe<I, O>: ETL<I, O>,
+    {
+        let output = pipe.extran(&input.to_string_lossy()).await?;
+        let actual = pretty(&serde_json::to_value(&output)?)?;
+        let golden = Self::golden(input);
+        let expected = match std::fs::read_to_string(&golden) {
+            Ok(expected) => Some(expected),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        // compared as JSON, so the golden files' formatting can't fail them
+        let reformatted = match &expected {
+            Some(expected) => serde_json::from_str::<Value>(expected)
+                .ok()
+                .map(|expected| pretty(&expected))
+                .transpose()?,
+            None => None,
+        };
+        if reformatted.as_ref() == Some(&actual) {
+            return Ok(Verdict::Matched);
+        }
+        if self.bless {
+            std::fs::write(&golden, &actual)?;
+            return Ok(Verdict::Blessed);
+        }
+        Ok(match (reformatted, expected) {
+            (Some(reformatted), _) => Verdict::Differs(diff(&reformatted, &actual)),
+            (None, Some(expected)) => Verdict::Differs(diff(&expected, &actual)),
+            (None, None) => Verdict::Missing,
+        })
+    }
+}
+
+impl Checked {
+    /// Whether every fixture matched (or was blessed).
+    pub fn is_ok(&self) -> bool {
+        !self.fixtures.is_empty()
+            && self
+                .fixtures
+                .iter()
+                .all(|(_, verdict)| matches!(verdict, Verdict::Matched | Verdict::Blessed))
+    }
+
+    /// Fail the calling test unless every fixture matched, showing the diffs of those that didn't.
+    #[allow(clippy::panic)] // a test helper, failing its test
+    pub fn assert(&self) {
+        if !self.is_ok() {
+            panic!("{self}");
+        }
+    }
+}
+
+impl fmt::Display for Checked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.fixtures.is_empty() {
+            return write!(f, "no fixtures");
+        }
+        let failed = self
+            .fixtures
+            .iter()
+            .filter(|(_, verdict)| !matches!(verdict, Verdict::Matched | Verdict::Blessed));
+        let mut failures = 0;
+        for (path, verdict) in failed {
+            failures += 1;
+            match verdict {
+                Verdict::Missing => writeln!(f, "{}: no golden file", path.display())?,
+                Verdict::Failed(e) => writeln!(f, "{}: {e}", path.display())?,
+                Verdict::Differs(diff) => {
+                    writeln!(f, "{}: differs from its golden file", path.display())?;
+                    writeln!(f, "{diff}")?;
+                }
+                Verdict::Matched | Verdict::Blessed => {}
+            }
+        }
+        write!(f, "{failures} of {} fixtures failed", self.fixtures.len())?;
+        if failures > 0 {
+            write!(
+                f,
+                "; rerun with {BLESS}=1 to write the outputs as golden files"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// `value`, as the golden files hold it
+fn pretty(value: &Value) -> Result<String, Error> {
+    Ok(serde_json::to_string_pretty(value)? + "\n")
+}
+
+// The lines changed from `expected` to `actual`: `-` removed, `+` added, & a few unchanged lines
+// of context around each change.
+fn diff(expected: &str, actual: &str) -> String {
+    let (old, new): (Vec<&str>, Vec<&str>) = (expected.lines().collect(), actual.lines().collect());
+    // the longest common subsequence of every pair of suffixes; only for files of a sensible size
+    let (n, m) = (old.len(), new.len());
+    if n.saturating_mul(m) > 16_000_000 {
+        return format!("- ({n} lines)\n+ ({m} lines)");
+    }
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = match old[i] == new[j] {
+                true => lcs[i + 1][j + 1] + 1,
+                false => lcs[i + 1][j].max(lcs[i][j + 1]),
+            };
+        }
+    }
+    let mut lines = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && old[i] == new[j] {
+            lines.push((' ', old[i]));
+            (i, j) = (i + 1, j + 1);
+        } else if j < m && (i == n || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            lines.push(('+', new[j]));
+            j += 1;
+        } else {
+            lines.push(('-', old[i]));
+            i += 1;
+        }
+    }
+
+    // only the unchanged lines near a change
+    let mut near = vec![false; lines.len()];
+    for (k, _) in lines
+        .iter()
+        .enumerate()
+        .filter(|(_, (sign, _))| *sign != ' ')
+    {
+        let end = (k + CONTEXT + 1).min(lines.len());
+        near[k.saturating_sub(CONTEXT)..end].fill(true);
+    }
+    let mut shown = vec![];
+    let mut skipped = false;
+    for (k, (sign, line)) in lines.iter().enumerate() {
+        if near[k] {
+            shown.push(format!("{sign} {line}"));
+            skipped = false;
+        } else if !skipped {
+            shown.push("  ...".to_string());
+            skipped = true;
+        }
+    }
+    shown.join("\n")
+}
@@ -0,0 +1,109 @@
+use super::{Error, EtlReport, Input, Output, Pipe, ETL};
+use futures::future::LocalBoxFuture;
+
+/// One input, many outputs: extract once, then transform & load it once per branch.
+///
+/// Each branch is an ordinary [`Pipe`] from the same input type `I`, with its own `transform()` and sink;
+/// its other options (retries, timeouts, observer) apply to its transform & load.
+/// The extraction is done by the first branch, with its configuration.
+///
+/// ```rust,ignore
+/// let prices = Pipe::<RawPrice, Prices>::builder().sink(sink::Postgres::new(conn, "prices")).build()?;
+/// let meta = Pipe::<RawPrice, Meta>::builder().sink(sink::CouchDb::new(couch, "meta")).build()?;
+///
+/// Fork::new().branch(prices).branch(meta).run(url).await?;
+/// ```
+///
+/// Every branch but the last gets a clone of the input, so `I` must be `Clone`.
+pub struct Fork<I> {
+    branches: Vec<Box<dyn Branch<I>>>,
+}
+
+// a `Pipe<I, O>` with its `O` erased, so that branches with different outputs can be stored together
+trait Branch<I> {
+    fn extract_boxed<'a>(&'a self, path: &'a str) -> LocalBoxFuture<'a, Result<I, Error>>;
+    fn transform_and_load<'a>(
+        &'a self,
+        input: I,
+        report: &'a mut EtlReport,
+    ) -> LocalBoxFuture<'a, Result<(), Error>>;
+}
+
+impl<I, O> Branch<I> for Pipe<I, O>
+where
+    I: Input,
+    O: Output,
+    Pipe<I, O>: ETL<I, O>,
+{
+    fn extract_boxed<'a>(&'a self, path: &'a str) -> LocalBoxFuture<'a, Result<I, Error>> {
+        Box::pin(self.extract_stage(path))
+    }
+
+    fn transform_and_load<'a>(
+        &'a self,
+        input: I,
+        report: &'a mut EtlReport,
+    ) -> LocalBoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let output = self.transform_stage(input).await?;
+            let output = self.enrich_stage(output).await?;
+            self.replay_stage(report).await?;
+            report.push(self.load_stage(&output).await?);
+            Ok(())
+        })
+    }
+}
+
+impl<I> Default for Fork<I>
+where
+    I: Input + Clone + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I> Fork<I>
+where
+    I: Input + Clone + 'static,
+{
+    pub fn new() -> Self {
+        Fork { branches: vec![] }
+    }
+
+    /// Add a branch; its pipe must have a sink configured.
+    pub fn branch<O>(mut self, pipe: Pipe<I, O>) -> Self
+    where
+        O: Output + 'static,
+        Pipe<I, O>: ETL<I, O>,
+    {
+        self.branches.push(Box::new(pipe));
+        self
+    }
+
+    /// Extract `path` once, then transform & load it through every branch, in order; reporting
+    /// every branch's loads, in order, with those replayed from its journal (if any) first.
+    pub async fn run(&self, path: &str) -> Result<EtlReport, Error> {
+        let first = self
+            .branches
+            .first()
+            .ok_or_else(|| Error::Config("a fork needs at least 1 branch".into()))?;
+        let input = first.extract_boxed(path).await?;
+        self.run_input(input).await
+    }
+
+    /// Transform & load an already extracted `input` through every branch, in order; see
+    /// [`run()`](Fork::run).
+    pub async fn run_input(&self, input: I) -> Result<EtlReport, Error> {
+        let mut report = EtlReport::new();
+        if let Some((last, rest)) = self.branches.split_last() {
+            for branch in rest {
+                branch
+                    .transform_and_load(input.clone(), &mut report)
+                    .await?;
+            }
+            last.transform_and_load(input, &mut report).await?;
+        }
+        Ok(report)
+    }
+}
@@ -0,0 +1,74 @@
+//! Files written the same way on Linux, macOS & Windows: names derived from ids (e.g., a
+//! document's versions, or a pipeline's name) escaped into valid file names, and writes that
+//! replace a file whole, or not at all.
+//!
+//! ```rust,ignore
+//! let path = dir.join(fs::file_name("prices@2024-06-01T12:00:00Z") + ".json");
+//! // prices@2024-06-01T12%3A00%3A00Z.json
+//! fs::write_atomic(&path, &bytes)?;
+//! ```
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// `name`, escaped into a file name that's valid on every platform; e.g., `a/b:c` is `a%2Fb%3Ac`.
+///
+/// Path separators, the characters Windows reserves (`<>:"/\|?*`), control characters & `%`
+/// are escaped as `%XX`; as are a trailing dot or space, and the first character of a name
+/// Windows reserves for a device (e.g., `CON`, `nul.json`). Anything else is kept, so distinct
+/// names stay distinct.
+pub fn file_name(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for (i, c) in name.char_indices() {
+        let last = i + c.len_utf8() == name.len();
+        let reserved = matches!(
+            c,
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' | '%'
+        ) || c.is_control()
+            || (last && matches!(c, '.' | ' '))
+            || (i == 0 && is_device(name));
+        match reserved {
+            true => escaped.extend(c.to_string().bytes().map(|b| format!("%{b:02X}"))),
+            false => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Whether Windows reserves `name` for a device, with or without an extension.
+fn is_device(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or_default().trim_end();
+    let stem = stem.to_ascii_uppercase();
+    matches!(stem.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+        || (stem.len() == 4
+            && (stem.starts_with("COM") || stem.starts_with("LPT"))
+            && stem.as_bytes()[3].is_ascii_digit()
+            && stem.as_bytes()[3] != b'0')
+}
+
+/// Write `bytes` to `path`, replacing the file whole: they're written to a temporary file
+/// beside it, which is then renamed over it; so a reader, or a crash, never sees half a file.
+///
+/// The temporary file is in the same directory, rather than the platform's temp dir, as a rename
+/// is only atomic within one file system.
+pub fn write_atomic(path: impl AsRef<Path>, bytes: &[u8]) -> std::io::Result<()> {
+    let path = path.as_ref();
+    let temp = temp_path(path);
+    let written = std::fs::File::create(&temp).and_then(|mut file| {
+        file.write_all(bytes)?;
+        file.sync_all()
+    });
+    match written.and_then(|()| std::fs::rename(&temp, path)) {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            let _ = std::fs::remove_file(&temp);
+            Err(e)
+        }
+    }
+}
+
+// `.<name>.<id>.tmp`, beside `path`.
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{name}.{}.tmp", uuid::Uuid::new_v4().simple()))
+}
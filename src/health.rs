@@ -0,0 +1,237 @@
+use super::db::*;
+use super::observer::Event;
+use super::sink::{self, Sink};
+use super::{Error, Observer, Outcome};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A sink whose destination can be checked, without loading anything to it.
+pub trait Health: Send + Sync {
+    /// Connect to the destination, and check that it's ready to be loaded to.
+    fn check(&self) -> impl Future<Output = Result<(), Error>> + Send;
+}
+
+/// The state of a [`Breaker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Circuit {
+    /// Loads go through to the sink.
+    Closed,
+    /// The sink kept failing; loads fail straight away, until the cooldown has passed.
+    Open,
+    /// The cooldown has passed; the next load is let through after a health check, as a probe,
+    /// while any others fail straight away until it's done.
+    HalfOpen,
+}
+
+impl std::fmt::Display for Circuit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Circuit::Closed => write!(f, "closed"),
+            Circuit::Open => write!(f, "open"),
+            Circuit::HalfOpen => write!(f, "half-open"),
+        }
+    }
+}
+
+struct State {
+    circuit: Circuit,
+    failures: u32,
+    opened: Instant,
+    // when the half-open circuit's probe was let through; `None` if there's none in flight
+    probing: Option<Instant>,
+}
+
+/// A circuit breaker around a sink, so a failing destination fails fast instead of on every load.
+///
+/// After `threshold` consecutive failures (of loads or health checks) the circuit opens, and loads
+/// fail with [`Error::CircuitOpen`] without reaching the sink. Once the cooldown has passed, the
+/// circuit half-opens: the next load first checks the sink's health (reconnecting, for the
+/// database sinks), closing the circuit on success, or opening it again on failure. Only that one
+/// load probes the sink; the others fail fast until it's done (or a cooldown has passed without
+/// it finishing, e.g., as it was cancelled).
+///
+/// For long-lived pipes, [`monitor()`] also checks the sink's health in the background; every
+/// change of state is reported to the observer as an [`Event::Circuit`].
+///
+/// ```rust,ignore
+/// let sink = Arc::new(Breaker::new(sink::Postgres::new(conn, "prices"), 5, Duration::from_secs(30)));
+/// let monitor = sink.monitor(Duration::from_secs(10));
+/// let pipe = Pipe::<I, O>::builder().sink(sink.clone()).build()?;
+/// ```
+///
+/// [`monitor()`]: Breaker::monitor
+pub struct Breaker<S> {
+    sink: S,
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<State>,
+    observer: Option<Arc<dyn Observer>>,
+}
+
+impl<S> Breaker<S>
+where
+    S: Health,
+{
+    pub fn new(sink: S, threshold: u32, cooldown: Duration) -> Self {
+        Breaker {
+            sink,
+            threshold: threshold.max(1),
+            cooldown,
+            state: Mutex::new(State {
+                circuit: Circuit::Closed,
+                failures: 0,
+                opened: Instant::now(),
+                probing: None,
+            }),
+            observer: None,
+        }
+    }
+
+    /// Receive an [`Event::Circuit`] whenever the circuit changes state.
+    pub fn observer<T>(mut self, observer: T) -> Self
+    where
+        T: Observer + 'static,
+    {
+        self.observer = Some(Arc::new(observer));
+        self
+    }
+
+    /// The wrapped sink.
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    pub fn circuit(&self) -> Circuit {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .circuit
+    }
+
+    /// Check the sink's health now, counting a failure towards opening the circuit.
+    pub async fn probe(&self) -> Result<(), Error> {
+        let result = self.sink.check().await;
+        self.record(result.is_ok());
+        result
+    }
+
+    /// Check the sink's health every `every`, in the background, until the handle is aborted.
+    pub fn monitor(self: &Arc<Self>, every: Duration) -> tokio::task::JoinHandle<()>
+    where
+        S: 'static,
+    {
+        let breaker = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(every);
+            loop {
+                interval.tick().await;
+                let _ = breaker.probe().await;
+            }
+        })
+    }
+
+    // Let a load through? `Ok(true)` if it has to be preceded by a health check.
+    fn admit(&self) -> Result<bool, Error> {
+        let mut state = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        match state.circuit {
+            Circuit::Closed => Ok(false),
+            Circuit::HalfOpen => match state.probing {
+                Some(probing) if probing.elapsed() < self.cooldown => Err(Error::CircuitOpen {
+                    retry_in: self.cooldown.saturating_sub(probing.elapsed()),
+                }),
+                _ => {
+                    state.probing = Some(Instant::now());
+                    Ok(true)
+                }
+            },
+            Circuit::Open => match state.opened.elapsed() >= self.cooldown {
+                true => {
+                    self.transition(&mut state, Circuit::HalfOpen);
+                    state.probing = Some(Instant::now());
+                    Ok(true)
+                }
+                false => Err(Error::CircuitOpen {
+                    retry_in: self.cooldown.saturating_sub(state.opened.elapsed()),
+                }),
+            },
+        }
+    }
+
+    fn record(&self, ok: bool) {
+        let mut state = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        state.probing = None;
+        if ok {
+            state.failures = 0;
+            self.transition(&mut state, Circuit::Closed);
+            return;
+        }
+        state.failures += 1;
+        if state.circuit == Circuit::HalfOpen || state.failures >= self.threshold {
+            state.opened = Instant::now();
+            self.transition(&mut state, Circuit::Open);
+        }
+    }
+
+    fn transition(&self, state: &mut State, circuit: Circuit) {
+        if state.circuit == circuit {
+            return;
+        }
+        state.circuit = circuit;
+        if let Some(observer) = &self.observer {
+            observer.on_event(&Event::Circuit { circuit });
+        }
+    }
+}
+
+impl<O, S> Sink<O> for Breaker<S>
+where
+    O: Sync + ?Sized,
+    S: Sink<O> + Health,
+{
+    async fn load(&self, output: &O) -> Result<Outcome, Error> {
+        if self.admit()? {
+            self.probe().await?;
+        }
+        let result = self.sink.load(output).await;
+        self.record(result.is_ok());
+        result
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+// sinks
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+impl Health for sink::CouchDb {
+    async fn check(&self) -> Result<(), Error> {
+        reqwest::get(&self.conn).await?.error_for_status()?;
+        Ok(())
+    }
+}
+
+impl Health for sink::Postgres {
+    // on a connection from the sinks' pool; a closed one is replaced by a new connection
+    async fn check(&self) -> Result<(), Error> {
+        let client = postgresql::pooled(&self.conn).await?;
+        client.simple_query("SELECT 1").await?;
+        Ok(())
+    }
+}
+
+impl Health for sink::File {
+    async fn check(&self) -> Result<(), Error> {
+        let dir = match self.path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => std::path::Path::new("."),
+        };
+        tokio::fs::metadata(dir).await?;
+        Ok(())
+    }
+}
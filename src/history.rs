@@ -0,0 +1,320 @@
+//! Reports of past runs, persisted per pipeline, so runs can be compared with the ones before;
+//! basic observability without a metrics stack.
+//!
+//! A [`Runner`] given a [`History`] saves a [`RunRecord`] after every run; the latest ones can be
+//! fetched back as a [`Trend`]:
+//!
+//! ```rust,ignore
+//! let runner = Runner::new()
+//!     .pipe("prices", prices, Duration::from_secs(60 * 60))
+//!     .history(history::Postgres::new(conn, "etl_history"));
+//!
+//! let trend = history::Postgres::new(conn, "etl_history").trend("prices", 10).await?;
+//! if let Some(diff) = trend.diff() {
+//!     println!("{:+} rows, {:+} ms since the previous run", diff.rows_affected, diff.duration_ms);
+//! }
+//! ```
+//!
+//! [`Runner`]: crate::Runner
+
+use super::db::{couchdb, postgresql};
+use super::fs;
+use super::{Error, EtlReport};
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::future::Future;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// One run of a pipeline, as persisted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub pipeline: String,
+    /// The run's id, as its archive & dump are named; see [`clock::run_id()`].
+    ///
+    /// [`clock::run_id()`]: crate::clock::run_id
+    pub run_id: String,
+    /// When the run started.
+    pub at: DateTime<Utc>,
+    pub duration_ms: u64,
+    /// Why the run failed, if it did; its report is then empty.
+    pub error: Option<String>,
+    pub report: EtlReport,
+}
+
+impl RunRecord {
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(self.duration_ms)
+    }
+
+    // sortable, and unique enough per pipeline
+    fn key(&self) -> String {
+        self.at.format("%Y%m%dT%H%M%S%.6fZ").to_string()
+    }
+}
+
+/// Where run records are persisted.
+pub trait History: Send + Sync {
+    /// Persist `run`.
+    fn save(&self, run: &RunRecord) -> impl Future<Output = Result<(), Error>> + Send;
+
+    /// The latest `n` runs of `pipeline`, oldest first.
+    fn recent(
+        &self,
+        pipeline: &str,
+        n: usize,
+    ) -> impl Future<Output = Result<Vec<RunRecord>, Error>> + Send;
+
+    /// The latest `n` runs of `pipeline`, to compare; see [`Trend`].
+    fn trend(&self, pipeline: &str, n: usize) -> impl Future<Output = Result<Trend, Error>> + Send {
+        async move {
+            Ok(Trend {
+                runs: self.recent(pipeline, n).await?,
+            })
+        }
+    }
+}
+
+// `History` returns `impl Future`, so it cannot be boxed as-is; runners store histories through this instead.
+pub(crate) trait DynHistory: Send + Sync {
+    fn save_boxed<'a>(&'a self, run: &'a RunRecord) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+impl<H: History> DynHistory for H {
+    fn save_boxed<'a>(&'a self, run: &'a RunRecord) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(self.save(run))
+    }
+}
+
+/// The latest runs of a pipeline, oldest first.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Trend {
+    pub runs: Vec<RunRecord>,
+}
+
+impl Trend {
+    /// The loads of every run.
+    pub fn loads(&self) -> Vec<usize> {
+        self.runs.iter().map(|run| run.report.loads()).collect()
+    }
+
+    /// The rows written by every run; see [`EtlReport::rows_affected()`].
+    pub fn rows_affected(&self) -> Vec<u64> {
+        self.runs
+            .iter()
+            .map(|run| run.report.rows_affected())
+            .collect()
+    }
+
+    /// The duration of every run.
+    pub fn durations(&self) -> Vec<Duration> {
+        self.runs.iter().map(RunRecord::duration).collect()
+    }
+
+    /// How many of the runs failed.
+    pub fn failures(&self) -> usize {
+        self.runs.iter().filter(|run| run.error.is_some()).count()
+    }
+
+    /// The latest run, compared with the one before it; `None` with fewer than 2 runs.
+    pub fn diff(&self) -> Option<Diff> {
+        match self.runs.as_slice() {
+            [.., previous, latest] => Some(Diff::between(previous, latest)),
+            _ => None,
+        }
+    }
+}
+
+/// The change from one run to a later one; positive if the later one did more, or took longer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Diff {
+    pub loads: i64,
+    pub rows_affected: i64,
+    pub duration_ms: i64,
+}
+
+impl Diff {
+    pub fn between(before: &RunRecord, after: &RunRecord) -> Self {
+        let delta = |before: u64, after: u64| after as i64 - before as i64;
+        Diff {
+            loads: delta(before.report.loads() as u64, after.report.loads() as u64),
+            rows_affected: delta(before.report.rows_affected(), after.report.rows_affected()),
+            duration_ms: delta(before.duration_ms, after.duration_ms),
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+// dir
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// A JSON file per run, at `<path>/<pipeline>/<started at>.json`; the pipeline's name escaped as
+/// by [`fs::file_name()`](crate::fs::file_name).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dir {
+    pub path: PathBuf,
+}
+
+impl Dir {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Dir { path: path.into() }
+    }
+}
+
+impl History for Dir {
+    async fn save(&self, run: &RunRecord) -> Result<(), Error> {
+        let dir = self.path.join(fs::file_name(&run.pipeline));
+        std::fs::create_dir_all(&dir)?;
+        let json = serde_json::to_vec_pretty(run)?;
+        fs::write_atomic(dir.join(format!("{}.json", run.key())), &json)?;
+        Ok(())
+    }
+
+    async fn recent(&self, pipeline: &str, n: usize) -> Result<Vec<RunRecord>, Error> {
+        let dir = self.path.join(fs::file_name(pipeline));
+        if !dir.exists() {
+            return Ok(vec![]);
+        }
+        let mut files = vec![];
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                files.push(path);
+            }
+        }
+        files.sort();
+        let skip = files.len().saturating_sub(n);
+        files[skip..]
+            .iter()
+            .map(|path| Ok(serde_json::from_slice(&std::fs::read(path)?)?))
+            .collect()
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+// couchdb
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// A document per run, with the id `<pipeline>:<started at>`, in the database at `conn`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CouchDb {
+    pub conn: String,
+}
+
+impl CouchDb {
+    pub fn new(conn: impl Into<String>) -> Self {
+        CouchDb { conn: conn.into() }
+    }
+}
+
+impl History for CouchDb {
+    async fn save(&self, run: &RunRecord) -> Result<(), Error> {
+        let doc_id = format!("{}:{}", run.pipeline, run.key());
+        couchdb::upsert_doc(run, &self.conn, &doc_id, 3, None).await?;
+        Ok(())
+    }
+
+    async fn recent(&self, pipeline: &str, n: usize) -> Result<Vec<RunRecord>, Error> {
+        #[derive(Deserialize)]
+        struct Rows {
+            rows: Vec<Row>,
+        }
+        #[derive(Deserialize)]
+        struct Row {
+            doc: Value,
+        }
+
+        // newest first, from the end of the pipeline's range of ids
+        let rows: Rows = reqwest::Client::new()
+            .get(format!("{}/_all_docs", self.conn))
+            .query(&[
+                ("include_docs", "true".to_string()),
+                ("descending", "true".to_string()),
+                ("startkey", format!("\"{pipeline}:\u{fff0}\"")),
+                ("endkey", format!("\"{pipeline}:\"")),
+                ("limit", n.to_string()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let mut runs = rows
+            .rows
+            .into_iter()
+            .map(|row| Ok(serde_json::from_value(row.doc)?))
+            .collect::<Result<Vec<RunRecord>, Error>>()?;
+        runs.reverse();
+        Ok(runs)
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+// postgres
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// A row per run, in `table` (created if it doesn't exist): `(pipeline, at, record)`, with the
+/// record as JSON text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Postgres {
+    pub conn: String,
+    pub table: String,
+}
+
+impl Postgres {
+    pub fn new(conn: impl Into<String>, table: impl Into<String>) -> Self {
+        Postgres {
+            conn: conn.into(),
+            table: table.into(),
+        }
+    }
+
+    async fn connect(&self) -> Result<tokio_postgres::Client, Error> {
+        let client = postgresql::connect(&self.conn).await?;
+        client
+            .batch_execute(&format!(
+                "CREATE TABLE IF NOT EXISTS {} (pipeline TEXT NOT NULL, at TEXT NOT NULL, record TEXT NOT NULL)",
+                postgresql::quote_table(&self.table)
+            ))
+            .await?;
+        Ok(client)
+    }
+}
+
+impl History for Postgres {
+    async fn save(&self, run: &RunRecord) -> Result<(), Error> {
+        let client = self.connect().await?;
+        let record = serde_json::to_string(run)?;
+        client
+            .execute(
+                &format!(
+                    "INSERT INTO {} (pipeline, at, record) VALUES ($1, $2, $3)",
+                    postgresql::quote_table(&self.table)
+                ),
+                &[&run.pipeline, &run.key(), &record],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn recent(&self, pipeline: &str, n: usize) -> Result<Vec<RunRecord>, Error> {
+        let client = self.connect().await?;
+        let rows = client
+            .query(
+                &format!(
+                    "SELECT record FROM {} WHERE pipeline = $1 ORDER BY at DESC LIMIT $2",
+                    postgresql::quote_table(&self.table)
+                ),
+                &[&pipeline, &(n as i64)],
+            )
+            .await?;
+        let mut runs = rows
+            .iter()
+            .map(|row| Ok(serde_json::from_str(row.try_get::<_, &str>(0)?)?))
+            .collect::<Result<Vec<RunRecord>, Error>>()?;
+        runs.reverse();
+        Ok(runs)
+    }
+}
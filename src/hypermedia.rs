@@ -0,0 +1,185 @@
+//! Extraction from APIs following the JSON:API (`data`, `attributes`, `relationships`) or HAL
+//! (`_embedded`, `_links`) conventions: every page of resources, following the `next` links,
+//! each flattened into a plain object for `serde` to deserialize into a user struct.
+//!
+//! ```rust,ignore
+//! #[derive(Deserialize)]
+//! struct Ticket {
+//!     id: String,
+//!     subject: String,
+//!     assignee: Option<String>, // a relationship, by id
+//! }
+//!
+//! pipeline! {
+//!     Vec<Ticket> -> Vec<Row> {
+//!         async fn extract(&self, path: &str) -> pipe_io::Result<Vec<Ticket>> {
+//!             Hypermedia::JsonApi.extract(self, path).await
+//!         }
+//!         ...
+//!     }
+//! }
+//! ```
+//!
+//! A JSON:API resource flattens to its `id` & `type`, its attributes, and each relationship as the
+//! id (or array of ids, or `null`) it links to:
+//!
+//! ```json
+//! { "id": "1", "type": "tickets", "attributes": { "subject": "Help" },
+//!   "relationships": { "assignee": { "data": { "id": "7", "type": "people" } } } }
+//! ```
+//!
+//! is `{ "id": "1", "type": "tickets", "subject": "Help", "assignee": "7" }`. A HAL resource is
+//! its properties without `_links`, and with each resource it embeds as a property of the same
+//! name, flattened in turn.
+//!
+//! Pages are fetched with the pipe's [`fetch_default()`], so its client, pool & credentials apply;
+//! relative links are resolved against the page they're on. Extraction stops at a page without a
+//! `next` link, or with one back to a page already fetched. [`paginate`](crate::paginate) follows
+//! pages the same way, for APIs with a cursor instead.
+//!
+//! [`fetch_default()`]: crate::Pipe::fetch_default
+
+use super::{default, paginate, Error, Input, Output, Pipe};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+/// The convention a paginated API follows; see [`hypermedia`](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Hypermedia {
+    /// JSON:API: the resources are the document's `data`; the next page, its `links.next`. A
+    /// document with `errors` fails with [`Error::Upstream`].
+    JsonApi,
+    /// HAL: the resources are those embedded under `rel` (or, if `None`, the only rel the
+    /// document embeds); the next page, its `_links.next.href`.
+    Hal { rel: Option<String> },
+}
+
+impl Hypermedia {
+    /// HAL, with the resources under the only rel embedded.
+    pub fn hal() -> Self {
+        Hypermedia::Hal { rel: None }
+    }
+
+    /// Every resource at `url`, and the pages after it, as `T`s.
+    ///
+    /// Failures to decode are [`Error::Decode`], with the path of the resource, e.g., `[12].subject`;
+    /// and a page without resources is [`Error::Missing`].
+    pub async fn extract<T, I, O>(&self, pipe: &Pipe<I, O>, url: &str) -> Result<Vec<T>, Error>
+    where
+        T: DeserializeOwned,
+        I: Input,
+        O: Output,
+    {
+        let mut resources = vec![];
+        paginate::follow(pipe, url.to_string(), None, |url, page| {
+            let next = self.next(&page).map(|link| paginate::resolve(url, &link));
+            resources.extend(self.resources(page)?);
+            Ok(next)
+        })
+        .await?;
+        default::decode(serde_json::to_vec(&resources)?)
+    }
+
+    /// The resources of one page, flattened.
+    pub fn resources(&self, page: Value) -> Result<Vec<Value>, Error> {
+        let Value::Object(mut page) = page else {
+            return Err(Error::Missing("the document".into()));
+        };
+        match self {
+            Hypermedia::JsonApi => {
+                if let Some(errors) = page.get("errors").filter(|errors| !errors.is_null()) {
+                    return Err(Error::Upstream(errors.to_string()));
+                }
+                match page.remove("data") {
+                    Some(Value::Array(data)) => Ok(data.into_iter().map(json_api).collect()),
+                    Some(Value::Null) => Ok(vec![]),
+                    Some(data) => Ok(vec![json_api(data)]),
+                    None => Err(Error::Missing("data".into())),
+                }
+            }
+            Hypermedia::Hal { rel } => {
+                let Some(Value::Object(mut embedded)) = page.remove("_embedded") else {
+                    return Err(Error::Missing("_embedded".into()));
+                };
+                let only = match embedded.len() {
+                    1 => embedded.keys().next().cloned(),
+                    _ => None,
+                };
+                let rel = match rel.clone().or(only) {
+                    Some(rel) => rel,
+                    None => {
+                        let rels: Vec<&String> = embedded.keys().collect();
+                        return Err(Error::Config(format!(
+                            "the document embeds {rels:?}; name the rel to extract"
+                        )));
+                    }
+                };
+                match embedded.remove(&rel) {
+                    Some(Value::Array(resources)) => Ok(resources.into_iter().map(hal).collect()),
+                    Some(resource) => Ok(vec![hal(resource)]),
+                    None => Err(Error::Missing(format!("_embedded.{rel}"))),
+                }
+            }
+        }
+    }
+
+    /// The link to the page after `page`, if any, as written.
+    pub fn next(&self, page: &Value) -> Option<String> {
+        let next = match self {
+            Hypermedia::JsonApi => page.get("links")?.get("next")?,
+            Hypermedia::Hal { .. } => page.get("_links")?.get("next")?,
+        };
+        // JSON:API links may be objects too
+        match next {
+            Value::String(href) => Some(href.clone()),
+            next => next.get("href")?.as_str().map(str::to_string),
+        }
+    }
+}
+
+// a JSON:API resource object, flattened
+fn json_api(resource: Value) -> Value {
+    let Value::Object(mut resource) = resource else {
+        return resource;
+    };
+    let mut flat = Map::new();
+    for field in ["id", "type"] {
+        if let Some(value) = resource.remove(field) {
+            flat.insert(field.to_string(), value);
+        }
+    }
+    if let Some(Value::Object(attributes)) = resource.remove("attributes") {
+        flat.extend(attributes);
+    }
+    if let Some(Value::Object(relationships)) = resource.remove("relationships") {
+        for (name, relationship) in relationships {
+            let linked = match relationship.get("data") {
+                Some(Value::Array(linked)) => {
+                    Value::Array(linked.iter().map(|linked| linked["id"].clone()).collect())
+                }
+                Some(Value::Object(linked)) => linked.get("id").cloned().unwrap_or_default(),
+                _ => Value::Null,
+            };
+            flat.insert(name, linked);
+        }
+    }
+    Value::Object(flat)
+}
+
+// a HAL resource object, flattened
+fn hal(resource: Value) -> Value {
+    let Value::Object(mut resource) = resource else {
+        return resource;
+    };
+    resource.remove("_links");
+    if let Some(Value::Object(embedded)) = resource.remove("_embedded") {
+        for (rel, embedded) in embedded {
+            let embedded = match embedded {
+                Value::Array(embedded) => Value::Array(embedded.into_iter().map(hal).collect()),
+                embedded => hal(embedded),
+            };
+            resource.insert(rel, embedded);
+        }
+    }
+    Value::Object(resource)
+}
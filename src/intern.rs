@@ -0,0 +1,189 @@
+//! Shared strings for inputs with many copies of the same few values (currencies, units, period
+//! types): each distinct string is kept once, and every copy decoded is a reference to it, so a
+//! million `"USD"`s cost one allocation.
+//!
+//! Opt in per field, with [`Interned`] as the type:
+//!
+//! ```rust,ignore
+//! #[derive(Deserialize, Serialize)]
+//! struct Fact {
+//!     value: f64,
+//!     unit: Interned,
+//!     form: Option<Interned>,
+//! }
+//! ```
+//!
+//! Or keep the field an `Arc<str>`, with `#[serde(with = "pipe_io::intern")]`.
+//!
+//! Strings are interned into the [`global()`] interner, where they stay until [`Interner::prune()`]
+//! drops those no longer used; so intern fields of few distinct values, not ids or free text.
+
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+use std::borrow::Borrow;
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// A set of shared strings.
+#[derive(Debug, Default)]
+pub struct Interner {
+    strings: Mutex<HashSet<Arc<str>>>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The shared copy of `string`; added, if it's the first.
+    pub fn intern(&self, string: &str) -> Arc<str> {
+        let mut strings = self.lock();
+        if let Some(interned) = strings.get(string) {
+            return interned.clone();
+        }
+        let interned: Arc<str> = Arc::from(string);
+        strings.insert(interned.clone());
+        interned
+    }
+
+    /// How many distinct strings are interned.
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Drop every string only the interner still refers to, e.g., after a run; returning how many.
+    pub fn prune(&self) -> usize {
+        let mut strings = self.lock();
+        let before = strings.len();
+        strings.retain(|string| Arc::strong_count(string) > 1);
+        before - strings.len()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashSet<Arc<str>>> {
+        self.strings
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+static GLOBAL: OnceLock<Interner> = OnceLock::new();
+
+/// The interner every [`Interned`] string is decoded into.
+pub fn global() -> &'static Interner {
+    GLOBAL.get_or_init(Interner::new)
+}
+
+/// A string shared with every equal one, through the [`global()`] interner; (de)serialized as a
+/// plain string.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Interned(Arc<str>);
+
+impl Interned {
+    /// The shared copy of `string`.
+    pub fn new(string: &str) -> Self {
+        Interned(global().intern(string))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether `self` & `other` are the same shared copy, not just equal.
+    pub fn ptr_eq(&self, other: &Interned) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Deref for Interned {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for Interned {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for Interned {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Interned {
+    fn from(string: &str) -> Self {
+        Interned::new(string)
+    }
+}
+
+impl From<Interned> for Arc<str> {
+    fn from(interned: Interned) -> Self {
+        interned.0
+    }
+}
+
+impl PartialEq<str> for Interned {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Interned {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl fmt::Display for Interned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Serialize for Interned {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Interned {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize(deserializer).map(Interned)
+    }
+}
+
+/// Serialize an `Arc<str>` field as a plain string; for `#[serde(with = "pipe_io::intern")]`.
+pub fn serialize<S: Serializer>(string: &Arc<str>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(string)
+}
+
+/// Deserialize an `Arc<str>` field, interned into the [`global()`] interner; for
+/// `#[serde(with = "pipe_io::intern")]`.
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Arc<str>, D::Error> {
+    // visited as a `&str`, so no `String` is allocated for a string already interned
+    struct Strings;
+
+    impl Visitor<'_> for Strings {
+        type Value = Arc<str>;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("a string")
+        }
+
+        fn visit_str<E: de::Error>(self, string: &str) -> Result<Arc<str>, E> {
+            Ok(global().intern(string))
+        }
+    }
+
+    deserializer.deserialize_str(Strings)
+}
@@ -0,0 +1,131 @@
+//! A write-ahead journal of the outputs being loaded, so a load cut short by a crash is replayed
+//! on the next run, rather than lost; for effectively-once delivery to sinks that discard
+//! repeated writes, e.g., by [`idempotency_key()`].
+//!
+//! ```rust,ignore
+//! let pipe = Pipe::<I, O>::builder()
+//!     .source(Source::endpoint("https://example.com/orders.json"))
+//!     .journal(Journal::new("orders.journal"))
+//!     .sink(sink::Http::new("https://example.com/api/orders"))
+//!     .build()?;
+//! ```
+//!
+//! Each output is written to the journal before it's loaded, and removed once the sink has
+//! acknowledged it. Whatever is still there when a run starts (its load crashed, or failed) is
+//! loaded again first, oldest first, and reported alongside the run's own loads; a run stops at
+//! the first replay that fails, so nothing new is loaded ahead of it.
+//!
+//! Each entry is keyed by the hash of a new id, from the pipe's [`Clock`], and its output; and
+//! loaded (replays and retries included) with that key in scope, for the sink to make its write
+//! conditional on; [`sink::Http`] sends it as the `Idempotency-Key`. So a replay is recognised
+//! downstream, while the same output loaded again by a later run isn't taken for one. A sink that
+//! ignores the key may load a replayed output twice.
+//!
+//! One journal per pipe: a pipe replays whatever its journal holds.
+//!
+//! [`sink::Http`]: crate::sink::Http
+//! [`Clock`]: crate::clock::Clock
+
+use super::clock::Clock;
+use super::sink::content_hash;
+use super::{fs, Error};
+use serde_json::value::RawValue;
+use serde_json::Value;
+use std::future::Future;
+use std::path::PathBuf;
+
+tokio::task_local! {
+    static KEY: String;
+}
+
+/// The idempotency key of the journaled output being loaded, if any; for a sink to make its
+/// write conditional on, so a replay is discarded downstream.
+pub fn idempotency_key() -> Option<String> {
+    KEY.try_with(String::clone).ok()
+}
+
+// Run `load` with `key` as its idempotency key.
+pub(crate) async fn keyed<F: Future>(key: String, load: F) -> F::Output {
+    KEY.scope(key, load).await
+}
+
+/// The outputs not yet acknowledged by the sink, one JSON file each, in `dir`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Journal {
+    /// Created on the first write.
+    pub dir: PathBuf,
+}
+
+/// An output in a [`Journal`], not yet acknowledged.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub key: String,
+    pub output: Value,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Saved<T> {
+    // when the entry was written, in nanoseconds since the epoch, and its id; to replay in order
+    written: i64,
+    id: u128,
+    output: T,
+}
+
+impl Journal {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Journal { dir: dir.into() }
+    }
+
+    /// Every entry not yet acknowledged, oldest first.
+    pub fn pending(&self) -> Result<Vec<Entry>, Error> {
+        let dir = match std::fs::read_dir(&self.dir) {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+        let mut saved = vec![];
+        for file in dir {
+            let path = file?.path();
+            let (Some(key), Some("json")) = (
+                path.file_stem().and_then(|stem| stem.to_str()),
+                path.extension().and_then(|extension| extension.to_str()),
+            ) else {
+                continue;
+            };
+            let entry: Saved<Value> = serde_json::from_slice(&std::fs::read(&path)?)?;
+            saved.push(((entry.written, entry.id), key.to_string(), entry.output));
+        }
+        saved.sort_by_key(|(written, _, _)| *written);
+        Ok(saved
+            .into_iter()
+            .map(|(_, key, output)| Entry { key, output })
+            .collect())
+    }
+
+    // Journal the serialized output `payload`, before it's loaded; returning its key.
+    pub(crate) fn write(&self, payload: &[u8], clock: &dyn Clock) -> Result<String, Error> {
+        let (written, id) = (clock.now().timestamp_nanos_opt().unwrap_or(0), clock.uuid());
+        let key = content_hash(&[id.as_bytes(), payload]);
+        let output: &RawValue = serde_json::from_slice(payload)?;
+        std::fs::create_dir_all(&self.dir)?;
+        let saved = Saved {
+            written,
+            id: id.as_u128(),
+            output,
+        };
+        fs::write_atomic(self.path(&key), &serde_json::to_vec(&saved)?)?;
+        Ok(key)
+    }
+
+    // Remove the entry of `key`, once the sink has acknowledged it.
+    pub(crate) fn ack(&self, key: &str) -> Result<(), Error> {
+        match std::fs::remove_file(self.path(key)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.json"))
+    }
+}
@@ -0,0 +1,275 @@
+#[cfg(feature = "avro")]
+use super::avro::{Avro, SchemaRegistry};
+use super::sink::Sink;
+use super::source::{Checkpoint, Source};
+use super::warning::{self, Warning};
+use super::{Error, Input, Outcome};
+use futures::future::BoxFuture;
+use rdkafka::consumer::{CommitMode, Consumer, StreamConsumer};
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::util::Timeout;
+use rdkafka::{ClientConfig, Message, Offset, TopicPartitionList};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// A Kafka consumer, feeding each message's JSON payload into a pipe as one input `I`.
+///
+/// Offsets are committed in batches, and only after the messages up to them have been loaded;
+/// with auto-commit disabled, the consumer group resumes from the first unloaded message after
+/// a failure, giving at-least-once delivery end-to-end. A payload that doesn't decode is skipped
+/// with a warning, and committed past along with the messages after it.
+///
+/// ```rust,ignore
+/// let source = KafkaSource::new("localhost:9092", "prices-loader", &["prices"])?
+///     .commit_every(100)
+///     .into_source::<Price>();
+/// let pipe = Pipe::<Price, Row>::builder().source(source).sink(sink).build()?;
+/// pipe.run().await?; // runs until the consumer is closed
+/// ```
+pub struct KafkaSource {
+    consumer: Arc<StreamConsumer>,
+    commit_every: usize,
+}
+
+impl KafkaSource {
+    /// Join consumer group `group_id` on `brokers` (comma-separated `host:port`s), subscribed to `topics`.
+    pub fn new(brokers: &str, group_id: &str, topics: &[&str]) -> Result<Self, Error> {
+        let mut config = ClientConfig::new();
+        config
+            .set("bootstrap.servers", brokers)
+            .set("group.id", group_id)
+            .set("enable.auto.commit", "false")
+            .set("auto.offset.reset", "earliest");
+        Self::from_config(&config, topics)
+    }
+
+    /// Use a custom client configuration; `enable.auto.commit` is always overridden to `false`.
+    pub fn from_config(config: &ClientConfig, topics: &[&str]) -> Result<Self, Error> {
+        let mut config = config.clone();
+        config.set("enable.auto.commit", "false");
+        let consumer: StreamConsumer = config.create()?;
+        consumer.subscribe(topics)?;
+        Ok(KafkaSource {
+            consumer: Arc::new(consumer),
+            commit_every: 100,
+        })
+    }
+
+    /// Commit offsets after every `n` loaded messages (and when the stream ends). Defaults to 100.
+    pub fn commit_every(mut self, n: usize) -> Self {
+        self.commit_every = n.max(1);
+        self
+    }
+
+    /// The checkpointed streaming source, decoding each message payload as JSON.
+    pub fn into_source<I>(self) -> Source<I>
+    where
+        I: Input + 'static,
+    {
+        let offsets = Arc::new(Mutex::new(Offsets::default()));
+        let positions = offsets.clone();
+        let stream = futures::stream::unfold(self.consumer.clone(), move |consumer| {
+            let positions = positions.clone();
+            async move {
+                loop {
+                    let message = match consumer.recv().await {
+                        Ok(message) => message,
+                        Err(e) => return Some((Err(Error::from(e)), consumer)),
+                    };
+                    // remember the position, to be committed once this message is loaded
+                    let pending = (message.topic().to_string(), message.partition());
+                    let mut offsets = positions
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner());
+                    offsets.pending.insert(pending, message.offset());
+                    offsets.received += 1;
+                    drop(offsets);
+                    let payload = message.payload().unwrap_or_default();
+                    match serde_json::from_slice::<I>(payload) {
+                        Ok(input) => return Some((Ok(input), consumer)),
+                        // skipped, rather than ending the stream; committed past with the next
+                        // message loaded, so it isn't redelivered forever
+                        Err(e) => warning::warn(Warning::skipped(format!(
+                            "undecodable message {}[{}]@{}: {e}",
+                            message.topic(),
+                            message.partition(),
+                            message.offset()
+                        ))),
+                    }
+                }
+            }
+        });
+
+        let checkpoint = KafkaCheckpoint {
+            consumer: self.consumer,
+            commit_every: self.commit_every,
+            offsets,
+        };
+        Source::checkpointed(stream, checkpoint)
+    }
+}
+
+#[derive(Default)]
+struct Offsets {
+    // the last message of each partition handed to the pipe, not yet loaded; several, for a
+    // batched source
+    pending: HashMap<(String, i32), i64>,
+    // the messages handed to the pipe, not yet loaded
+    received: usize,
+    // loaded, but not yet committed
+    loaded: HashMap<(String, i32), i64>,
+    uncommitted: usize,
+}
+
+struct KafkaCheckpoint {
+    consumer: Arc<StreamConsumer>,
+    commit_every: usize,
+    offsets: Arc<Mutex<Offsets>>,
+}
+
+impl KafkaCheckpoint {
+    fn commit(&self) -> Result<(), Error> {
+        let loaded = {
+            let mut offsets = self
+                .offsets
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            // called once every message handed to the pipe is loaded; any still pending were
+            // skipped, as undecodable
+            let pending = std::mem::take(&mut offsets.pending);
+            offsets.loaded.extend(pending);
+            offsets.uncommitted = 0;
+            std::mem::take(&mut offsets.loaded)
+        };
+        if loaded.is_empty() {
+            return Ok(());
+        }
+        let mut list = TopicPartitionList::new();
+        for ((topic, partition), offset) in loaded {
+            // the committed offset is the next message to read
+            list.add_partition_offset(&topic, partition, Offset::Offset(offset + 1))?;
+        }
+        self.consumer.commit(&list, CommitMode::Sync)?;
+        Ok(())
+    }
+}
+
+impl Checkpoint for KafkaCheckpoint {
+    fn loaded(&self) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(async move {
+            let due = {
+                let mut offsets = self
+                    .offsets
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+                let pending = std::mem::take(&mut offsets.pending);
+                offsets.loaded.extend(pending);
+                offsets.uncommitted += std::mem::take(&mut offsets.received);
+                offsets.uncommitted >= self.commit_every
+            };
+            match due {
+                true => self.commit(),
+                false => Ok(()),
+            }
+        })
+    }
+
+    fn flush(&self) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(async move { self.commit() })
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+// sink
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Produces every record of the output to a Kafka topic, as one message each; as JSON, or with
+/// the `avro` feature, as Avro (see [`avro()`](KafkaSink::avro)).
+///
+/// The load waits for the brokers to acknowledge every message, and fails if any isn't; so it may
+/// be retried, with at-least-once delivery.
+///
+/// ```rust,ignore
+/// let sink = KafkaSink::new("localhost:9092", "prices.clean")?;
+/// let pipe = Pipe::<RawPrice, Vec<Price>>::builder().source(source).sink(sink).build()?;
+/// ```
+pub struct KafkaSink {
+    producer: FutureProducer,
+    topic: String,
+    encoding: Encoding,
+}
+
+enum Encoding {
+    Json,
+    #[cfg(feature = "avro")]
+    Avro {
+        avro: Avro,
+        registry: SchemaRegistry,
+    },
+}
+
+impl KafkaSink {
+    /// Produce to `topic`, on `brokers` (comma-separated `host:port`s).
+    pub fn new(brokers: &str, topic: impl Into<String>) -> Result<Self, Error> {
+        let mut config = ClientConfig::new();
+        config.set("bootstrap.servers", brokers);
+        Self::from_config(&config, topic)
+    }
+
+    /// Use a custom client configuration, e.g., with credentials, or `acks=all`.
+    pub fn from_config(config: &ClientConfig, topic: impl Into<String>) -> Result<Self, Error> {
+        Ok(KafkaSink {
+            producer: config.create()?,
+            topic: topic.into(),
+            encoding: Encoding::Json,
+        })
+    }
+
+    /// Encode records as Avro, instead of JSON, in the schema registry's wire format: `avro`'s
+    /// schema is registered under the subject `<topic>-value` on the first load, and each message
+    /// carries its id.
+    #[cfg(feature = "avro")]
+    pub fn avro(mut self, avro: Avro, registry: SchemaRegistry) -> Self {
+        self.encoding = Encoding::Avro { avro, registry };
+        self
+    }
+
+    async fn encode<T: Serialize>(&self, records: &[T]) -> Result<Vec<Vec<u8>>, Error> {
+        match &self.encoding {
+            Encoding::Json => records
+                .iter()
+                .map(|record| Ok(serde_json::to_vec(record)?))
+                .collect(),
+            #[cfg(feature = "avro")]
+            Encoding::Avro { avro, registry } => {
+                let subject = format!("{}-value", self.topic);
+                let id = registry.id(&subject, avro.schema()).await?;
+                records
+                    .iter()
+                    .map(|record| avro.framed(id, record))
+                    .collect()
+            }
+        }
+    }
+}
+
+impl<T> Sink<Vec<T>> for KafkaSink
+where
+    T: Serialize + Sync,
+{
+    async fn load(&self, output: &Vec<T>) -> Result<Outcome, Error> {
+        // every record is encoded before any is sent, so a bad one sends none
+        let payloads = self.encode(output).await?;
+        let sends = payloads.iter().map(|payload| {
+            let record = FutureRecord::<(), _>::to(&self.topic).payload(payload);
+            self.producer.send(record, Timeout::Never)
+        });
+        for delivery in futures::future::join_all(sends).await {
+            delivery.map_err(|(e, _)| e)?;
+        }
+        Ok(Outcome::Published {
+            messages: output.len() as u64,
+        })
+    }
+}
@@ -13,6 +13,9 @@
 //!
 //! By default, `transform()` will always need defining, but `extract()` and `load()` do come with useful default implementations.
 //!
+//! `extract()` is itself `fetch(endpoint)`, for the raw bytes, then `decode(bytes)`; either half can be customized on its own,
+//! e.g., an authenticated fetch decoded as JSON, or the default fetch decoded as protobuf.
+//!
 //! ## Aggregrations
 //! Subsequent aggregate methods are then derived, for example:
 //! - `extran(endpoint)` - a combination of `extract()` and `transform()`.
@@ -20,7 +23,7 @@
 //!
 //! ## Example 1: Generic
 //!
-//! ```rust
+//! ```rust,ignore
 //! #[serde(Deserialize)]
 //! struct Input {
 //!     ...
@@ -31,11 +34,11 @@
 //!     ...
 //! };
 //!
-//! impl ETL<Input, Output> for Input {
+//! impl SelfPipe<Output> for Input {
 //!
 //!     // using extract() default impl
 //!
-//!     pub fn transform(data: Input) -> Result<Output, etl::Error> {
+//!     async fn transform(data: Input) -> Result<Output, pipe_io::Error> {
 //!         ...
 //!     }
 //!
@@ -44,14 +47,16 @@
 //!
 //! #[tokio::main]
 //! async fn main() {
-//!     Input::etl().await;
+//!     Input::etl(path, conn, doc_id).await;
+//!     // or, as a pipe
+//!     Input::pipe().etl(path, conn, doc_id).await;
 //! }
 //! ```
 //!
 //! Default implementations defined where possible, but a `transform()` definition is always required by the user.
 //!
 //! ## Example 2: Specific
-//! ```rust
+//! ```rust,ignore
 //! // Input Type; the struct/enum that deserializes the source material
 //! #[derive(Deserialize, Debug)]
 //! struct RawPrice {
@@ -71,19 +76,17 @@
 //!     volume: u64,
 //! }
 //!
-//! // The following is the impl, which could be written one of two ways (in generic terms):
-//! //      impl ETL<I, O> for I { ... }
-//! //      impl ETL<I, O> for O { ... }
-//! impl ETL<RawPrice, Vec<Price>> for RawPrice
+//! // The impl is on the input type; `pipeline!` (below) writes the same on `Pipe<I, O>` instead
+//! impl SelfPipe<Vec<Price>> for RawPrice
 //! {
 //!     // reading JSON from static str
-//!     async fn extract(init: &str) -> Result<RawPrice, etl::Error> {
+//!     async fn extract(init: &str) -> Result<RawPrice, pipe_io::Error> {
 //!         let data: RawPrice = serde_json::from_str(&init)?;
 //!         Ok(data)
 //!     }
 //!
 //!     // method that unpacks Input into Output (the following steps are situational)
-//!     async fn transform(data: RawPrice) -> Result<Vec<Price>, etl::Error> {
+//!     async fn transform(data: RawPrice) -> Result<Vec<Price>, pipe_io::Error> {
 //!         let base = &data.chart.result[0];
 //!         let price = &base.indicators.quote[0];
 //!         let adjclose = &base.indicators.adjclose[0].adjclose;
@@ -110,28 +113,196 @@
 //!     }
 //! }
 //! ```
+//!
+//! ## Example 3: Derived
+//! When the output is just fields plucked from the input, `#[derive(Transform)]` writes `transform()` instead:
+//! ```rust,ignore
+//! #[derive(Transform, Serialize, Deserialize, Debug)]
+//! #[etl(input = RawPrice)]
+//! struct Listing {
+//!     #[etl(from = "chart.result[0].meta.currency")]
+//!     currency: String,
+//!     #[etl(from = "chart.result[0].meta.symbol")]
+//!     symbol: String,
+//! }
+//!
+//! let listing = Pipe::<RawPrice, Listing>::new().extran(url).await?;
+//! ```
+//!
+//! ## Duplicate pipelines
+//! Each `I -> O` pair can only be defined once per `pipeline!`; to split one over several blocks, mark the later blocks with `extend`:
+//! ```rust,ignore
+//! pipeline! {
+//!     RawPrice -> Price {
+//!         async fn transform(&self, input: RawPrice) -> pipe_io::Result<Price> { ... }
+//!     }
+//!
+//!     extend RawPrice -> Price {
+//!         async fn extract(&self, path: &str) -> pipe_io::Result<RawPrice> { ... }
+//!     }
+//! }
+//! ```
+//!
+//! ## TLS
+//! Every HTTP component (extraction, the CouchDB & HTTP sinks, OAuth2) connects through `reqwest`, with the TLS
+//! backend chosen by feature: `native-tls` (the default; OpenSSL on Linux), or `rustls`, which needs no system
+//! libraries, e.g., for musl or `scratch` containers:
+//! ```toml
+//! pipe-io = { version = "0.1", default-features = false, features = ["rustls"] }
+//! ```
+//! With neither, only plain `http://` endpoints can be reached.
+
+// Library code never panics on a pipeline's data; failures are `Error`s (tests may unwrap)
+#![cfg_attr(
+    not(test),
+    deny(
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::panic,
+        clippy::todo,
+        clippy::unimplemented,
+        clippy::unreachable
+    )
+)]
 
 // Modules
+pub mod archive;
+pub mod array;
+pub mod artifact;
+pub mod audit;
+#[cfg(feature = "avro")]
+pub mod avro;
+pub mod backfill;
+#[cfg(feature = "bigquery")]
+pub mod bigquery;
+pub mod builder;
+pub mod cache;
+pub mod catalog;
+#[cfg(feature = "chaos")]
+pub mod chaos;
+#[cfg(feature = "cli")]
+pub mod cli;
+pub mod client;
+pub mod clock;
+pub mod columns;
+pub mod config;
+#[cfg(feature = "bench")]
+pub mod counters;
+#[cfg(feature = "crypto")]
+pub mod crypto;
+#[cfg(feature = "dashboard")]
+pub mod dashboard;
 pub mod db;
 pub mod default;
+pub mod dump;
+pub mod dynamic;
+pub mod endpoint;
+pub mod enrich;
+pub mod envelope;
 pub mod error;
 pub mod etl;
+pub mod fixtures;
+pub mod fork;
+pub mod fs;
+#[cfg(feature = "it-harness")]
+pub mod harness;
+pub mod health;
+pub mod history;
+pub mod hypermedia;
+pub mod intern;
+pub mod journal;
+#[cfg(feature = "kafka")]
+pub mod kafka;
+pub mod lineage;
+pub mod mapping;
+pub mod memory;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+#[cfg(feature = "nats")]
+pub mod nats;
+pub mod normalize;
+#[cfg(feature = "smtp")]
+pub mod notify;
+#[cfg(feature = "oauth2")]
+pub mod oauth2;
+pub mod observer;
+pub mod paginate;
+pub mod partition;
+pub mod passthrough;
 pub mod pipe;
+#[cfg(feature = "dyn")]
+pub mod plugin;
+pub mod pool;
+pub mod quality;
+pub mod quota;
+#[cfg(feature = "rabbitmq")]
+pub mod rabbitmq;
+pub mod rate_limit;
+pub mod report;
+pub mod retry;
+pub mod runner;
+pub mod sample;
+pub mod sink;
+pub mod source;
+pub mod staging;
+pub mod summary;
+pub mod tabular;
+pub mod template;
+pub mod throttle;
+pub mod time;
+pub mod unchanged;
+pub mod version;
+pub mod warning;
+#[cfg(feature = "watch")]
+pub mod watch;
+pub mod window;
+pub mod wire;
 
 // Re-exports
-pub use error::Error;
-pub use etl::ETL;
-pub use macros::{pipe, pipeline};
-pub use pipe::Pipe;
+pub use archive::Archive;
+pub use artifact::{Artifact, Artifacts};
+pub use builder::PipeBuilder;
+pub use bytes::Bytes;
+pub use cache::Cache;
+pub use client::ClientConfig;
+pub use clock::Clock;
+pub use db::{couchdb::CouchOutcome, postgresql::PgOutcome};
+pub use dump::Dumps;
+pub use dynamic::{DynamicPipe, Rules};
+pub use endpoint::Endpoint;
+pub use enrich::Enrich;
+pub use error::{Error, Errors};
+pub use etl::{SelfPipe, ETL};
+pub use fork::Fork;
+pub use macros::{pipe, pipeline, Tabular, Transform};
+pub use observer::Observer;
+pub use pipe::{DynPipeline, Pipe};
+pub use pool::HostPool;
+pub use rate_limit::RateLimit;
+pub use report::{EtlReport, Outcome};
+pub use retry::RetryPolicy;
+pub use runner::Runner;
+pub use sink::Sink;
+pub use source::{Metadata, Source, SourceSpec};
+pub use staging::Staged;
+pub use tabular::Tabular;
+pub use version::Versioned;
+pub use warning::{warn, Warning};
+pub use wire::WireLog;
 
-// Crate-wide traits
+// Crate-wide traits; implemented for every type with the right serde impls
 pub trait Input: serde::de::DeserializeOwned + Send {}
-pub trait Output: serde::de::DeserializeOwned + serde::Serialize + Send {}
+pub trait Output: serde::de::DeserializeOwned + serde::Serialize + Send + Sync {}
+
+impl<T> Input for T where T: serde::de::DeserializeOwned + Send {}
+impl<T> Output for T where T: serde::de::DeserializeOwned + serde::Serialize + Send + Sync {}
 
 // Result wrapper
 pub type Result<T> = std::result::Result<T, Error>;
 
 // Prelude: Commonly Packaged
 pub mod core {
-    pub use super::{pipe, pipeline, ETL, Pipe};
+    pub use super::{
+        pipe, pipeline, Pipe, PipeBuilder, SelfPipe, Sink, Source, Tabular, Transform, ETL,
+    };
 }
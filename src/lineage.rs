@@ -0,0 +1,124 @@
+//! Field-level lineage: which input paths each output field is derived from; declared by the
+//! transform, for data governance, and exported with the [`Catalog`].
+//!
+//! Lineage comes from three places, in increasing precedence:
+//!
+//! - the transform's [`ETL::lineage()`]; a `#[derive(Transform)]` declares each field's
+//!   `#[etl(from = "path")]`;
+//! - the [`Rules`] of a dynamic pipe, from its `map`, `rename`, `set` & `select` rules;
+//! - annotations, given to [`PipeBuilder::lineage()`].
+//!
+//! ```rust,ignore
+//! let pipe = Pipe::<RawPrice, Vec<Price>>::builder()
+//!     .lineage(
+//!         Lineage::new()
+//!             .field("date", ["chart.result[0].timestamp"])
+//!             .derived("close", ["chart.result[0].indicators.quote[0].close"], "rounded to cents"),
+//!     )
+//!     .build()?;
+//!
+//! let edges = runner.catalog().lineage();
+//! // [{"pipeline": "prices", "from": "chart.result[0].timestamp", "field": "date"}, ...]
+//! ```
+//!
+//! Input paths are written as for [`Path`]; for rules, they're within each record. A field
+//! derived from no paths is a constant, or generated by the transform.
+//!
+//! [`Catalog`]: crate::catalog::Catalog
+//! [`Rules`]: crate::Rules
+//! [`Path`]: crate::dynamic::Path
+//! [`PipeBuilder::lineage()`]: crate::PipeBuilder::lineage
+//! [`ETL::lineage()`]: crate::ETL::lineage
+
+use serde::{Deserialize, Serialize};
+
+/// The derivation of each (annotated) field of an output; serialized as the array of them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Lineage {
+    /// In the order they were declared; one per field.
+    pub fields: Vec<Derivation>,
+}
+
+/// Where one output field comes from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Derivation {
+    /// The output field, e.g., `close`.
+    pub field: String,
+    /// The input paths it's derived from, e.g., `chart.result[0].indicators.quote[0].close`.
+    pub from: Vec<String>,
+    /// How it's derived, if not copied as is.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl Lineage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declare `field` as copied from `from`.
+    pub fn field<S: Into<String>>(self, field: &str, from: impl IntoIterator<Item = S>) -> Self {
+        self.derivation(Derivation {
+            field: field.to_string(),
+            from: from.into_iter().map(Into::into).collect(),
+            note: None,
+        })
+    }
+
+    /// Declare `field` as derived from `from`, as described by `note`; e.g., "sum of".
+    pub fn derived<S: Into<String>>(
+        self,
+        field: &str,
+        from: impl IntoIterator<Item = S>,
+        note: &str,
+    ) -> Self {
+        self.derivation(Derivation {
+            field: field.to_string(),
+            from: from.into_iter().map(Into::into).collect(),
+            note: Some(note.to_string()),
+        })
+    }
+
+    /// Declare a derivation, replacing any declared for the same field.
+    pub fn derivation(mut self, derivation: Derivation) -> Self {
+        match self
+            .fields
+            .iter_mut()
+            .find(|declared| declared.field == derivation.field)
+        {
+            Some(declared) => *declared = derivation,
+            None => self.fields.push(derivation),
+        }
+        self
+    }
+
+    /// This lineage, with every derivation of `other` declared over it.
+    pub fn merge(self, other: Lineage) -> Self {
+        other.fields.into_iter().fold(self, Lineage::derivation)
+    }
+
+    /// The derivation of `field`, if declared.
+    pub fn of(&self, field: &str) -> Option<&Derivation> {
+        self.fields.iter().find(|declared| declared.field == field)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
+
+/// One edge of the lineage graph of a [`Catalog`]: an input path of a pipeline, to the output
+/// field derived from it; see [`Catalog::lineage()`].
+///
+/// [`Catalog`]: crate::catalog::Catalog
+/// [`Catalog::lineage()`]: crate::catalog::Catalog::lineage
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Edge {
+    pub pipeline: String,
+    /// The input path; `None` for a constant, or generated, field.
+    pub from: Option<String>,
+    pub field: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
@@ -0,0 +1,208 @@
+//! Which fields a transform drops, and which it makes up: its input & output fields, from sample
+//! inputs run through it, matched to each other; so a field upstream adds, that the transform
+//! silently ignores, shows up before its data is lost.
+//!
+//! ```rust,ignore
+//! let samples = [serde_json::from_slice(&std::fs::read("tests/fixtures/prices/nvda.json")?)?];
+//! let mapping = Mapping::of(&Pipe::<RawPrice, Vec<Price>>::new(), samples).await?;
+//! assert!(mapping.unused.is_empty(), "{mapping}");
+//! // unused input fields: chart.result[].meta.currency
+//! ```
+//!
+//! An output field's source is an input field that's
+//!
+//! - declared as its [`lineage`](crate::lineage), or a parent of it (e.g., `meta` for
+//!   `meta.symbol`);
+//! - of the same name, ignoring case, `_` & `-`, e.g., `closePrice` for `close_price`; or
+//! - of the same value, in any sample; numbers & non-empty strings only.
+//!
+//! Paths are written as for [`Path`], with `[]` for every element of an array, e.g.,
+//! `chart.result[].timestamp[]`; and within each record, for an array of them.
+//!
+//! [`Path`]: crate::dynamic::Path
+
+use super::{Error, Input, Output, Pipe, ETL};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// The input & output fields of a transform's samples, and those of each without the other.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Mapping {
+    /// Every input field, by path, in alphabetical order.
+    pub inputs: Vec<String>,
+    /// Every output field, by path, in alphabetical order.
+    pub outputs: Vec<String>,
+    /// The input fields no output field came from.
+    pub unused: Vec<String>,
+    /// The output fields with no obvious source: constants, generated, or derived undeclared.
+    pub unsourced: Vec<String>,
+}
+
+// the values of each field, by path
+type Fields = BTreeMap<String, Vec<Value>>;
+
+impl Mapping {
+    /// The mapping of `pipe`'s transform, over `samples` of its input, as JSON.
+    ///
+    /// A sample that isn't an `I` is [`Error::JSON`]; one the transform fails fails with it.
+    pub async fn of<I, O>(
+        pipe: &Pipe<I, O>,
+        samples: impl IntoIterator<Item = Value>,
+    ) -> Result<Self, Error>
+    where
+        I: Input,
+        O: Output,
+        Pipe<I, O>: ETL<I, O>,
+    {
+        let mut inputs = Fields::new();
+        let mut outputs = Fields::new();
+        for sample in samples {
+            let input: I = serde_json::from_value(sample.clone())?;
+            let output = serde_json::to_value(pipe.transform(input).await?)?;
+            fields(&sample, String::new(), &mut inputs);
+            fields(&output, String::new(), &mut outputs);
+        }
+        let declared: Vec<(String, Vec<String>)> = ETL::lineage(pipe)
+            .merge(pipe.lineage.clone())
+            .fields
+            .into_iter()
+            .map(|derivation| {
+                let from = derivation.from.iter().map(|from| path(from)).collect();
+                (path(&derivation.field), from)
+            })
+            .collect();
+        Ok(Self::matched(inputs, outputs, &declared))
+    }
+
+    // The mapping of `inputs` to `outputs`, given the `declared` input paths of output fields.
+    fn matched(inputs: Fields, outputs: Fields, declared: &[(String, Vec<String>)]) -> Self {
+        let source = |(output, values): (&String, &Vec<Value>),
+                      (input, others): (&String, &Vec<Value>)| {
+            let lineage = declared.iter().any(|(field, from)| {
+                within(output, field) && from.iter().any(|from| within(input, from))
+            });
+            lineage
+                || name(output) == name(input)
+                || values
+                    .iter()
+                    .any(|value| others.iter().any(|other| same(value, other)))
+        };
+        let unused = inputs
+            .iter()
+            .filter(|&input| !outputs.iter().any(|output| source(output, input)))
+            .map(|(input, _)| input.clone())
+            .collect();
+        let unsourced = outputs
+            .iter()
+            .filter(|&output| !inputs.iter().any(|input| source(output, input)))
+            .map(|(output, _)| output.clone())
+            .collect();
+        Mapping {
+            inputs: inputs.into_keys().collect(),
+            outputs: outputs.into_keys().collect(),
+            unused,
+            unsourced,
+        }
+    }
+
+    /// Whether every input field was used, and every output field had a source.
+    pub fn is_complete(&self) -> bool {
+        self.unused.is_empty() && self.unsourced.is_empty()
+    }
+}
+
+impl fmt::Display for Mapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_complete() {
+            return write!(f, "every field is mapped");
+        }
+        if !self.unused.is_empty() {
+            write!(f, "unused input fields: {}", self.unused.join(", "))?;
+        }
+        if !self.unsourced.is_empty() {
+            if !self.unused.is_empty() {
+                writeln!(f)?;
+            }
+            write!(
+                f,
+                "output fields without a source: {}",
+                self.unsourced.join(", ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// Add the leaf fields of `value`, at `at`, to `fields`; each with its values, but nulls.
+fn fields(value: &Value, at: String, fields: &mut Fields) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, value) in map {
+                let at = match at.is_empty() {
+                    true => key.clone(),
+                    false => format!("{at}.{key}"),
+                };
+                self::fields(value, at, fields);
+            }
+        }
+        Value::Array(values) if !values.is_empty() => {
+            for value in values {
+                self::fields(value, format!("{at}[]"), fields);
+            }
+        }
+        value => {
+            let values = fields.entry(path(&at)).or_default();
+            if !value.is_null() {
+                values.push(value.clone());
+            }
+        }
+    }
+}
+
+// `path`, with every index as `[]`, and within each record of an array
+fn path(path: &str) -> String {
+    let mut normalized = String::with_capacity(path.len());
+    let mut chars = path.chars();
+    while let Some(c) = chars.next() {
+        normalized.push(c);
+        if c == '[' {
+            for c in chars.by_ref() {
+                if c == ']' {
+                    normalized.push(c);
+                    break;
+                }
+            }
+        }
+    }
+    let within = normalized.trim_start_matches("[]");
+    within.strip_prefix('.').unwrap_or(within).to_string()
+}
+
+// whether `path` is `parent`, or within it
+fn within(path: &str, parent: &str) -> bool {
+    match path.strip_prefix(parent) {
+        Some(rest) => parent.is_empty() || rest.is_empty() || rest.starts_with(['.', '[']),
+        None => false,
+    }
+}
+
+// the last key of `path`, for comparison: lowercase, without `_` or `-`
+fn name(path: &str) -> String {
+    let key = path.trim_end_matches("[]");
+    let key = key.rsplit('.').next().unwrap_or(key);
+    key.chars()
+        .filter(|c| !matches!(c, '_' | '-'))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+// whether two values are the same, so likely one came from the other
+fn same(value: &Value, other: &Value) -> bool {
+    match (value, other) {
+        (Value::Number(value), Value::Number(other)) => value.as_f64() == other.as_f64(),
+        (Value::String(value), Value::String(other)) => !value.is_empty() && value == other,
+        _ => false,
+    }
+}
@@ -0,0 +1,307 @@
+//! Approximate sizes of the data moving through a pipe, for capacity planning: the bytes fetched,
+//! the bytes of each output (serialized as JSON), and, with the [`Counting`] allocator installed,
+//! the peak heap of each stage; reported in [`EtlReport::sizes`], and to the observer as
+//! [`Event::Sized`].
+//!
+//! ```rust,ignore
+//! #[global_allocator]
+//! static ALLOCATOR: memory::Counting = memory::Counting;
+//!
+//! let pipe = Pipe::<I, Vec<Price>>::builder()
+//!     .memory(Memory::new().soft_limit(64 * 1024 * 1024))
+//!     .build()?;
+//! let sizes = pipe.run().await?.sizes.unwrap();
+//! println!("{} bytes fetched, {} loaded", sizes.fetched, sizes.transformed);
+//! ```
+//!
+//! With a soft limit, an output whose JSON is larger than the limit is loaded in chunks, each
+//! under it (a record over the limit by itself is loaded alone), and its outcome is
+//! [`Outcome::Batches`]; so the sink never serializes all of it at once. The output is serialized
+//! once, as compact JSON, to be cut up; and each chunk is only decoded as it's loaded, so there's
+//! no more than one chunk's records besides. Only a JSON array can be chunked; any other output is
+//! loaded whole, with a warning.
+//!
+//! Only fetches made by [`Pipe::fetch_default()`] are counted. Peaks are of the whole process,
+//! so stages running concurrently (e.g., other pipes) inflate each other's; but each is measured
+//! from its own start, up to 64 at once (any more go unmeasured).
+//!
+//! [`EtlReport::sizes`]: crate::EtlReport::sizes
+//! [`Event::Sized`]: crate::observer::Event::Sized
+//! [`Outcome::Batches`]: crate::Outcome::Batches
+//! [`Pipe::fetch_default()`]: crate::Pipe::fetch_default
+
+use super::observer::Stage;
+use super::EtlReport;
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+tokio::task_local! {
+    // the sizes of the measured run in progress
+    static SIZES: RefCell<Sizes>;
+    // the whole output being loaded in chunks, as JSON, until a sink has checked it
+    static WHOLE: RefCell<Option<Arc<String>>>;
+}
+
+/// Measure a pipe's runs; see [`memory`](self).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Memory {
+    /// The size, in bytes of JSON, over which an output is loaded in chunks.
+    pub soft_limit: Option<u64>,
+}
+
+impl Memory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn soft_limit(mut self, bytes: u64) -> Self {
+        self.soft_limit = Some(bytes.max(1));
+        self
+    }
+}
+
+/// The sizes of one run's data, in bytes.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sizes {
+    /// Fetched, over every extraction.
+    pub fetched: u64,
+    /// Of every output loaded, serialized as JSON; after transform (and enrich, if any).
+    pub transformed: u64,
+    /// Of the largest output loaded, serialized as JSON.
+    pub largest: u64,
+    /// How many outputs were over the soft limit, and loaded in chunks.
+    pub chunked: u64,
+    /// The peak heap during each stage, over its attempts; only with [`Counting`] installed.
+    pub peaks: BTreeMap<Stage, u64>,
+}
+
+// Run `run`, collecting the sizes measured meanwhile into its report; if `measured`.
+pub(crate) async fn collect<E>(
+    measured: bool,
+    run: impl Future<Output = Result<EtlReport, E>>,
+) -> Result<EtlReport, E> {
+    if !measured {
+        return run.await;
+    }
+    SIZES
+        .scope(RefCell::new(Sizes::default()), async {
+            let result = run.await;
+            let sizes = SIZES.with(|sizes| sizes.take());
+            result.map(|mut report| {
+                report.sizes = Some(sizes);
+                report
+            })
+        })
+        .await
+}
+
+// Count `bytes` fetched, if the run is measured.
+pub(crate) fn fetched(bytes: usize) {
+    let _ = SIZES.try_with(|sizes| sizes.borrow_mut().fetched += bytes as u64);
+}
+
+// Count an output of `bytes` loaded, `chunked` or not.
+pub(crate) fn transformed(bytes: u64, chunked: bool) {
+    let _ = SIZES.try_with(|sizes| {
+        let mut sizes = sizes.borrow_mut();
+        sizes.transformed += bytes;
+        sizes.largest = sizes.largest.max(bytes);
+        sizes.chunked += u64::from(chunked);
+    });
+}
+
+// The size of `value`, serialized as JSON; without keeping the JSON.
+pub(crate) fn size<T: Serialize + ?Sized>(value: &T) -> Result<u64, serde_json::Error> {
+    struct Count(u64);
+    impl std::io::Write for Count {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0 += buf.len() as u64;
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    let mut count = Count(0);
+    serde_json::to_writer(&mut count, value)?;
+    Ok(count.0)
+}
+
+// The ranges of `records` to load as chunks of at most `limit` bytes of JSON each; a record over
+// the limit by itself is a chunk alone.
+pub(crate) fn chunks(records: &[&RawValue], limit: u64) -> Vec<Range<usize>> {
+    let mut chunks = vec![];
+    let mut start = 0;
+    let mut bytes = 2; // `[]`
+    for (i, record) in records.iter().enumerate() {
+        let len = record.get().len() as u64;
+        // every record after a chunk's first also takes a `,`
+        if i > start && bytes + 1 + len > limit {
+            chunks.push(start..i);
+            (start, bytes) = (i, 2);
+        }
+        bytes += len + u64::from(i > start);
+    }
+    if start < records.len() {
+        chunks.push(start..records.len());
+    }
+    chunks
+}
+
+// The JSON array of `records`.
+pub(crate) fn chunk(records: &[&RawValue]) -> String {
+    let mut json = String::from("[");
+    for (i, record) in records.iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        json.push_str(record.get());
+    }
+    json.push(']');
+    json
+}
+
+// Run `load`, of the chunks of `json`, with it as the whole output; see `whole()`.
+pub(crate) async fn chunked<F: Future>(json: Arc<String>, load: F) -> F::Output {
+    WHOLE.scope(RefCell::new(Some(json)), load).await
+}
+
+// Whether the output being loaded is a chunk of a larger one: if so, the whole output's JSON, for
+// a sink to check (e.g., estimate) it once, before its first chunk; `Some(None)` once `checked()`.
+pub(crate) fn whole() -> Option<Option<Arc<String>>> {
+    WHOLE.try_with(|whole| whole.borrow().clone()).ok()
+}
+
+// Mark the whole output being loaded in chunks as checked; see `whole()`.
+pub(crate) fn checked() {
+    let _ = WHOLE.try_with(|whole| whole.borrow_mut().take());
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+// allocator
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+static ALLOCATED: AtomicU64 = AtomicU64::new(0);
+// the peak of each stage being measured, since it started; those in use are set in `MEASURING`
+static PEAKS: [AtomicU64; 64] = [const { AtomicU64::new(0) }; 64];
+static MEASURING: AtomicU64 = AtomicU64::new(0);
+
+/// The system allocator, counting the bytes allocated; install it as the `#[global_allocator]`
+/// for [`Sizes::peaks`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Counting;
+
+impl Counting {
+    fn add(bytes: usize) {
+        let allocated = ALLOCATED.fetch_add(bytes as u64, Ordering::Relaxed) + bytes as u64;
+        let mut measuring = MEASURING.load(Ordering::Relaxed);
+        while measuring != 0 {
+            PEAKS[measuring.trailing_zeros() as usize].fetch_max(allocated, Ordering::Relaxed);
+            measuring &= measuring - 1;
+        }
+        if !INSTALLED.load(Ordering::Relaxed) {
+            INSTALLED.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn sub(bytes: usize) {
+        ALLOCATED.fetch_sub(bytes as u64, Ordering::Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            Self::add(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            Self::add(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        Self::sub(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new = System.realloc(ptr, layout, new_size);
+        if !new.is_null() {
+            Self::sub(layout.size());
+            Self::add(new_size);
+        }
+        new
+    }
+}
+
+/// The bytes currently allocated on the heap; `None` unless [`Counting`] is installed.
+pub fn allocated() -> Option<u64> {
+    INSTALLED
+        .load(Ordering::Relaxed)
+        .then(|| ALLOCATED.load(Ordering::Relaxed))
+}
+
+// The peak heap of a stage being measured, in a slot of `PEAKS`; see `start()`. Dropped
+// unfinished (e.g., timed out), the slot is freed all the same.
+pub(crate) struct Measuring {
+    stage: Stage,
+    slot: usize,
+}
+
+// Start measuring the peak heap of `stage`, if the run is measured & `Counting` is installed;
+// unless 64 stages are already.
+pub(crate) fn start(stage: Stage) -> Option<Measuring> {
+    let measured = SIZES.try_with(|_| ()).is_ok();
+    let allocated = allocated().filter(|_| measured)?;
+    let mut measuring = MEASURING.load(Ordering::Relaxed);
+    loop {
+        let slot = (!measuring).trailing_zeros() as usize;
+        if slot == PEAKS.len() {
+            return None;
+        }
+        PEAKS[slot].store(allocated, Ordering::Relaxed);
+        match MEASURING.compare_exchange_weak(
+            measuring,
+            measuring | 1 << slot,
+            Ordering::AcqRel,
+            Ordering::Relaxed,
+        ) {
+            Ok(_) => return Some(Measuring { stage, slot }),
+            Err(now) => measuring = now,
+        }
+    }
+}
+
+// Record the peak heap since `start()`.
+pub(crate) fn finish(measuring: Option<Measuring>) {
+    let Some(measuring) = measuring else {
+        return;
+    };
+    let peak = PEAKS[measuring.slot].load(Ordering::Relaxed);
+    let _ = SIZES.try_with(|sizes| {
+        let mut sizes = sizes.borrow_mut();
+        let recorded = sizes.peaks.entry(measuring.stage).or_default();
+        *recorded = (*recorded).max(peak);
+    });
+}
+
+impl Drop for Measuring {
+    fn drop(&mut self) {
+        MEASURING.fetch_and(!(1 << self.slot), Ordering::AcqRel);
+    }
+}
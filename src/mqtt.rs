@@ -0,0 +1,293 @@
+use super::sink::Sink;
+use super::source::{Checkpoint, Source};
+use super::warning::{self, Warning};
+use super::{Error, Input, Outcome};
+use futures::future::BoxFuture;
+use rumqttc::{
+    AsyncClient, ConnectionError, Event, EventLoop, MqttOptions, Outgoing, Packet, Publish,
+};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+pub use rumqttc::QoS;
+
+/// An MQTT subscriber, feeding each message's JSON payload into a pipe as one input `I`.
+///
+/// The connection is re-established whenever it drops, after the reconnect delay, and the
+/// topic filters are subscribed to again; messages published in between are only delivered
+/// if the broker kept the session, i.e., with [`clean_session(false)`] and QoS 1 or 2. Each
+/// failed attempt is a [`Warning`] of the run; after too many in a row (see
+/// [`max_reconnects()`]), the connection error is the stream's last item, failing the run.
+///
+/// A QoS 1 or 2 message is only acknowledged once its input has been loaded, so one that
+/// wasn't is delivered again.
+///
+/// ```rust,ignore
+/// let source = MqttSource::new("localhost", 1883, "telemetry-loader")
+///     .subscribe("sensors/+/readings", QoS::AtLeastOnce)
+///     .into_source::<Reading>();
+/// let pipe = Pipe::<Reading, Row>::builder().source(source).sink(sink).build()?;
+/// pipe.run().await?; // runs until the source is dropped, or gives up reconnecting
+/// ```
+///
+/// [`clean_session(false)`]: MqttSource::clean_session
+/// [`max_reconnects()`]: MqttSource::max_reconnects
+pub struct MqttSource {
+    options: MqttOptions,
+    filters: Vec<(String, QoS)>,
+    reconnect: Duration,
+    max_reconnects: u32,
+}
+
+impl MqttSource {
+    /// Connect to the broker at `host:port` as `client_id`.
+    pub fn new(host: &str, port: u16, client_id: &str) -> Self {
+        MqttSource {
+            options: MqttOptions::new(client_id, host, port),
+            filters: vec![],
+            reconnect: Duration::from_secs(1),
+            max_reconnects: 10,
+        }
+    }
+
+    /// Subscribe to the topic `filter` (which may contain `+` & `#` wildcards), at `qos`.
+    pub fn subscribe(mut self, filter: impl Into<String>, qos: QoS) -> Self {
+        self.filters.push((filter.into(), qos));
+        self
+    }
+
+    /// Authenticate with `username` & `password`.
+    pub fn credentials(mut self, username: &str, password: &str) -> Self {
+        self.options.set_credentials(username, password);
+        self
+    }
+
+    /// Ask the broker to keep the session (and queue QoS 1 & 2 messages) while disconnected,
+    /// i.e., `clean_session(false)`. Defaults to a clean session.
+    pub fn clean_session(mut self, clean: bool) -> Self {
+        self.options.set_clean_session(clean);
+        self
+    }
+
+    /// How long to wait before reconnecting, after the connection drops. Defaults to 1 second.
+    pub fn reconnect_delay(mut self, delay: Duration) -> Self {
+        self.reconnect = delay;
+        self
+    }
+
+    /// How many times in a row to try reconnecting, before giving up. Defaults to 10.
+    pub fn max_reconnects(mut self, max: u32) -> Self {
+        self.max_reconnects = max;
+        self
+    }
+
+    /// The streaming source, decoding each message payload as JSON.
+    pub fn into_source<I>(mut self) -> Source<I>
+    where
+        I: Input + 'static,
+    {
+        self.options.set_manual_acks(true);
+        let (client, eventloop) = AsyncClient::new(self.options, 64);
+        let acks = Acks {
+            client: client.clone(),
+            pending: Arc::default(),
+        };
+        let subscriber = Subscriber {
+            client,
+            eventloop,
+            filters: self.filters,
+            reconnect: self.reconnect,
+            max_reconnects: self.max_reconnects,
+            pending: acks.pending.clone(),
+        };
+        let stream = futures::stream::unfold(Some(subscriber), |subscriber| async move {
+            let mut subscriber = subscriber?;
+            match subscriber.next::<I>().await {
+                Ok(input) => Some((input, Some(subscriber))),
+                // given up reconnecting: the last item
+                Err(e) => Some((Err(e), None)),
+            }
+        });
+        Source::checkpointed(stream, acks)
+    }
+}
+
+// The client of an `MqttSource`'s stream, and its connection.
+struct Subscriber {
+    client: AsyncClient,
+    eventloop: EventLoop,
+    filters: Vec<(String, QoS)>,
+    reconnect: Duration,
+    max_reconnects: u32,
+    pending: Arc<Mutex<Vec<Publish>>>,
+}
+
+impl Subscriber {
+    // The next input; or, having given up reconnecting, the connection error.
+    async fn next<I: Input>(&mut self) -> Result<Result<I, Error>, Error> {
+        let mut failures = 0;
+        loop {
+            match self.eventloop.poll().await {
+                Ok(Event::Incoming(Packet::Publish(publish))) => {
+                    let input = serde_json::from_slice::<I>(&publish.payload)?;
+                    self.pending
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .push(publish);
+                    return Ok(Ok(input));
+                }
+                // (re)connected: (re)subscribe
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    failures = 0;
+                    for (filter, qos) in &self.filters {
+                        if let Err(e) = self.client.subscribe(filter.clone(), *qos).await {
+                            return Ok(Err(e.into()));
+                        }
+                    }
+                }
+                Ok(_) => {}
+                Err(e) if failures >= self.max_reconnects => {
+                    return Err(Error::Mqtt(e.to_string()))
+                }
+                // the next poll reconnects
+                Err(e) => {
+                    failures += 1;
+                    warning::warn(Warning::other(format!(
+                        "mqtt connection error, reconnecting: {e}"
+                    )));
+                    tokio::time::sleep(self.reconnect).await;
+                }
+            }
+        }
+    }
+}
+
+// Acknowledges the messages whose inputs have been loaded; sent as the next message is polled.
+struct Acks {
+    client: AsyncClient,
+    pending: Arc<Mutex<Vec<Publish>>>,
+}
+
+impl Checkpoint for Acks {
+    fn loaded(&self) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(async move {
+            let loaded = std::mem::take(
+                &mut *self
+                    .pending
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner()),
+            );
+            for publish in &loaded {
+                self.client.ack(publish).await?;
+            }
+            Ok(())
+        })
+    }
+
+    fn flush(&self) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Publishes the output as JSON to an MQTT topic.
+///
+/// Messages are handed to the client's event loop, which runs in the background from the first
+/// load until the sink is dropped; it reconnects whenever the connection drops, and re-sends QoS
+/// 1 & 2 messages the broker hadn't acknowledged yet.
+///
+/// ```rust,ignore
+/// let sink = MqttSink::new("localhost", 1883, "telemetry-publisher", "telemetry/clean")
+///     .qos(QoS::AtLeastOnce);
+/// ```
+pub struct MqttSink {
+    client: AsyncClient,
+    eventloop: Mutex<Option<EventLoop>>,
+    topic: String,
+    qos: QoS,
+    retain: bool,
+    reconnect: Duration,
+}
+
+impl MqttSink {
+    /// Publish to `topic`, on the broker at `host:port`, as `client_id`.
+    pub fn new(host: &str, port: u16, client_id: &str, topic: impl Into<String>) -> Self {
+        Self::from_options(MqttOptions::new(client_id, host, port), topic)
+    }
+
+    /// Use custom client options, e.g., with credentials or a longer keep-alive.
+    pub fn from_options(options: MqttOptions, topic: impl Into<String>) -> Self {
+        let (client, eventloop) = AsyncClient::new(options, 64);
+        MqttSink {
+            client,
+            eventloop: Mutex::new(Some(eventloop)),
+            topic: topic.into(),
+            qos: QoS::AtMostOnce,
+            retain: false,
+            reconnect: Duration::from_secs(1),
+        }
+    }
+
+    /// The delivery guarantee for published messages. Defaults to [`QoS::AtMostOnce`].
+    pub fn qos(mut self, qos: QoS) -> Self {
+        self.qos = qos;
+        self
+    }
+
+    /// Have the broker keep the last message, for new subscribers.
+    pub fn retain(mut self, retain: bool) -> Self {
+        self.retain = retain;
+        self
+    }
+
+    /// How long to wait before reconnecting, after the connection drops. Defaults to 1 second.
+    pub fn reconnect_delay(mut self, delay: Duration) -> Self {
+        self.reconnect = delay;
+        self
+    }
+}
+
+impl<O> Sink<O> for MqttSink
+where
+    O: serde::Serialize + Sync + ?Sized,
+{
+    async fn load(&self, output: &O) -> Result<Outcome, Error> {
+        let eventloop = self
+            .eventloop
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take();
+        if let Some(mut eventloop) = eventloop {
+            let reconnect = self.reconnect;
+            tokio::spawn(async move {
+                loop {
+                    match eventloop.poll().await {
+                        // the sink was dropped
+                        Ok(Event::Outgoing(Outgoing::Disconnect))
+                        | Err(ConnectionError::RequestsDone) => break,
+                        Ok(_) => {}
+                        // the next poll reconnects
+                        Err(e) => {
+                            warning::warn(Warning::other(format!(
+                                "mqtt connection error, reconnecting: {e}"
+                            )));
+                            tokio::time::sleep(reconnect).await;
+                        }
+                    }
+                }
+            });
+        }
+
+        let payload = serde_json::to_vec(output)?;
+        self.client
+            .publish(&self.topic, self.qos, self.retain, payload)
+            .await?;
+        Ok(Outcome::Done)
+    }
+}
+
+impl Drop for MqttSink {
+    // ends the event loop, once it has sent what was published
+    fn drop(&mut self) {
+        let _ = self.client.try_disconnect();
+    }
+}
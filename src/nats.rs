@@ -0,0 +1,79 @@
+use super::sink::Sink;
+use super::{Error, Outcome};
+use async_nats::jetstream::{self, context::PublishAckFuture};
+use serde::Serialize;
+use tokio::sync::OnceCell;
+
+/// Publishes every record of the output to a NATS JetStream subject, as one JSON message each.
+///
+/// The messages are all sent before any acknowledgement is awaited, then the load waits for the
+/// stream to acknowledge every one; so a load that succeeds has been stored by the stream, and one
+/// that fails (e.g., with no stream bound to the subject) may be retried, with at-least-once
+/// delivery. The connection is opened on the first load.
+///
+/// ```rust,ignore
+/// let sink = NatsSink::new("nats://localhost:4222", "prices.clean");
+/// let pipe = Pipe::<RawPrice, Vec<Price>>::builder().source(source).sink(sink).build()?;
+/// ```
+pub struct NatsSink {
+    url: String,
+    subject: String,
+    context: OnceCell<jetstream::Context>,
+}
+
+impl NatsSink {
+    /// Publish to `subject`, on the server at `url`.
+    pub fn new(url: impl Into<String>, subject: impl Into<String>) -> Self {
+        NatsSink {
+            url: url.into(),
+            subject: subject.into(),
+            context: OnceCell::new(),
+        }
+    }
+
+    /// Publish over an existing client, e.g., one connected with credentials or TLS.
+    pub fn from_client(client: async_nats::Client, subject: impl Into<String>) -> Self {
+        NatsSink {
+            url: String::new(),
+            subject: subject.into(),
+            context: OnceCell::new_with(Some(jetstream::new(client))),
+        }
+    }
+
+    async fn context(&self) -> Result<&jetstream::Context, Error> {
+        self.context
+            .get_or_try_init(|| async {
+                let client = async_nats::connect(&self.url).await.map_err(nats)?;
+                Ok(jetstream::new(client))
+            })
+            .await
+    }
+}
+
+impl<T> Sink<Vec<T>> for NatsSink
+where
+    T: Serialize + Sync,
+{
+    async fn load(&self, output: &Vec<T>) -> Result<Outcome, Error> {
+        let context = self.context().await?;
+        let mut acks: Vec<PublishAckFuture> = Vec::with_capacity(output.len());
+        for record in output {
+            let payload = serde_json::to_vec(record)?;
+            let ack = context
+                .publish(self.subject.clone(), payload.into())
+                .await
+                .map_err(nats)?;
+            acks.push(ack);
+        }
+        for ack in acks {
+            ack.await.map_err(nats)?;
+        }
+        Ok(Outcome::Published {
+            messages: output.len() as u64,
+        })
+    }
+}
+
+fn nats(error: impl std::fmt::Display) -> Error {
+    Error::Nats(error.to_string())
+}
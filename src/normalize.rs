@@ -0,0 +1,275 @@
+//! Nested JSON, normalized into flat record sets, one per table: each array of a record is
+//! exploded into a child table, a row per element, with a generated key back to its parent row;
+//! and each nested object is flattened into its parent's columns (or, if named, a table of its
+//! own). For relational sinks, loading one table each; see [`Sinks`].
+//!
+//! ```rust,ignore
+//! // { "symbol": "NVDA", "meta": { "currency": "USD" }, "prices": [{ "close": 120.5 }, ...] }
+//! let tables = Normalize::new("quotes").apply(&quote)?;
+//! // quotes:        { "_id": 1, "symbol": "NVDA", "meta_currency": "USD" }
+//! // quotes_prices: { "_id": 1, "quotes_id": 1, "close": 120.5 }, ...
+//!
+//! let sink = Sinks::new()
+//!     .table("quotes", sink::Postgres::new(conn, "quotes"))
+//!     .table("quotes_prices", sink::Postgres::new(conn, "prices"));
+//! ```
+//!
+//! Keys are numbered from 1 in each table, in the order the rows are found, so they're only
+//! unique within one output; load each output into fresh (or staging) tables, or key the rows
+//! on their own fields downstream. A child table is named after its parent and the field it came
+//! from (e.g., `quotes_prices`), unless named with [`Normalize::table()`]; an array of anything
+//! other than objects is exploded into rows of one `value` column.
+
+use super::error::{Context, Errors};
+use super::sink::{DynSink, Sink};
+use super::{Error, Outcome};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap};
+
+/// A row of a normalized table.
+pub type Row = Map<String, Value>;
+
+/// The rows of every table, by name; see [`Normalize::apply()`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Tables {
+    pub tables: BTreeMap<String, Vec<Row>>,
+}
+
+impl Tables {
+    /// The rows of `table`; none, if it has none.
+    pub fn rows(&self, table: &str) -> &[Row] {
+        self.tables.get(table).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// How to normalize a value into [`Tables`]; see [`normalize`](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Normalize {
+    root: String,
+    id: String,
+    separator: String,
+    // tables named by the path of the field they come from, e.g., `indicators.quote`
+    names: HashMap<String, String>,
+}
+
+impl Normalize {
+    /// Normalize the records of a value (its elements, if it's an array) into the `root` table.
+    pub fn new(root: &str) -> Self {
+        Normalize {
+            root: root.to_string(),
+            id: "_id".to_string(),
+            separator: "_".to_string(),
+            names: HashMap::new(),
+        }
+    }
+
+    /// Key the rows of every table by `column`. Defaults to `_id`; a child's key to its parent is
+    /// the parent's name, then `column` without any leading `_`, e.g., `quotes_id`.
+    pub fn id(mut self, column: &str) -> Self {
+        self.id = column.to_string();
+        self
+    }
+
+    /// Join the names of flattened columns (& child tables) with `separator`. Defaults to `_`.
+    pub fn separator(mut self, separator: &str) -> Self {
+        self.separator = separator.to_string();
+        self
+    }
+
+    /// Name the child table of the field at `path` (from the root, e.g., `chart.meta`); and if
+    /// it's an object, make it a child table of one row, rather than flattening it.
+    pub fn table(mut self, path: &str, name: &str) -> Self {
+        self.names.insert(path.to_string(), name.to_string());
+        self
+    }
+
+    /// The tables of `value`'s records.
+    ///
+    /// Returns [`Error::Config`] if a record isn't an object, or two of a row's columns would have
+    /// the same name: a field named as a generated key (e.g., `_id`, or `quotes_id`), or two fields
+    /// joined into the same name (e.g., `meta_currency` & `meta.currency`).
+    pub fn apply<T: Serialize + ?Sized>(&self, value: &T) -> Result<Tables, Error> {
+        let records = match serde_json::to_value(value)? {
+            Value::Array(records) => records,
+            record => vec![record],
+        };
+        let mut tables = Tables::default();
+        tables.tables.insert(self.root.clone(), vec![]);
+        for (i, record) in records.into_iter().enumerate() {
+            let Value::Object(record) = record else {
+                return Err(Error::Config(format!("record {i} isn't an object")));
+            };
+            self.row(&mut tables, &self.root, "", record, None)?;
+        }
+        Ok(tables)
+    }
+
+    // Add `record` as a row of `table`, keyed back to `parent` (its table & key), if any; with
+    // its arrays & named objects as rows of child tables. `path` is the record's, from the root.
+    fn row(
+        &self,
+        tables: &mut Tables,
+        table: &str,
+        path: &str,
+        record: Row,
+        parent: Option<(&str, u64)>,
+    ) -> Result<(), Error> {
+        // the key is taken before any child's, in case a child table is named after its parent
+        let rows = tables.tables.entry(table.to_string()).or_default();
+        let id = rows.len() as u64 + 1;
+        rows.push(Row::new());
+
+        let mut row = Row::new();
+        row.insert(self.id.clone(), id.into());
+        if let Some((parent, key)) = parent {
+            row.insert(self.foreign_key(parent), key.into());
+        }
+        let mut children = vec![];
+        self.flatten(table, path, "", record, &mut row, &mut children)?;
+        if let Some(slot) = tables
+            .tables
+            .get_mut(table)
+            .and_then(|rows| rows.get_mut(id as usize - 1))
+        {
+            *slot = row;
+        }
+
+        for (child, child_path, records) in children {
+            for record in records {
+                let record = match record {
+                    Value::Object(record) => record,
+                    value => Row::from_iter([("value".to_string(), value)]),
+                };
+                self.row(tables, &child, &child_path, record, Some((table, id)))?;
+            }
+        }
+        Ok(())
+    }
+
+    // Flatten the fields of `record` into `row`, prefixing their columns with `prefix`; setting
+    // aside the child table (name, path & records) of each array & named object.
+    fn flatten(
+        &self,
+        table: &str,
+        path: &str,
+        prefix: &str,
+        record: Row,
+        row: &mut Row,
+        children: &mut Vec<(String, String, Vec<Value>)>,
+    ) -> Result<(), Error> {
+        for (field, value) in record {
+            let field_path = match path {
+                "" => field.clone(),
+                path => format!("{path}.{field}"),
+            };
+            let column = format!("{prefix}{field}");
+            let named = self.names.get(&field_path);
+            match value {
+                Value::Array(elements) if !elements.iter().any(Value::is_array) => {
+                    let name = named.cloned().unwrap_or_else(|| self.child(table, &column));
+                    children.push((name, field_path, elements));
+                }
+                Value::Object(object) => match named {
+                    Some(name) => {
+                        children.push((name.clone(), field_path, vec![Value::Object(object)]))
+                    }
+                    None => {
+                        let prefix = format!("{column}{}", self.separator);
+                        self.flatten(table, &field_path, &prefix, object, row, children)?;
+                    }
+                },
+                // arrays of arrays have no rows to explode into
+                value => {
+                    if row.contains_key(&column) {
+                        return Err(Error::Config(format!(
+                            "`{field_path}` would overwrite column `{column}` of table `{table}`"
+                        )));
+                    }
+                    row.insert(column, value);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // the name of `table`'s child table, from its `column`
+    fn child(&self, table: &str, column: &str) -> String {
+        format!("{table}{}{column}", self.separator)
+    }
+
+    // the column of a child's key to a row of `parent`
+    fn foreign_key(&self, parent: &str) -> String {
+        format!(
+            "{parent}{}{}",
+            self.separator,
+            self.id.trim_start_matches('_')
+        )
+    }
+}
+
+/// Loads each table of [`Tables`] to its own sink, in the order the tables were added; so parents
+/// can be loaded before the children that refer to them.
+///
+/// The load stops at the first table that fails, with the error in [`Error::Many`], and the
+/// table as its route. A table with rows but no sink fails the load before any is loaded; a table
+/// with a sink but no rows isn't loaded.
+#[derive(Default)]
+pub struct Sinks {
+    sinks: Vec<Table>,
+}
+
+struct Table {
+    name: String,
+    sink: Box<dyn DynSink<Vec<Row>>>,
+}
+
+impl Sinks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load the rows of `table` to `sink`; replacing any sink of the same table.
+    pub fn table<S>(mut self, table: impl Into<String>, sink: S) -> Self
+    where
+        S: Sink<Vec<Row>> + 'static,
+    {
+        let table = table.into();
+        self.sinks.retain(|sink| sink.name != table);
+        self.sinks.push(Table {
+            name: table,
+            sink: Box::new(sink),
+        });
+        self
+    }
+}
+
+impl Sink<Tables> for Sinks {
+    async fn load(&self, output: &Tables) -> Result<Outcome, Error> {
+        let missing = output.tables.iter().find(|(table, rows)| {
+            !rows.is_empty() && !self.sinks.iter().any(|sink| sink.name == **table)
+        });
+        if let Some((table, _)) = missing {
+            return Err(Error::Config(format!("table `{table}` has no sink")));
+        }
+
+        let mut outcomes = BTreeMap::new();
+        for Table { name: table, sink } in &self.sinks {
+            let Some(rows) = output.tables.get(table).filter(|rows| !rows.is_empty()) else {
+                continue;
+            };
+            match sink.load_boxed(rows).await {
+                Ok(outcome) => {
+                    outcomes.insert(table.clone(), outcome);
+                }
+                Err(error) => {
+                    let mut errors = Errors::new();
+                    errors.push(Context::Route(table.clone()), error);
+                    return Err(Error::Many(errors));
+                }
+            }
+        }
+        Ok(Outcome::Routes(outcomes))
+    }
+}
@@ -0,0 +1,117 @@
+use super::Error;
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+/// OAuth2 client credentials, used to authenticate extraction requests with a bearer token.
+///
+/// The token is acquired on first use, shared by every request made by the pipe, and refreshed
+/// shortly before it expires (see [`skew()`]); a `401 Unauthorized` response also forces a refresh.
+///
+/// ```rust,ignore
+/// let pipe = Pipe::<I, O>::builder()
+///     .oauth2(OAuth2::new("https://auth.example.com/token", "client-id", "client-secret").scope("read"))
+///     .build()?;
+/// ```
+///
+/// [`skew()`]: OAuth2::skew
+#[derive(Debug)]
+pub struct OAuth2 {
+    pub token_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub scope: Option<String>,
+    skew: Duration,
+    token: Mutex<Option<Token>>,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    access_token: String,
+    // `None` if the server didn't say; the token is then only refreshed on a 401
+    expires_at: Option<Instant>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: Option<u64>,
+}
+
+impl OAuth2 {
+    pub fn new(
+        token_url: impl Into<String>,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+    ) -> Self {
+        OAuth2 {
+            token_url: token_url.into(),
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+            scope: None,
+            skew: Duration::from_secs(60),
+            token: Mutex::new(None),
+        }
+    }
+
+    /// Request the token for a specific scope.
+    pub fn scope(mut self, scope: impl Into<String>) -> Self {
+        self.scope = Some(scope.into());
+        self
+    }
+
+    /// Treat tokens as expired this long before the server says they are, to allow for clock
+    /// differences and in-flight requests. Defaults to 60 seconds.
+    pub fn skew(mut self, skew: Duration) -> Self {
+        self.skew = skew;
+        self
+    }
+
+    /// A valid access token, fetching a new one if there is none or it is about to expire.
+    pub async fn bearer(&self, client: &reqwest::Client) -> Result<String, Error> {
+        let mut token = self.token.lock().await;
+        let fresh = token.as_ref().is_some_and(|t| match t.expires_at {
+            Some(expires_at) => Instant::now() + self.skew < expires_at,
+            None => true,
+        });
+        if !fresh {
+            *token = Some(self.fetch(client).await?);
+        }
+        Ok(token
+            .as_ref()
+            .map(|t| t.access_token.clone())
+            .unwrap_or_default())
+    }
+
+    /// Drop the current token, so the next request fetches a new one.
+    pub async fn invalidate(&self) {
+        *self.token.lock().await = None;
+    }
+
+    async fn fetch(&self, client: &reqwest::Client) -> Result<Token, Error> {
+        let mut form = vec![
+            ("grant_type", "client_credentials"),
+            ("client_id", &self.client_id),
+            ("client_secret", &self.client_secret),
+        ];
+        if let Some(scope) = &self.scope {
+            form.push(("scope", scope));
+        }
+        let issued = Instant::now();
+        let response: TokenResponse = client
+            .post(&self.token_url)
+            .form(&form)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(Token {
+            access_token: response.access_token,
+            expires_at: response
+                .expires_in
+                .map(|secs| issued + Duration::from_secs(secs)),
+        })
+    }
+}
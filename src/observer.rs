@@ -0,0 +1,101 @@
+use super::health::Circuit;
+use super::runner::Overlap;
+use super::warning::Warning;
+use super::Error;
+use serde::{Deserialize, Serialize};
+
+/// The stages of a pipe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    Extract,
+    Transform,
+    /// Only run if the pipe was configured with an enrichment; see [`PipeBuilder::enrich()`].
+    ///
+    /// [`PipeBuilder::enrich()`]: crate::PipeBuilder::enrich
+    Enrich,
+    Load,
+}
+
+impl std::fmt::Display for Stage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Stage::Extract => write!(f, "extract"),
+            Stage::Transform => write!(f, "transform"),
+            Stage::Enrich => write!(f, "enrich"),
+            Stage::Load => write!(f, "load"),
+        }
+    }
+}
+
+/// Something that happened while running a pipe.
+///
+/// New events may be added, so observers' matches on it need a wildcard arm.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Event<'a> {
+    /// A run has started, reading from `source`.
+    Started { source: &'a str },
+    /// A fresh output was found in the cache; extract & transform are skipped.
+    CacheHit { source: &'a str },
+    /// The payload was the one last loaded from `source`; transform & load are skipped. See
+    /// [`unchanged`](crate::unchanged).
+    Unchanged { source: &'a str },
+    /// A stage completed successfully.
+    Completed { stage: Stage },
+    /// The size of the data in a measured run: of a payload as fetched (`Extract`), or of an
+    /// output about to be loaded, as JSON (`Transform`). See [`memory`](crate::memory).
+    Sized { stage: Stage, bytes: u64 },
+    /// A stage failed, and is about to be attempted again.
+    Retrying {
+        stage: Stage,
+        attempt: u32,
+        error: &'a Error,
+    },
+    /// A stage failed for good; the run is aborted.
+    Failed { stage: Stage, error: &'a Error },
+    /// The run finished successfully.
+    Finished,
+    /// The run failed, and was dumped `to` a file or sink; or couldn't be, with the error. See
+    /// [`dump`](crate::dump).
+    Dumped {
+        run_id: &'a str,
+        to: Result<&'a str, &'a Error>,
+    },
+    /// A stage raised a warning, and carried on; see [`Pipe::warn()`].
+    ///
+    /// [`Pipe::warn()`]: crate::Pipe::warn
+    Warning { warning: &'a Warning },
+    /// A sink's circuit breaker changed state; see [`Breaker`].
+    ///
+    /// [`Breaker`]: crate::health::Breaker
+    Circuit { circuit: Circuit },
+    /// A run of a [`Runner`]'s `pipeline` was due while its last was in progress, and the
+    /// runner did `overlap` with it; see [`Runner::overlap()`].
+    ///
+    /// [`Runner`]: crate::Runner
+    /// [`Runner::overlap()`]: crate::Runner::overlap
+    Overlapped { pipeline: &'a str, overlap: Overlap },
+}
+
+/// Receives [`Event`]s from a running pipe, e.g., for logging or metrics.
+///
+/// Any `Fn(&Event)` closure is an `Observer`:
+///
+/// ```rust,ignore
+/// let pipe = Pipe::<I, O>::builder()
+///     .observer(|event: &Event| println!("{event:?}"))
+///     .build()?;
+/// ```
+pub trait Observer: Send + Sync {
+    fn on_event(&self, event: &Event);
+}
+
+impl<F> Observer for F
+where
+    F: Fn(&Event) + Send + Sync,
+{
+    fn on_event(&self, event: &Event) {
+        self(event)
+    }
+}
@@ -0,0 +1,338 @@
+//! Extraction from REST APIs that page their results with a cursor: every page's items, following
+//! one of the common SaaS conventions, rather than a next-page closure written for each API.
+//!
+//! ```rust,ignore
+//! pipeline! {
+//!     Vec<Contact> -> Vec<Row> {
+//!         async fn extract(&self, url: &str) -> pipe_io::Result<Vec<Contact>> {
+//!             Paginator::preset("hubspot")?.extract(self, url).await
+//!         }
+//!         ...
+//!     }
+//! }
+//! ```
+//!
+//! The presets, by name:
+//!
+//! | name              | items     | next page                                                     |
+//! |-------------------|-----------|---------------------------------------------------------------|
+//! | `next_page_token` | `items`   | `?pageToken=` the page's `nextPageToken`                      |
+//! | `hubspot`         | `results` | `?after=` the page's `paging.next.after`                      |
+//! | `offset_limit`    | `data`    | `?offset=` past the items so far, `&limit=100`                |
+//! | `has_more`        | `data`    | while `has_more`, `?starting_after=` the last item's `id`     |
+//! | `salesforce`      | `records` | the page's `nextRecordsUrl`                                   |
+//!
+//! Each can be adjusted, e.g., `Paginator::preset("offset_limit")?.items("results")`; or built
+//! from [`Cursor`] for an API of its own. Paths are dotted, e.g., `paging.next.after`; an empty
+//! path is the page itself.
+//!
+//! Pages are followed as [`hypermedia`](crate::hypermedia) links are: fetched with the pipe's
+//! [`fetch_default()`], until the last page, or one already fetched (e.g., a cursor that never
+//! changes).
+//!
+//! [`fetch_default()`]: crate::Pipe::fetch_default
+
+use super::{default, Error, Input, Output, Pipe};
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::str::FromStr;
+
+/// How a paginated API's pages hold their items, and lead to the next; see [`paginate`](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Paginator {
+    /// The path of a page's array of items.
+    pub items: String,
+    pub cursor: Cursor,
+    /// The most pages fetched, if limited.
+    pub max_pages: Option<usize>,
+}
+
+/// How a page leads to the next.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cursor {
+    /// The page's `token` is sent back as the query parameter `param`; the last page has none
+    /// (or an empty one).
+    Token { token: String, param: String },
+    /// Pages of up to `limit` items, starting at the query parameter `offset`; the last page is
+    /// empty, as an API may send fewer than `limit` before then (e.g., capping it).
+    Offset {
+        offset: String,
+        limit: String,
+        size: usize,
+    },
+    /// While the page's `has_more` is true, the next starts after the `id` of its last item,
+    /// sent as the query parameter `param`.
+    HasMore {
+        has_more: String,
+        param: String,
+        id: String,
+    },
+    /// The page's `next` is the next page's URL, absolute or relative to the page.
+    NextUrl { next: String },
+}
+
+/// The names of the [`Paginator::preset()`]s.
+pub const PRESETS: [&str; 5] = [
+    "next_page_token",
+    "hubspot",
+    "offset_limit",
+    "has_more",
+    "salesforce",
+];
+
+impl Paginator {
+    pub fn new(items: impl Into<String>, cursor: Cursor) -> Self {
+        Paginator {
+            items: items.into(),
+            cursor,
+            max_pages: None,
+        }
+    }
+
+    /// The preset named `name`, one of [`PRESETS`]; see [`paginate`](self).
+    ///
+    /// Returns [`Error::Config`] for any other name.
+    pub fn preset(name: &str) -> Result<Self, Error> {
+        let paginator = match name {
+            "next_page_token" => Self::new("items", Cursor::token("nextPageToken", "pageToken")),
+            "hubspot" => Self::new("results", Cursor::token("paging.next.after", "after")),
+            "offset_limit" => Self::new(
+                "data",
+                Cursor::Offset {
+                    offset: "offset".into(),
+                    limit: "limit".into(),
+                    size: 100,
+                },
+            ),
+            "has_more" => Self::new(
+                "data",
+                Cursor::HasMore {
+                    has_more: "has_more".into(),
+                    param: "starting_after".into(),
+                    id: "id".into(),
+                },
+            ),
+            "salesforce" => Self::new(
+                "records",
+                Cursor::NextUrl {
+                    next: "nextRecordsUrl".into(),
+                },
+            ),
+            _ => {
+                return Err(Error::Config(format!(
+                    "no paginator named `{name}`; expected one of {}",
+                    PRESETS.join(", ")
+                )))
+            }
+        };
+        Ok(paginator)
+    }
+
+    /// Take the items from `path`, instead.
+    pub fn items(mut self, path: impl Into<String>) -> Self {
+        self.items = path.into();
+        self
+    }
+
+    /// Fetch `size` items a page, for [`Cursor::Offset`]; otherwise, ignored.
+    pub fn size(mut self, size: usize) -> Self {
+        if let Cursor::Offset {
+            size: ref mut page, ..
+        } = self.cursor
+        {
+            *page = size.max(1);
+        }
+        self
+    }
+
+    /// Stop after `pages` pages, even if there are more.
+    pub fn max_pages(mut self, pages: usize) -> Self {
+        self.max_pages = Some(pages);
+        self
+    }
+
+    /// Every item at `url`, and the pages after it, as `T`s.
+    ///
+    /// Failures to decode are [`Error::Decode`], with the path of the item, e.g., `[12].email`;
+    /// a page without items is [`Error::Missing`]. A cursor other than [`Cursor::NextUrl`] needs
+    /// `url` to be an HTTP(S) URL, to add its query parameters to.
+    pub async fn extract<T, I, O>(&self, pipe: &Pipe<I, O>, url: &str) -> Result<Vec<T>, Error>
+    where
+        T: DeserializeOwned,
+        I: Input,
+        O: Output,
+    {
+        let mut items = vec![];
+        follow(pipe, self.first(url)?, self.max_pages, |url, page| {
+            let page_items = self.page_items(&page)?;
+            let count = page_items.len();
+            items.extend(page_items);
+            self.next(url, &page, count, items.len())
+        })
+        .await?;
+        default::decode(serde_json::to_vec(&items)?)
+    }
+
+    /// The items of one page.
+    pub fn page_items(&self, page: &Value) -> Result<Vec<Value>, Error> {
+        match pluck(page, &self.items) {
+            Some(Value::Array(items)) => Ok(items.clone()),
+            Some(Value::Null) => Ok(vec![]),
+            _ => Err(Error::Missing(self.items.clone())),
+        }
+    }
+
+    // the URL of the first page
+    fn first(&self, url: &str) -> Result<String, Error> {
+        match &self.cursor {
+            Cursor::Offset {
+                offset,
+                limit,
+                size,
+            } => with_params(url, [(offset, "0".into()), (limit, size.to_string())]),
+            _ => Ok(url.to_string()),
+        }
+    }
+
+    // The URL of the page after `page`, fetched from `url` with `count` of `total` items so far;
+    // `None` if it's the last.
+    fn next(
+        &self,
+        url: &str,
+        page: &Value,
+        count: usize,
+        total: usize,
+    ) -> Result<Option<String>, Error> {
+        match &self.cursor {
+            Cursor::Token { token, param } => match pluck(page, token).and_then(scalar) {
+                Some(token) if !token.is_empty() => Ok(Some(with_params(url, [(param, token)])?)),
+                _ => Ok(None),
+            },
+            Cursor::Offset {
+                offset,
+                limit,
+                size,
+            } => match count {
+                0 => Ok(None),
+                _ => Ok(Some(with_params(
+                    url,
+                    [(offset, total.to_string()), (limit, size.to_string())],
+                )?)),
+            },
+            Cursor::HasMore {
+                has_more,
+                param,
+                id,
+            } => {
+                let more = pluck(page, has_more).and_then(Value::as_bool) == Some(true);
+                if !more || count == 0 {
+                    return Ok(None);
+                }
+                let items = self.page_items(page)?;
+                let last = items
+                    .last()
+                    .and_then(|item| pluck(item, id))
+                    .and_then(scalar)
+                    .ok_or_else(|| Error::Missing(format!("{} of the last item", id)))?;
+                Ok(Some(with_params(url, [(param, last)])?))
+            }
+            Cursor::NextUrl { next } => Ok(pluck(page, next)
+                .and_then(Value::as_str)
+                .map(|link| resolve(url, link))),
+        }
+    }
+}
+
+impl FromStr for Paginator {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Error> {
+        Self::preset(name)
+    }
+}
+
+impl Cursor {
+    /// [`Cursor::Token`].
+    pub fn token(token: impl Into<String>, param: impl Into<String>) -> Self {
+        Cursor::Token {
+            token: token.into(),
+            param: param.into(),
+        }
+    }
+}
+
+// Fetch the page at `url`, then each after it, handing each (with its URL) to `page`, which takes
+// its items and returns the URL of the next; until there's none, it's a page already fetched, or
+// `max_pages` have been.
+pub(crate) async fn follow<I, O>(
+    pipe: &Pipe<I, O>,
+    url: String,
+    max_pages: Option<usize>,
+    mut page: impl FnMut(&str, Value) -> Result<Option<String>, Error>,
+) -> Result<(), Error>
+where
+    I: Input,
+    O: Output,
+{
+    let mut fetched = HashSet::new();
+    let mut next = Some(url);
+    while let Some(url) = next.take() {
+        let body: Value = default::decode(pipe.fetch_default(&url).await?)?;
+        let after = page(&url, body)?;
+        fetched.insert(url);
+        if max_pages.is_some_and(|max| fetched.len() >= max) {
+            break;
+        }
+        next = after.filter(|after| !fetched.contains(after));
+    }
+    Ok(())
+}
+
+// the value at the dotted `path` of `value`
+fn pluck<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .filter(|key| !key.is_empty())
+        .try_fold(value, |value, key| value.get(key))
+}
+
+// a cursor as a query parameter: strings as they are, numbers as written
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(value) => Some(value.clone()),
+        Value::Number(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+// `url`, with the query parameters `params` set, replacing any of the same names
+fn with_params<'a>(
+    url: &str,
+    params: impl IntoIterator<Item = (&'a String, String)>,
+) -> Result<String, Error> {
+    let mut url = Url::parse(url).map_err(|e| {
+        Error::Config(format!(
+            "a paginated endpoint needs a URL, not {url:?}: {e}"
+        ))
+    })?;
+    let params: Vec<(&String, String)> = params.into_iter().collect();
+    let kept: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(key, _)| !params.iter().any(|(param, _)| *param == key))
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(kept)
+        .extend_pairs(params);
+    Ok(url.to_string())
+}
+
+// `link`, relative to the page at `url`; file paths aren't resolved
+pub(crate) fn resolve(url: &str, link: &str) -> String {
+    match Url::parse(url).and_then(|url| url.join(link)) {
+        Ok(resolved) => resolved.to_string(),
+        Err(_) => link.to_string(),
+    }
+}
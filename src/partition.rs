@@ -0,0 +1,204 @@
+//! Decoding large local NDJSON & CSV files across threads; for files so large that parsing them
+//! on one thread is the bottleneck.
+//!
+//! The file is split into partitions of (about) [`chunk_size()`] bytes, each starting on a record
+//! boundary, which are decoded in parallel, on the blocking thread pool, and streamed into the
+//! pipe as `I`s; in file order, or as each partition is done.
+//!
+//! ```rust,ignore
+//! let prices = Partitioned::json_lines("prices.ndjson")
+//!     .threads(8)
+//!     .ordered(false)
+//!     .into_stream::<Price>()
+//!     .await?;
+//! let pipe = Pipe::<Price, Row>::builder().source(Source::stream(prices)).sink(sink).build()?;
+//! pipe.run().await?;
+//! ```
+//!
+//! Records are split on newlines, so a CSV field can't contain one, even quoted.
+//!
+//! [`chunk_size()`]: Partitioned::chunk_size
+
+use super::{default, Error, Input};
+use futures::stream::{self, BoxStream, StreamExt};
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// A local NDJSON or CSV file, to be decoded in parallel partitions; see [`partition`](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Partitioned {
+    path: PathBuf,
+    layout: Layout,
+    chunk_size: u64,
+    threads: usize,
+    ordered: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layout {
+    JsonLines,
+    // with a header line, naming the fields
+    Csv { delimiter: u8 },
+}
+
+impl Partitioned {
+    /// One JSON value per line; blank lines are skipped.
+    pub fn json_lines(path: impl Into<PathBuf>) -> Self {
+        Self::new(path.into(), Layout::JsonLines)
+    }
+
+    /// Comma-separated values, with a header line naming the fields.
+    pub fn csv(path: impl Into<PathBuf>) -> Self {
+        Self::new(path.into(), Layout::Csv { delimiter: b',' })
+    }
+
+    fn new(path: PathBuf, layout: Layout) -> Self {
+        Partitioned {
+            path,
+            layout,
+            chunk_size: 8 * 1024 * 1024,
+            threads: std::thread::available_parallelism().map_or(4, usize::from),
+            ordered: true,
+        }
+    }
+
+    /// Separate the fields of a CSV file with `delimiter` (e.g., `b'\t'`), instead of commas.
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        if let Layout::Csv { .. } = self.layout {
+            self.layout = Layout::Csv { delimiter };
+        }
+        self
+    }
+
+    /// The size of each partition, in bytes, before it's extended to the end of its last record.
+    /// Defaults to 8 MiB.
+    pub fn chunk_size(mut self, bytes: u64) -> Self {
+        self.chunk_size = bytes.max(1);
+        self
+    }
+
+    /// How many partitions are decoded at once; at most this many are held in memory. Defaults
+    /// to the number of CPUs.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Whether the records are streamed in file order; if not, each partition's are streamed as
+    /// soon as it's decoded, so one slow partition doesn't hold up the rest. Defaults to `true`.
+    pub fn ordered(mut self, ordered: bool) -> Self {
+        self.ordered = ordered;
+        self
+    }
+
+    /// The records of the file, as they're decoded; a record that can't be decoded is an `Err`,
+    /// in its place, and the rest carry on.
+    pub async fn into_stream<I>(self) -> Result<BoxStream<'static, Result<I, Error>>, Error>
+    where
+        I: Input + 'static,
+    {
+        let len = tokio::fs::metadata(&self.path).await?.len();
+        let path = self.path.clone();
+        let layout = self.layout;
+        let (start, headers) = tokio::task::spawn_blocking(move || header(&path, layout))
+            .await
+            .map_err(|e| Error::Other(e.into()))??;
+
+        let file = Arc::new(File {
+            path: self.path,
+            layout: self.layout,
+            headers,
+            start,
+        });
+        let chunk_size = self.chunk_size;
+        let partitions = stream::iter((start..len).step_by(chunk_size as usize)).map(move |from| {
+            let file = file.clone();
+            let to = (from + chunk_size).min(len);
+            async move {
+                tokio::task::spawn_blocking(move || file.decode::<I>(from, to))
+                    .await
+                    .unwrap_or_else(|e| vec![Err(Error::Other(e.into()))])
+            }
+        });
+        let records = match self.ordered {
+            true => partitions.buffered(self.threads).boxed(),
+            false => partitions.buffer_unordered(self.threads).boxed(),
+        };
+        Ok(records.flat_map(stream::iter).boxed())
+    }
+}
+
+// Where the records start, past the header of a CSV file (if any), and the header's fields.
+fn header(path: &Path, layout: Layout) -> Result<(u64, Option<csv::StringRecord>), Error> {
+    let Layout::Csv { delimiter } = layout else {
+        return Ok((0, None));
+    };
+    let mut line = vec![];
+    let len = BufReader::new(std::fs::File::open(path)?).read_until(b'\n', &mut line)?;
+    let headers = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .from_reader(line.as_slice())
+        .records()
+        .next()
+        .transpose()?
+        .unwrap_or_default();
+    Ok((len as u64, Some(headers)))
+}
+
+// The file being decoded, shared by its partitions.
+struct File {
+    path: PathBuf,
+    layout: Layout,
+    // the fields of a CSV file
+    headers: Option<csv::StringRecord>,
+    // where the first record starts
+    start: u64,
+}
+
+impl File {
+    // The records starting in bytes `from..to`.
+    fn decode<I: Input>(&self, from: u64, to: u64) -> Vec<Result<I, Error>> {
+        let bytes = match self.read(from, to) {
+            Ok(bytes) => bytes,
+            Err(e) => return vec![Err(e)],
+        };
+        match (self.layout, &self.headers) {
+            (Layout::Csv { delimiter }, Some(headers)) => csv::ReaderBuilder::new()
+                .delimiter(delimiter)
+                .has_headers(false)
+                .from_reader(bytes.as_slice())
+                .records()
+                .map(|record| Ok(record?.deserialize(Some(headers))?))
+                .collect(),
+            _ => bytes
+                .split(|byte| *byte == b'\n')
+                .filter(|line| !line.trim_ascii().is_empty())
+                .map(default::decode)
+                .collect(),
+        }
+    }
+
+    // Every line starting in bytes `from..to`, including the end of the last, past `to`.
+    fn read(&self, from: u64, to: u64) -> Result<Vec<u8>, Error> {
+        let mut reader = BufReader::new(std::fs::File::open(&self.path)?);
+        let mut at = from;
+        let mut bytes = vec![];
+        if from > self.start {
+            // skip the end of the record before, which belongs to the partition before
+            reader.seek(SeekFrom::Start(from - 1))?;
+            at = from - 1 + reader.read_until(b'\n', &mut bytes)? as u64;
+            bytes.clear();
+        } else {
+            reader.seek(SeekFrom::Start(from))?;
+        }
+        while at < to {
+            match reader.read_until(b'\n', &mut bytes)? {
+                0 => break,
+                read => at += read as u64,
+            }
+        }
+        Ok(bytes)
+    }
+}
@@ -0,0 +1,48 @@
+//! Pipes that copy JSON from an endpoint to a sink unchanged, without defining any types.
+//!
+//! [`Pipe<Value, Value>`] decodes the JSON into a [`Value`] on the way, so sinks that take rows
+//! (e.g., [`sink::Postgres`]) can still read its fields; [`Pipe<Raw, Raw>`] only validates the
+//! JSON, and loads its bytes verbatim, e.g., to archive an API's responses to file exactly as served.
+//!
+//! ```rust,ignore
+//! let pipe = Pipe::<Raw, Raw>::builder()
+//!     .source(Source::endpoint("https://example.com/prices.json"))
+//!     .sink(sink::File::new("prices.json"))
+//!     .build()?;
+//! pipe.run().await?;
+//! ```
+//!
+//! Both extract with [`Pipe::extract_default()`], and their transforms return the input as-is;
+//! so there's nothing to write with `pipeline!`; which, in turn, can't be used for these two pairs.
+//! To reshape the JSON on the way, with rules from config, see [`dynamic`].
+//!
+//! [`Pipe<Value, Value>`]: crate::Pipe
+//! [`Pipe<Raw, Raw>`]: crate::Pipe
+//! [`sink::Postgres`]: crate::sink::Postgres
+//! [`dynamic`]: crate::dynamic
+
+use super::{Error, Pipe, ETL};
+pub use serde_json::Value;
+
+/// Unparsed (but valid) JSON, kept byte for byte.
+pub type Raw = Box<serde_json::value::RawValue>;
+
+impl ETL<Value, Value> for Pipe<Value, Value> {
+    async fn extract(&self, path: &str) -> Result<Value, Error> {
+        self.extract_default(path).await
+    }
+
+    async fn transform(&self, input: Value) -> Result<Value, Error> {
+        Ok(input)
+    }
+}
+
+impl ETL<Raw, Raw> for Pipe<Raw, Raw> {
+    async fn extract(&self, path: &str) -> Result<Raw, Error> {
+        self.extract_default(path).await
+    }
+
+    async fn transform(&self, input: Raw) -> Result<Raw, Error> {
+        Ok(input)
+    }
+}
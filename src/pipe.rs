@@ -1,23 +1,118 @@
-use super::{Input, Output};
-use std::marker::PhantomData;
+use super::archive::{Archive, ArchiveRun};
+use super::artifact::{self, Artifact, Artifacts};
+use super::catalog::Description;
+use super::client::Overrides;
+use super::clock::{self, Clock};
+use super::dump::{self, Dumps};
+use super::enrich::DynEnrich;
+use super::envelope::Envelope;
+use super::error::{Context, Errors};
+use super::journal::{self, Journal};
+use super::lineage::Lineage;
+use super::memory::{self, Memory};
+use super::observer::{Event, Stage};
+use super::pool::{self, HostPool};
+use super::quality::{Quality, QualityReport};
+use super::quota::Quota;
+use super::report::{Loaded, Skip};
+use super::sink::DynSink;
+use super::source::{Format, Metadata, SourceSpec};
+use super::template::Vars;
+use super::time::{self, Timezone};
+use super::unchanged::Unchanged;
+use super::warning::{self, Warning};
+use super::wire;
+use super::{
+    default, Cache, Error, EtlReport, Input, Observer, Outcome, Output, PipeBuilder, RateLimit,
+    RetryPolicy, Source, WireLog, ETL,
+};
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::StreamExt;
+use serde::Serialize;
+use serde_json::value::RawValue;
+use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub(crate) type Tap<T> = Box<dyn Fn(&T) + Send + Sync>;
+
+tokio::task_local! {
+    // the endpoint being extracted by `etl_many()`, with its overrides
+    static SPEC: SourceSpec;
+    // the metadata of the payload last fetched by the run in progress
+    static METADATA: RefCell<Metadata>;
+}
+
+// Note the metadata of the payload just fetched (or, before an extract, none); outside of a run, a
+// no-op.
+fn located(metadata: Metadata) {
+    let _ = METADATA.try_with(|located| *located.borrow_mut() = metadata);
+}
+
+// A transformed output, and the digest of its payload; see `Pipe::extran_changed()`.
+pub(crate) struct Changed<O> {
+    pub(crate) output: O,
+    pub(crate) digest: Option<String>,
+}
 
 /// A pipeline of ETL methods; from input `I` to output `O`.
 ///
-/// ```rust
+/// ```rust,ignore
 /// let pipe = Pipe::<I, O>::new();
-/// let _ = pipe.map_transform(|| {
-///     ...
-///     })
-///     .await?
-///     .load(...)
-///     .await?;
+/// let output = pipe.extran(path).await?;
+/// pipe.load(output, conn, "prices").await?;
+/// ```
+///
+/// A pipe can also carry its own configuration (source, sink, retries, etc.), set with
+/// [`Pipe::builder()`] and used by [`Pipe::run()`].
 pub struct Pipe<I, O> {
-    // There's no actual data to hold, so we use PhantomData to remember the 2 types of the process.
-    // This way the compiler stays happy, and we have a concise way to declare a new ETL process, i.e.;
-    // ```
-    // let pipe = Pipe::<I, O>::new();
-    // ```
-    _phantom: PhantomData<(I, O)>,
+    pub(crate) source: Option<Source<I>>,
+    pub(crate) retry: Option<RetryPolicy>,
+    pub(crate) rate_limit: Option<RateLimit>,
+    pub(crate) quota: Option<Quota>,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) cache: Option<Cache>,
+    pub(crate) unchanged: Option<Unchanged>,
+    pub(crate) envelope: Option<Envelope>,
+    pub(crate) journal: Option<Journal>,
+    pub(crate) memory: Option<Memory>,
+    pub(crate) lineage: Lineage,
+    pub(crate) archive: Option<Archive>,
+    pub(crate) artifacts: Option<Artifacts>,
+    pub(crate) dumps: Option<Dumps>,
+    pub(crate) enrich: Option<Box<dyn DynEnrich<O>>>,
+    pub(crate) quality: Option<Quality>,
+    pub(crate) tap_extract: Vec<Tap<I>>,
+    pub(crate) tap_transform: Vec<Tap<O>>,
+    pub(crate) sink: Option<Box<dyn DynSink<O>>>,
+    pub(crate) observer: Option<Arc<dyn Observer>>,
+    #[cfg(feature = "oauth2")]
+    pub(crate) oauth2: Option<Arc<crate::oauth2::OAuth2>>,
+    #[cfg(feature = "smtp")]
+    pub(crate) notifier: Option<Arc<crate::notify::Notify>>,
+    pub(crate) wire_log: Option<WireLog>,
+    pub(crate) clock: Arc<dyn Clock>,
+    pub(crate) timezone: Timezone,
+    pub(crate) vars: Vars,
+    pub(crate) client: reqwest::Client,
+    pub(crate) overrides: Overrides,
+    pub(crate) pool: Option<HostPool>,
+    #[cfg(feature = "chaos")]
+    pub(crate) chaos: crate::chaos::Chaos,
+}
+
+impl<I, O> Default for Pipe<I, O>
+where
+    I: Input,
+    O: Output,
+{
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<I, O> Pipe<I, O>
@@ -28,7 +123,1003 @@ where
     /// Initialise an ETL pipeline.
     pub fn new() -> Self {
         Pipe {
-            _phantom: PhantomData,
+            source: None,
+            retry: None,
+            rate_limit: None,
+            quota: None,
+            timeout: None,
+            cache: None,
+            unchanged: None,
+            envelope: None,
+            journal: None,
+            memory: None,
+            lineage: Lineage::default(),
+            archive: None,
+            artifacts: None,
+            dumps: None,
+            enrich: None,
+            quality: None,
+            tap_extract: vec![],
+            tap_transform: vec![],
+            sink: None,
+            observer: None,
+            #[cfg(feature = "oauth2")]
+            oauth2: None,
+            #[cfg(feature = "smtp")]
+            notifier: None,
+            wire_log: None,
+            clock: clock::system(),
+            timezone: Timezone::Utc,
+            vars: Vars::default(),
+            client: reqwest::Client::new(),
+            overrides: Overrides::default(),
+            pool: None,
+            #[cfg(feature = "chaos")]
+            chaos: crate::chaos::Chaos::new(),
+        }
+    }
+
+    /// Configure an ETL pipeline; see [`PipeBuilder`].
+    pub fn builder() -> PipeBuilder<I, O> {
+        PipeBuilder::new()
+    }
+
+    /// The configured source, if any.
+    pub fn source(&self) -> Option<&Source<I>> {
+        self.source.as_ref()
+    }
+
+    /// The timezone policy for transforms to parse dates & times with; within the pipe's stages,
+    /// also [`time::timezone()`].
+    ///
+    /// [`time::timezone()`]: crate::time::timezone
+    pub fn timezone(&self) -> Timezone {
+        self.timezone
+    }
+
+    /// The values the pipe was instantiated with, e.g., its ticker symbol; see [`template`].
+    ///
+    /// [`template`]: crate::template
+    pub fn vars(&self) -> &Vars {
+        &self.vars
+    }
+
+    /// The default extraction, using the pipe's configuration: [`fetch_default()`], then
+    /// [`decode_default()`].
+    ///
+    /// The `pipeline!` macro uses these as `fetch()` & `decode()`, unless the block defines its
+    /// own; or its own `extract()`.
+    ///
+    /// [`fetch_default()`]: Pipe::fetch_default
+    /// [`decode_default()`]: Pipe::decode_default
+    pub async fn extract_default(&self, path: &str) -> Result<I, Error> {
+        let bytes = self.fetch_default(path).await?;
+        self.decode_default(bytes)
+    }
+
+    /// The default fetch, using the pipe's configuration.
+    ///
+    /// Behaves like [`default::fetch()`], but URLs are fetched with the pipe's own HTTP client
+    /// (or pool; see [`HostPool`]), and authenticated with its credentials (if any); and within [`etl_many()`], with the
+    /// endpoint's own overrides; see [`SourceSpec`]. An endpoint with its own proxy or CAs is
+    /// fetched with a client of its own, built from the pipe's (or pool's) config.
+    ///
+    /// [`etl_many()`]: Pipe::etl_many
+    pub async fn fetch_default(&self, path: &str) -> Result<Bytes, Error> {
+        let bytes = self.fetch_uncounted(path).await?;
+        dump::fetched(&bytes);
+        if self.memory.is_some() {
+            memory::fetched(bytes.len());
+            self.notify(Event::Sized {
+                stage: Stage::Extract,
+                bytes: bytes.len() as u64,
+            });
+        }
+        Ok(bytes)
+    }
+
+    async fn fetch_uncounted(&self, path: &str) -> Result<Bytes, Error> {
+        if !path.starts_with("http") {
+            let bytes = tokio::fs::read(path).await?.into();
+            located(Metadata::of_file(path).await);
+            return Ok(bytes);
+        }
+
+        let spec = SPEC.try_with(SourceSpec::clone).ok();
+        // held until the body is read
+        let pool = self.pool.clone().unwrap_or_else(pool::global);
+        let _permit = pool.acquire(path).await?;
+        #[allow(unused_mut)]
+        let mut response = self.get(path, spec.as_ref()).await?;
+        #[cfg(feature = "oauth2")]
+        if let Some(oauth2) = &self.oauth2 {
+            // the token may have been revoked early; try once more with a new one
+            if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+                oauth2.invalidate().await;
+                response = self.get(path, spec.as_ref()).await?;
+            }
+        }
+
+        let response = response.error_for_status()?;
+        located(Metadata::of_response(path, &response));
+        let bytes = default::body(response).await?;
+        if let Some(wire_log) = &self.wire_log {
+            wire_log.body(&String::from_utf8_lossy(&bytes));
+        }
+        Ok(bytes)
+    }
+
+    /// The metadata of the payload the run in progress last fetched with [`fetch_default()`]: its
+    /// URL & headers, or its path & modification time; for a transform to branch on. Within a
+    /// `pipeline!` transform, it can be taken as an argument instead; see [`Metadata`].
+    ///
+    /// Outside of a run (or preview), or for an input that wasn't fetched (e.g., streamed), the
+    /// default.
+    ///
+    /// [`fetch_default()`]: Pipe::fetch_default
+    pub fn metadata(&self) -> Metadata {
+        METADATA
+            .try_with(|metadata| metadata.borrow().clone())
+            .unwrap_or_default()
+    }
+
+    /// The default decode: as JSON, unwrapped from the pipe's envelope (if any; see
+    /// [`envelope`](crate::envelope)); or within [`etl_many()`], in the endpoint's own [`Format`].
+    /// Failures are [`Error::Decode`], see [`default::decode()`].
+    ///
+    /// [`etl_many()`]: Pipe::etl_many
+    /// [`Format`]: crate::source::Format
+    pub fn decode_default(&self, bytes: Bytes) -> Result<I, Error> {
+        match SPEC.try_with(|spec| spec.format).unwrap_or_default() {
+            Format::Json => match &self.envelope {
+                Some(envelope) => default::decode(envelope.unwrap(&bytes)?),
+                None => default::decode(bytes),
+            },
+            format => {
+                // as reading a file to a string would fail
+                let text = std::str::from_utf8(&bytes)
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+                format.parse(text)
+            }
+        }
+    }
+
+    async fn get(&self, url: &str, spec: Option<&SourceSpec>) -> Result<reqwest::Response, Error> {
+        #[allow(unused_mut)]
+        let mut request = self
+            .client
+            .get(url)
+            .header("User-Agent", "example@example.com");
+        #[cfg(feature = "oauth2")]
+        if let Some(oauth2) = &self.oauth2 {
+            request = request.bearer_auth(oauth2.bearer(&self.client).await?);
+        }
+        if let Some(spec) = spec {
+            for (name, value) in &spec.headers {
+                request = request.header(name, value);
+            }
+            if let Some(timeout) = spec.timeout {
+                request = request.timeout(timeout);
+            }
+        }
+
+        let request = request.build()?;
+        if let Some(wire_log) = &self.wire_log {
+            wire_log.request(&request);
+        }
+        let overridden = match spec {
+            Some(spec) => self.overrides.client(spec)?,
+            None => None,
+        };
+        let client = match (overridden, &self.pool) {
+            (Some(client), _) => client,
+            (None, Some(pool)) => pool.client(url)?,
+            (None, None) => self.client.clone(),
+        };
+        let response = client.execute(request).await?;
+        if let Some(wire_log) = &self.wire_log {
+            wire_log.response(&response);
+        }
+        Ok(response)
+    }
+
+    // Refuse a sweep of `extractions` that the quota (if any) can't cover.
+    pub(crate) fn check_quota(&self, extractions: usize) -> Result<(), Error> {
+        if let Some(quota) = &self.quota {
+            quota.check(u32::try_from(extractions).unwrap_or(u32::MAX))?;
+        }
+        Ok(())
+    }
+
+    /// Raise `warning` from a stage, carrying on; it's collected into the run's [`EtlReport`], and
+    /// reported to the observer.
+    pub fn warn(&self, warning: Warning) {
+        self.notify(Event::Warning { warning: &warning });
+        warning::warn(warning);
+    }
+
+    /// Emit `artifact` from a stage; it's written where the pipe's [`Artifacts`] routes it once
+    /// the run has finished, and listed in the run's [`EtlReport::artifacts`].
+    pub fn artifact(&self, artifact: Artifact) {
+        artifact::emit(artifact);
+    }
+
+    pub(crate) fn notify(&self, event: Event) {
+        if let Some(observer) = &self.observer {
+            observer.on_event(&event);
+        }
+    }
+
+    // Run a single stage attempt, bounded by the configured timeout.
+    async fn attempt<T>(
+        &self,
+        stage: Stage,
+        fut: impl Future<Output = Result<T, Error>>,
+    ) -> Result<T, Error> {
+        #[cfg(feature = "bench")]
+        let started = std::time::Instant::now();
+        let measuring = memory::start(stage);
+        let fut = time::scoped(self.timezone, fut);
+        let fut = clock::scoped(self.clock.clone(), fut);
+        #[cfg(feature = "chaos")]
+        let fut = self.chaos.attempt(stage, self.timeout, fut);
+        let result = match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, fut)
+                .await
+                .map_err(|_| Error::Timeout { stage, timeout })
+                .and_then(|result| result),
+            None => fut.await,
+        };
+        memory::finish(measuring);
+        #[cfg(feature = "bench")]
+        crate::counters::attempted(stage, result.is_ok(), started.elapsed());
+        result
+    }
+
+    // Run `run`, collecting its warnings, artifacts (and, if the pipe is measured, its sizes) into
+    // its report; then notifying of it, if the pipe notifies.
+    pub(crate) async fn collect<E: From<Error> + std::fmt::Display>(
+        &self,
+        run: impl Future<Output = Result<EtlReport, E>>,
+    ) -> Result<EtlReport, E> {
+        // boxed, as a run's future is large enough for the scopes around it to overflow the stack
+        let run = memory::collect(self.memory.is_some(), Box::pin(run));
+        let run = artifact::collect(self.artifacts.as_ref(), run);
+        let run = dump::collect(
+            self.dumps.as_ref(),
+            &*self.clock,
+            || self.config(),
+            |event| self.notify(event),
+            run,
+        );
+        #[cfg(feature = "smtp")]
+        let run = crate::notify::collect(self.notifier.as_deref(), &*self.clock, run);
+        let run = METADATA.scope(RefCell::default(), warning::collect(run));
+        clock::identified(|| clock::new_run_id(&*self.clock), run).await
+    }
+
+    // The pipe's configuration, by setting, for a dump; redacted, and only those set.
+    fn config(&self) -> BTreeMap<String, String> {
+        let settings = [
+            ("input", Some(std::any::type_name::<I>().to_string())),
+            ("output", Some(std::any::type_name::<O>().to_string())),
+            (
+                "source",
+                self.source
+                    .as_ref()
+                    .map(|source| source.describe().to_string()),
+            ),
+            (
+                "sink",
+                self.sink.as_ref().map(|sink| sink.type_name().to_string()),
+            ),
+            (
+                "retry",
+                self.retry.as_ref().map(|retry| format!("{retry:?}")),
+            ),
+            (
+                "rate_limit",
+                self.rate_limit.as_ref().map(|limit| format!("{limit:?}")),
+            ),
+            (
+                "timeout",
+                self.timeout.map(|timeout| format!("{timeout:?}")),
+            ),
+            (
+                "cache",
+                self.cache.as_ref().map(|cache| format!("{cache:?}")),
+            ),
+            (
+                "unchanged",
+                self.unchanged
+                    .as_ref()
+                    .map(|unchanged| format!("{unchanged:?}")),
+            ),
+            (
+                "envelope",
+                self.envelope
+                    .as_ref()
+                    .map(|envelope| format!("{envelope:?}")),
+            ),
+            (
+                "journal",
+                self.journal.as_ref().map(|journal| format!("{journal:?}")),
+            ),
+            (
+                "memory",
+                self.memory.as_ref().map(|memory| format!("{memory:?}")),
+            ),
+            (
+                "archive",
+                self.archive.as_ref().map(|archive| format!("{archive:?}")),
+            ),
+            (
+                "artifacts",
+                self.artifacts
+                    .as_ref()
+                    .map(|artifacts| format!("{artifacts:?}")),
+            ),
+            ("timezone", Some(format!("{:?}", self.timezone))),
+        ];
+        let vars = self
+            .vars
+            .values
+            .iter()
+            .map(|(name, value)| (format!("vars.{name}"), Some(value.clone())));
+        settings
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .chain(vars)
+            .filter_map(|(name, value)| {
+                let value = dump::redact(&name, value?);
+                Some((name, value))
+            })
+            .collect()
+    }
+
+    // Run a stage with the configured retry policy, reporting its outcome to the observer.
+    async fn stage<T, F, Fut>(&self, stage: Stage, mut op: F) -> Result<T, Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let result = match &self.retry {
+            Some(retry) => {
+                retry
+                    .run(
+                        || self.attempt(stage, op()),
+                        |attempt, error| {
+                            #[cfg(feature = "bench")]
+                            crate::counters::retried();
+                            self.notify(Event::Retrying {
+                                stage,
+                                attempt,
+                                error,
+                            })
+                        },
+                    )
+                    .await
+            }
+            None => self.attempt(stage, op()).await,
+        };
+        self.report(stage, &result);
+        result
+    }
+
+    // Run a stage that cannot be retried (its input is consumed), reporting its outcome to the observer.
+    async fn once<T>(
+        &self,
+        stage: Stage,
+        fut: impl Future<Output = Result<T, Error>>,
+    ) -> Result<T, Error> {
+        let result = self.attempt(stage, fut).await;
+        self.report(stage, &result);
+        result
+    }
+
+    fn report<T>(&self, stage: Stage, result: &Result<T, Error>) {
+        match result {
+            Ok(_) => self.notify(Event::Completed { stage }),
+            Err(error) => {
+                dump::failed(stage);
+                self.notify(Event::Failed { stage, error })
+            }
+        }
+    }
+}
+
+impl<I, O> Pipe<I, O>
+where
+    I: Input,
+    O: Output,
+    Self: ETL<I, O>,
+{
+    /// Run the pipe with its configured [`Source`] and [`Sink`].
+    ///
+    /// Unlike [`ETL::etl()`], this applies the rest of the pipe's configuration too:
+    /// retries, rate limiting, timeouts, caching, and observer events.
+    ///
+    /// Returns what the sink reported for every load; see [`EtlReport`].
+    ///
+    /// [`Sink`]: crate::Sink
+    pub async fn run(&self) -> Result<EtlReport, Error> {
+        self.collect(self.run_uncollected()).await
+    }
+
+    /// [`run()`](Pipe::run), extracting from `path` rather than the configured source; e.g., to
+    /// rerun one file by hand.
+    pub async fn run_from(&self, path: &str) -> Result<EtlReport, Error> {
+        self.collect(self.run_source(&Source::Endpoint(path.to_string())))
+            .await
+    }
+
+    /// Extract from `path` (or, if `None`, the configured endpoint) & transform, as a run would,
+    /// but return the output rather than loading it; nothing is loaded, archived, or saved as
+    /// unchanged.
+    ///
+    /// Warnings are logged to stderr, as there's no report to add them to.
+    pub async fn preview(&self, path: Option<&str>) -> Result<O, Error> {
+        METADATA
+            .scope(RefCell::default(), self.preview_unscoped(path))
+            .await
+    }
+
+    async fn preview_unscoped(&self, path: Option<&str>) -> Result<O, Error> {
+        let input = match (path, &self.source) {
+            (Some(path), _) => self.extract_stage(path).await?,
+            (None, Some(Source::Endpoint(path))) => self.extract_stage(path).await?,
+            (None, Some(Source::Inline(payload))) => self.extract_inline(payload).await?,
+            (None, Some(Source::Stream { .. } | Source::Streams(_))) => {
+                return Err(Error::Config(
+                    "a streaming source can't be previewed".into(),
+                ))
+            }
+            (None, None) => return Err(Error::Config("no source configured".into())),
+        };
+        let output = self.transform_stage(input).await?;
+        self.enrich_stage(output).await
+    }
+
+    async fn run_uncollected(&self) -> Result<EtlReport, Error> {
+        let source = self
+            .source
+            .as_ref()
+            .ok_or_else(|| Error::Config("no source configured".into()))?;
+        self.run_source(source).await
+    }
+
+    async fn run_source(&self, source: &Source<I>) -> Result<EtlReport, Error> {
+        self.sink()?;
+        let made;
+        let source = match source {
+            Source::Streams(make) => {
+                made = make()?;
+                &made
+            }
+            source => source,
+        };
+        self.notify(Event::Started {
+            source: source.describe(),
+        });
+        let mut archive = self
+            .archive
+            .as_ref()
+            .map(|archive| archive.start(&*self.clock))
+            .transpose()?;
+
+        let mut report = EtlReport::new();
+        self.replay_stage(&mut report).await?;
+        match source {
+            Source::Endpoint(path) => match self.extran_changed(path).await? {
+                Some(changed) => {
+                    let output = self.enrich_stage(changed.output).await?;
+                    report.push(self.load_stage(&output).await?);
+                    if let Some(archive) = &mut archive {
+                        archive.write(&output)?;
+                    }
+                    self.loaded(path, changed.digest)?;
+                }
+                None => report.push(Loaded::skipped(Skip::Unchanged)),
+            },
+            Source::Inline(payload) => {
+                let input = self.extract_inline(payload).await?;
+                let output = self.transform_stage(input).await?;
+                let output = self.enrich_stage(output).await?;
+                report.push(self.load_stage(&output).await?);
+                if let Some(archive) = &mut archive {
+                    archive.write(&output)?;
+                }
+            }
+            Source::Stream { stream, checkpoint } => {
+                let mut stream = stream.lock().await.take().ok_or_else(|| {
+                    Error::Config("the streaming source has already been consumed".into())
+                })?;
+                while let Some(input) = stream.next().await {
+                    let output = self.transform_stage(input?).await?;
+                    let output = self.enrich_stage(output).await?;
+                    report.push(self.load_stage(&output).await?);
+                    if let Some(archive) = &mut archive {
+                        archive.write(&output)?;
+                    }
+                    if let Some(checkpoint) = checkpoint {
+                        checkpoint.loaded().await?;
+                    }
+                }
+                if let Some(checkpoint) = checkpoint {
+                    checkpoint.flush().await?;
+                }
+            }
+            Source::Streams(_) => {
+                return Err(Error::Config(
+                    "a run's streaming source made another, rather than a stream".into(),
+                ))
+            }
         }
+
+        if let Some(archive) = archive {
+            archive.finish()?;
+        }
+        self.notify(Event::Finished);
+        Ok(report)
+    }
+
+    /// Run the pipe over several endpoints, in order, loading each to the configured [`Sink`].
+    ///
+    /// Each endpoint is a path, or a [`SourceSpec`] with its own headers, timeout & format.
+    /// A failing endpoint doesn't stop the rest; every failure is returned, tagged with its endpoint.
+    /// Otherwise, returns what the sink reported for every endpoint, in order.
+    ///
+    /// [`Sink`]: crate::Sink
+    pub async fn etl_many<S>(
+        &self,
+        sources: impl IntoIterator<Item = S>,
+    ) -> Result<EtlReport, Errors>
+    where
+        S: Into<SourceSpec>,
+    {
+        self.collect(self.etl_many_uncollected(sources)).await
+    }
+
+    async fn etl_many_uncollected<S>(
+        &self,
+        sources: impl IntoIterator<Item = S>,
+    ) -> Result<EtlReport, Errors>
+    where
+        S: Into<SourceSpec>,
+    {
+        let mut errors = Errors::new();
+        if let Err(error) = self.sink() {
+            errors.push(Context::None, error);
+            return Err(errors);
+        }
+        let mut archive = match self
+            .archive
+            .as_ref()
+            .map(|archive| archive.start(&*self.clock))
+            .transpose()
+        {
+            Ok(archive) => archive,
+            Err(error) => {
+                errors.push(Context::None, error);
+                return Err(errors);
+            }
+        };
+
+        let sources: Vec<SourceSpec> = sources.into_iter().map(Into::into).collect();
+        if let Err(error) = self.check_quota(sources.len()) {
+            errors.push(Context::None, error);
+            return Err(errors);
+        }
+
+        let mut report = EtlReport::new();
+        if let Err(error) = self.replay_stage(&mut report).await {
+            errors.push(Context::None, error);
+            return Err(errors);
+        }
+        for spec in sources {
+            let path = spec.path.as_str();
+            self.notify(Event::Started { source: path });
+            let result = match SPEC.scope(spec.clone(), self.extran_changed(path)).await {
+                Ok(Some(changed)) => match self.enrich_stage(changed.output).await {
+                    Ok(output) => self
+                        .load_stage(&output)
+                        .await
+                        .and_then(|loaded| match &mut archive {
+                            Some(archive) => archive.write(&output).map(|()| loaded),
+                            None => Ok(loaded),
+                        })
+                        .and_then(|loaded| self.loaded(path, changed.digest).map(|()| loaded)),
+                    Err(error) => Err(error),
+                },
+                Ok(None) => Ok(Loaded::skipped(Skip::Unchanged)),
+                Err(error) => Err(error),
+            };
+            match result {
+                Ok(loaded) => {
+                    report.push(loaded);
+                    self.notify(Event::Finished);
+                }
+                Err(error) => errors.push(Context::Endpoint(path.to_string()), error),
+            }
+        }
+
+        if let Some(Err(error)) = archive.map(ArchiveRun::finish) {
+            errors.push(Context::None, error);
+        }
+        errors.into_result().map(|()| report)
+    }
+
+    // Extract & transform `path`, or read the output from the cache if it's fresh.
+    pub(crate) async fn extran_cached(&self, path: &str) -> Result<O, Error> {
+        if let Some(output) = self.cached(path) {
+            return Ok(output);
+        }
+
+        let input = self.extract_stage(path).await?;
+        let output = self.transform_stage(input).await?;
+
+        if let Some(cache) = &self.cache {
+            cache.put(path, &output)?;
+        }
+        Ok(output)
+    }
+
+    // As `extran_cached()`; but if the pipe skips unchanged payloads, `None` for a payload that
+    // was the last loaded from `path`. Once the output is loaded, its digest is to be saved with
+    // `loaded()`.
+    pub(crate) async fn extran_changed(&self, path: &str) -> Result<Option<Changed<O>>, Error> {
+        let Some(unchanged) = &self.unchanged else {
+            let output = self.extran_cached(path).await?;
+            return Ok(Some(Changed {
+                output,
+                digest: None,
+            }));
+        };
+        if !self.extracts_by_fetch() {
+            return Err(Error::Config(
+                "unchanged payloads can't be skipped with a custom extract(); fetch() & decode() \
+                 are what's hashed"
+                    .into(),
+            ));
+        }
+        if let Some(output) = self.cached(path) {
+            return Ok(Some(Changed {
+                output,
+                digest: None,
+            }));
+        }
+
+        located(Metadata::default());
+        let extracted = self
+            .stage(Stage::Extract, || async {
+                self.admit().await?;
+                let payload = self.fetch(path).await?;
+                let digest = Unchanged::digest(&payload);
+                if unchanged.get(path)?.as_ref() == Some(&digest) {
+                    return Ok(None);
+                }
+                Ok(Some((self.decode(payload)?, digest)))
+            })
+            .await?;
+        let Some((input, digest)) = extracted else {
+            self.notify(Event::Unchanged { source: path });
+            return Ok(None);
+        };
+        self.tap_extract.iter().for_each(|tap| tap(&input));
+        let output = self.transform_stage(input).await?;
+
+        if let Some(cache) = &self.cache {
+            cache.put(path, &output)?;
+        }
+        Ok(Some(Changed {
+            output,
+            digest: Some(digest),
+        }))
+    }
+
+    // Save the digest of the payload just loaded from `path`, if the pipe skips unchanged ones.
+    pub(crate) fn loaded(&self, path: &str, digest: Option<String>) -> Result<(), Error> {
+        match (&self.unchanged, digest) {
+            (Some(unchanged), Some(digest)) => unchanged.save(path, digest),
+            _ => Ok(()),
+        }
+    }
+
+    // A fresh output from the cache, if any.
+    fn cached(&self, path: &str) -> Option<O> {
+        let output = self.cache.as_ref().and_then(|cache| cache.get(path))?;
+        self.notify(Event::CacheHit { source: path });
+        Some(output)
+    }
+
+    // The extract stage: rate limited, retried & timed out as configured, then tapped.
+    pub(crate) async fn extract_stage(&self, path: &str) -> Result<I, Error> {
+        located(Metadata::default());
+        let input = self
+            .stage(Stage::Extract, || async {
+                self.admit().await?;
+                self.extract(path).await
+            })
+            .await?;
+        self.tap_extract.iter().for_each(|tap| tap(&input));
+        Ok(input)
+    }
+
+    /// Extract an input from `payload`, as if it had been fetched: decoded with [`decode()`]
+    /// (e.g., unwrapped from the pipe's envelope), then tapped; but not rate limited, counted
+    /// against the quota, or retried. See [`Source::Inline`].
+    ///
+    /// [`decode()`]: ETL::decode
+    pub async fn extract_inline<T>(&self, payload: &T) -> Result<I, Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        located(Metadata::default());
+        let decoded = async { self.decode(Bytes::from(serde_json::to_vec(payload)?)) };
+        let input = self.once(Stage::Extract, decoded).await?;
+        self.tap_extract.iter().for_each(|tap| tap(&input));
+        Ok(input)
+    }
+
+    // Wait for the rate limit (if any), and spend a request of the quota (if any).
+    async fn admit(&self) -> Result<(), Error> {
+        if let Some(rate_limit) = &self.rate_limit {
+            rate_limit.acquire().await;
+        }
+        if let Some(quota) = &self.quota {
+            quota.spend().await?;
+        }
+        Ok(())
+    }
+
+    // The transform stage: timed out as configured, then tapped.
+    pub(crate) async fn transform_stage(&self, input: I) -> Result<O, Error> {
+        let output = self.once(Stage::Transform, self.transform(input)).await?;
+        self.tap_transform.iter().for_each(|tap| tap(&output));
+        Ok(output)
+    }
+
+    // The enrich stage, if one is configured: timed out as configured.
+    pub(crate) async fn enrich_stage(&self, output: O) -> Result<O, Error> {
+        match &self.enrich {
+            Some(enrich) => self.once(Stage::Enrich, enrich.enrich_boxed(output)).await,
+            None => Ok(output),
+        }
+    }
+
+    // The load stage: measured, then loaded to the configured sink, retried & timed out as configured.
+    pub(crate) async fn load_stage(&self, output: &O) -> Result<Loaded, Error> {
+        let sink = self.sink()?;
+        let quality = match &self.quality {
+            Some(quality) => {
+                let report = quality.report(output)?;
+                // a report failing enforced checks is the one most worth keeping
+                if let Err(error) = quality.judge(&report) {
+                    self.load_quality(quality, &report).await;
+                    return Err(error);
+                }
+                Some(report)
+            }
+            None => None,
+        };
+        let outcome = match &self.memory {
+            Some(memory) => {
+                let bytes = memory::size(output)?;
+                self.notify(Event::Sized {
+                    stage: Stage::Transform,
+                    bytes,
+                });
+                match memory.soft_limit.filter(|limit| bytes > *limit) {
+                    Some(limit) => self.load_chunked(sink, output, bytes, limit).await?,
+                    None => {
+                        memory::transformed(bytes, false);
+                        self.load_output(sink, output).await?
+                    }
+                }
+            }
+            None => self.load_output(sink, output).await?,
+        };
+        if let (Some(quality), Some(report)) = (&self.quality, &quality) {
+            self.load_quality(quality, report).await;
+        }
+        #[cfg(feature = "smtp")]
+        crate::notify::loaded(output);
+        Ok(Loaded { outcome, quality })
+    }
+
+    // Load `report` to the quality sink, warning if it can't be; the output's load doesn't hang on
+    // its metrics.
+    async fn load_quality(&self, quality: &Quality, report: &QualityReport) {
+        if let Err(e) = quality.load(report).await {
+            self.warn(Warning::other(format!(
+                "the quality metrics couldn't be loaded: {e}"
+            )));
+        }
+    }
+
+    // Load `output`, an array of `bytes` of JSON over the soft `limit`, in chunks under it.
+    async fn load_chunked(
+        &self,
+        sink: &dyn DynSink<O>,
+        output: &O,
+        bytes: u64,
+        limit: u64,
+    ) -> Result<Outcome, Error> {
+        let json = Arc::new(serde_json::to_string(output)?);
+        let Ok(records) = serde_json::from_str::<Vec<&RawValue>>(&json) else {
+            self.warn(Warning::other(format!(
+                "the output is {bytes} bytes of JSON, over the soft limit of {limit}, \
+                 but isn't an array to load in chunks"
+            )));
+            memory::transformed(bytes, false);
+            return self.load_output(sink, output).await;
+        };
+        memory::transformed(bytes, true);
+        let load = async {
+            let mut outcomes = vec![];
+            for chunk in memory::chunks(&records, limit) {
+                let chunk: O = serde_json::from_str(&memory::chunk(&records[chunk]))?;
+                outcomes.push(self.load_output(sink, &chunk).await?);
+            }
+            Ok(Outcome::Batches(outcomes))
+        };
+        memory::chunked(json.clone(), load).await
+    }
+
+    // Load `output` to `sink`, through the journal if there is one.
+    async fn load_output(&self, sink: &dyn DynSink<O>, output: &O) -> Result<Outcome, Error> {
+        match &self.journal {
+            Some(journal) => {
+                let key = journal.write(&serde_json::to_vec(output)?, &*self.clock)?;
+                self.load_keyed(journal, sink, key, output).await
+            }
+            None => self.stage(Stage::Load, || sink.load_boxed(output)).await,
+        }
+    }
+
+    // Load what the journal (if any) still holds from earlier runs, before anything new; see
+    // `journal`.
+    pub(crate) async fn replay_stage(&self, report: &mut EtlReport) -> Result<(), Error> {
+        let Some(journal) = &self.journal else {
+            return Ok(());
+        };
+        let sink = self.sink()?;
+        for entry in journal.pending()? {
+            let output: O = serde_json::from_value(entry.output)?;
+            let outcome = self.load_keyed(journal, sink, entry.key, &output).await?;
+            report.push(Loaded {
+                outcome,
+                quality: None,
+            });
+        }
+        Ok(())
+    }
+
+    // Load the journaled `output` with its `key` in scope, then acknowledge it.
+    async fn load_keyed(
+        &self,
+        journal: &Journal,
+        sink: &dyn DynSink<O>,
+        key: String,
+        output: &O,
+    ) -> Result<Outcome, Error> {
+        let load = self.stage(Stage::Load, || sink.load_boxed(output));
+        let outcome = journal::keyed(key.clone(), load).await?;
+        journal.ack(&key)?;
+        Ok(outcome)
+    }
+
+    fn sink(&self) -> Result<&dyn DynSink<O>, Error> {
+        self.sink
+            .as_deref()
+            .ok_or_else(|| Error::Config("no sink configured".into()))
+    }
+}
+
+/// A pipe with its `I` & `O` types erased, so that pipes of different types can be kept together;
+/// e.g., in a `Vec<Box<dyn DynPipeline>>`, or a registry by name. Implemented for every runnable
+/// [`Pipe`].
+///
+/// ```rust,ignore
+/// let pipes: Vec<Box<dyn DynPipeline>> = vec![Box::new(prices), Box::new(holidays)];
+/// for pipe in &pipes {
+///     let report = pipe.run().await?;
+/// }
+/// ```
+///
+/// The futures are `Send`, as [`ETL`]'s are; so a pipe can be run on a task of its own, e.g., with
+/// `tokio::spawn()`.
+pub trait DynPipeline: Send + Sync {
+    /// Run the pipe with its configured [`Source`] and [`Sink`]; see [`Pipe::run()`].
+    ///
+    /// [`Sink`]: crate::Sink
+    fn run(&self) -> BoxFuture<'_, Result<EtlReport, Error>>;
+
+    /// The pipe's types, source & sink; e.g., for a [`Catalog`](crate::catalog::Catalog).
+    fn describe(&self) -> Description;
+
+    /// The pipe's rate limit, if it has one; e.g., for a [`Runner`](crate::Runner) to change
+    /// on a config reload. `None` by default.
+    fn rate_limit(&self) -> Option<&RateLimit> {
+        None
+    }
+
+    /// Run the pipe once, extracting from `path` rather than its source; see [`Pipe::run_from()`].
+    /// [`Error::Config`] by default.
+    fn run_from<'a>(&'a self, path: &'a str) -> BoxFuture<'a, Result<EtlReport, Error>> {
+        let _ = path;
+        Box::pin(async {
+            Err(Error::Config(
+                "the pipeline can't be run from a path".into(),
+            ))
+        })
+    }
+
+    /// The pipe's output, as JSON, without loading it; see [`Pipe::preview()`].
+    /// [`Error::Config`] by default.
+    fn preview<'a>(&'a self, path: Option<&'a str>) -> BoxFuture<'a, Result<Value, Error>> {
+        let _ = path;
+        Box::pin(async { Err(Error::Config("the pipeline can't be previewed".into())) })
+    }
+}
+
+impl<I, O> DynPipeline for Pipe<I, O>
+where
+    I: Input,
+    O: Output,
+    Pipe<I, O>: ETL<I, O>,
+{
+    fn run(&self) -> BoxFuture<'_, Result<EtlReport, Error>> {
+        Box::pin(Pipe::run(self))
+    }
+
+    fn describe(&self) -> Description {
+        Description {
+            input: std::any::type_name::<I>().into(),
+            output: std::any::type_name::<O>().into(),
+            source: self.source.as_ref().map(|source| {
+                let endpoint = source.describe();
+                match reqwest::Url::parse(endpoint) {
+                    Ok(url) if endpoint.starts_with("http") => wire::redact_url(&url),
+                    _ => endpoint.into(),
+                }
+            }),
+            sink: self.sink.as_ref().map(|sink| sink.type_name().into()),
+            lineage: ETL::lineage(self).merge(self.lineage.clone()),
+        }
+    }
+
+    fn rate_limit(&self) -> Option<&RateLimit> {
+        self.rate_limit.as_ref()
+    }
+
+    fn run_from<'a>(&'a self, path: &'a str) -> BoxFuture<'a, Result<EtlReport, Error>> {
+        Box::pin(Pipe::run_from(self, path))
+    }
+
+    fn preview<'a>(&'a self, path: Option<&'a str>) -> BoxFuture<'a, Result<Value, Error>> {
+        Box::pin(async move { Ok(serde_json::to_value(Pipe::preview(self, path).await?)?) })
+    }
+}
+
+impl<P: DynPipeline + ?Sized> DynPipeline for Box<P> {
+    fn run(&self) -> BoxFuture<'_, Result<EtlReport, Error>> {
+        (**self).run()
+    }
+
+    fn describe(&self) -> Description {
+        (**self).describe()
+    }
+
+    fn rate_limit(&self) -> Option<&RateLimit> {
+        (**self).rate_limit()
+    }
+
+    fn run_from<'a>(&'a self, path: &'a str) -> BoxFuture<'a, Result<EtlReport, Error>> {
+        (**self).run_from(path)
+    }
+
+    fn preview<'a>(&'a self, path: Option<&'a str>) -> BoxFuture<'a, Result<Value, Error>> {
+        (**self).preview(path)
     }
 }
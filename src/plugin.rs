@@ -0,0 +1,202 @@
+//! Object-safe sinks & sources, for connectors shipped by other crates as trait objects; only with
+//! the `dyn` feature.
+//!
+//! [`Sink`](crate::Sink) returns `impl Future`, so it can't be made a trait object; these are
+//! its [`async_trait`] equivalents, which can. A plugin crate implements them for its
+//! connectors, and registers them by name in a [`Registry`], to be chosen at runtime, e.g., from
+//! a config file:
+//!
+//! ```rust,ignore
+//! use pipe_io::plugin::{async_trait, Registry, Sink};
+//!
+//! struct Bucket { url: String }
+//!
+//! #[async_trait]
+//! impl Sink<Value> for Bucket {
+//!     async fn load(&self, output: &Value) -> pipe_io::Result<Outcome> { ... }
+//! }
+//!
+//! let registry = Registry::<Value, Value>::new().register_sink("bucket", |settings| {
+//!     let url = settings["url"].as_str().ok_or_else(|| Error::Missing("url".into()))?;
+//!     Ok(Box::new(Bucket { url: url.into() }))
+//! });
+//!
+//! // e.g., `{ "sink": "bucket", "settings": { "url": "s3://prices" } }`
+//! let pipe = Pipe::<Value, Value>::builder()
+//!     .source(Source::endpoint("https://example.com/prices.json"))
+//!     .sink(registry.sink(&config.sink, &config.settings)?)
+//!     .build()?;
+//! let runner = Runner::new().pipe("prices", pipe, Duration::from_secs(60 * 60));
+//! ```
+//!
+//! A boxed (or shared) plugin sink is a [`Sink`](crate::Sink); a boxed plugin source is a
+//! streaming [`Source`](crate::Source), acknowledged as a checkpointed one is. One from a
+//! [`Registry`] is made afresh from its settings for every run, so its pipe can be run again &
+//! again, e.g., on a [`Runner`](crate::Runner)'s schedule.
+
+use super::source::Checkpoint;
+use super::{Error, Outcome};
+use futures::future::BoxFuture;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// The attribute to implement [`Sink`] & [`Source`] with; re-exported, so plugins use the same
+/// version as this crate.
+pub use async_trait::async_trait;
+
+/// An object-safe [`Sink`](crate::Sink): a destination for output type `O`.
+#[async_trait]
+pub trait Sink<O: ?Sized + Sync>: Send + Sync {
+    /// Load `output` to the destination, reporting what was written.
+    async fn load(&self, output: &O) -> Result<Outcome, Error>;
+}
+
+impl<O: ?Sized + Sync> crate::Sink<O> for dyn Sink<O> {
+    async fn load(&self, output: &O) -> Result<Outcome, Error> {
+        Sink::load(self, output).await
+    }
+}
+
+impl<O: ?Sized + Sync> crate::Sink<O> for Box<dyn Sink<O>> {
+    async fn load(&self, output: &O) -> Result<Outcome, Error> {
+        Sink::load(&**self, output).await
+    }
+}
+
+/// An object-safe source of input type `I`: its inputs, one at a time, as they arrive.
+#[async_trait]
+pub trait Source<I>: Send {
+    /// The next input; `None` once there are no more.
+    async fn next(&mut self) -> Option<Result<I, Error>>;
+
+    /// Called after the latest input has been loaded; see [`Checkpoint::loaded()`]. By default,
+    /// nothing.
+    async fn loaded(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called once there are no more inputs, and every one has been loaded; see
+    /// [`Checkpoint::flush()`]. By default, nothing.
+    async fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<I: Send + 'static> From<Box<dyn Source<I>>> for crate::Source<I> {
+    fn from(source: Box<dyn Source<I>>) -> Self {
+        // shared by the stream of inputs & their checkpoint, which take turns
+        let shared = Arc::new(Mutex::new(source));
+        let stream = futures::stream::unfold(shared.clone(), |source| async move {
+            let next = source.lock().await.next().await;
+            next.map(|input| (input, source))
+        });
+        crate::Source::checkpointed(stream, Shared(shared))
+    }
+}
+
+// a plugin source, as the checkpoint of its own stream
+struct Shared<I>(Arc<Mutex<Box<dyn Source<I>>>>);
+
+impl<I> Checkpoint for Shared<I> {
+    fn loaded(&self) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(async move { self.0.lock().await.loaded().await })
+    }
+
+    fn flush(&self) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(async move { self.0.lock().await.flush().await })
+    }
+}
+
+type SinkFactory<O> = Box<dyn Fn(&Value) -> Result<Box<dyn Sink<O>>, Error> + Send + Sync>;
+type SourceFactory<I> = Arc<dyn Fn(&Value) -> Result<Box<dyn Source<I>>, Error> + Send + Sync>;
+
+/// Plugin sinks & sources by name, each made from its JSON settings; e.g., as named by a pipeline's
+/// config file.
+pub struct Registry<I, O> {
+    sinks: BTreeMap<String, SinkFactory<O>>,
+    sources: BTreeMap<String, SourceFactory<I>>,
+}
+
+impl<I, O> Default for Registry<I, O> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I, O> Registry<I, O> {
+    pub fn new() -> Self {
+        Registry {
+            sinks: BTreeMap::new(),
+            sources: BTreeMap::new(),
+        }
+    }
+
+    /// Register the sink `name`, made by `make` from its settings; over any of the same name.
+    pub fn register_sink<F>(mut self, name: impl Into<String>, make: F) -> Self
+    where
+        F: Fn(&Value) -> Result<Box<dyn Sink<O>>, Error> + Send + Sync + 'static,
+    {
+        self.sinks.insert(name.into(), Box::new(make));
+        self
+    }
+
+    /// Register the source `name`, made by `make` from its settings; over any of the same name.
+    pub fn register_source<F>(mut self, name: impl Into<String>, make: F) -> Self
+    where
+        F: Fn(&Value) -> Result<Box<dyn Source<I>>, Error> + Send + Sync + 'static,
+    {
+        self.sources.insert(name.into(), Arc::new(make));
+        self
+    }
+
+    /// The registered sinks' names, in order.
+    pub fn sinks(&self) -> impl Iterator<Item = &str> {
+        self.sinks.keys().map(String::as_str)
+    }
+
+    /// The registered sources' names, in order.
+    pub fn sources(&self) -> impl Iterator<Item = &str> {
+        self.sources.keys().map(String::as_str)
+    }
+
+    /// The sink `name`, made from `settings`; an unregistered name is [`Error::Config`].
+    pub fn sink(&self, name: &str, settings: &Value) -> Result<Box<dyn Sink<O>>, Error> {
+        match self.sinks.get(name) {
+            Some(make) => make(settings),
+            None => Err(unregistered("sink", name, self.sinks())),
+        }
+    }
+
+    /// The source `name`, made from `settings` for every run, as a streaming
+    /// [`Source::Streams`](crate::Source::Streams); an unregistered name is [`Error::Config`].
+    ///
+    /// The first run's is made here, so settings it rejects are an error now, not at that run.
+    pub fn source(&self, name: &str, settings: &Value) -> Result<crate::Source<I>, Error>
+    where
+        I: Send + 'static,
+    {
+        let Some(make) = self.sources.get(name) else {
+            return Err(unregistered("source", name, self.sources()));
+        };
+        let first = std::sync::Mutex::new(Some(make(settings)?));
+        let (make, settings) = (make.clone(), settings.clone());
+        Ok(crate::Source::streams(move || {
+            let first = first.lock().unwrap_or_else(|p| p.into_inner()).take();
+            let source = match first {
+                Some(source) => source,
+                None => make(&settings)?,
+            };
+            Ok(source.into())
+        }))
+    }
+}
+
+fn unregistered<'a>(kind: &str, name: &str, names: impl Iterator<Item = &'a str>) -> Error {
+    let names = names.collect::<Vec<_>>();
+    Error::Config(format!(
+        "no {kind} plugin named `{name}`; expected one of {}",
+        names.join(", ")
+    ))
+}
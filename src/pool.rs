@@ -0,0 +1,168 @@
+use super::client::ClientConfig;
+use super::Error;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// HTTP clients shared by host, each with its own limits; so sweeping one API heavily doesn't
+/// starve the other pipes of the process, which extract from other hosts through other clients.
+///
+/// Every extraction takes a [`Permit`] for its host before it's sent, and holds it until the body
+/// is read; pipes extract through the [`global()`] pool, unless given their own with
+/// [`PipeBuilder::pool()`]. Clones share the same clients & permits.
+///
+/// ```rust,ignore
+/// let pool = HostPool::new()
+///     .limits(Limits::new().in_flight(16))
+///     .host("query1.finance.yahoo.com", Limits::new().connections(4));
+/// let pipe = Pipe::<I, O>::builder().pool(pool.clone()).build()?;
+/// ```
+///
+/// [`PipeBuilder::pool()`]: crate::PipeBuilder::pool
+#[derive(Debug, Clone, Default)]
+pub struct HostPool {
+    config: ClientConfig,
+    limits: Limits,
+    per_host: HashMap<String, Limits>,
+    hosts: Arc<Mutex<HashMap<String, Host>>>,
+}
+
+/// How much of a host one [`HostPool`] may use at once; unlimited by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// The most connections kept open to the host; also the most requests in flight, unless
+    /// [`in_flight`] is set.
+    ///
+    /// [`in_flight`]: Limits::in_flight
+    pub connections: Option<usize>,
+    /// The most requests in flight to the host; the rest wait their turn.
+    pub in_flight: Option<usize>,
+}
+
+impl Limits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn connections(mut self, max: usize) -> Self {
+        self.connections = Some(max);
+        self
+    }
+
+    pub fn in_flight(mut self, max: usize) -> Self {
+        self.in_flight = Some(max);
+        self
+    }
+}
+
+// the client & permits of one host, created on first use
+#[derive(Debug)]
+struct Host {
+    client: reqwest::Client,
+    permits: Option<Arc<Semaphore>>,
+}
+
+/// A request's claim on its host, released when dropped; see [`HostPool::acquire()`].
+#[derive(Debug)]
+pub struct Permit {
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl HostPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build every host's client from `config`; [`Limits::connections`] takes precedence over
+    /// its idle pool size.
+    pub fn config(mut self, config: ClientConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// The limits of every host without its own.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// The limits of `host` (e.g., `api.example.com`, or `localhost:8080` for another port).
+    pub fn host(mut self, host: impl Into<String>, limits: Limits) -> Self {
+        self.per_host.insert(host.into(), limits);
+        self
+    }
+
+    pub(crate) fn client_config(&self) -> &ClientConfig {
+        &self.config
+    }
+
+    /// Wait for a turn to send a request to the host of `url`.
+    pub async fn acquire(&self, url: &str) -> Result<Permit, Error> {
+        let permits = self.with_host(url, |host| host.permits.clone())?;
+        match permits {
+            Some(permits) => {
+                let permit = permits
+                    .acquire_owned()
+                    .await
+                    .map_err(|e| Error::Invariant(format!("the semaphore of {url}: {e}")))?;
+                Ok(Permit {
+                    _permit: Some(permit),
+                })
+            }
+            None => Ok(Permit { _permit: None }),
+        }
+    }
+
+    /// The client for the host of `url`.
+    pub fn client(&self, url: &str) -> Result<reqwest::Client, Error> {
+        self.with_host(url, |host| host.client.clone())
+    }
+
+    fn with_host<T>(&self, url: &str, f: impl FnOnce(&Host) -> T) -> Result<T, Error> {
+        let url = reqwest::Url::parse(url).map_err(|e| Error::Config(format!("{url}: {e}")))?;
+        let name = url.host_str().unwrap_or_default();
+        let key = match url.port() {
+            Some(port) => format!("{name}:{port}"),
+            None => name.to_string(),
+        };
+        let mut hosts = self
+            .hosts
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(host) = hosts.get(&key) {
+            return Ok(f(host));
+        }
+
+        let limits = self.per_host.get(&key).copied().unwrap_or(self.limits);
+        let mut config = self.config.clone();
+        if let Some(max) = limits.connections {
+            config.pool_max_idle_per_host = Some(max);
+        }
+        let host = Host {
+            client: config.build()?,
+            permits: limits
+                .in_flight
+                .or(limits.connections)
+                .map(|max| Arc::new(Semaphore::new(max))),
+        };
+        Ok(f(hosts.entry(key).or_insert(host)))
+    }
+}
+
+static GLOBAL: OnceLock<HostPool> = OnceLock::new();
+
+/// The pool shared by every extraction of the process that isn't given another; unlimited,
+/// unless replaced with [`set_global()`].
+pub fn global() -> HostPool {
+    GLOBAL.get_or_init(HostPool::new).clone()
+}
+
+/// Replace the global pool, e.g., to limit every host; before any extraction, as it's fixed on
+/// first use.
+///
+/// Returns [`Error::Config`] if the global pool is already in use, or already set.
+pub fn set_global(pool: HostPool) -> Result<(), Error> {
+    GLOBAL
+        .set(pool)
+        .map_err(|_| Error::Config("the global host pool is already in use".into()))
+}
@@ -0,0 +1,449 @@
+use super::dynamic::Path;
+use super::sink::{DynSink, Sink};
+use super::warning::{self, Warning};
+use super::{time, Error};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fmt;
+
+/// Data-quality metrics, taken from every output before it's loaded; so a pipe notices when,
+/// say, the volume column is suddenly all zeros, rather than a dashboard weeks later.
+///
+/// Each element of an array output is a record; anything else is one record. The metrics of
+/// every load are in [`EtlReport::quality`], and are also loaded to the quality [`sink()`] if one
+/// is configured, e.g., to chart them over time.
+///
+/// ```rust,ignore
+/// let quality = Quality::new()
+///     .nulls("close")?
+///     .numeric("volume")?
+///     .distinct("symbol")?
+///     .sink(sink::Postgres::new(conn, "price_quality"));
+/// let pipe = Pipe::<I, O>::builder().quality(quality).build()?;
+///
+/// let report = pipe.run().await?;
+/// let volume = report.quality[0].field("volume").and_then(|field| field.numeric.as_ref());
+/// ```
+///
+/// Outputs can also be held to [`Check`]s, e.g., that dates only increase; see [`check()`].
+///
+/// [`EtlReport::quality`]: crate::EtlReport::quality
+/// [`sink()`]: Quality::sink
+/// [`check()`]: Quality::check
+#[derive(Default)]
+pub struct Quality {
+    fields: Vec<Measured>,
+    checks: Vec<Check>,
+    enforced: bool,
+    sink: Option<Box<dyn DynSink<QualityReport>>>,
+}
+
+// one field, and which of its metrics to take
+struct Measured {
+    path: Path,
+    nulls: bool,
+    numeric: bool,
+    distinct: bool,
+}
+
+impl Quality {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Measure the rate of `null` (or missing) values at `path`.
+    pub fn nulls(self, path: &str) -> Result<Self, Error> {
+        self.measure(path, |field| field.nulls = true)
+    }
+
+    /// Measure the min, max & mean of the numbers at `path`, and the rate of zeros.
+    pub fn numeric(self, path: &str) -> Result<Self, Error> {
+        self.measure(path, |field| field.numeric = true)
+    }
+
+    /// Count the distinct values at `path`.
+    pub fn distinct(self, path: &str) -> Result<Self, Error> {
+        self.measure(path, |field| field.distinct = true)
+    }
+
+    /// Hold every output to `check`; its result is in the report, and a failure is a warning,
+    /// unless [`enforced()`](Quality::enforced).
+    ///
+    /// ```rust,ignore
+    /// let quality = Quality::new()
+    ///     .check(Check::increasing("date")?)
+    ///     .check(Check::positive("close")?)
+    ///     .check(Check::non_negative("volume")?)
+    ///     .check(Check::max_change("close", 25.0)?)
+    ///     .enforced();
+    /// ```
+    pub fn check(mut self, check: Check) -> Self {
+        self.checks.push(check);
+        self
+    }
+
+    /// Hold every output to each of `checks`, e.g., as read from a config file:
+    /// `[{ "check": "increasing", "path": "date" }, { "check": "max_change", "path": "close", "percent": 25 }]`.
+    pub fn checks(self, checks: impl IntoIterator<Item = Check>) -> Self {
+        checks.into_iter().fold(self, Quality::check)
+    }
+
+    /// Refuse to load an output that fails any check, with [`Error::Checks`]; its metrics are
+    /// still loaded to the [`sink()`](Quality::sink), failures & all.
+    pub fn enforced(mut self) -> Self {
+        self.enforced = true;
+        self
+    }
+
+    /// Load the metrics of every output to `sink`, after the output itself is loaded (or refused,
+    /// if checks are enforced); one that can't be is a [`Warning`] of the run, rather than a
+    /// failed load.
+    pub fn sink<S>(mut self, sink: S) -> Self
+    where
+        S: Sink<QualityReport> + 'static,
+    {
+        self.sink = Some(Box::new(sink));
+        self
+    }
+
+    fn measure(mut self, path: &str, metric: impl FnOnce(&mut Measured)) -> Result<Self, Error> {
+        let path = Path::parse(path)?;
+        let index = match self.fields.iter().position(|field| field.path == path) {
+            Some(index) => index,
+            None => {
+                self.fields.push(Measured {
+                    path,
+                    nulls: false,
+                    numeric: false,
+                    distinct: false,
+                });
+                self.fields.len() - 1
+            }
+        };
+        metric(&mut self.fields[index]);
+        Ok(self)
+    }
+
+    /// The configured metrics of `output`.
+    pub fn report<O: Serialize + ?Sized>(&self, output: &O) -> Result<QualityReport, Error> {
+        let output = serde_json::to_value(output)?;
+        let records = match &output {
+            Value::Array(records) => records.iter().collect(),
+            record => vec![record],
+        };
+        let fields = self
+            .fields
+            .iter()
+            .map(|field| field.report(&records))
+            .collect();
+        let checks = self
+            .checks
+            .iter()
+            .map(|check| check.run(&records))
+            .collect();
+        Ok(QualityReport {
+            records: records.len(),
+            fields,
+            checks,
+        })
+    }
+
+    // Warn of every check `report` failed; or, if enforced, fail with them.
+    pub(crate) fn judge(&self, report: &QualityReport) -> Result<(), Error> {
+        let failed: Vec<Checked> = report.failed().cloned().collect();
+        if failed.is_empty() {
+            return Ok(());
+        }
+        if self.enforced {
+            return Err(Error::Checks(Failed(failed)));
+        }
+        for checked in failed {
+            warning::warn(Warning::other(format!("quality check failed: {checked}")));
+        }
+        Ok(())
+    }
+
+    // Load `report` to the quality sink, if there is one.
+    pub(crate) async fn load(&self, report: &QualityReport) -> Result<(), Error> {
+        if let Some(sink) = &self.sink {
+            sink.load_boxed(report).await?;
+        }
+        Ok(())
+    }
+}
+
+impl Measured {
+    fn report(&self, records: &[&Value]) -> FieldMetrics {
+        let values: Vec<&Value> = records
+            .iter()
+            .filter_map(|record| self.path.get(record))
+            .filter(|value| !value.is_null())
+            .collect();
+        let rate = |count: usize| match records.len() {
+            0 => 0.0,
+            n => count as f64 / n as f64,
+        };
+
+        let numeric = self.numeric.then(|| {
+            let numbers: Vec<f64> = values.iter().filter_map(|value| value.as_f64()).collect();
+            let count = numbers.len();
+            let fold = |f: fn(f64, f64) -> f64| numbers.iter().copied().reduce(f);
+            Numeric {
+                count,
+                min: fold(f64::min),
+                max: fold(f64::max),
+                mean: (count > 0).then(|| numbers.iter().sum::<f64>() / count as f64),
+                zero_rate: match count {
+                    0 => 0.0,
+                    n => numbers.iter().filter(|n| **n == 0.0).count() as f64 / n as f64,
+                },
+            }
+        });
+        let distinct = self.distinct.then(|| {
+            values
+                .iter()
+                .map(|value| value.to_string())
+                .collect::<HashSet<_>>()
+                .len()
+        });
+        FieldMetrics {
+            field: self.path.to_string(),
+            null_rate: self.nulls.then(|| rate(records.len() - values.len())),
+            numeric,
+            distinct,
+        }
+    }
+}
+
+/// The metrics of one output; see [`Quality`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QualityReport {
+    pub records: usize,
+    pub fields: Vec<FieldMetrics>,
+    /// The result of every check, in the order they were added.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub checks: Vec<Checked>,
+}
+
+impl QualityReport {
+    /// The metrics of the field at `path`, as configured.
+    pub fn field(&self, path: &str) -> Option<&FieldMetrics> {
+        self.fields.iter().find(|field| field.field == path)
+    }
+
+    /// The checks the output failed.
+    pub fn failed(&self) -> impl Iterator<Item = &Checked> {
+        self.checks.iter().filter(|checked| !checked.passed())
+    }
+}
+
+/// The metrics of one field, over every record of an output; only those configured are `Some`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldMetrics {
+    pub field: String,
+    /// The share of records where the field is `null` or missing, from 0 to 1.
+    pub null_rate: Option<f64>,
+    pub numeric: Option<Numeric>,
+    /// How many distinct non-null values the field has.
+    pub distinct: Option<usize>,
+}
+
+// the rates are of counts, and the rest of JSON numbers, neither of which are ever NaN
+impl Eq for FieldMetrics {}
+
+/// The spread of a numeric field; values that aren't numbers are skipped.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Numeric {
+    /// How many values were numbers.
+    pub count: usize,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub mean: Option<f64>,
+    /// The share of the numbers that are zero, from 0 to 1.
+    pub zero_rate: f64,
+}
+
+impl Eq for Numeric {}
+
+/// A rule for every record of an output, e.g., of a price series; see [`Quality::check()`].
+///
+/// Records without a value at the path (or with `null`) are skipped, as are values of the wrong
+/// type; measure them with [`Quality::nulls()`] & [`Quality::numeric()`].
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "check", rename_all = "snake_case")]
+pub enum Check {
+    /// Each value is after the one before: dates & times (as ISO 8601), or numbers; or, unless
+    /// `strict` (the default, in config too), equal to it.
+    Increasing {
+        path: Path,
+        #[serde(default = "strictly")]
+        strict: bool,
+    },
+    /// Each number is zero or more, e.g., a volume.
+    NonNegative { path: Path },
+    /// Each number is more than zero, e.g., a price.
+    Positive { path: Path },
+    /// Each number is within `percent` % of the one before, e.g., a day's close of the last.
+    MaxChange { path: Path, percent: f64 },
+}
+
+// unless told otherwise, an increasing check allows no repeats
+fn strictly() -> bool {
+    true
+}
+
+impl Check {
+    /// [`Check::Increasing`], strictly: no repeats, e.g., of a date.
+    pub fn increasing(path: &str) -> Result<Self, Error> {
+        Ok(Check::Increasing {
+            path: Path::parse(path)?,
+            strict: true,
+        })
+    }
+
+    /// [`Check::NonNegative`].
+    pub fn non_negative(path: &str) -> Result<Self, Error> {
+        Ok(Check::NonNegative {
+            path: Path::parse(path)?,
+        })
+    }
+
+    /// [`Check::Positive`].
+    pub fn positive(path: &str) -> Result<Self, Error> {
+        Ok(Check::Positive {
+            path: Path::parse(path)?,
+        })
+    }
+
+    /// [`Check::MaxChange`].
+    pub fn max_change(path: &str, percent: f64) -> Result<Self, Error> {
+        Ok(Check::MaxChange {
+            path: Path::parse(path)?,
+            percent,
+        })
+    }
+
+    fn path(&self) -> &Path {
+        match self {
+            Check::Increasing { path, .. }
+            | Check::NonNegative { path }
+            | Check::Positive { path }
+            | Check::MaxChange { path, .. } => path,
+        }
+    }
+
+    // The check over `records`; each value with the one before it, for those comparing them.
+    fn run(&self, records: &[&Value]) -> Checked {
+        let values = records.iter().enumerate().filter_map(|(index, record)| {
+            let value = self.path().get(record).filter(|value| !value.is_null())?;
+            Some((index, value))
+        });
+        let mut violations = vec![];
+        let mut before: Option<&Value> = None;
+        for (index, value) in values {
+            let violation = match self {
+                Check::Increasing { strict, .. } => before
+                    .filter(|before| {
+                        compare(before, value)
+                            .is_some_and(|order| order.is_gt() || (*strict && order.is_eq()))
+                    })
+                    .map(|before| format!("{value} after {before}")),
+                Check::NonNegative { .. } => value
+                    .as_f64()
+                    .filter(|number| *number < 0.0)
+                    .map(|_| value.to_string()),
+                Check::Positive { .. } => value
+                    .as_f64()
+                    .filter(|number| *number <= 0.0)
+                    .map(|_| value.to_string()),
+                Check::MaxChange { percent, .. } => before.and_then(|before| {
+                    let (last, number) = (before.as_f64()?, value.as_f64()?);
+                    let change = (number - last) / last.abs() * 100.0;
+                    (last != 0.0 && change.abs() > *percent)
+                        .then(|| format!("{value}, {change:+.1}% on {before}"))
+                }),
+            };
+            if let Some(violation) = violation {
+                violations.push((index, violation));
+            }
+            before = Some(value);
+        }
+        let first = violations.first();
+        Checked {
+            check: self.to_string(),
+            violations: violations.len(),
+            first: first.map(|(index, _)| *index),
+            example: first.map(|(_, violation)| violation.clone()),
+        }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Check::Increasing { path, strict: true } => write!(f, "`{path}` strictly increasing"),
+            Check::Increasing { path, .. } => write!(f, "`{path}` increasing"),
+            Check::NonNegative { path } => write!(f, "`{path}` non-negative"),
+            Check::Positive { path } => write!(f, "`{path}` positive"),
+            Check::MaxChange { path, percent } => write!(f, "`{path}` changing at most {percent}%"),
+        }
+    }
+}
+
+// the order of two values of the same kind: numbers, or dates & times; any other strings as text
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        (Value::String(a), Value::String(b)) => match (time::iso8601(a), time::iso8601(b)) {
+            (Ok(a), Ok(b)) => Some(a.cmp(&b)),
+            _ => Some(a.cmp(b)),
+        },
+        _ => None,
+    }
+}
+
+/// The result of one [`Check`] of an output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checked {
+    /// The check, e.g., "`close` positive".
+    pub check: String,
+    /// How many records failed it.
+    pub violations: usize,
+    /// The index of the first record that failed it.
+    pub first: Option<usize>,
+    /// What was wrong with the first, e.g., "\"2024-06-01\" after \"2024-06-03\"".
+    pub example: Option<String>,
+}
+
+impl Checked {
+    pub fn passed(&self) -> bool {
+        self.violations == 0
+    }
+}
+
+impl fmt::Display for Checked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.check)?;
+        if let (Some(first), Some(example)) = (self.first, &self.example) {
+            write!(
+                f,
+                ": {} failed, first [{first}]: {example}",
+                self.violations
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The checks an output failed, refusing its load; see [`Quality::enforced()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failed(pub Vec<Checked>);
+
+impl fmt::Display for Failed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let failed: Vec<String> = self.0.iter().map(Checked::to_string).collect();
+        write!(f, "{}", failed.join("; "))
+    }
+}
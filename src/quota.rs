@@ -0,0 +1,180 @@
+//! Daily request budgets for APIs that cap how many requests a key can make per day; tracked in a
+//! file, so every run counts against the same budget, and one accidental backfill can't burn
+//! through it.
+//!
+//! ```rust,ignore
+//! let quota = Quota::new("alphavantage.quota", 500);
+//! let pipe = Pipe::<I, O>::builder().quota(quota.clone()).build()?;
+//!
+//! // before a sweep: how much is left, and whether the sweep fits
+//! println!("{}", quota.budget()?);
+//! quota.check(urls.len() as u32)?;
+//! pipe.etl_many(urls).await?;
+//! ```
+//!
+//! Every extraction attempt (retries included) spends 1 request; a sweep, i.e.,
+//! [`Pipe::etl_many()`] or a [`Backfill`], is refused before its first extraction if it needs
+//! more requests than are left. Alternatively, with [`wait()`], extractions pause until the
+//! budget resets instead; and sweeps aren't refused, but spread over as many days as they take.
+//!
+//! Days are UTC days. The file is read & written on every request; runs one after another share
+//! it, but processes running at the same time may each spend the last of it.
+//!
+//! [`Pipe::etl_many()`]: crate::Pipe::etl_many
+//! [`Backfill`]: crate::backfill::Backfill
+//! [`wait()`]: Quota::wait
+
+use super::clock::{self, Clock};
+use super::{fs, Error};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// A daily request budget, persisted in a file; see [`quota`](self).
+///
+/// Clones share the same budget, so one `Quota` can be handed to every pipe that uses the key.
+#[derive(Clone)]
+pub struct Quota {
+    path: PathBuf,
+    limit: u32,
+    wait: bool,
+    clock: Arc<dyn Clock>,
+    // so spends in this process are counted one at a time
+    lock: Arc<Mutex<()>>,
+}
+
+/// The state of a [`Quota`] on a day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Budget {
+    pub day: NaiveDate,
+    /// The requests spent on `day`.
+    pub used: u32,
+    /// The requests allowed per day.
+    pub limit: u32,
+}
+
+impl Budget {
+    /// The requests left on `day`.
+    pub fn remaining(&self) -> u32 {
+        self.limit.saturating_sub(self.used)
+    }
+
+    /// When the budget resets: the start of the next (UTC) day.
+    pub fn resets_at(&self) -> DateTime<Utc> {
+        let next = self.day.succ_opt().unwrap_or(self.day);
+        next.and_time(chrono::NaiveTime::MIN).and_utc()
+    }
+}
+
+impl std::fmt::Display for Budget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} of {} requests left on {}; resets at {}",
+            self.remaining(),
+            self.limit,
+            self.day,
+            self.resets_at().format("%Y-%m-%dT%H:%M:%SZ"),
+        )
+    }
+}
+
+impl Quota {
+    /// Allow `requests` per day, tracked in the file at `path`.
+    pub fn new(path: impl Into<PathBuf>, requests: u32) -> Self {
+        Quota {
+            path: path.into(),
+            limit: requests,
+            wait: false,
+            clock: clock::system(),
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Whether an extraction over budget waits for the reset, instead of failing with
+    /// [`Error::QuotaExceeded`]. Defaults to `false`.
+    pub fn wait(mut self, wait: bool) -> Self {
+        self.wait = wait;
+        self
+    }
+
+    /// Tell the day by `clock`, instead of the system clock.
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// The budget as of now; e.g., to report before a run.
+    pub fn budget(&self) -> Result<Budget, Error> {
+        let today = self.clock.now().date_naive();
+        let saved = match std::fs::read_to_string(&self.path) {
+            Ok(saved) => Some(serde_json::from_str::<Budget>(&saved)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        let used = match saved {
+            Some(budget) if budget.day == today => budget.used,
+            _ => 0,
+        };
+        Ok(Budget {
+            day: today,
+            used,
+            limit: self.limit,
+        })
+    }
+
+    /// Whether `requests` more fit in today's budget; if not, [`Error::QuotaExceeded`], unless the quota
+    /// [`wait()`]s for resets instead. Nothing is spent.
+    ///
+    /// [`wait()`]: Quota::wait
+    pub fn check(&self, requests: u32) -> Result<Budget, Error> {
+        let budget = self.budget()?;
+        if !self.wait && requests > budget.remaining() {
+            return Err(exceeded(requests, budget));
+        }
+        Ok(budget)
+    }
+
+    // Spend 1 request, waiting for the reset (or failing) if none are left.
+    pub(crate) async fn spend(&self) -> Result<(), Error> {
+        loop {
+            let budget = {
+                let _lock = self.lock.lock().await;
+                let mut budget = self.budget()?;
+                if budget.remaining() > 0 {
+                    budget.used += 1;
+                    fs::write_atomic(&self.path, &serde_json::to_vec(&budget)?)?;
+                    return Ok(());
+                }
+                budget
+            };
+            if !self.wait {
+                return Err(exceeded(1, budget));
+            }
+            let until = (budget.resets_at() - self.clock.now())
+                .to_std()
+                .unwrap_or_default();
+            tokio::time::sleep(until).await;
+        }
+    }
+}
+
+impl std::fmt::Debug for Quota {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Quota")
+            .field("path", &self.path)
+            .field("limit", &self.limit)
+            .field("wait", &self.wait)
+            .finish()
+    }
+}
+
+fn exceeded(requests: u32, budget: Budget) -> Error {
+    Error::QuotaExceeded {
+        needed: requests,
+        remaining: budget.remaining(),
+        resets_at: budget.resets_at(),
+    }
+}
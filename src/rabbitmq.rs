@@ -0,0 +1,166 @@
+use super::sink::Sink;
+use super::{Error, Outcome};
+use lapin::options::{BasicPublishOptions, ConfirmSelectOptions};
+use lapin::{BasicProperties, Channel, Confirmation, Connection, ConnectionProperties};
+use serde::Serialize;
+use tokio::sync::Mutex;
+
+/// Publishes every record of the output to a RabbitMQ exchange, as one JSON message each, with
+/// `routing_key`.
+///
+/// The channel is put in confirm mode, and the messages are published as mandatory: the load
+/// waits for the broker to confirm every one, and fails if any is nacked, or can't be routed to a
+/// queue; so it may be retried, with at-least-once delivery. The connection is opened on the first
+/// load, and reopened by a later one once it's lost (e.g., to a broker restart); a load that loses
+/// it part way is published again in full, on a new one, before it fails.
+///
+/// ```rust,ignore
+/// let sink = RabbitMqSink::new("amqp://localhost:5672/%2f", "prices", "prices.clean")
+///     .persistent(true);
+/// let pipe = Pipe::<RawPrice, Vec<Price>>::builder().source(source).sink(sink).build()?;
+/// ```
+pub struct RabbitMqSink {
+    uri: String,
+    exchange: String,
+    routing_key: String,
+    persistent: bool,
+    channel: Mutex<Option<(Connection, Channel)>>,
+}
+
+impl RabbitMqSink {
+    /// Publish to `exchange` (`""` for the default exchange, routing to the queue named
+    /// `routing_key`), on the broker at `uri`.
+    pub fn new(
+        uri: impl Into<String>,
+        exchange: impl Into<String>,
+        routing_key: impl Into<String>,
+    ) -> Self {
+        RabbitMqSink {
+            uri: uri.into(),
+            exchange: exchange.into(),
+            routing_key: routing_key.into(),
+            persistent: false,
+            channel: Mutex::new(None),
+        }
+    }
+
+    /// Have the broker write the messages to disk, to survive a restart (with a durable queue).
+    /// Defaults to `false`.
+    pub fn persistent(mut self, persistent: bool) -> Self {
+        self.persistent = persistent;
+        self
+    }
+
+    // The open channel, and whether it's just been opened; opening one if there's none, or the
+    // last has closed.
+    async fn channel(&self) -> Result<(Channel, bool), Error> {
+        let mut open = self.channel.lock().await;
+        if let Some((connection, channel)) = open.as_ref() {
+            if connection.status().connected() && channel.status().connected() {
+                return Ok((channel.clone(), false));
+            }
+        }
+        *open = None;
+        let connection = Connection::connect(&self.uri, ConnectionProperties::default())
+            .await
+            .map_err(rabbitmq)?;
+        let channel = connection.create_channel().await.map_err(rabbitmq)?;
+        channel
+            .confirm_select(ConfirmSelectOptions::default())
+            .await
+            .map_err(rabbitmq)?;
+        *open = Some((connection, channel.clone()));
+        Ok((channel, true))
+    }
+
+    // Forget `channel`, if it's still the open one, so the next load opens another.
+    async fn close(&self, channel: &Channel) {
+        let mut open = self.channel.lock().await;
+        if open
+            .as_ref()
+            .is_some_and(|(_, open)| open.id() == channel.id())
+        {
+            *open = None;
+        }
+    }
+
+    // Publish every record on `channel`, and wait for their confirms; the outer error is the
+    // channel's, the inner one the records'.
+    async fn publish<T: Serialize>(
+        &self,
+        channel: &Channel,
+        output: &[T],
+    ) -> Result<Result<(), Error>, lapin::Error> {
+        let mut properties =
+            BasicProperties::default().with_content_type("application/json".into());
+        if self.persistent {
+            properties = properties.with_delivery_mode(2);
+        }
+        let options = BasicPublishOptions {
+            mandatory: true,
+            ..BasicPublishOptions::default()
+        };
+
+        let mut confirms = Vec::with_capacity(output.len());
+        for record in output {
+            let payload = match serde_json::to_vec(record) {
+                Ok(payload) => payload,
+                Err(e) => return Ok(Err(e.into())),
+            };
+            let confirm = channel
+                .basic_publish(
+                    self.exchange.as_str().into(),
+                    self.routing_key.as_str().into(),
+                    options,
+                    &payload,
+                    properties.clone(),
+                )
+                .await?;
+            confirms.push(confirm);
+        }
+        for (i, confirm) in confirms.into_iter().enumerate() {
+            match confirm.await? {
+                Confirmation::Ack(None) | Confirmation::NotRequested => {}
+                Confirmation::Ack(Some(_)) => {
+                    return Ok(Err(Error::RabbitMq(format!(
+                        "record {i} could not be routed by `{}`, with `{}`",
+                        self.exchange, self.routing_key
+                    ))))
+                }
+                Confirmation::Nack(_) => {
+                    return Ok(Err(Error::RabbitMq(format!("record {i} was nacked"))))
+                }
+            }
+        }
+        Ok(Ok(()))
+    }
+}
+
+impl<T> Sink<Vec<T>> for RabbitMqSink
+where
+    T: Serialize + Sync,
+{
+    async fn load(&self, output: &Vec<T>) -> Result<Outcome, Error> {
+        loop {
+            let (channel, opened) = self.channel().await?;
+            match self.publish(&channel, output).await {
+                Ok(published) => {
+                    published?;
+                    return Ok(Outcome::Published {
+                        messages: output.len() as u64,
+                    });
+                }
+                // lost since an earlier load: once more, on a new channel
+                Err(_) if !opened => self.close(&channel).await,
+                Err(e) => {
+                    self.close(&channel).await;
+                    return Err(rabbitmq(e));
+                }
+            }
+        }
+    }
+}
+
+fn rabbitmq(error: lapin::Error) -> Error {
+    Error::RabbitMq(error.to_string())
+}
@@ -0,0 +1,77 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+/// Spaces out extractions so that no more than `requests` are started per `per` interval.
+///
+/// Clones share the same schedule, so one `RateLimit` can be handed to several pipes
+/// that hit the same API; and changing the limit of one, with [`set()`](RateLimit::set), changes
+/// it for every clone.
+#[derive(Debug, Clone)]
+pub struct RateLimit {
+    // the interval it was built with, to go back to with `reset()`
+    built: Duration,
+    interval: Arc<std::sync::Mutex<Duration>>,
+    next: Arc<Mutex<Option<Instant>>>,
+}
+
+impl RateLimit {
+    /// Allow `requests` extractions every `per`.
+    pub fn new(requests: u32, per: Duration) -> Self {
+        let built = interval(requests, per);
+        RateLimit {
+            built,
+            interval: Arc::new(std::sync::Mutex::new(built)),
+            next: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Allow `requests` extractions every second.
+    pub fn per_second(requests: u32) -> Self {
+        Self::new(requests, Duration::from_secs(1))
+    }
+
+    /// The minimum gap between two extractions.
+    pub fn interval(&self) -> Duration {
+        *self
+            .interval
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Allow `requests` extractions every `per` from now on, instead; e.g., on a config reload.
+    /// A slot already claimed is kept.
+    pub fn set(&self, requests: u32, per: Duration) {
+        *self
+            .interval
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = interval(requests, per);
+    }
+
+    /// Go back to the limit it was built with, undoing any [`set()`](RateLimit::set); e.g., on a
+    /// config reload that no longer sets one.
+    pub fn reset(&self) {
+        *self
+            .interval
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = self.built;
+    }
+
+    /// Wait until the next slot is free, and claim it.
+    pub async fn acquire(&self) {
+        let mut next = self.next.lock().await;
+        let now = Instant::now();
+        let slot = match *next {
+            Some(at) if at > now => at,
+            _ => now,
+        };
+        *next = Some(slot + self.interval());
+        drop(next);
+        tokio::time::sleep_until(slot).await;
+    }
+}
+
+fn interval(requests: u32, per: Duration) -> Duration {
+    per.checked_div(requests).unwrap_or(Duration::ZERO)
+}
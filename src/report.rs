@@ -0,0 +1,184 @@
+use super::artifact::Written;
+use super::db::couchdb::CouchOutcome;
+use super::db::postgresql::PgOutcome;
+use super::memory::Sizes;
+use super::quality::QualityReport;
+use super::warning::Warning;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// What a [`Sink`] load did, as reported by the sink.
+///
+/// [`Sink`]: crate::Sink
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    /// Loaded, with nothing more to report; e.g., by a custom sink.
+    #[default]
+    Done,
+    /// A CouchDB document written.
+    Couch(CouchOutcome),
+    /// Rows written to a PostgreSQL table.
+    Pg(PgOutcome),
+    /// Rows written to a ScyllaDB table.
+    Scylla { rows: u64 },
+    /// Rows streamed into a warehouse table, e.g., by the BigQuery sink.
+    Streamed { rows: u64 },
+    /// The status of the HTTP response; `None` if the payload had already been accepted, and
+    /// wasn't sent again.
+    Http { status: Option<u16> },
+    /// Bytes written to a file, or another [`RawSink`].
+    ///
+    /// [`RawSink`]: crate::sink::RawSink
+    File { bytes: u64 },
+    /// Messages published to a queue, each confirmed by the broker.
+    Published { messages: u64 },
+    /// One outcome per batch, of a [`Batched`] sink.
+    ///
+    /// [`Batched`]: crate::sink::Batched
+    Batches(Vec<Outcome>),
+    /// One outcome per route loaded to, of a [`Router`] sink.
+    ///
+    /// [`Router`]: crate::sink::Router
+    Routes(BTreeMap<String, Outcome>),
+    /// Nothing was loaded, on purpose.
+    Skipped(Skip),
+}
+
+/// Why a load was skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Skip {
+    /// The payload was the one last loaded from its source; see [`unchanged`].
+    ///
+    /// [`unchanged`]: crate::unchanged
+    Unchanged,
+}
+
+impl Outcome {
+    /// Every outcome, with [`Outcome::Batches`] & [`Outcome::Routes`] flattened into the outcomes
+    /// of their batches & routes.
+    pub fn flatten(&self) -> Vec<&Outcome> {
+        match self {
+            Outcome::Batches(batches) => batches.iter().flat_map(Outcome::flatten).collect(),
+            Outcome::Routes(routes) => routes.values().flat_map(Outcome::flatten).collect(),
+            outcome => vec![outcome],
+        }
+    }
+
+    /// The rows written to a database table, if any.
+    pub fn rows_affected(&self) -> u64 {
+        self.flatten()
+            .into_iter()
+            .map(|outcome| match outcome {
+                Outcome::Pg(pg) => pg.rows_affected,
+                Outcome::Scylla { rows } | Outcome::Streamed { rows } => *rows,
+                _ => 0,
+            })
+            .sum()
+    }
+}
+
+/// What a run loaded: the [`Outcome`] of every load, in order, with aggregates over them.
+///
+/// Returned by [`Pipe::run()`] & co., e.g., to log what happened:
+///
+/// ```rust,ignore
+/// let report = pipe.run().await?;
+/// println!(
+///     "{} loads: {} rows, {} docs created, {} updated",
+///     report.loads(),
+///     report.rows_affected(),
+///     report.docs_created(),
+///     report.docs_updated(),
+/// );
+/// ```
+///
+/// [`Pipe::run()`]: crate::Pipe::run
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EtlReport {
+    pub outcomes: Vec<Outcome>,
+    /// The data-quality metrics of every load, in order; if the pipe takes any, see [`Quality`].
+    ///
+    /// [`Quality`]: crate::quality::Quality
+    #[serde(default)]
+    pub quality: Vec<QualityReport>,
+    /// The anomalies the run recovered from, in the order they were raised; see [`Warning`].
+    #[serde(default)]
+    pub warnings: Vec<Warning>,
+    /// The sizes of the run's data; if the pipe was measured, see [`memory`].
+    ///
+    /// [`memory`]: crate::memory
+    #[serde(default)]
+    pub sizes: Option<Sizes>,
+    /// The artifacts the run wrote, by name; see [`artifact`].
+    ///
+    /// [`artifact`]: crate::artifact
+    #[serde(default)]
+    pub artifacts: Vec<Written>,
+}
+
+// what one load did, and the quality of what it loaded
+pub(crate) struct Loaded {
+    pub(crate) outcome: Outcome,
+    pub(crate) quality: Option<QualityReport>,
+}
+
+impl Loaded {
+    pub(crate) fn skipped(skip: Skip) -> Self {
+        Loaded {
+            outcome: Outcome::Skipped(skip),
+            quality: None,
+        }
+    }
+}
+
+impl EtlReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn push(&mut self, loaded: Loaded) {
+        self.outcomes.push(loaded.outcome);
+        self.quality.extend(loaded.quality);
+    }
+
+    /// How many outputs were loaded; not counting skipped ones.
+    pub fn loads(&self) -> usize {
+        self.outcomes.len() - self.skipped()
+    }
+
+    /// How many loads were skipped; see [`Outcome::Skipped`].
+    pub fn skipped(&self) -> usize {
+        self.outcomes
+            .iter()
+            .filter(|outcome| matches!(outcome, Outcome::Skipped(_)))
+            .count()
+    }
+
+    /// The rows written to database tables, over every load.
+    pub fn rows_affected(&self) -> u64 {
+        self.outcomes.iter().map(Outcome::rows_affected).sum()
+    }
+
+    /// The CouchDB documents written, in order.
+    pub fn docs(&self) -> impl Iterator<Item = &CouchOutcome> {
+        self.outcomes
+            .iter()
+            .flat_map(Outcome::flatten)
+            .filter_map(|outcome| match outcome {
+                Outcome::Couch(doc) => Some(doc),
+                _ => None,
+            })
+    }
+
+    /// How many CouchDB documents were created, rather than updated.
+    pub fn docs_created(&self) -> usize {
+        self.docs().filter(|doc| doc.created).count()
+    }
+
+    /// How many existing CouchDB documents were updated.
+    pub fn docs_updated(&self) -> usize {
+        self.docs().filter(|doc| !doc.created).count()
+    }
+}
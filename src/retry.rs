@@ -0,0 +1,158 @@
+use super::{clock, Error};
+use std::future::Future;
+use std::time::Duration;
+
+/// How many times a failing stage is attempted, and how long to wait between attempts.
+///
+/// ```rust,ignore
+/// let retry = RetryPolicy::new(3).backoff(Duration::from_millis(500), Duration::from_secs(10));
+/// ```
+///
+/// A sink that pushes back waits as long as it asks to (up to `max_delay`), rather than the backoff;
+/// see [`Hint`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first; must be at least 1.
+    pub max_attempts: u32,
+    /// Delay before the second attempt; doubled for every attempt after that.
+    pub base_delay: Duration,
+    /// Upper bound for the delay between any two attempts.
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Retry up to `max_attempts` times (in total), with the default exponential backoff.
+    pub fn new(max_attempts: u32) -> Self {
+        RetryPolicy {
+            max_attempts,
+            ..Default::default()
+        }
+    }
+
+    /// Set the exponential backoff; `base` is doubled per attempt, capped at `max`.
+    pub fn backoff(mut self, base: Duration, max: Duration) -> Self {
+        self.base_delay = base;
+        self.max_delay = max;
+        self
+    }
+
+    /// The delay to wait after the failed `attempt` (starting from 1).
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.base_delay.saturating_mul(factor).min(self.max_delay)
+    }
+
+    /// The delay to wait after the failed `attempt`, as hinted by its `error` (but at most the
+    /// policy's longest delay); of a batch's failures (an [`Error::Many`]), the longest.
+    pub fn delay_after(&self, attempt: u32, error: &Error) -> Duration {
+        match error {
+            Error::Many(errors) if !errors.is_empty() => errors
+                .iter()
+                .map(|failure| self.delay_after(attempt, &failure.error))
+                .max()
+                .unwrap_or_default(),
+            error => match Hint::of(error) {
+                Hint::Backoff => self.delay(attempt),
+                Hint::After(delay) => delay.min(self.max_delay),
+                Hint::Overloaded => self.max_delay,
+            },
+        }
+    }
+
+    /// Run `op` until it succeeds or the attempts run out, returning the last error.
+    ///
+    /// `on_retry` is called with the failed attempt number and its error, before sleeping for
+    /// [`delay_after()`](RetryPolicy::delay_after) it.
+    pub async fn run<T, F, Fut>(
+        &self,
+        mut op: F,
+        mut on_retry: impl FnMut(u32, &Error),
+    ) -> Result<T, Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match op().await {
+                Ok(value) => return Ok(value),
+                Err(e) if attempt < self.max_attempts => {
+                    on_retry(attempt, &e);
+                    tokio::time::sleep(self.delay_after(attempt, &e)).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// How a failure asks to be retried: the backpressure signals of sinks, classified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hint {
+    /// After the policy's backoff; any failure without a hint.
+    Backoff,
+    /// After the given delay (up to the policy's longest), instead of the backoff:
+    ///
+    /// - [`Error::Backpressure`] with a `retry_after`, e.g., CouchDB's `429` & `Retry-After`;
+    /// - [`Error::CircuitOpen`], once the circuit closes again;
+    /// - a Postgres serialization failure or deadlock, at once; the transaction it conflicted
+    ///   with has already finished.
+    After(Duration),
+    /// After the policy's longest delay: the sink is overloaded, but didn't say for how long;
+    /// [`Error::Backpressure`] without a `retry_after`, or Scylla's `Overloaded` &
+    /// `RateLimitReached`.
+    Overloaded,
+}
+
+impl Hint {
+    pub fn of(error: &Error) -> Hint {
+        use scylla::transport::errors::{DbError, QueryError};
+        use tokio_postgres::error::SqlState;
+        match error {
+            Error::Backpressure {
+                retry_after: Some(delay),
+                ..
+            } => Hint::After(*delay),
+            Error::Backpressure {
+                retry_after: None, ..
+            } => Hint::Overloaded,
+            Error::CircuitOpen { retry_in } => Hint::After(*retry_in),
+            Error::Postgres(e)
+                if e.code().is_some_and(|code| {
+                    *code == SqlState::T_R_SERIALIZATION_FAILURE
+                        || *code == SqlState::T_R_DEADLOCK_DETECTED
+                }) =>
+            {
+                Hint::After(Duration::ZERO)
+            }
+            Error::Scylla(QueryError::DbError(
+                DbError::Overloaded | DbError::RateLimitReached { .. },
+                _,
+            )) => Hint::Overloaded,
+            _ => Hint::Backoff,
+        }
+    }
+}
+
+/// The delay of a `Retry-After` header's `value`: a number of seconds, or an HTTP date (zero, if
+/// it's passed, by the [current](clock::current) clock); `None` if it's neither.
+pub fn retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let delay = at.with_timezone(&chrono::Utc) - clock::current().now();
+    Some(delay.to_std().unwrap_or_default())
+}
@@ -0,0 +1,858 @@
+use super::catalog::{Catalog, Entry};
+use super::clock::{self, Clock};
+use super::config::RunnerConfig;
+use super::db::postgresql::Transaction;
+use super::error::{Context, Errors};
+use super::history::{DynHistory, History, RunRecord};
+use super::observer::{Event, Observer};
+use super::pipe::DynPipeline;
+use super::{Error, EtlReport};
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::{BTreeMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{oneshot, watch};
+use tokio::time::{Instant, MissedTickBehavior};
+
+/// How many errors each pipeline's [`Status`] keeps.
+pub const RECENT_ERRORS: usize = 10;
+
+/// How long a run can wait for a slot before it goes ahead of higher priorities, by default; see
+/// [`Runner::starvation()`].
+pub const STARVATION: Duration = Duration::from_secs(10 * 60);
+
+/// Runs several pipelines, each on its own schedule, and keeps track of how their runs went.
+///
+/// ```rust,ignore
+/// let runner = Runner::new()
+///     .pipe("prices", prices, Duration::from_secs(60 * 60))
+///     .pipe("holidays", holidays, Duration::from_secs(24 * 60 * 60));
+///
+/// // runs every pipeline straight away, then every time its interval has passed
+/// runner.run().await?;
+/// ```
+///
+/// A run that fails is recorded in the pipeline's [`Status`], and the pipeline runs again at its
+/// next interval; so one failing pipeline doesn't stop the rest. With the `dashboard` feature, the
+/// statuses can be served over HTTP too; see [`dashboard`](crate::dashboard).
+///
+/// With a [`config()`](Runner::config) file, schedules, rate limits, priorities & which
+/// pipelines are enabled can be changed while the runner runs, without a restart.
+///
+/// Pipelines run at once unless the runner's [`concurrency()`](Runner::concurrency) is limited;
+/// runs then wait for a free slot, taken by [`priority()`](Runner::priority):
+///
+/// ```rust,ignore
+/// let runner = Runner::new()
+///     .pipe("prices", prices, Duration::from_secs(60))
+///     .pipe("backfill", backfill, Duration::from_secs(60 * 60))
+///     .priority("prices", 10)
+///     .priority("backfill", -10)
+///     .concurrency(2);
+/// ```
+///
+/// A run that's due while the pipeline's last is still in progress is queued behind it, unless
+/// the pipeline's [`overlap()`](Runner::overlap) says otherwise.
+pub struct Runner {
+    pipes: Vec<Scheduled>,
+    priorities: BTreeMap<String, i32>,
+    overlaps: BTreeMap<String, Overlap>,
+    slots: Slots,
+    statuses: Statuses,
+    clock: Arc<dyn Clock>,
+    history: Option<Box<dyn DynHistory>>,
+    observer: Option<Arc<dyn Observer>>,
+    config: Option<PathBuf>,
+    reload: Duration,
+    // the config in force, swapped whole on a reload
+    settings: watch::Sender<Arc<RunnerConfig>>,
+}
+
+struct Scheduled {
+    every: Duration,
+    pipe: Box<dyn DynPipeline>,
+}
+
+impl Default for Runner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Runner {
+    pub fn new() -> Self {
+        Runner {
+            pipes: vec![],
+            priorities: BTreeMap::new(),
+            overlaps: BTreeMap::new(),
+            slots: Slots {
+                limit: None,
+                starvation: STARVATION,
+                queue: Mutex::default(),
+            },
+            statuses: Statuses::default(),
+            clock: clock::system(),
+            history: None,
+            observer: None,
+            config: None,
+            reload: Duration::from_secs(1),
+            settings: watch::Sender::new(Arc::default()),
+        }
+    }
+
+    /// Schedule `pipe` as `name`, to run `every` so often; its source & sink must be configured.
+    /// Either a [`Pipe`](crate::Pipe), or one already boxed as a [`DynPipeline`].
+    ///
+    /// Returns [`Error::Config`] from [`run()`] if the name is taken.
+    ///
+    /// [`run()`]: Runner::run
+    pub fn pipe<P>(mut self, name: impl Into<String>, pipe: P, every: Duration) -> Self
+    where
+        P: DynPipeline + 'static,
+    {
+        self.statuses.push(Status::new(name.into(), every));
+        self.pipes.push(Scheduled {
+            every,
+            pipe: Box::new(pipe),
+        });
+        self
+    }
+
+    /// Run the pipeline named `name` ahead of those of lower priorities, when both are waiting
+    /// for a slot; see [`concurrency()`](Runner::concurrency). Defaults to 0; higher goes first.
+    ///
+    /// Returns [`Error::Config`] from [`run()`] if there's no such pipeline.
+    ///
+    /// [`run()`]: Runner::run
+    pub fn priority(mut self, name: impl Into<String>, priority: i32) -> Self {
+        self.priorities.insert(name.into(), priority);
+        self
+    }
+
+    /// What to do when a run of the pipeline named `name` is due while its last is still in
+    /// progress; [`Overlap::Queue`] by default. Each decision is told to the runner's
+    /// [`observer()`](Runner::observer), and counted in the pipeline's [`Status`].
+    ///
+    /// Returns [`Error::Config`] from [`run()`] if there's no such pipeline.
+    ///
+    /// [`run()`]: Runner::run
+    pub fn overlap(mut self, name: impl Into<String>, overlap: Overlap) -> Self {
+        self.overlaps.insert(name.into(), overlap);
+        self
+    }
+
+    /// Run at most `max` pipelines at once; the rest wait for a slot, the highest
+    /// [`priority()`](Runner::priority) first, then the longest waiting. A run in progress is never
+    /// interrupted; a waiting one takes the next slot freed.
+    ///
+    /// Returns [`Error::Config`] from [`run()`] if `max` is 0.
+    ///
+    /// [`run()`]: Runner::run
+    pub fn concurrency(mut self, max: usize) -> Self {
+        self.slots.limit = Some(max);
+        self
+    }
+
+    /// Let a run that has waited for a slot for `after` go ahead of any priority, so pipelines of
+    /// low priorities can't be starved by busier ones. Defaults to [`STARVATION`].
+    pub fn starvation(mut self, after: Duration) -> Self {
+        self.slots.starvation = after;
+        self
+    }
+
+    /// The clock runs are timestamped with; the system clock by default.
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Persist a record of every run to `history`, e.g., to compare runs over time; see [`history`].
+    ///
+    /// A record that can't be saved is logged to stderr; the run itself still counts.
+    ///
+    /// [`history`]: crate::history
+    pub fn history<H: History + 'static>(mut self, history: H) -> Self {
+        self.history = Some(Box::new(history));
+        self
+    }
+
+    /// Receive the runner's own [`Event`]s, e.g., its [`Event::Overlapped`] decisions; those of
+    /// each pipe's runs go to the pipe's own observer.
+    pub fn observer<T>(mut self, observer: T) -> Self
+    where
+        T: Observer + 'static,
+    {
+        self.observer = Some(Arc::new(observer));
+        self
+    }
+
+    /// Apply the [`RunnerConfig`] in the JSON file at `path` over the pipelines' own settings,
+    /// when the runner starts and again whenever the file changes; it's checked every
+    /// [`reload_every()`](Runner::reload_every).
+    ///
+    /// A config is checked before it's applied, and applied whole: one naming an unknown pipeline,
+    /// or a rate limit for a pipe without one, fails [`run()`] with [`Error::Config`] at start; and
+    /// on a reload, is logged to stderr, keeping the config before. Runs in progress finish as
+    /// they started; the next run of each pipeline is scheduled by the new config.
+    ///
+    /// [`run()`]: Runner::run
+    pub fn config(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config = Some(path.into());
+        self
+    }
+
+    /// How often the [`config()`](Runner::config) file is checked for changes. Defaults to 1s.
+    pub fn reload_every(mut self, every: Duration) -> Self {
+        self.reload = every;
+        self
+    }
+
+    /// The config in force; empty without a [`config()`](Runner::config) file, or before it's
+    /// first loaded.
+    pub fn settings(&self) -> Arc<RunnerConfig> {
+        self.settings.borrow().clone()
+    }
+
+    /// The status of every pipeline, shared; it stays up to date while the runner runs.
+    pub fn statuses(&self) -> Statuses {
+        self.statuses.clone()
+    }
+
+    /// Every pipeline, with its name, types, source, sink & schedule; e.g., to export as JSON.
+    /// See [`catalog`](crate::catalog).
+    pub fn catalog(&self) -> Catalog {
+        let names = self.statuses.snapshot();
+        let pipelines = self
+            .pipes
+            .iter()
+            .zip(names)
+            .map(|(pipe, status)| Entry {
+                name: status.name,
+                pipe: pipe.pipe.describe(),
+                every_ms: u64::try_from(status.every.as_millis()).unwrap_or(u64::MAX),
+            })
+            .collect();
+        Catalog { pipelines }
+    }
+
+    /// Run every enabled pipeline on its schedule, starting straight away; only returns on an
+    /// invalid configuration, e.g., no pipelines at all.
+    pub async fn run(&self) -> Result<(), Error> {
+        self.validate()?;
+        // the config file as last read, to reload it only when it changes
+        let mut last = match &self.config {
+            Some(path) => {
+                let text = std::fs::read_to_string(path)?;
+                self.load_config(&text)?;
+                Ok(text)
+            }
+            None => Ok(String::new()),
+        };
+
+        let schedules = (0..self.pipes.len()).map(|index| async move {
+            let mut changes = self.settings.subscribe();
+            let mut ran: Option<Instant> = None;
+            // the run in progress, if any; and whether another is queued behind it
+            let mut running: Option<BoxFuture<'_, Result<EtlReport, Error>>> = None;
+            let mut queued = false;
+            loop {
+                // rescheduled whenever the config changes
+                let (every, enabled) = self.schedule(index);
+                let next = ran.map_or_else(Instant::now, |ran| ran + every);
+                let finished = async {
+                    match running.as_mut() {
+                        Some(run) => run.await,
+                        None => std::future::pending().await,
+                    }
+                };
+                tokio::select! {
+                    _ = tokio::time::sleep_until(next), if enabled => {
+                        ran = Some(Instant::now());
+                        if running.is_none() {
+                            running = Some(Box::pin(self.run_pipe(index)));
+                            continue;
+                        }
+                        match self.overlapped(index, queued) {
+                            Overlap::Skip => {}
+                            Overlap::Queue => queued = true,
+                            Overlap::Cancel => running = Some(Box::pin(self.run_pipe(index))),
+                        }
+                    }
+                    _ = finished => {
+                        running = None;
+                        if std::mem::take(&mut queued) {
+                            running = Some(Box::pin(self.run_pipe(index)));
+                        }
+                    }
+                    _ = changes.changed() => {}
+                }
+            }
+        });
+        let reloads = async {
+            let Some(path) = &self.config else {
+                return;
+            };
+            let mut interval = tokio::time::interval(self.reload);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                let read = std::fs::read_to_string(path).map_err(|e| e.to_string());
+                if read == last {
+                    continue;
+                }
+                last = read;
+                let result = match &last {
+                    Ok(text) => self.load_config(text),
+                    Err(e) => Err(Error::Config(format!("the file can't be read: {e}"))),
+                };
+                if let Err(error) = result {
+                    eprintln!(
+                        "could not reload the runner config from `{}`, keeping the last one: {error}",
+                        path.display()
+                    );
+                }
+            }
+        };
+        futures::future::join(futures::future::join_all(schedules), reloads).await;
+        Ok(())
+    }
+
+    /// Run every enabled pipeline once, in order, regardless of its schedule; e.g., for a one-off
+    /// catch-up.
+    ///
+    /// A failing pipeline doesn't stop the rest; every failure is returned, tagged with its name.
+    pub async fn run_once(&self) -> Result<(), Errors> {
+        let mut errors = Errors::new();
+        if let Err(error) = self.prepare() {
+            errors.push(Context::None, error);
+            return Err(errors);
+        }
+        for index in 0..self.pipes.len() {
+            if !self.schedule(index).1 {
+                continue;
+            }
+            if let Err(error) = self.run_pipe(index).await {
+                errors.push(Context::Pipeline(self.statuses.name(index)), error);
+            }
+        }
+        errors.into_result()
+    }
+
+    /// [`run_once()`](Runner::run_once), in `transaction`: it's begun first, then committed if
+    /// every pipeline succeeded, or rolled back if any failed; so the tables of the pipelines
+    /// enlisted in it (see [`sink::Postgres::transaction()`]) are a consistent snapshot.
+    ///
+    /// Only the pipelines whose sinks are enlisted load within the transaction; the rest load as
+    /// usual, and aren't rolled back.
+    ///
+    /// [`sink::Postgres::transaction()`]: crate::sink::Postgres::transaction
+    pub async fn run_in_transaction(&self, transaction: &Transaction) -> Result<(), Errors> {
+        let mut errors = Errors::new();
+        if let Err(error) = transaction.begin().await {
+            errors.push(Context::None, error);
+            return Err(errors);
+        }
+        let ended = match self.run_once().await {
+            Ok(()) => transaction.commit().await,
+            Err(failed) => {
+                errors = failed;
+                transaction.rollback().await
+            }
+        };
+        if let Err(error) = ended {
+            errors.push(Context::None, error);
+        }
+        errors.into_result()
+    }
+
+    /// Run the pipeline named `name` once, now, recording the run like a scheduled one; even if
+    /// the config disables it. Returns [`Error::Config`] if there's no such pipeline.
+    pub async fn run_named(&self, name: &str) -> Result<EtlReport, Error> {
+        self.prepare()?;
+        let index = self.index(name)?;
+        self.run_pipe(index).await
+    }
+
+    /// The pipeline named `name`, if any; e.g., to run it by hand, without recording the run.
+    pub fn pipeline(&self, name: &str) -> Option<&dyn DynPipeline> {
+        let index = self.index(name).ok()?;
+        Some(&*self.pipes[index].pipe)
+    }
+
+    fn index(&self, name: &str) -> Result<usize, Error> {
+        self.statuses
+            .snapshot()
+            .iter()
+            .position(|status| status.name == name)
+            .ok_or_else(|| Error::Config(format!("no pipeline named `{name}`")))
+    }
+
+    // validate the pipelines, then apply the config file, if there is one
+    fn prepare(&self) -> Result<(), Error> {
+        self.validate()?;
+        match &self.config {
+            Some(path) => self.apply(RunnerConfig::from_file(path)?),
+            None => Ok(()),
+        }
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        if self.pipes.is_empty() {
+            return Err(Error::Config("the runner has no pipelines".into()));
+        }
+        if self.slots.limit == Some(0) {
+            return Err(Error::Config("the runner needs at least 1 slot".into()));
+        }
+        let names = self.statuses.snapshot();
+        for (i, status) in names.iter().enumerate() {
+            if names[..i].iter().any(|other| other.name == status.name) {
+                return Err(Error::Config(format!(
+                    "more than 1 pipeline named `{}`",
+                    status.name
+                )));
+            }
+        }
+        if let Some(name) = self
+            .priorities
+            .keys()
+            .find(|name| !names.iter().any(|status| &status.name == *name))
+        {
+            return Err(Error::Config(format!(
+                "no pipeline named `{name}` to prioritize"
+            )));
+        }
+        if let Some(name) = self
+            .overlaps
+            .keys()
+            .find(|name| !names.iter().any(|status| &status.name == *name))
+        {
+            return Err(Error::Config(format!(
+                "no pipeline named `{name}` to set the overlap of"
+            )));
+        }
+        for index in 0..self.pipes.len() {
+            let (priority, overlap) = (self.priority_of(index), self.overlap_of(index));
+            self.statuses.update(index, |status| {
+                status.priority = priority;
+                status.overlap = overlap;
+            });
+        }
+        Ok(())
+    }
+
+    fn load_config(&self, text: &str) -> Result<(), Error> {
+        self.apply(RunnerConfig::from_json(text)?)
+    }
+
+    // check `config` against the pipelines, then swap it in
+    fn apply(&self, config: RunnerConfig) -> Result<(), Error> {
+        let names = self.statuses.snapshot();
+        for (name, settings) in &config.pipelines {
+            let Some(index) = names.iter().position(|status| &status.name == name) else {
+                return Err(Error::Config(format!("no pipeline named `{name}`")));
+            };
+            if settings.every_secs == Some(0) {
+                return Err(Error::Config(format!("`{name}` can't run every 0 seconds")));
+            }
+            if settings.requests_per_second.is_some()
+                && self.pipes[index].pipe.rate_limit().is_none()
+            {
+                return Err(Error::Config(format!(
+                    "`{name}` has no rate limit to change; build it with one"
+                )));
+            }
+        }
+
+        // a pipe the config no longer limits goes back to the limit it was built with
+        for (index, status) in names.iter().enumerate() {
+            let Some(rate_limit) = self.pipes[index].pipe.rate_limit() else {
+                continue;
+            };
+            let settings = config.pipelines.get(&status.name);
+            match settings.and_then(|settings| settings.requests_per_second) {
+                Some(requests) => rate_limit.set(requests, Duration::from_secs(1)),
+                None => rate_limit.reset(),
+            }
+        }
+        self.settings.send_replace(Arc::new(config));
+        for index in 0..self.pipes.len() {
+            let (every, enabled) = self.schedule(index);
+            let (priority, overlap) = (self.priority_of(index), self.overlap_of(index));
+            self.statuses.update(index, |status| {
+                status.every = every;
+                status.enabled = enabled;
+                status.priority = priority;
+                status.overlap = overlap;
+            });
+        }
+        Ok(())
+    }
+
+    // Decide what to do with a run of the pipe at `index` that's due while its last is in
+    // progress (and maybe another `queued`), telling the observer of & counting the decision; a
+    // second run queued is skipped, so they can't pile up.
+    fn overlapped(&self, index: usize, queued: bool) -> Overlap {
+        let overlap = match self.overlap_of(index) {
+            Overlap::Queue if queued => Overlap::Skip,
+            overlap => overlap,
+        };
+        if let Some(observer) = &self.observer {
+            observer.on_event(&Event::Overlapped {
+                pipeline: &self.statuses.name(index),
+                overlap,
+            });
+        }
+        self.statuses.update(index, |status| match overlap {
+            Overlap::Skip => status.skipped += 1,
+            Overlap::Queue => status.delayed += 1,
+            Overlap::Cancel => {
+                status.queued = false;
+                status.running = false;
+                status.cancelled += 1;
+            }
+        });
+        overlap
+    }
+
+    // how often the pipe at `index` runs, and whether it runs at all, under the config in force
+    fn schedule(&self, index: usize) -> (Duration, bool) {
+        let settings = self.settings.borrow();
+        let name = self.statuses.name(index);
+        let settings = settings.pipelines.get(&name);
+        let every = settings
+            .and_then(|settings| settings.every_secs)
+            .map_or(self.pipes[index].every, Duration::from_secs);
+        let enabled = settings
+            .and_then(|settings| settings.enabled)
+            .unwrap_or(true);
+        (every, enabled)
+    }
+
+    // the priority of the pipe at `index`, under the config in force
+    fn priority_of(&self, index: usize) -> i32 {
+        let name = self.statuses.name(index);
+        let configured = self
+            .settings
+            .borrow()
+            .pipelines
+            .get(&name)
+            .and_then(|settings| settings.priority);
+        configured
+            .or_else(|| self.priorities.get(&name).copied())
+            .unwrap_or(0)
+    }
+
+    // what the pipe at `index` does when a run is due while its last is in progress, under the
+    // config in force
+    fn overlap_of(&self, index: usize) -> Overlap {
+        let name = self.statuses.name(index);
+        let configured = self
+            .settings
+            .borrow()
+            .pipelines
+            .get(&name)
+            .and_then(|settings| settings.overlap);
+        configured
+            .or_else(|| self.overlaps.get(&name).copied())
+            .unwrap_or_default()
+    }
+
+    // run the pipe at `index` once it has a slot, recording the run in its status
+    async fn run_pipe(&self, index: usize) -> Result<EtlReport, Error> {
+        self.statuses.update(index, |status| status.queued = true);
+        let _slot = self.slots.acquire(self.priority_of(index)).await;
+        let at = self.clock.now();
+        let started = Instant::now();
+        self.statuses.update(index, |status| {
+            status.queued = false;
+            status.running = true;
+        });
+        let run_id = clock::new_run_id(&*self.clock);
+        let result = clock::identified(|| run_id.clone(), self.pipes[index].pipe.run()).await;
+        let run = LastRun {
+            at,
+            duration: started.elapsed(),
+            ok: result.is_ok(),
+            rows_affected: result.as_ref().map_or(0, EtlReport::rows_affected),
+        };
+        if let Some(history) = &self.history {
+            let record = RunRecord {
+                pipeline: self.statuses.name(index),
+                run_id,
+                at,
+                duration_ms: u64::try_from(run.duration.as_millis()).unwrap_or(u64::MAX),
+                error: result.as_ref().err().map(Error::to_string),
+                report: result.as_ref().cloned().unwrap_or_default(),
+            };
+            if let Err(error) = history.save_boxed(&record).await {
+                eprintln!(
+                    "could not save the run of `{}` to its history: {error}",
+                    record.pipeline
+                );
+            }
+        }
+        self.statuses
+            .update(index, |status| status.record(run, result.as_ref().err()));
+        result
+    }
+}
+
+/// How one pipeline of a [`Runner`] has been doing.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Status {
+    pub name: String,
+    #[serde(rename = "every_ms", serialize_with = "millis")]
+    pub every: Duration,
+    /// Whether the pipeline runs at all; see [`Runner::config()`].
+    pub enabled: bool,
+    /// See [`Runner::priority()`].
+    pub priority: i32,
+    /// Whether a run is waiting for a slot; see [`Runner::concurrency()`].
+    pub queued: bool,
+    /// Whether a run is in progress.
+    pub running: bool,
+    /// See [`Runner::overlap()`].
+    pub overlap: Overlap,
+    pub runs: u64,
+    pub failures: u64,
+    /// Runs due while the last was in progress, that were skipped; see [`Runner::overlap()`].
+    pub skipped: u64,
+    /// Runs due while the last was in progress, that were queued behind it.
+    pub delayed: u64,
+    /// Runs in progress that were cancelled, for the run due.
+    pub cancelled: u64,
+    pub last_run: Option<LastRun>,
+    /// The latest errors, oldest first; at most [`RECENT_ERRORS`].
+    pub recent_errors: VecDeque<RunError>,
+}
+
+impl Status {
+    fn new(name: String, every: Duration) -> Self {
+        Status {
+            name,
+            every,
+            enabled: true,
+            priority: 0,
+            queued: false,
+            running: false,
+            overlap: Overlap::default(),
+            runs: 0,
+            failures: 0,
+            skipped: 0,
+            delayed: 0,
+            cancelled: 0,
+            last_run: None,
+            recent_errors: VecDeque::new(),
+        }
+    }
+
+    fn record(&mut self, run: LastRun, error: Option<&Error>) {
+        self.running = false;
+        self.runs += 1;
+        if let Some(error) = error {
+            self.failures += 1;
+            if self.recent_errors.len() == RECENT_ERRORS {
+                self.recent_errors.pop_front();
+            }
+            self.recent_errors.push_back(RunError {
+                at: run.at,
+                message: error.to_string(),
+            });
+        }
+        self.last_run = Some(run);
+    }
+}
+
+/// What a [`Runner`] does when a pipeline's run is due while its last is still in progress; see
+/// [`Runner::overlap()`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Overlap {
+    /// Skip the run due; the next is due an interval later.
+    Skip,
+    /// Start the run due once the last finishes; at most one is queued, any more are skipped.
+    #[default]
+    Queue,
+    /// Cancel the run in progress, dropping it where it is, and start the run due instead; e.g.,
+    /// for a pipeline where only the latest data matters. A cancelled run isn't recorded.
+    Cancel,
+}
+
+/// The latest run of a pipeline.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LastRun {
+    /// When it started.
+    pub at: DateTime<Utc>,
+    #[serde(rename = "duration_ms", serialize_with = "millis")]
+    pub duration: Duration,
+    pub ok: bool,
+    /// See [`EtlReport::rows_affected()`].
+    pub rows_affected: u64,
+}
+
+/// A failed run of a pipeline.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RunError {
+    /// When the run started.
+    pub at: DateTime<Utc>,
+    pub message: String,
+}
+
+fn millis<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(u64::try_from(duration.as_millis()).unwrap_or(u64::MAX))
+}
+
+/// The [`Status`] of every pipeline of a [`Runner`], in the order they were added.
+///
+/// Clones share the same statuses, so a handle can be given to, e.g., a monitoring task while
+/// the runner runs.
+#[derive(Debug, Clone, Default)]
+pub struct Statuses(Arc<Mutex<Vec<Status>>>);
+
+impl Statuses {
+    /// A copy of every status, as of now.
+    pub fn snapshot(&self) -> Vec<Status> {
+        self.lock().clone()
+    }
+
+    /// A copy of the status of pipeline `name`, as of now.
+    pub fn get(&self, name: &str) -> Option<Status> {
+        self.lock()
+            .iter()
+            .find(|status| status.name == name)
+            .cloned()
+    }
+
+    fn push(&self, status: Status) {
+        self.lock().push(status);
+    }
+
+    fn name(&self, index: usize) -> String {
+        self.lock()[index].name.clone()
+    }
+
+    fn update(&self, index: usize, f: impl FnOnce(&mut Status)) {
+        f(&mut self.lock()[index]);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Status>> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+// The runs allowed at once (unlimited, if `None`), & those waiting for a slot: the highest
+// priority first, then the longest waiting; but before either, any waiting past `starvation`.
+struct Slots {
+    limit: Option<usize>,
+    starvation: Duration,
+    queue: Mutex<Queue>,
+}
+
+#[derive(Default)]
+struct Queue {
+    running: usize,
+    waiting: Vec<Waiter>,
+    // the order the runs were queued in
+    queued: u64,
+}
+
+struct Waiter {
+    priority: i32,
+    since: Instant,
+    order: u64,
+    turn: oneshot::Sender<()>,
+}
+
+// a slot, freed for the next run when dropped
+struct Slot<'a>(&'a Slots);
+
+// a run waiting for its turn, which it hands on if dropped as it's given one
+struct Pending<'a> {
+    slots: &'a Slots,
+    turn: Option<oneshot::Receiver<()>>,
+}
+
+impl Slots {
+    async fn acquire(&self, priority: i32) -> Slot<'_> {
+        let turn = {
+            let mut queue = self.lock();
+            let free = self.limit.is_none_or(|limit| queue.running < limit);
+            if free && queue.waiting.is_empty() {
+                queue.running += 1;
+                return Slot(self);
+            }
+            let (tx, rx) = oneshot::channel();
+            let order = queue.queued;
+            queue.queued += 1;
+            queue.waiting.push(Waiter {
+                priority,
+                since: Instant::now(),
+                order,
+                turn: tx,
+            });
+            rx
+        };
+        let mut pending = Pending {
+            slots: self,
+            turn: Some(turn),
+        };
+        if let Some(turn) = pending.turn.as_mut() {
+            // the runner owns the senders, so they're only dropped with a turn given
+            let _ = turn.await;
+        }
+        pending.turn = None;
+        Slot(self)
+    }
+
+    // hand the slot of a finished run to the next waiting, if any
+    fn release(&self) {
+        let mut queue = self.lock();
+        while let Some(next) = self.next(&queue) {
+            let waiter = queue.waiting.remove(next);
+            if waiter.turn.send(()).is_ok() {
+                return;
+            }
+        }
+        queue.running = queue.running.saturating_sub(1);
+    }
+
+    fn next(&self, queue: &Queue) -> Option<usize> {
+        let waiting = queue.waiting.iter().enumerate();
+        let starved = waiting
+            .clone()
+            .filter(|(_, waiter)| waiter.since.elapsed() >= self.starvation)
+            .min_by_key(|(_, waiter)| waiter.order);
+        starved
+            .or_else(|| {
+                waiting.max_by_key(|(_, waiter)| (waiter.priority, std::cmp::Reverse(waiter.order)))
+            })
+            .map(|(i, _)| i)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Queue> {
+        self.queue
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Drop for Slot<'_> {
+    fn drop(&mut self) {
+        self.0.release();
+    }
+}
+
+impl Drop for Pending<'_> {
+    fn drop(&mut self) {
+        if let Some(mut turn) = self.turn.take() {
+            if turn.try_recv().is_ok() {
+                self.slots.release();
+            }
+        }
+    }
+}
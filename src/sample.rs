@@ -0,0 +1,89 @@
+//! Samples of an output's records, to load before committing to a full load; so a sink's
+//! credentials, schema & mapping are verified end to end in seconds, not hours in.
+//!
+//! ```rust,ignore
+//! let output = pipe.extran(url).await?;
+//! pipe.load_sample(&output, Sample::Random(100), conn, "prices").await?;
+//! // ... check the 100 rows in the table, then
+//! pipe.load(output, conn, "prices").await?;
+//! ```
+//!
+//! Only a JSON array is sampled; any other output is one record, and is sampled whole, unless the
+//! sample is of none: [`Error::Config`], rather than loading more than asked for.
+
+use super::{Error, Output};
+use serde_json::Value;
+use uuid::Uuid;
+
+/// Which of an output's records to load; see [`ETL::load_sample()`].
+///
+/// A number of records is a [`Sample::Head`].
+///
+/// [`ETL::load_sample()`]: crate::ETL::load_sample
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sample {
+    /// The first `n` records.
+    Head(usize),
+    /// `n` records chosen at random, each as likely as any other; in the order they're output.
+    Random(usize),
+}
+
+impl From<usize> for Sample {
+    fn from(n: usize) -> Self {
+        Sample::Head(n)
+    }
+}
+
+impl Sample {
+    /// The most records the sample keeps.
+    pub fn n(&self) -> usize {
+        match *self {
+            Sample::Head(n) | Sample::Random(n) => n,
+        }
+    }
+
+    /// The sample of `output`, as the same type; all of it, if it has no more than `n` records.
+    ///
+    /// [`Error::Config`] for a sample of 0 records of an output that isn't an array.
+    pub fn of<O: Output>(&self, output: &O) -> Result<O, Error> {
+        let records = match serde_json::to_value(output)? {
+            Value::Array(records) => records,
+            _ if self.n() == 0 => {
+                return Err(Error::Config(
+                    "a sample of 0 records can't be taken of an output that's one record".into(),
+                ))
+            }
+            record => return Ok(serde_json::from_value(record)?),
+        };
+        let sampled = match *self {
+            Sample::Head(n) => records.into_iter().take(n).collect(),
+            Sample::Random(n) => random(records, n),
+        };
+        Ok(serde_json::from_value(Value::Array(sampled))?)
+    }
+}
+
+// `n` of `records`, uniformly at random, in order; by selection sampling (Knuth's Algorithm S)
+fn random(records: Vec<Value>, n: usize) -> Vec<Value> {
+    let mut remaining = records.len();
+    let mut needed = n.min(remaining);
+    let mut sampled = Vec::with_capacity(needed);
+    for record in records {
+        if needed == 0 {
+            break;
+        }
+        // keep it with the chance `needed / remaining`
+        if (unit() * remaining as f64) < needed as f64 {
+            sampled.push(record);
+            needed -= 1;
+        }
+        remaining -= 1;
+    }
+    sampled
+}
+
+// a random number in [0, 1); from the low 53 bits of a v4 uuid, all random
+fn unit() -> f64 {
+    const BITS: u128 = (1 << 53) - 1;
+    (Uuid::new_v4().as_u128() & BITS) as f64 / (BITS + 1) as f64
+}
@@ -0,0 +1,1258 @@
+use super::clock::{self, Clock};
+use super::error::{Context, Errors};
+use super::{db::*, Error, Outcome, WireLog};
+use super::{fs, journal, memory, time, Tabular};
+use futures::future::BoxFuture;
+use serde::Serialize;
+use serde_json::value::RawValue;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// A destination for output type `O`.
+///
+/// Configured on a pipe with [`PipeBuilder::sink()`], and used by [`Pipe::run()`] in place
+/// of [`ETL::load()`].
+///
+/// [`PipeBuilder::sink()`]: crate::PipeBuilder::sink
+/// [`Pipe::run()`]: crate::Pipe::run
+/// [`ETL::load()`]: crate::ETL::load
+pub trait Sink<O: ?Sized>: Send + Sync {
+    /// Load `output` to the destination, reporting what was written.
+    fn load(&self, output: &O) -> impl Future<Output = Result<Outcome, Error>> + Send;
+}
+
+// `Sink` returns `impl Future`, so it cannot be boxed as-is; pipes store sinks through this instead.
+pub(crate) trait DynSink<O: ?Sized>: Send + Sync {
+    fn load_boxed<'a>(&'a self, output: &'a O) -> BoxFuture<'a, Result<Outcome, Error>>;
+    // the sink's type, for a catalog
+    fn type_name(&self) -> &'static str;
+}
+
+impl<O, S> DynSink<O> for S
+where
+    O: ?Sized,
+    S: Sink<O>,
+{
+    fn load_boxed<'a>(&'a self, output: &'a O) -> BoxFuture<'a, Result<Outcome, Error>> {
+        Box::pin(self.load(output))
+    }
+
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<S>()
+    }
+}
+
+// shared sinks, e.g., a `Breaker` that is also monitored
+impl<O, S> Sink<O> for Arc<S>
+where
+    O: ?Sized,
+    S: Sink<O> + ?Sized,
+{
+    fn load(&self, output: &O) -> impl Future<Output = Result<Outcome, Error>> + Send {
+        (**self).load(output)
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+// collision
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// What a [`CouchDb`] or [`File`] sink does with the document (or file) an earlier load wrote.
+///
+/// ```rust,ignore
+/// // every run is kept, as `prices@2024-06-01T12:00:00Z`; the last also as `prices@latest`
+/// let sink = sink::CouchDb::new(conn, "prices").collision(Collision::Versioned(clock::system()));
+/// ```
+#[derive(Clone, Default)]
+pub enum Collision {
+    /// Replace it.
+    #[default]
+    Overwrite,
+    /// Keep it; each load is written to a new version, `<doc_id>@<time>` (to the second, from the
+    /// clock), and copied to `<doc_id>@latest`, so earlier outputs can still be read back.
+    Versioned(Arc<dyn Clock>),
+}
+
+/// The version [`Collision::Versioned`] copies every load to.
+pub const LATEST: &str = "latest";
+
+impl Collision {
+    /// The ids to write a load of `doc_id` to: the version first, then the alias.
+    pub fn ids(&self, doc_id: &str) -> Vec<String> {
+        match self {
+            Collision::Overwrite => vec![doc_id.to_string()],
+            Collision::Versioned(clock) => {
+                let at = clock.now().format("%Y-%m-%dT%H:%M:%SZ");
+                vec![
+                    version_id(doc_id, &at.to_string()),
+                    version_id(doc_id, LATEST),
+                ]
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for Collision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Collision::Overwrite => write!(f, "Overwrite"),
+            Collision::Versioned(_) => write!(f, "Versioned(..)"),
+        }
+    }
+}
+
+impl PartialEq for Collision {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Collision::Overwrite, Collision::Overwrite) => true,
+            (Collision::Versioned(a), Collision::Versioned(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+/// The id of `version` of `doc_id`, e.g., `prices@2024-06-01T12:00:00Z`, or `prices@latest`.
+pub fn version_id(doc_id: &str, version: &str) -> String {
+    format!("{doc_id}@{version}")
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+// couch
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Loads the output as a single CouchDB document; see [`couchdb::upsert_doc()`].
+///
+/// A long array output can be split across several documents instead, with [`layout()`].
+///
+/// [`layout()`]: CouchDb::layout
+#[derive(Debug, Clone, PartialEq)]
+pub struct CouchDb {
+    pub conn: String,
+    pub doc_id: String,
+    pub wire_log: Option<WireLog>,
+    pub conflict_retries: u32,
+    pub layout: couchdb::Layout,
+    pub collision: Collision,
+}
+
+impl CouchDb {
+    pub fn new(conn: impl Into<String>, doc_id: impl Into<String>) -> Self {
+        CouchDb {
+            conn: conn.into(),
+            doc_id: doc_id.into(),
+            wire_log: None,
+            conflict_retries: 3,
+            layout: couchdb::Layout::Single,
+            collision: Collision::Overwrite,
+        }
+    }
+
+    /// Keep, or replace, the document of an earlier load; see [`Collision`]. Defaults to
+    /// [`Collision::Overwrite`].
+    pub fn collision(mut self, collision: Collision) -> Self {
+        self.collision = collision;
+        self
+    }
+
+    /// How to lay out the output; e.g., `Layout::Chunked { records: 1000 }` for outputs too
+    /// large for one document. Defaults to [`Layout::Single`].
+    ///
+    /// [`Layout::Single`]: couchdb::Layout::Single
+    pub fn layout(mut self, layout: couchdb::Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// How many times to retry a write that conflicts with a concurrent one. Defaults to 3.
+    pub fn conflict_retries(mut self, retries: u32) -> Self {
+        self.conflict_retries = retries;
+        self
+    }
+
+    /// Log every request & response (redacted) to stderr, for debugging.
+    pub fn wire_log(mut self, wire_log: WireLog) -> Self {
+        self.wire_log = Some(wire_log);
+        self
+    }
+}
+
+impl<O> Sink<O> for CouchDb
+where
+    O: serde::Serialize + Sync + ?Sized,
+{
+    async fn load(&self, output: &O) -> Result<Outcome, Error> {
+        // the version's outcome is the load's; the alias is just a copy
+        let mut outcome = None;
+        for doc_id in self.collision.ids(&self.doc_id) {
+            let written = self.write(output, &doc_id).await?;
+            outcome.get_or_insert(written);
+        }
+        outcome.ok_or_else(|| Error::Invariant(format!("no document ids for `{}`", self.doc_id)))
+    }
+}
+
+impl CouchDb {
+    async fn write<O>(&self, output: &O, doc_id: &str) -> Result<Outcome, Error>
+    where
+        O: serde::Serialize + Sync + ?Sized,
+    {
+        match self.layout {
+            couchdb::Layout::Single => {
+                let doc = couchdb::upsert_doc(
+                    output,
+                    &self.conn,
+                    doc_id,
+                    self.conflict_retries,
+                    self.wire_log.as_ref(),
+                )
+                .await?;
+                Ok(Outcome::Couch(doc))
+            }
+            couchdb::Layout::Chunked { records } => {
+                let docs = couchdb::upsert_chunked(
+                    output,
+                    &self.conn,
+                    doc_id,
+                    records,
+                    self.conflict_retries,
+                    self.wire_log.as_ref(),
+                )
+                .await?;
+                Ok(Outcome::Batches(
+                    docs.into_iter().map(Outcome::Couch).collect(),
+                ))
+            }
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+// postgres
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Inserts the output as rows of a PostgreSQL table; see [`postgresql::insert_doc()`].
+///
+/// ```rust,ignore
+/// let sink = sink::Postgres::new("host=localhost user=postgres password=password", "prices")
+///     .on_conflict(["symbol", "date"], OnConflict::Update);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Postgres {
+    pub conn: String,
+    pub table: String,
+    pub conflict: Option<postgresql::Conflict>,
+    pub create: Option<types::Conversions>,
+    pub drift: Option<postgresql::OnDrift>,
+    pub transaction: Option<postgresql::Transaction>,
+    pub preflight: Option<postgresql::Preflight>,
+}
+
+impl Postgres {
+    pub fn new(conn: impl Into<String>, table: impl Into<String>) -> Self {
+        Postgres {
+            conn: conn.into(),
+            table: table.into(),
+            conflict: None,
+            create: None,
+            drift: None,
+            transaction: None,
+            preflight: None,
+        }
+    }
+
+    /// Estimate each load before it's written, refusing one over `preflight`'s thresholds unless
+    /// confirmed; see [`postgresql::Preflight`]. An output loaded in chunks (see
+    /// [`memory`](crate::memory)) is estimated once, as a whole, before its first chunk.
+    pub fn preflight(mut self, preflight: postgresql::Preflight) -> Self {
+        self.preflight = Some(preflight);
+        self
+    }
+
+    /// What loading `output` would write; see [`postgresql::estimate()`].
+    pub async fn estimate<O>(&self, output: &O) -> Result<postgresql::Estimate, Error>
+    where
+        O: serde::Serialize + ?Sized,
+    {
+        postgresql::estimate(output, &self.conn, &self.table).await
+    }
+
+    /// Insert rows through `transaction` while it's open, rather than committing each load on its
+    /// own; see [`postgresql::Transaction`].
+    pub fn transaction(mut self, transaction: &postgresql::Transaction) -> Self {
+        self.transaction = Some(transaction.clone());
+        self
+    }
+
+    /// Skip or update rows that collide on the natural `key`, instead of failing the load.
+    pub fn on_conflict<K>(mut self, key: K, action: postgresql::OnConflict) -> Self
+    where
+        K: IntoIterator,
+        K::Item: Into<String>,
+    {
+        self.conflict = Some(postgresql::Conflict::new(key, action));
+        self
+    }
+
+    /// Create the table before loading, if it doesn't exist yet, with column types from `types`.
+    pub fn create_table(mut self, types: types::Conversions) -> Self {
+        self.create = Some(types);
+        self
+    }
+
+    /// Check the output against the table's columns before loading; see [`postgresql::reconcile()`].
+    ///
+    /// Added columns take their types from [`create_table()`], or else PostgreSQL's defaults.
+    ///
+    /// [`create_table()`]: Postgres::create_table
+    pub fn on_drift(mut self, on_drift: postgresql::OnDrift) -> Self {
+        self.drift = Some(on_drift);
+        self
+    }
+
+    // The columns to insert, once any drift is dealt with; `None` for every field.
+    pub(crate) async fn reconcile<O>(&self, output: &O) -> Result<Option<Vec<String>>, Error>
+    where
+        O: serde::Serialize + ?Sized,
+    {
+        let Some(on_drift) = self.drift else {
+            return Ok(None);
+        };
+        let types = self
+            .create
+            .clone()
+            .unwrap_or_else(types::Conversions::postgres);
+        let columns =
+            postgresql::reconcile(output, &self.conn, &self.table, on_drift, &types).await?;
+        Ok(Some(columns))
+    }
+}
+
+impl<O> Sink<O> for Postgres
+where
+    O: serde::Serialize + Sync + ?Sized,
+{
+    async fn load(&self, output: &O) -> Result<Outcome, Error> {
+        if let Some(preflight) = &self.preflight {
+            match memory::whole() {
+                None => preflight.check(self.estimate(output).await?)?,
+                // a chunk of a larger output, which is checked as a whole, before its first
+                Some(Some(whole)) => {
+                    let whole: &RawValue = serde_json::from_str(&whole)?;
+                    preflight.check(self.estimate(whole).await?)?;
+                    memory::checked();
+                }
+                Some(None) => {}
+            }
+        }
+        if let Some(types) = &self.create {
+            postgresql::create_table(output, &self.conn, &self.table, types).await?;
+        }
+        let columns = self.reconcile(output).await?;
+        if let Some(transaction) = &self.transaction {
+            if transaction.conn() != self.conn {
+                return Err(Error::Config(format!(
+                    "`{}` is enlisted in a transaction on another database",
+                    self.table
+                )));
+            }
+            let inserted = transaction
+                .insert(
+                    output,
+                    &self.table,
+                    columns.as_deref(),
+                    self.conflict.as_ref(),
+                )
+                .await?;
+            if let Some(rows_affected) = inserted {
+                return Ok(Outcome::Pg(postgresql::PgOutcome { rows_affected }));
+            }
+        }
+        let rows_affected = postgresql::insert_columns(
+            output,
+            &self.conn,
+            &self.table,
+            columns.as_deref(),
+            self.conflict.as_ref(),
+        )
+        .await?;
+        Ok(Outcome::Pg(postgresql::PgOutcome { rows_affected }))
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+// scylla
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Inserts the output as rows of a ScyllaDB table, in parallel; see [`scylladb::insert_batched()`].
+///
+/// Rows are grouped by `partition_key` into unlogged batches, so each batch is written to a
+/// single partition (and its replicas), rather than coordinated across the cluster.
+///
+/// ```rust,ignore
+/// let sink = sink::Scylla::new("127.0.0.1:9042", "ks.prices", ["symbol"])
+///     .batch_size(100)
+///     .parallelism(16);
+/// ```
+#[derive(Debug)]
+pub struct Scylla {
+    pub nodes: String,
+    pub table: String,
+    pub partition_key: Vec<String>,
+    pub batch_size: usize,
+    pub parallelism: usize,
+    // connected on the first load, then reused
+    session: tokio::sync::OnceCell<scylla::Session>,
+}
+
+impl Scylla {
+    /// Loads to `table` (i.e., `keyspace.table`), whose partition key is the `partition_key` columns.
+    pub fn new<K>(nodes: impl Into<String>, table: impl Into<String>, partition_key: K) -> Self
+    where
+        K: IntoIterator,
+        K::Item: Into<String>,
+    {
+        Scylla {
+            nodes: nodes.into(),
+            table: table.into(),
+            partition_key: partition_key.into_iter().map(Into::into).collect(),
+            batch_size: 50,
+            parallelism: 8,
+            session: tokio::sync::OnceCell::new(),
+        }
+    }
+
+    /// The most rows in one batch. Defaults to 50; large batches are slow for a coordinator to apply.
+    pub fn batch_size(mut self, size: usize) -> Self {
+        self.batch_size = size;
+        self
+    }
+
+    /// The most batches in flight at once. Defaults to 8.
+    pub fn parallelism(mut self, parallelism: usize) -> Self {
+        self.parallelism = parallelism;
+        self
+    }
+}
+
+impl<O> Sink<O> for Scylla
+where
+    O: serde::Serialize + Sync + ?Sized,
+{
+    async fn load(&self, output: &O) -> Result<Outcome, Error> {
+        let session = self
+            .session
+            .get_or_try_init(|| scylladb::connect(&self.nodes))
+            .await?;
+        let partition_key = self
+            .partition_key
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        let rows = scylladb::insert_batched(
+            output,
+            session,
+            &self.table,
+            &partition_key,
+            self.batch_size,
+            self.parallelism,
+        )
+        .await?;
+        Ok(Outcome::Scylla { rows })
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+// http
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// POSTs the output as JSON to a plain HTTP API.
+///
+/// Every request carries an `Idempotency-Key` header, derived from the [run's id] and the payload, so
+/// a retried request (e.g., after its response was lost) can be recognised downstream as a replay;
+/// or, for a journaled output, its key in the journal, so a replay after a crash is too (see
+/// [`journal`](crate::journal)). Payloads that were already accepted under the same key aren't
+/// sent again at all.
+///
+/// ```rust,ignore
+/// let sink = sink::Http::new("https://example.com/api/prices").run_id("2024-06-01");
+/// ```
+///
+/// [run's id]: crate::clock::run_id
+#[derive(Debug)]
+pub struct Http {
+    pub url: String,
+    /// The id keys derive from, if given one; otherwise, that of the run in progress, or outside
+    /// of one, the sink's own.
+    pub run_id: Option<String>,
+    pub wire_log: Option<WireLog>,
+    // the id keys derive from outside of a run, without a `run_id`
+    own_id: String,
+    client: reqwest::Client,
+    // keys of the requests that have already been accepted
+    sent: Mutex<HashSet<String>>,
+}
+
+impl Http {
+    /// A sink keyed by the id of each run; outside of one, by a fresh id of its own, from the
+    /// system [`Clock`].
+    ///
+    /// [`Clock`]: crate::clock::Clock
+    pub fn new(url: impl Into<String>) -> Self {
+        Http {
+            url: url.into(),
+            run_id: None,
+            wire_log: None,
+            own_id: clock::System.uuid().to_string(),
+            client: reqwest::Client::new(),
+            sent: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Take the sink's own id from `clock` instead, e.g., a [`Fixed`] clock in tests.
+    ///
+    /// [`Fixed`]: crate::clock::Fixed
+    pub fn clock(mut self, clock: &dyn Clock) -> Self {
+        self.own_id = clock.uuid().to_string();
+        self
+    }
+
+    /// Derive keys from `run_id` instead, in every run; re-running with the same id replays the
+    /// same keys, so the downstream service can discard what it already has.
+    pub fn run_id(mut self, run_id: impl Into<String>) -> Self {
+        self.run_id = Some(run_id.into());
+        self
+    }
+
+    /// Log every request & response (redacted) to stderr, for debugging.
+    pub fn wire_log(mut self, wire_log: WireLog) -> Self {
+        self.wire_log = Some(wire_log);
+        self
+    }
+
+    /// The `Idempotency-Key` for `payload`: a stable hash of the run id & payload, in hex.
+    pub fn idempotency_key(&self, payload: &[u8]) -> String {
+        let run_id = self.run_id.clone().or_else(clock::run_id);
+        let run_id = run_id.as_deref().unwrap_or(&self.own_id);
+        content_hash(&[run_id.as_bytes(), &[0], payload])
+    }
+}
+
+impl<O> Sink<O> for Http
+where
+    O: serde::Serialize + Sync + ?Sized,
+{
+    async fn load(&self, output: &O) -> Result<Outcome, Error> {
+        let payload = serde_json::to_vec(output)?;
+        let key = journal::idempotency_key().unwrap_or_else(|| self.idempotency_key(&payload));
+        if self
+            .sent
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .contains(&key)
+        {
+            return Ok(Outcome::Http { status: None });
+        }
+
+        let request = self
+            .client
+            .post(&self.url)
+            .header("Content-Type", "application/json")
+            .header("Idempotency-Key", &key)
+            .body(payload)
+            .build()?;
+        if let Some(wire_log) = &self.wire_log {
+            wire_log.request(&request);
+        }
+        let response = self.client.execute(request).await?;
+        if let Some(wire_log) = &self.wire_log {
+            wire_log.response(&response);
+        }
+        let status = response.error_for_status()?.status();
+
+        self.sent
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(key);
+        Ok(Outcome::Http {
+            status: Some(status.as_u16()),
+        })
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+// file
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Writes the output to a JSON file, replacing any previous contents; or, with
+/// [`Collision::Versioned`], to a new file per load, e.g., `prices@2024-06-01T12%3A00%3A00Z.json`
+/// beside `prices@latest.json`, the ids escaped as by [`fs::file_name()`].
+///
+/// Every file is replaced whole, never left half written; see [`fs::write_atomic()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct File {
+    pub path: PathBuf,
+    pub collision: Collision,
+}
+
+impl File {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        File {
+            path: path.into(),
+            collision: Collision::Overwrite,
+        }
+    }
+
+    /// Keep, or replace, the file of an earlier load; see [`Collision`]. Defaults to
+    /// [`Collision::Overwrite`].
+    pub fn collision(mut self, collision: Collision) -> Self {
+        self.collision = collision;
+        self
+    }
+
+    // The files to write a load to; the version's id goes before the extension.
+    fn paths(&self) -> Vec<PathBuf> {
+        if self.collision == Collision::Overwrite {
+            return vec![self.path.clone()];
+        }
+        let stem = self.path.file_stem().unwrap_or_default().to_string_lossy();
+        self.collision
+            .ids(&stem)
+            .into_iter()
+            .map(|id| match self.path.extension() {
+                Some(ext) => self.path.with_file_name(format!(
+                    "{}.{}",
+                    fs::file_name(&id),
+                    ext.to_string_lossy()
+                )),
+                None => self.path.with_file_name(fs::file_name(&id)),
+            })
+            .collect()
+    }
+}
+
+impl<O> Sink<O> for File
+where
+    O: serde::Serialize + Sync + ?Sized,
+{
+    async fn load(&self, output: &O) -> Result<Outcome, Error> {
+        let bytes = serde_json::to_vec_pretty(output)?;
+        for path in self.paths() {
+            fs::write_atomic(path, &bytes)?;
+        }
+        Ok(Outcome::File {
+            bytes: bytes.len() as u64,
+        })
+    }
+}
+
+impl RawSink for File {
+    async fn load_bytes(&self, bytes: &[u8]) -> Result<(), Error> {
+        for path in self.paths() {
+            fs::write_atomic(path, bytes)?;
+        }
+        Ok(())
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+// csv
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Writes an array output as CSV, a row per record, with the header taken from the first
+/// record's field names; e.g., for analysts who'd rather open a spreadsheet than query a table.
+///
+/// Records are structs (or tuples) of scalar fields, in field order; nested values can't be
+/// written as CSV, so the records must be [`Tabular`], checked as the pipe compiles.
+///
+/// ```rust,ignore
+/// let sink = sink::Csv::from_url("file:///data/prices.csv")?
+///     .dialect(Dialect { delimiter: ';', bom: true, ..Default::default() })
+///     .append(true);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Csv {
+    pub path: PathBuf,
+    pub dialect: Dialect,
+    pub append: bool,
+}
+
+/// How a [`Csv`] sink writes its files; deserializable, e.g., from a pipeline's configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Dialect {
+    /// Separates fields; must be ASCII. Defaults to `,`.
+    pub delimiter: char,
+    pub quoting: Quoting,
+    /// Write a header row (unless appending to a file that already has rows). Defaults to `true`.
+    pub header: bool,
+    /// Start the file with a UTF-8 byte order mark, for spreadsheets that need one to detect the
+    /// encoding (e.g., Excel). Defaults to `false`.
+    pub bom: bool,
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Dialect {
+            delimiter: ',',
+            quoting: Quoting::Necessary,
+            header: true,
+            bom: false,
+        }
+    }
+}
+
+/// Which fields of a [`Csv`] file are quoted; quotes within them are always escaped, by doubling.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Quoting {
+    /// Only those containing a delimiter, quote, or line break.
+    #[default]
+    Necessary,
+    Always,
+    /// Every field that isn't a number.
+    NonNumeric,
+}
+
+impl Csv {
+    /// Truncate & write the file at `path`, with the default [`Dialect`].
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Csv {
+            path: path.into(),
+            dialect: Dialect::default(),
+            append: false,
+        }
+    }
+
+    /// A sink for a `file://` URL ending in `.csv`, e.g., `file:///data/prices.csv`.
+    ///
+    /// Returns [`Error::Config`] for any other URL.
+    pub fn from_url(url: &str) -> Result<Self, Error> {
+        match url.strip_prefix("file://") {
+            Some(path) if path.ends_with(".csv") => Ok(Self::new(path)),
+            _ => Err(Error::Config(format!(
+                "expected a file://...csv URL for a CSV sink, not {url:?}"
+            ))),
+        }
+    }
+
+    pub fn dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Add rows to the end of the file, rather than replacing it. Defaults to `false`.
+    pub fn append(mut self, append: bool) -> Self {
+        self.append = append;
+        self
+    }
+
+    fn write<T: Serialize>(&self, records: &[T]) -> Result<Vec<u8>, Error> {
+        let delimiter = u8::try_from(self.dialect.delimiter)
+            .ok()
+            .filter(u8::is_ascii)
+            .ok_or_else(|| {
+                Error::Config(format!(
+                    "CSV delimiter {:?} is not ASCII",
+                    self.dialect.delimiter
+                ))
+            })?;
+        // a file that already has rows has its header, and its byte order mark
+        let fresh = !self.append || std::fs::metadata(&self.path).map_or(true, |m| m.len() == 0);
+        let mut bytes = vec![];
+        if fresh && self.dialect.bom {
+            bytes.extend_from_slice("\u{feff}".as_bytes());
+        }
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(delimiter)
+            .quote_style(match self.dialect.quoting {
+                Quoting::Necessary => csv::QuoteStyle::Necessary,
+                Quoting::Always => csv::QuoteStyle::Always,
+                Quoting::NonNumeric => csv::QuoteStyle::NonNumeric,
+            })
+            .has_headers(fresh && self.dialect.header)
+            .from_writer(bytes);
+        for record in records {
+            writer.serialize(record)?;
+        }
+        writer.into_inner().map_err(|e| Error::IO(e.into_error()))
+    }
+}
+
+impl<T> Sink<Vec<T>> for Csv
+where
+    T: Tabular + Sync,
+{
+    async fn load(&self, output: &Vec<T>) -> Result<Outcome, Error> {
+        use std::io::Write;
+
+        let bytes = self.write(output)?;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(self.append)
+            .truncate(!self.append)
+            .open(&self.path)?;
+        file.write_all(&bytes)?;
+        Ok(Outcome::File {
+            bytes: bytes.len() as u64,
+        })
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+// hive
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Writes records as gzipped NDJSON into Hive-style partitions, a directory per value of each
+/// partition column: e.g., `<dir>/dt=2024-06-01/symbol=NVDA/part-0001.ndjson.gz`; so Spark,
+/// Athena & co. can read the output directly, pruning partitions by their columns.
+///
+/// ```rust,ignore
+/// let sink = sink::Hive::new("s3-mirror/prices").date("dt", "timestamp").key("symbol");
+/// ```
+///
+/// Each load adds a part file to every partition it has records for, numbered after the highest
+/// already there; loads running at once each take a number of their own. A [`key()`](Hive::key) column is taken out of the records, as the query engine
+/// reads it from the path; a missing or null value goes to `__HIVE_DEFAULT_PARTITION__`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hive {
+    pub dir: PathBuf,
+    pub columns: Vec<HiveColumn>,
+    pub level: u32,
+}
+
+/// A partition column of a [`Hive`] sink.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HiveColumn {
+    /// The record's `field`, as is.
+    Key(String),
+    /// The (UTC) date of the record's `field`, an ISO 8601 date/time or epoch seconds.
+    Date { column: String, field: String },
+}
+
+/// Where Hive puts records without a value for a partition column.
+pub const HIVE_DEFAULT_PARTITION: &str = "__HIVE_DEFAULT_PARTITION__";
+
+impl Hive {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Hive {
+            dir: dir.into(),
+            columns: vec![],
+            level: flate2::Compression::default().level(),
+        }
+    }
+
+    /// Partition by the value of `field`, in a directory per value named `<field>=<value>`.
+    pub fn key(mut self, field: impl Into<String>) -> Self {
+        self.columns.push(HiveColumn::Key(field.into()));
+        self
+    }
+
+    /// Partition by the date of `field`, in a directory per day named `<column>=2024-06-01`; the
+    /// field is kept in the records.
+    pub fn date(mut self, column: impl Into<String>, field: impl Into<String>) -> Self {
+        self.columns.push(HiveColumn::Date {
+            column: column.into(),
+            field: field.into(),
+        });
+        self
+    }
+
+    /// The gzip compression level, from 0 (none) to 9 (best). Defaults to 6.
+    pub fn level(mut self, level: u32) -> Self {
+        self.level = level.min(9);
+        self
+    }
+
+    // The partition directory of `record`, relative to `dir`, taking out its key columns.
+    fn partition(&self, record: &mut serde_json::Map<String, Value>) -> Result<PathBuf, Error> {
+        let mut partition = PathBuf::new();
+        for column in &self.columns {
+            let (column, value) = match column {
+                HiveColumn::Key(field) => (field, record.remove(field).and_then(hive_value)),
+                HiveColumn::Date { column, field } => {
+                    let date = match record.get(field) {
+                        Some(Value::String(date)) => Some(time::iso8601(date)?.to_utc()),
+                        Some(Value::Number(seconds)) => match seconds.as_i64() {
+                            Some(seconds) => Some(time::epoch_seconds(seconds)?),
+                            None => {
+                                return Err(Error::Time(format!("not epoch seconds: {seconds}")))
+                            }
+                        },
+                        _ => None,
+                    };
+                    (column, date.map(|date| date.format("%Y-%m-%d").to_string()))
+                }
+            };
+            let value = value.unwrap_or_else(|| HIVE_DEFAULT_PARTITION.to_string());
+            partition.push(format!("{}={}", hive_escape(column), hive_escape(&value)));
+        }
+        Ok(partition)
+    }
+}
+
+// A partition value; none for null, which Hive has its default partition for.
+fn hive_value(value: Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(value) => Some(value),
+        value => Some(value.to_string()),
+    }
+}
+
+// A path segment's name or value, escaped as a file name (and `=`, which separates them).
+fn hive_escape(name: &str) -> String {
+    fs::file_name(name).replace('=', "%3D")
+}
+
+// Write `bytes` as the next part file in `dir`: numbered after the highest already there (so a
+// gap in the numbering isn't filled), and created new, so a concurrent load that took the same
+// number first isn't overwritten; this one takes the number after it instead.
+fn write_part(dir: &std::path::Path, bytes: &[u8]) -> Result<PathBuf, Error> {
+    use std::io::Write;
+
+    let mut number = last_part(dir)? + 1;
+    loop {
+        let part = dir.join(format!("part-{number:04}.ndjson.gz"));
+        let mut file = match std::fs::File::options()
+            .write(true)
+            .create_new(true)
+            .open(&part)
+        {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                number += 1;
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if let Err(e) = file.write_all(bytes).and_then(|()| file.sync_all()) {
+            let _ = std::fs::remove_file(&part);
+            return Err(e.into());
+        }
+        return Ok(part);
+    }
+}
+
+// The highest number of the part files in `dir`; 0 if it has none.
+fn last_part(dir: &std::path::Path) -> Result<usize, Error> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            name.strip_prefix("part-")?.split('.').next()?.parse().ok()
+        })
+        .max()
+        .unwrap_or(0))
+}
+
+impl<O> Sink<O> for Hive
+where
+    O: Serialize + Sync + ?Sized,
+{
+    async fn load(&self, output: &O) -> Result<Outcome, Error> {
+        use std::io::Write;
+
+        let records = match serde_json::to_value(output)? {
+            Value::Array(records) => records,
+            record => vec![record],
+        };
+        let mut partitions: BTreeMap<PathBuf, Vec<u8>> = BTreeMap::new();
+        for record in records {
+            let Value::Object(mut record) = record else {
+                return Err(Error::Config(
+                    "Hive partitions hold records: objects, or arrays of them".into(),
+                ));
+            };
+            let lines = partitions.entry(self.partition(&mut record)?).or_default();
+            serde_json::to_writer(&mut *lines, &record)?;
+            lines.push(b'\n');
+        }
+
+        let mut outcomes = BTreeMap::new();
+        for (partition, lines) in partitions {
+            let dir = self.dir.join(&partition);
+            std::fs::create_dir_all(&dir)?;
+            let mut encoder =
+                flate2::write::GzEncoder::new(vec![], flate2::Compression::new(self.level));
+            encoder.write_all(&lines)?;
+            let bytes = encoder.finish()?;
+            let part = write_part(&dir, &bytes)?;
+            let name = part.strip_prefix(&self.dir).unwrap_or(&part);
+            outcomes.insert(
+                name.to_string_lossy().replace('\\', "/"),
+                Outcome::File {
+                    bytes: bytes.len() as u64,
+                },
+            );
+        }
+        Ok(Outcome::Routes(outcomes))
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+// serialized
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// A destination that takes the output already serialized, in whatever format it was given.
+///
+/// Any raw sink can be turned into a [`Sink`] with [`with_serializer()`].
+///
+/// [`with_serializer()`]: RawSink::with_serializer
+pub trait RawSink: Send + Sync {
+    /// Load the serialized output to the destination.
+    fn load_bytes(&self, bytes: &[u8]) -> impl Future<Output = Result<(), Error>> + Send;
+
+    /// Serialize outputs with `serializer`, instead of as JSON.
+    ///
+    /// ```rust,ignore
+    /// let sink = sink::File::new("prices.csv").with_serializer(|rows: &Vec<Row>| {
+    ///     let mut csv = csv::WriterBuilder::new().quote_style(QuoteStyle::Always).from_writer(vec![]);
+    ///     rows.iter().for_each(|row| csv.serialize(row).unwrap());
+    ///     csv.into_inner().unwrap()
+    /// });
+    /// ```
+    fn with_serializer<O, F>(self, serializer: F) -> Serialized<Self, O>
+    where
+        Self: Sized,
+        O: ?Sized,
+        F: Fn(&O) -> Vec<u8> + Send + Sync + 'static,
+    {
+        Serialized {
+            sink: self,
+            serializer: Box::new(serializer),
+        }
+    }
+}
+
+type Serializer<O> = Box<dyn Fn(&O) -> Vec<u8> + Send + Sync>;
+
+/// A [`RawSink`] with a custom serializer; see [`RawSink::with_serializer()`].
+pub struct Serialized<S, O: ?Sized> {
+    pub sink: S,
+    serializer: Serializer<O>,
+}
+
+impl<S, O> Sink<O> for Serialized<S, O>
+where
+    S: RawSink,
+    O: Sync + ?Sized,
+{
+    async fn load(&self, output: &O) -> Result<Outcome, Error> {
+        let bytes = (self.serializer)(output);
+        self.sink.load_bytes(&bytes).await?;
+        Ok(Outcome::File {
+            bytes: bytes.len() as u64,
+        })
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+// batched
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Loads a `Vec` output in batches of `size` records, each batch loaded to `sink` as a slice.
+///
+/// Batches can also be sized by their payload, with [`max_bytes()`]: a batch is cut before the
+/// record that would take its serialized JSON over the limit; so batches of small records fill
+/// up, and batches of large ones stay under a CouchDB or HTTP body limit. A record over the
+/// limit by itself is loaded alone.
+///
+/// Every batch is attempted, even after an earlier one fails; the failures are returned together
+/// as [`Error::Many`], each with the range of records in its batch.
+///
+/// ```rust,ignore
+/// let sink = sink::Batched::new(sink::Postgres::new(conn, "prices"), 1000);
+/// // at most 5 MB per request, however many records that is
+/// let sink = sink::Batched::by_bytes(sink::Http::new(url), 5 * 1024 * 1024);
+/// ```
+///
+/// [`max_bytes()`]: Batched::max_bytes
+#[derive(Debug, Clone, PartialEq)]
+pub struct Batched<S> {
+    pub sink: S,
+    pub size: usize,
+    pub max_bytes: Option<usize>,
+}
+
+impl<S> Batched<S> {
+    pub fn new(sink: S, size: usize) -> Self {
+        Batched {
+            sink,
+            size: size.max(1),
+            max_bytes: None,
+        }
+    }
+
+    /// Batches of at most `max_bytes` of JSON each, of any number of records.
+    pub fn by_bytes(sink: S, max_bytes: usize) -> Self {
+        Batched::new(sink, usize::MAX).max_bytes(max_bytes)
+    }
+
+    /// Cut batches at `max_bytes` of JSON too, as well as at `size` records.
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes.max(1));
+        self
+    }
+
+    // the ranges of the batches of `output`
+    fn batches<T: Serialize>(&self, output: &[T]) -> Result<Vec<std::ops::Range<usize>>, Error> {
+        let Some(max_bytes) = self.max_bytes else {
+            return Ok((0..output.len())
+                .step_by(self.size)
+                .map(|start| start..output.len().min(start.saturating_add(self.size)))
+                .collect());
+        };
+        let mut batches = vec![];
+        let mut start = 0;
+        let mut bytes = 2; // `[]`
+        for (i, record) in output.iter().enumerate() {
+            let len = serde_json::to_vec(record)?.len();
+            // every record after a batch's first also takes a `,`
+            if i > start && (i - start == self.size || bytes + 1 + len > max_bytes) {
+                batches.push(start..i);
+                start = i;
+                bytes = 2;
+            }
+            bytes += len + usize::from(i > start);
+        }
+        if start < output.len() {
+            batches.push(start..output.len());
+        }
+        Ok(batches)
+    }
+}
+
+impl<T, S> Sink<Vec<T>> for Batched<S>
+where
+    T: Serialize + Sync,
+    S: Sink<[T]>,
+{
+    async fn load(&self, output: &Vec<T>) -> Result<Outcome, Error> {
+        let mut errors = Errors::new();
+        let mut outcomes = vec![];
+        for batch in self.batches(output)? {
+            match self.sink.load(&output[batch.clone()]).await {
+                Ok(outcome) => outcomes.push(outcome),
+                Err(error) => errors.push(
+                    Context::Records {
+                        start: batch.start,
+                        end: batch.end,
+                    },
+                    error,
+                ),
+            }
+        }
+        errors.into_result()?;
+        Ok(Outcome::Batches(outcomes))
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+// router
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Loads each record of a `Vec` output to one of several sinks, by the name `route` gives it;
+/// e.g., records that fail a check to a quarantine table, and the rest to the main one.
+///
+/// Each route's records keep their order, and are loaded together, in one load per route (in the
+/// order the routes were added); routes with no records aren't loaded. Every route is attempted,
+/// even after an earlier one fails; the failures are returned together as [`Error::Many`], each
+/// with its route. A record routed to a name with no sink fails the load before any is loaded.
+///
+/// ```rust,ignore
+/// let sink = sink::Router::new(|price: &Price| match price.close > 0.0 {
+///     true => "valid",
+///     false => "quarantine",
+/// })
+/// .route("valid", sink::Postgres::new(conn, "prices"))
+/// .route("quarantine", sink::Postgres::new(conn, "prices_quarantine"));
+/// ```
+pub struct Router<T> {
+    route: Box<dyn Fn(&T) -> String + Send + Sync>,
+    routes: Vec<Route<T>>,
+}
+
+struct Route<T> {
+    name: String,
+    sink: Box<dyn DynSink<Vec<T>>>,
+}
+
+impl<T> Router<T> {
+    pub fn new<F, R>(route: F) -> Self
+    where
+        F: Fn(&T) -> R + Send + Sync + 'static,
+        R: Into<String>,
+    {
+        Router {
+            route: Box::new(move |record| route(record).into()),
+            routes: vec![],
+        }
+    }
+
+    /// Load the records routed to `name` to `sink`; replacing any sink already routed to.
+    pub fn route<S>(mut self, name: impl Into<String>, sink: S) -> Self
+    where
+        S: Sink<Vec<T>> + 'static,
+    {
+        let name = name.into();
+        self.routes.retain(|route| route.name != name);
+        self.routes.push(Route {
+            name,
+            sink: Box::new(sink),
+        });
+        self
+    }
+}
+
+impl<T> Sink<Vec<T>> for Router<T>
+where
+    T: Clone + Send + Sync,
+{
+    async fn load(&self, output: &Vec<T>) -> Result<Outcome, Error> {
+        let mut routed: Vec<Vec<T>> = self.routes.iter().map(|_| vec![]).collect();
+        for (i, record) in output.iter().enumerate() {
+            let route = (self.route)(record);
+            let Some(at) = self.routes.iter().position(|to| to.name == route) else {
+                return Err(Error::Config(format!(
+                    "record {i} was routed to `{route}`, which has no sink"
+                )));
+            };
+            routed[at].push(record.clone());
+        }
+
+        let mut errors = Errors::new();
+        let mut outcomes = std::collections::BTreeMap::new();
+        for (route, records) in self.routes.iter().zip(routed) {
+            if records.is_empty() {
+                continue;
+            }
+            match route.sink.load_boxed(&records).await {
+                Ok(outcome) => {
+                    outcomes.insert(route.name.clone(), outcome);
+                }
+                Err(error) => errors.push(Context::Route(route.name.clone()), error),
+            }
+        }
+        errors.into_result()?;
+        Ok(Outcome::Routes(outcomes))
+    }
+}
+
+// FNV-1a of the concatenated `parts`, in hex; unlike `DefaultHasher`, stable across processes & compiler versions
+pub(crate) fn content_hash(parts: &[&[u8]]) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in parts.iter().flat_map(|part| part.iter()) {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{hash:016x}")
+}
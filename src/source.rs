@@ -0,0 +1,408 @@
+use super::window::{self, Batch};
+use super::{default, wire, Error};
+use chrono::{DateTime, Utc};
+use futures::future::{BoxFuture, FutureExt};
+use futures::stream::{BoxStream, Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// Where a pipe reads its input from.
+pub enum Source<I> {
+    /// A File Path or URL, handed to [`extract()`] on every run.
+    ///
+    /// [`extract()`]: crate::ETL::extract
+    Endpoint(String),
+
+    /// A payload given inline, rather than read from anywhere; decoded into the input as an
+    /// extracted one would be. For tests, examples & demos, and payloads built by the program.
+    ///
+    /// ```rust,ignore
+    /// let pipe = Pipe::<RawPrice, Vec<Price>>::builder()
+    ///     .source(Source::inline(json!({ "chart": { "result": [] } })))
+    ///     .sink(sink)
+    ///     .build()?;
+    /// ```
+    Inline(Value),
+
+    /// A stream of already-decoded inputs; each one is transformed & loaded as it arrives.
+    ///
+    /// A stream can only be consumed once, so a pipe with a streaming source can only be run once;
+    /// unless it's [`Streams`](Source::Streams).
+    Stream {
+        stream: Mutex<Option<BoxStream<'static, Result<I, Error>>>>,
+        checkpoint: Option<Arc<dyn Checkpoint>>,
+    },
+
+    /// A streaming source made afresh for every run, e.g., a [plugin](crate::plugin)'s; so,
+    /// unlike a [`Stream`](Source::Stream), its pipe can be run again & again, as by a
+    /// [`Runner`](crate::Runner).
+    Streams(MakeStream<I>),
+}
+
+/// What makes each run's source of a [`Source::Streams`]; a [`Source::Stream`], as a rule.
+pub type MakeStream<I> = Arc<dyn Fn() -> Result<Source<I>, Error> + Send + Sync>;
+
+/// Acknowledges streamed inputs back to their origin, once they've been loaded.
+///
+/// Only inputs that made it all the way to the sink are acknowledged, so a run that fails
+/// (or a process that dies) picks up from the first unloaded input next time: at-least-once delivery.
+pub trait Checkpoint: Send + Sync {
+    /// Called after the latest input from the stream has been loaded successfully.
+    fn loaded(&self) -> BoxFuture<'_, Result<(), Error>>;
+
+    /// Called once the stream has ended, and everything from it has been loaded.
+    fn flush(&self) -> BoxFuture<'_, Result<(), Error>>;
+}
+
+impl<I> Source<I> {
+    /// A File Path or URL source.
+    pub fn endpoint(path: impl Into<String>) -> Self {
+        Source::Endpoint(path.into())
+    }
+
+    /// An inline source, of `payload`.
+    pub fn inline(payload: Value) -> Self {
+        Source::Inline(payload)
+    }
+
+    /// A streaming source.
+    pub fn stream<S>(stream: S) -> Self
+    where
+        S: Stream<Item = Result<I, Error>> + Send + 'static,
+    {
+        Source::Stream {
+            stream: Mutex::new(Some(stream.boxed())),
+            checkpoint: None,
+        }
+    }
+
+    /// A streaming source, that acknowledges each input once it has been loaded.
+    pub fn checkpointed<S, C>(stream: S, checkpoint: C) -> Self
+    where
+        S: Stream<Item = Result<I, Error>> + Send + 'static,
+        C: Checkpoint + 'static,
+    {
+        Source::Stream {
+            stream: Mutex::new(Some(stream.boxed())),
+            checkpoint: Some(Arc::new(checkpoint)),
+        }
+    }
+
+    /// A streaming source, made by `make` for every run; see [`Source::Streams`].
+    pub fn streams<F>(make: F) -> Self
+    where
+        F: Fn() -> Result<Source<I>, Error> + Send + Sync + 'static,
+    {
+        Source::Streams(Arc::new(make))
+    }
+
+    /// This streaming source, accumulated into batches, e.g., "every 1000 records or 30 seconds";
+    /// so each load is of a `Vec` of inputs, rather than one; see [`window::batches()`].
+    ///
+    /// A checkpoint acknowledges each batch, once it has been loaded. Only a streaming source can
+    /// be batched; any other is [`Error::Config`].
+    ///
+    /// ```rust,ignore
+    /// let source = KafkaSource::new("localhost:9092", "prices", &["prices.raw"])?
+    ///     .into_source::<RawPrice>()
+    ///     .batched(Batch::new().records(1000).wait(Duration::from_secs(30)))?;
+    /// let pipe = Pipe::<Vec<RawPrice>, Vec<Price>>::builder().source(source).sink(sink).build()?;
+    /// ```
+    ///
+    /// [`window::batches()`]: crate::window::batches
+    pub fn batched(self, batch: Batch) -> Result<Source<Vec<I>>, Error>
+    where
+        I: Send + 'static,
+    {
+        self.batched_until(batch, std::future::pending())
+    }
+
+    /// [`batched()`](Source::batched), ending the stream once `shutdown` completes (e.g., on
+    /// `tokio::signal::ctrl_c()`), with the pending batch loaded rather than dropped.
+    pub fn batched_until<F>(self, batch: Batch, shutdown: F) -> Result<Source<Vec<I>>, Error>
+    where
+        I: Send + 'static,
+        F: Future<Output = ()> + Send + 'static,
+    {
+        if let Source::Streams(make) = self {
+            // every run's stream ends with the same shutdown
+            let shutdown = shutdown.boxed().shared();
+            return Ok(Source::streams(move || {
+                make()?.batched_until(batch, shutdown.clone())
+            }));
+        }
+        let Source::Stream { stream, checkpoint } = self else {
+            return Err(Error::Config(format!(
+                "only a streaming source can be batched, not {}",
+                self.describe()
+            )));
+        };
+        let records = stream
+            .into_inner()
+            .unwrap_or_else(|| futures::stream::empty().boxed());
+        Ok(Source::Stream {
+            stream: Mutex::new(Some(window::batches(records, batch, shutdown))),
+            checkpoint,
+        })
+    }
+
+    pub fn is_stream(&self) -> bool {
+        matches!(self, Source::Stream { .. } | Source::Streams(_))
+    }
+
+    /// A short description of the source, as reported to observers.
+    pub fn describe(&self) -> &str {
+        match self {
+            Source::Endpoint(path) => path,
+            Source::Inline(_) => "<inline>",
+            Source::Stream { .. } | Source::Streams(_) => "<stream>",
+        }
+    }
+}
+
+impl<I> std::fmt::Debug for Source<I> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Endpoint(path) => f.debug_tuple("Endpoint").field(path).finish(),
+            Source::Inline(payload) => f.debug_tuple("Inline").field(payload).finish(),
+            Source::Stream { checkpoint, .. } => f
+                .debug_struct("Stream")
+                .field("checkpointed", &checkpoint.is_some())
+                .finish(),
+            Source::Streams(_) => f.debug_tuple("Streams").finish_non_exhaustive(),
+        }
+    }
+}
+
+/// One endpoint of [`Pipe::etl_many()`], with its own overrides of the pipe's extraction; so
+/// endpoints of mixed APIs (some authenticated, some not, some slower) can be swept in one run.
+///
+/// ```rust,ignore
+/// let report = pipe
+///     .etl_many([
+///         SourceSpec::new("https://public.example.com/prices.json"),
+///         SourceSpec::new("https://private.example.com/prices.ndjson")
+///             .header("Authorization", format!("Bearer {token}"))
+///             .timeout(Duration::from_secs(120))
+///             .format(Format::JsonLines),
+///         SourceSpec::new("https://internal.example.com/prices.json")
+///             .proxy("http://proxy.internal:3128")
+///             .ca_certificate("/etc/ssl/internal-ca.pem"),
+///     ])
+///     .await?;
+/// ```
+///
+/// Overrides apply to the default extraction, [`Pipe::extract_default()`]; a `pipeline!` block
+/// with its own `extract()` only gets them if it calls that. A plain path (`&str` or `String`)
+/// is a spec without overrides.
+///
+/// [`Pipe::etl_many()`]: crate::Pipe::etl_many
+/// [`Pipe::extract_default()`]: crate::Pipe::extract_default
+#[derive(Clone, Default, PartialEq)]
+pub struct SourceSpec {
+    /// A File Path or URL.
+    pub path: String,
+    /// Sent with the request, after the pipe's own headers; ignored for files.
+    pub headers: Vec<(String, String)>,
+    /// A timeout for the request alone, rather than the whole extract stage; ignored for files.
+    pub timeout: Option<Duration>,
+    pub format: Format,
+    /// The proxy to send the request through, instead of the client's; ignored for files.
+    pub proxy: Option<String>,
+    /// PEM files of CA certificates to trust, alongside the client's; ignored for files.
+    pub ca_certificates: Vec<PathBuf>,
+}
+
+impl SourceSpec {
+    pub fn new(path: impl Into<String>) -> Self {
+        SourceSpec {
+            path: path.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Send the header `name: value`.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Time the request out after `timeout`.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Send the request through the proxy at `url`; see [`ClientConfig::proxy()`].
+    ///
+    /// [`ClientConfig::proxy()`]: crate::ClientConfig::proxy
+    pub fn proxy(mut self, url: impl Into<String>) -> Self {
+        self.proxy = Some(url.into());
+        self
+    }
+
+    /// Trust the CA certificates of the PEM file at `path`, for this endpoint; e.g., a private
+    /// CA's.
+    pub fn ca_certificate(mut self, path: impl Into<PathBuf>) -> Self {
+        self.ca_certificates.push(path.into());
+        self
+    }
+
+    /// Parse the response (or file) as `format`; JSON by default.
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+}
+
+// The proxy's password & secret headers' values are shown as `***`.
+impl std::fmt::Debug for SourceSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let headers: Vec<(&str, &str)> = self
+            .headers
+            .iter()
+            .map(|(name, value)| match wire::is_secret(name) {
+                true => (name.as_str(), "***"),
+                false => (name.as_str(), value.as_str()),
+            })
+            .collect();
+        f.debug_struct("SourceSpec")
+            .field("path", &self.path)
+            .field("headers", &headers)
+            .field("timeout", &self.timeout)
+            .field("format", &self.format)
+            .field("proxy", &self.proxy.as_deref().map(wire::redact_proxy))
+            .field("ca_certificates", &self.ca_certificates)
+            .finish()
+    }
+}
+
+impl From<&str> for SourceSpec {
+    fn from(path: &str) -> Self {
+        SourceSpec::new(path)
+    }
+}
+
+impl From<String> for SourceSpec {
+    fn from(path: String) -> Self {
+        SourceSpec::new(path)
+    }
+}
+
+/// What's known of where a payload came from: its URL (or path), and the response's headers (or
+/// the file's modification time); for transforms that branch on it, e.g., parsing a `v1` & a `v2`
+/// endpoint differently by a version header.
+///
+/// ```rust,ignore
+/// pipeline! {
+///     RawPrice -> Vec<Price> {
+///         async fn transform(&self, input: RawPrice, source: &Metadata) -> pipe_io::Result<Vec<Price>> {
+///             match source.header("x-api-version") {
+///                 Some("2") => v2::prices(input),
+///                 _ => v1::prices(input),
+///             }
+///         }
+///     }
+/// }
+/// ```
+///
+/// The optional argument of a `pipeline!` transform is that of the payload it was decoded from,
+/// as is [`Pipe::metadata()`]; one from anywhere but the default fetch ([`Pipe::fetch_default()`])
+/// has none, so is [`Metadata::default()`].
+///
+/// [`Pipe::metadata()`]: crate::Pipe::metadata
+/// [`Pipe::fetch_default()`]: crate::Pipe::fetch_default
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    /// The URL or File Path fetched.
+    pub location: String,
+    /// The response's headers, by lowercase name; none for a file. A repeated header is joined
+    /// with `, `.
+    pub headers: BTreeMap<String, String>,
+    /// The response's `Content-Type`, if any; none for a file.
+    pub content_type: Option<String>,
+    /// When the file was last modified; or the response's `Last-Modified`, if any.
+    pub modified: Option<DateTime<Utc>>,
+}
+
+impl Metadata {
+    /// The value of the header `name`, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .get(&name.to_ascii_lowercase())
+            .map(String::as_str)
+    }
+
+    // The metadata of a `response` from `url`, before its body is read.
+    pub(crate) fn of_response(url: &str, response: &reqwest::Response) -> Self {
+        let mut headers = BTreeMap::<String, String>::new();
+        for (name, value) in response.headers() {
+            let value = String::from_utf8_lossy(value.as_bytes());
+            headers
+                .entry(name.as_str().to_string())
+                .and_modify(|joined| {
+                    joined.push_str(", ");
+                    joined.push_str(&value);
+                })
+                .or_insert_with(|| value.into_owned());
+        }
+        let modified = headers
+            .get("last-modified")
+            .and_then(|modified| DateTime::parse_from_rfc2822(modified).ok())
+            .map(|modified| modified.with_timezone(&Utc));
+        Metadata {
+            location: url.to_string(),
+            content_type: headers.get("content-type").cloned(),
+            headers,
+            modified,
+        }
+    }
+
+    // The metadata of the file at `path`.
+    pub(crate) async fn of_file(path: &str) -> Self {
+        let modified = match tokio::fs::metadata(path).await {
+            Ok(metadata) => metadata.modified().ok().map(DateTime::<Utc>::from),
+            Err(_) => None,
+        };
+        Metadata {
+            location: path.to_string(),
+            modified,
+            ..Default::default()
+        }
+    }
+}
+
+/// How an extracted payload is parsed into the input type.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    /// One JSON value.
+    #[default]
+    Json,
+    /// One JSON value per line (NDJSON), parsed as an array of them; blank lines are skipped.
+    JsonLines,
+}
+
+impl Format {
+    /// Parse `text` as an `I`; failures are [`Error::Decode`], see [`default::decode()`].
+    ///
+    /// [`default::decode()`]: crate::default::decode
+    pub fn parse<I: DeserializeOwned>(self, text: &str) -> Result<I, Error> {
+        match self {
+            Format::Json => default::decode(text),
+            Format::JsonLines => {
+                // as an array of the lines, so a failure's path starts with the line's index
+                let lines: Vec<&str> = text
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .collect();
+                default::decode(format!("[{}]", lines.join(",")))
+            }
+        }
+    }
+}
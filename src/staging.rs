@@ -0,0 +1,244 @@
+use super::db::*;
+use super::sink::{self, Sink};
+use super::{Error, Outcome};
+use std::future::Future;
+use std::path::PathBuf;
+
+/// A sink that can load to a staging area first, and then publish it to the real destination in one step.
+///
+/// Wrap a `Staging` sink in [`Staged`] to use it; consumers of the destination then never see a half-written output.
+pub trait Staging<O>: Send + Sync {
+    /// Load `output` to the staging area, replacing anything left there by a previous run.
+    fn load_staged(&self, output: &O) -> impl Future<Output = Result<Outcome, Error>> + Send;
+
+    /// Check that the staging area holds all of `output`.
+    fn verify_staged(&self, output: &O) -> impl Future<Output = Result<(), Error>> + Send;
+
+    /// Atomically replace the destination with the staging area, loaded with the `staged` outcome;
+    /// returning the outcome of the load to the destination, e.g., its document's new revision.
+    fn publish(&self, staged: Outcome) -> impl Future<Output = Result<Outcome, Error>> + Send;
+
+    /// Remove the staging area.
+    fn discard(&self) -> impl Future<Output = Result<(), Error>> + Send;
+}
+
+type Check<O> = Box<dyn Fn(&O) -> Result<(), Error> + Send + Sync>;
+
+/// Two-phase load: load to staging, verify, then publish; discarding the staging area on any failure.
+///
+/// ```rust,ignore
+/// let sink = Staged::new(sink::Postgres::new(conn, "prices"))
+///     .check(|prices: &Vec<Price>| match prices.is_empty() {
+///         true => Err(Error::Verification("no prices".into())),
+///         false => Ok(()),
+///     });
+/// ```
+pub struct Staged<S, O> {
+    sink: S,
+    check: Option<Check<O>>,
+}
+
+impl<S, O> Staged<S, O>
+where
+    S: Staging<O>,
+{
+    pub fn new(sink: S) -> Self {
+        Staged { sink, check: None }
+    }
+
+    /// An extra verification of the output, run after the staged load has been verified.
+    pub fn check<F>(mut self, check: F) -> Self
+    where
+        F: Fn(&O) -> Result<(), Error> + Send + Sync + 'static,
+    {
+        self.check = Some(Box::new(check));
+        self
+    }
+
+    async fn stage_and_publish(&self, output: &O) -> Result<Outcome, Error> {
+        let outcome = self.sink.load_staged(output).await?;
+        self.sink.verify_staged(output).await?;
+        if let Some(check) = &self.check {
+            check(output)?;
+        }
+        self.sink.publish(outcome).await
+    }
+}
+
+impl<S, O> Sink<O> for Staged<S, O>
+where
+    S: Staging<O>,
+    O: Sync,
+{
+    async fn load(&self, output: &O) -> Result<Outcome, Error> {
+        match self.stage_and_publish(output).await {
+            Ok(outcome) => Ok(outcome),
+            Err(e) => {
+                // the original failure matters more than a failed cleanup
+                let _ = self.sink.discard().await;
+                Err(e)
+            }
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+// couch
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// staged as `<doc_id>.staging`, then published with CouchDB's `COPY`
+impl sink::CouchDb {
+    fn staging_id(&self) -> String {
+        format!("{}.staging", self.doc_id)
+    }
+}
+
+impl<O> Staging<O> for sink::CouchDb
+where
+    O: serde::Serialize + serde::de::DeserializeOwned + Sync,
+{
+    async fn load_staged(&self, output: &O) -> Result<Outcome, Error> {
+        // `COPY` publishes a single document, not the chunks a manifest links to
+        if let couchdb::Layout::Chunked { .. } = self.layout {
+            return Err(Error::Config(
+                "a chunked CouchDB layout cannot be staged".into(),
+            ));
+        }
+        let doc = couchdb::upsert_doc(
+            output,
+            &self.conn,
+            &self.staging_id(),
+            self.conflict_retries,
+            self.wire_log.as_ref(),
+        )
+        .await?;
+        Ok(Outcome::Couch(doc))
+    }
+
+    async fn verify_staged(&self, _output: &O) -> Result<(), Error> {
+        match couchdb::get_rev(&self.conn, &self.staging_id()).await? {
+            Some(_) => Ok(()),
+            None => Err(Error::Verification(format!(
+                "staged document `{}` is missing",
+                self.staging_id()
+            ))),
+        }
+    }
+
+    async fn publish(&self, _staged: Outcome) -> Result<Outcome, Error> {
+        let doc = couchdb::copy_doc(&self.conn, &self.staging_id(), &self.doc_id).await?;
+        couchdb::delete_doc(&self.conn, &self.staging_id()).await?;
+        Ok(Outcome::Couch(doc))
+    }
+
+    async fn discard(&self) -> Result<(), Error> {
+        couchdb::delete_doc(&self.conn, &self.staging_id()).await
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+// postgres
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// staged as `<table>_staging`, then swapped in with `ALTER TABLE ... RENAME`
+impl sink::Postgres {
+    fn staging_table(&self) -> String {
+        format!("{}_staging", self.table)
+    }
+}
+
+impl<O> Staging<O> for sink::Postgres
+where
+    O: serde::Serialize + Sync,
+{
+    async fn load_staged(&self, output: &O) -> Result<Outcome, Error> {
+        let staging = self.staging_table();
+        if let Some(types) = &self.create {
+            postgresql::create_table(output, &self.conn, &self.table, types).await?;
+        }
+        // drift is dealt with on the real table, so its staging copy has any added columns too
+        let columns = self.reconcile(output).await?;
+        postgresql::create_like(&self.conn, &self.table, &staging).await?;
+        let rows_affected = postgresql::insert_columns(
+            output,
+            &self.conn,
+            &staging,
+            columns.as_deref(),
+            self.conflict.as_ref(),
+        )
+        .await?;
+        Ok(Outcome::Pg(postgresql::PgOutcome { rows_affected }))
+    }
+
+    async fn verify_staged(&self, output: &O) -> Result<(), Error> {
+        let expected = match serde_json::to_value(output)? {
+            serde_json::Value::Array(rows) => rows.len() as u64,
+            _ => 1,
+        };
+        let staged = postgresql::count_rows(&self.conn, &self.staging_table()).await?;
+        // with a conflict target, duplicate keys collapse into fewer rows
+        if staged == expected || (self.conflict.is_some() && staged <= expected) {
+            Ok(())
+        } else {
+            Err(Error::Verification(format!(
+                "staged {staged} rows, expected {expected}"
+            )))
+        }
+    }
+
+    // the staged rows are the table's
+    async fn publish(&self, staged: Outcome) -> Result<Outcome, Error> {
+        postgresql::swap_table(&self.conn, &self.table, &self.staging_table()).await?;
+        Ok(staged)
+    }
+
+    async fn discard(&self) -> Result<(), Error> {
+        postgresql::drop_table(&self.conn, &self.staging_table()).await
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+// file
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// staged as `<path>.staging` next to the destination, then moved over it with a rename
+impl sink::File {
+    fn staging_path(&self) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".staging");
+        path.into()
+    }
+}
+
+impl<O> Staging<O> for sink::File
+where
+    O: serde::Serialize + Sync,
+{
+    async fn load_staged(&self, output: &O) -> Result<Outcome, Error> {
+        sink::File::new(self.staging_path()).load(output).await
+    }
+
+    async fn verify_staged(&self, output: &O) -> Result<(), Error> {
+        let file = std::fs::File::open(self.staging_path())?;
+        let staged: serde_json::Value = serde_json::from_reader(std::io::BufReader::new(file))?;
+        match staged == serde_json::to_value(output)? {
+            true => Ok(()),
+            false => Err(Error::Verification(
+                "staged file does not match the output".into(),
+            )),
+        }
+    }
+
+    // the staged bytes are the file's
+    async fn publish(&self, staged: Outcome) -> Result<Outcome, Error> {
+        std::fs::rename(self.staging_path(), &self.path)?;
+        Ok(staged)
+    }
+
+    async fn discard(&self) -> Result<(), Error> {
+        match std::fs::remove_file(self.staging_path()) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,105 @@
+//! Short descriptions of pipeline values (how many records, which fields, a truncated sample), to
+//! log instead of the values themselves; a multi-MB payload printed with `{:#?}` floods a terminal.
+//!
+//! ```rust,ignore
+//! println!("{}", summary::of(&prices)?);
+//! // 1024 records, 98.3 KB; fields: adj_close, close, date, high, low, open, volume; first: {"adj_close":…
+//!
+//! let pipe = Pipe::<I, O>::builder().tap_transform(summary::tap("transformed")).build()?;
+//! ```
+
+use super::Error;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeSet;
+
+/// How much of the first record a [`Summary`] keeps, in characters.
+pub const SAMPLE: usize = 80;
+
+/// A short description of a value; see [`of()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Summary {
+    /// The elements of an array; any other value is one record.
+    pub records: usize,
+    /// The fields of the records, in alphabetical order; none for scalar records.
+    pub fields: Vec<String>,
+    /// The size of the value, serialized as compact JSON.
+    pub bytes: usize,
+    /// The first record, as JSON, truncated to [`SAMPLE`] characters; `None` for an empty array.
+    pub sample: Option<String>,
+}
+
+/// Summarize `value`.
+pub fn of<T: Serialize + ?Sized>(value: &T) -> Result<Summary, Error> {
+    let value = serde_json::to_value(value)?;
+    let bytes = serde_json::to_vec(&value)?.len();
+    let records = match &value {
+        Value::Array(records) => records.iter().collect(),
+        record => vec![record],
+    };
+
+    let fields: BTreeSet<&String> = records
+        .iter()
+        .filter_map(|record| record.as_object())
+        .flat_map(|record| record.keys())
+        .collect();
+    let fields = fields.into_iter().cloned().collect();
+    let sample = records.first().map(|record| {
+        let json = record.to_string();
+        match json.char_indices().nth(SAMPLE) {
+            Some((end, _)) => format!("{}…", &json[..end]),
+            None => json,
+        }
+    });
+    Ok(Summary {
+        records: records.len(),
+        fields,
+        bytes,
+        sample,
+    })
+}
+
+/// A tap printing the summary of every value it sees to stderr, after `label`; see
+/// [`PipeBuilder::tap_extract()`] & [`PipeBuilder::tap_transform()`].
+///
+/// [`PipeBuilder::tap_extract()`]: crate::PipeBuilder::tap_extract
+/// [`PipeBuilder::tap_transform()`]: crate::PipeBuilder::tap_transform
+pub fn tap<T: Serialize>(label: impl Into<String>) -> impl Fn(&T) + Send + Sync + 'static {
+    let label = label.into();
+    move |value: &T| match of(value) {
+        Ok(summary) => eprintln!("{label}: {summary}"),
+        Err(e) => eprintln!("{label}: could not summarize: {e}"),
+    }
+}
+
+impl std::fmt::Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let plural = if self.records == 1 { "" } else { "s" };
+        write!(f, "{} record{plural}, {}", self.records, size(self.bytes))?;
+        if !self.fields.is_empty() {
+            write!(f, "; fields: {}", self.fields.join(", "))?;
+        }
+        if let Some(sample) = &self.sample {
+            write!(f, "; first: {sample}")?;
+        }
+        Ok(())
+    }
+}
+
+// `bytes`, in the largest unit it has at least 1 of
+fn size(bytes: usize) -> String {
+    const UNITS: [&str; 3] = ["KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = None;
+    for next in UNITS {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = Some(next);
+    }
+    match unit {
+        Some(unit) => format!("{size:.1} {unit}"),
+        None => format!("{bytes} bytes"),
+    }
+}
@@ -0,0 +1,104 @@
+//! Outputs that fit a table: records of scalar columns, as a [`Csv`] sink writes them; checked
+//! when the pipe is compiled, rather than failing its first load.
+//!
+//! A struct is tabular with `#[derive(Tabular)]`, which checks each of its fields is a [`Scalar`]:
+//!
+//! ```rust,ignore
+//! #[derive(Serialize, Tabular)]
+//! struct Price {
+//!     symbol: String,
+//!     date: NaiveDate,
+//!     close: Option<f64>,
+//!     #[serde(skip)]
+//!     history: Vec<f64>,
+//! }
+//! ```
+//!
+//! A nested field, e.g., `history: Vec<f64>` above without `#[serde(skip)]`, fails to compile
+//! with "`Vec<f64>` can't be a column of a table row", pointing at the field.
+//!
+//! [`Csv`]: crate::sink::Csv
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+// for the bounds of `#[derive(Tabular)]`, which can't name the deriving crate's serde
+#[doc(hidden)]
+pub use serde::Serialize;
+use std::borrow::Cow;
+
+/// A record that serializes to one row of scalar columns; see [`tabular`](self).
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` can't be written as rows of a table",
+    label = "not a record of scalar fields",
+    note = "`#[derive(Tabular)]` for a struct of scalar fields; nested structs, maps & `Vec`s don't fit in a row"
+)]
+pub trait Tabular: Serialize {}
+
+/// A value that fits in one column of a row: a number, string, bool, date or time, or an
+/// `Option` of one.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` can't be a column of a table row",
+    label = "not a scalar",
+    note = "skip the field with `#[serde(skip)]`, or flatten it into scalar fields"
+)]
+pub trait Scalar: Serialize {}
+
+macro_rules! scalars {
+    ($($scalar:ty),*) => {
+        $(impl Scalar for $scalar {})*
+    };
+}
+
+scalars!(
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    f32,
+    f64,
+    bool,
+    char,
+    str,
+    String,
+    NaiveDate,
+    NaiveDateTime,
+    NaiveTime,
+    crate::intern::Interned
+);
+
+impl<Tz: TimeZone> Scalar for DateTime<Tz> where DateTime<Tz>: Serialize {}
+impl<T: Scalar> Scalar for Option<T> {}
+impl<T: Scalar + ?Sized> Scalar for &T {}
+impl<T: Scalar + ?Sized> Scalar for Box<T> {}
+impl Scalar for Cow<'_, str> {}
+
+impl<T: Tabular + ?Sized> Tabular for &T {}
+impl<T: Tabular + ?Sized> Tabular for Box<T> {}
+
+macro_rules! tuples {
+    ($(($($column:ident),+))*) => {
+        $(impl<$($column: Scalar),+> Tabular for ($($column,)+) {})*
+    };
+}
+
+tuples! {
+    (A)
+    (A, B)
+    (A, B, C)
+    (A, B, C, D)
+    (A, B, C, D, E)
+    (A, B, C, D, E, F)
+    (A, B, C, D, E, F, G)
+    (A, B, C, D, E, F, G, H)
+    (A, B, C, D, E, F, G, H, J)
+    (A, B, C, D, E, F, G, H, J, K)
+    (A, B, C, D, E, F, G, H, J, K, L)
+    (A, B, C, D, E, F, G, H, J, K, L, M)
+}
@@ -0,0 +1,121 @@
+//! One pipeline definition, instantiated with different [`Vars`]: e.g., a `Pipe<RawPrice, Price>` per
+//! ticker symbol, each with its own endpoint & doc id, rather than a copy of the pipe per symbol.
+//!
+//! The template builds a pipe from its vars, whose values fill in `{name}` placeholders of its
+//! endpoints & doc ids; and every stage can read them back, with [`Pipe::vars()`]:
+//!
+//! ```rust,ignore
+//! let prices = Template::new(|vars: &Vars| {
+//!     Ok(Pipe::<RawPrice, Price>::builder()
+//!         .source(Endpoint::new("https://example.com/prices/{symbol}").vars(vars).try_into()?)
+//!         .sink(sink::CouchDb::new(conn, vars.fill("prices-{symbol}")?)))
+//! });
+//! let nvda = prices.instantiate(Vars::new().set("symbol", "NVDA"))?;
+//!
+//! pipeline! {
+//!     RawPrice -> Price {
+//!         async fn transform(&self, input: RawPrice) -> pipe_io::Result<Price> {
+//!             Ok(Price { symbol: self.vars().require("symbol")?.into(), close: input.close })
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! [`Pipe::vars()`]: crate::Pipe::vars
+
+use super::{Error, Input, Output, Pipe, PipeBuilder};
+use std::collections::BTreeMap;
+
+/// The named values a pipe was instantiated with; empty unless it was built with one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Vars {
+    pub values: BTreeMap<String, String>,
+}
+
+impl Vars {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(mut self, name: impl Into<String>, value: impl ToString) -> Self {
+        self.values.insert(name.into(), value.to_string());
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    /// The value named `name`, or [`Error::Missing`] if there's none.
+    pub fn require(&self, name: &str) -> Result<&str, Error> {
+        self.get(name)
+            .ok_or_else(|| Error::Missing(format!("vars.{name}")))
+    }
+
+    /// `template`, with each `{name}` placeholder replaced by its value, as is.
+    ///
+    /// Returns [`Error::Config`] if a placeholder has no value.
+    pub fn fill(&self, template: &str) -> Result<String, Error> {
+        let mut filled = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            let Some(end) = rest[start..].find('}').map(|end| start + end) else {
+                break;
+            };
+            let name = &rest[start + 1..end];
+            let value = self
+                .get(name)
+                .ok_or_else(|| Error::Config(format!("placeholder {{{name}}} has no value")))?;
+            filled.push_str(&rest[..start]);
+            filled.push_str(value);
+            rest = &rest[end + 1..];
+        }
+        filled.push_str(rest);
+        Ok(filled)
+    }
+}
+
+type Build<I, O> = Box<dyn Fn(&Vars) -> Result<PipeBuilder<I, O>, Error> + Send + Sync>;
+
+/// A pipe, built afresh for the vars of each instance; see [`template`](self).
+pub struct Template<I, O> {
+    build: Build<I, O>,
+}
+
+impl<I, O> Template<I, O>
+where
+    I: Input,
+    O: Output,
+{
+    pub fn new<F>(build: F) -> Self
+    where
+        F: Fn(&Vars) -> Result<PipeBuilder<I, O>, Error> + Send + Sync + 'static,
+    {
+        Template {
+            build: Box::new(build),
+        }
+    }
+
+    /// The pipe for `vars`, which its stages can read with [`Pipe::vars()`].
+    pub fn instantiate(&self, vars: Vars) -> Result<Pipe<I, O>, Error> {
+        (self.build)(&vars)?.vars(vars).build()
+    }
+
+    /// A pipe per value of `name`, e.g., per symbol; by value, e.g., for [`Runner::pipe()`].
+    ///
+    /// [`Runner::pipe()`]: crate::Runner::pipe
+    pub fn each<V: ToString>(
+        &self,
+        name: &str,
+        values: impl IntoIterator<Item = V>,
+    ) -> Result<BTreeMap<String, Pipe<I, O>>, Error> {
+        values
+            .into_iter()
+            .map(|value| {
+                let value = value.to_string();
+                let pipe = self.instantiate(Vars::new().set(name, &value))?;
+                Ok((value, pipe))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,104 @@
+use super::sink::Sink;
+use super::{Error, Outcome};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, Semaphore};
+use tokio::time::Instant;
+
+/// A sink that loads no faster than its destination can take, however fast the pipe extracts;
+/// e.g., so a backfill doesn't hit a small CouchDB or PostgreSQL instance with a burst of writes.
+///
+/// Loads are limited to `records_per_second` (counting each element of an array output as a
+/// record; anything else as one) and/or `max_concurrent` at once. A load over the rate goes
+/// through, but the next waits for it to be paid off, so the average rate holds either way.
+///
+/// Clones share the same limits, like [`RateLimit`]s; so one throttle can be put in front of
+/// several sinks to the same database. The throttle is independent of the pipe's own
+/// [`rate_limit()`], which only spaces out extractions.
+///
+/// ```rust,ignore
+/// // at most 500 rows/s, in batches of 100
+/// let sink = sink::Batched::new(
+///     Throttled::new(sink::Postgres::new(conn, "prices")).records_per_second(500),
+///     100,
+/// );
+/// ```
+///
+/// [`RateLimit`]: crate::RateLimit
+/// [`rate_limit()`]: crate::PipeBuilder::rate_limit
+#[derive(Debug, Clone)]
+pub struct Throttled<S> {
+    sink: S,
+    // the time one record takes, at the configured rate
+    per_record: Option<Duration>,
+    next: Arc<Mutex<Option<Instant>>>,
+    concurrency: Option<Arc<Semaphore>>,
+}
+
+impl<S> Throttled<S> {
+    /// Unthrottled, until limits are set.
+    pub fn new(sink: S) -> Self {
+        Throttled {
+            sink,
+            per_record: None,
+            next: Arc::new(Mutex::new(None)),
+            concurrency: None,
+        }
+    }
+
+    /// Load at most `records` every second, on average.
+    pub fn records_per_second(mut self, records: u32) -> Self {
+        self.per_record = Duration::from_secs(1).checked_div(records.max(1));
+        self
+    }
+
+    /// Load at most `loads` outputs at once; later loads wait for a free slot.
+    pub fn max_concurrent(mut self, loads: usize) -> Self {
+        self.concurrency = Some(Arc::new(Semaphore::new(loads.max(1))));
+        self
+    }
+
+    // Wait until `records` can be loaded at the configured rate, and claim the time they take.
+    async fn acquire(&self, records: u32) {
+        let Some(per_record) = self.per_record else {
+            return;
+        };
+        let mut next = self.next.lock().await;
+        let now = Instant::now();
+        let slot = match *next {
+            Some(at) if at > now => at,
+            _ => now,
+        };
+        *next = Some(slot + per_record * records);
+        drop(next);
+        tokio::time::sleep_until(slot).await;
+    }
+}
+
+impl<O, S> Sink<O> for Throttled<S>
+where
+    O: Serialize + Sync + ?Sized,
+    S: Sink<O>,
+{
+    async fn load(&self, output: &O) -> Result<Outcome, Error> {
+        let _permit = match &self.concurrency {
+            Some(semaphore) => Some(
+                semaphore
+                    .acquire()
+                    .await
+                    .map_err(|e| Error::Invariant(format!("the throttle's semaphore: {e}")))?,
+            ),
+            None => None,
+        };
+        if self.per_record.is_some() {
+            let records = match serde_json::to_value(output)? {
+                serde_json::Value::Array(records) => records.len(),
+                _ => 1,
+            };
+            self.acquire(u32::try_from(records).unwrap_or(u32::MAX))
+                .await;
+        }
+        self.sink.load(output).await
+    }
+}
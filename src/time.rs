@@ -0,0 +1,161 @@
+//! Parsers for the dates & times found in JSON APIs, with a timezone policy.
+//!
+//! ```rust,ignore
+//! #[derive(Deserialize)]
+//! struct Chart {
+//!     #[serde(rename = "timestamp", deserialize_with = "time::de::epoch_dates")]
+//!     dates: Vec<NaiveDate>,
+//!     #[serde(rename = "regularMarketTime")]
+//!     updated: i64,
+//!     #[serde(rename = "gmtoffset")]
+//!     offset: i32,
+//! }
+//!
+//! // in a transform; local to the exchange, or in UTC, as the pipe is configured
+//! let updated = time::timezone().exchange(chart.updated, chart.offset)?;
+//! ```
+//!
+//! Within a pipe's stages (its decode & transform, say), [`timezone()`] is the pipe's policy, as
+//! set with [`PipeBuilder::timezone()`]; so are the [`de`] helpers that take one.
+//!
+//! [`PipeBuilder::timezone()`]: crate::PipeBuilder::timezone
+
+use super::Error;
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, Utc};
+use std::future::Future;
+
+tokio::task_local! {
+    // the policy of the pipe whose stage is running
+    static TIMEZONE: Timezone;
+}
+
+/// The timezone policy of the pipe whose stage is running; outside of one, the default (UTC).
+pub fn timezone() -> Timezone {
+    TIMEZONE.try_with(|timezone| *timezone).unwrap_or_default()
+}
+
+// Run `fut` (a pipe's stage) with `timezone` as the policy; see `timezone()`.
+pub(crate) async fn scoped<T>(timezone: Timezone, fut: impl Future<Output = T>) -> T {
+    TIMEZONE.scope(timezone, fut).await
+}
+
+/// What to do with the offset of a parsed date/time; see [`PipeBuilder::timezone()`].
+///
+/// [`PipeBuilder::timezone()`]: crate::PipeBuilder::timezone
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Timezone {
+    /// Convert everything to UTC, so outputs from different sources compare directly.
+    #[default]
+    Utc,
+    /// Keep the offset it was given in, e.g., an exchange's local time.
+    Preserve,
+}
+
+impl Timezone {
+    /// Apply the policy to `time`.
+    pub fn normalize(self, time: DateTime<FixedOffset>) -> DateTime<FixedOffset> {
+        match self {
+            Timezone::Utc => time.with_timezone(&Utc).fixed_offset(),
+            Timezone::Preserve => time,
+        }
+    }
+
+    /// Parse an ISO 8601 date/time with [`iso8601()`], then apply the policy.
+    pub fn iso8601(self, s: &str) -> Result<DateTime<FixedOffset>, Error> {
+        Ok(self.normalize(iso8601(s)?))
+    }
+
+    /// The epoch `seconds` in a local time `offset` seconds east of UTC, e.g., an exchange's
+    /// `gmtoffset`; then the policy applied.
+    pub fn exchange(self, seconds: i64, offset: i32) -> Result<DateTime<FixedOffset>, Error> {
+        Ok(self.normalize(exchange_time(seconds, offset)?))
+    }
+
+    /// The dates of a series of epoch `seconds`, e.g., a chart's bars: their dates in UTC, or the
+    /// local ones `offset` seconds east of UTC (see [`exchange_date()`]), as the policy is.
+    pub fn dates(self, seconds: &[i64], offset: i32) -> Result<Vec<NaiveDate>, Error> {
+        seconds
+            .iter()
+            .map(|&seconds| Ok(self.exchange(seconds, offset)?.date_naive()))
+            .collect()
+    }
+}
+
+/// Seconds since the Unix epoch.
+pub fn epoch_seconds(seconds: i64) -> Result<DateTime<Utc>, Error> {
+    DateTime::from_timestamp(seconds, 0)
+        .ok_or_else(|| Error::Time(format!("epoch seconds out of range: {seconds}")))
+}
+
+/// Milliseconds since the Unix epoch, e.g., JavaScript's `Date.now()`.
+pub fn epoch_millis(millis: i64) -> Result<DateTime<Utc>, Error> {
+    DateTime::from_timestamp_millis(millis)
+        .ok_or_else(|| Error::Time(format!("epoch millis out of range: {millis}")))
+}
+
+/// An ISO 8601 date/time: with an offset (RFC 3339); without one, taken as UTC; or a bare
+/// date, taken as midnight UTC.
+pub fn iso8601(s: &str) -> Result<DateTime<FixedOffset>, Error> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+        return Ok(time);
+    }
+    if let Ok(time) = NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%.f") {
+        return Ok(time.and_utc().fixed_offset());
+    }
+    match NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        Ok(date) => Ok(date.and_time(Default::default()).and_utc().fixed_offset()),
+        Err(_) => Err(Error::Time(format!("not an ISO 8601 date/time: {s:?}"))),
+    }
+}
+
+/// The epoch `seconds`, in the local time `offset` seconds east of UTC.
+pub fn exchange_time(seconds: i64, offset: i32) -> Result<DateTime<FixedOffset>, Error> {
+    let offset = FixedOffset::east_opt(offset)
+        .ok_or_else(|| Error::Time(format!("invalid offset: {offset}")))?;
+    Ok(epoch_seconds(seconds)?.with_timezone(&offset))
+}
+
+/// The local date at the epoch `seconds`, `offset` seconds east of UTC; e.g., the trading day
+/// of a bar timestamped at the open, which in UTC might fall on the day before.
+pub fn exchange_date(seconds: i64, offset: i32) -> Result<NaiveDate, Error> {
+    Ok(exchange_time(seconds, offset)?.date_naive())
+}
+
+/// `deserialize_with` helpers, for fields of epoch timestamps.
+pub mod de {
+    use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
+    use serde::{Deserialize, Deserializer};
+
+    fn custom<E: serde::de::Error>(e: super::Error) -> E {
+        E::custom(e)
+    }
+
+    /// Epoch seconds, e.g., `1717200000`.
+    pub fn epoch_seconds<'de, D: Deserializer<'de>>(d: D) -> Result<DateTime<Utc>, D::Error> {
+        super::epoch_seconds(i64::deserialize(d)?).map_err(custom)
+    }
+
+    /// Epoch milliseconds, e.g., `1717200000000`.
+    pub fn epoch_millis<'de, D: Deserializer<'de>>(d: D) -> Result<DateTime<Utc>, D::Error> {
+        super::epoch_millis(i64::deserialize(d)?).map_err(custom)
+    }
+
+    /// An ISO 8601 date/time (see [`iso8601()`](super::iso8601)), with the policy of the pipe
+    /// decoding it applied; see [`timezone()`](super::timezone).
+    pub fn iso8601<'de, D: Deserializer<'de>>(d: D) -> Result<DateTime<FixedOffset>, D::Error> {
+        super::timezone()
+            .iso8601(&String::deserialize(d)?)
+            .map_err(custom)
+    }
+
+    /// An array of epoch seconds, as their dates in UTC; see [`Timezone::dates()`] for local ones.
+    ///
+    /// [`Timezone::dates()`]: super::Timezone::dates
+    pub fn epoch_dates<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<NaiveDate>, D::Error> {
+        Vec::<i64>::deserialize(d)?
+            .into_iter()
+            .map(|seconds| super::epoch_seconds(seconds).map(|time| time.date_naive()))
+            .collect::<Result<_, _>>()
+            .map_err(custom)
+    }
+}
@@ -0,0 +1,74 @@
+//! Skipping sources whose payload hasn't changed since it was last loaded; for scheduled sources
+//! that only change now & then, so most runs needn't transform & load the same data again.
+//!
+//! ```rust,ignore
+//! let pipe = Pipe::<I, O>::builder()
+//!     .source(Source::endpoint("https://example.com/holidays.json"))
+//!     .skip_unchanged(Unchanged::new("holidays.digests.json"))
+//!     .build()?;
+//!
+//! let report = pipe.run().await?;
+//! if report.skipped() > 0 {
+//!     println!("nothing new");
+//! }
+//! ```
+//!
+//! The payload is still fetched on every run (and counts against rate limits & quotas), then
+//! hashed; if its digest is the one saved when that source was last loaded, transform & load are
+//! skipped, and the run reports [`Outcome::Skipped`]\([`Skip::Unchanged`]). A digest is only saved
+//! once its output is loaded, so a failed load is retried from scratch on the next run.
+//!
+//! The payload is the bytes [`ETL::fetch()`] returns: with unchanged payloads skipped, the pipe
+//! extracts with `fetch()` then `decode()`; a pipe with its own `extract()` (see
+//! [`ETL::extracts_by_fetch()`]) fails to run with [`Error::Config`].
+//!
+//! Digests are 64-bit FNV-1a hashes, the same from one build to the next; they aren't
+//! cryptographic.
+//!
+//! [`Outcome::Skipped`]: crate::Outcome::Skipped
+//! [`Skip::Unchanged`]: crate::report::Skip::Unchanged
+//! [`ETL::fetch()`]: crate::ETL::fetch
+//! [`ETL::extracts_by_fetch()`]: crate::ETL::extracts_by_fetch
+
+use super::{fs, sink, Error};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// The digests of the payloads last loaded, by source, in a JSON file; see [`unchanged`](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unchanged {
+    /// The file the digests are kept in; created on the first load.
+    pub path: PathBuf,
+}
+
+impl Unchanged {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Unchanged { path: path.into() }
+    }
+
+    /// The digest of `payload`.
+    pub fn digest(payload: &[u8]) -> String {
+        sink::content_hash(&[payload])
+    }
+
+    /// The digest of the payload last loaded from `source`, if any.
+    pub fn get(&self, source: &str) -> Result<Option<String>, Error> {
+        Ok(self.digests()?.remove(source))
+    }
+
+    // Save `digest` as the one last loaded from `source`.
+    pub(crate) fn save(&self, source: &str, digest: String) -> Result<(), Error> {
+        let mut digests = self.digests()?;
+        digests.insert(source.to_string(), digest);
+        fs::write_atomic(&self.path, &serde_json::to_vec_pretty(&digests)?)?;
+        Ok(())
+    }
+
+    fn digests(&self) -> Result<BTreeMap<String, String>, Error> {
+        match std::fs::read(&self.path) {
+            Ok(saved) => Ok(serde_json::from_slice(&saved)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
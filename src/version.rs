@@ -0,0 +1,120 @@
+use super::sink::Sink;
+use super::{Error, Outcome};
+use serde::Serialize;
+use serde_json::Value;
+
+type Migrate = Box<dyn Fn(u32, Value) -> Result<Value, Error> + Send + Sync>;
+
+/// Wraps a sink, stamping every document it loads with the version of the pipe's output shape.
+///
+/// When a transform changes shape, bump the version, and give a `migrate` hook to upgrade
+/// documents of an older version; they're upgraded whenever they pass through the sink again
+/// (e.g., when re-loading an archive), or can be upgraded on read with [`Versioned::upgrade()`].
+/// So a store never holds a mix of unlabelled formats.
+///
+/// ```rust,ignore
+/// let sink = Versioned::new(sink::File::new("prices.json"), 2)
+///     .migrate(|from, mut doc| {
+///         if from < 2 {
+///             // v2 renamed `ticker` to `symbol`
+///             doc["symbol"] = doc["ticker"].take();
+///         }
+///         Ok(doc)
+///     });
+/// ```
+pub struct Versioned<S> {
+    sink: S,
+    version: u32,
+    field: String,
+    migrate: Option<Migrate>,
+}
+
+impl<S> Versioned<S> {
+    /// Stamp documents loaded to `sink` with `version`, in a `_version` field.
+    pub fn new(sink: S, version: u32) -> Self {
+        Versioned {
+            sink,
+            version,
+            field: "_version".into(),
+            migrate: None,
+        }
+    }
+
+    /// Store the version in `field`, instead of `_version`.
+    pub fn field(mut self, field: impl Into<String>) -> Self {
+        self.field = field.into();
+        self
+    }
+
+    /// Upgrade a document of version `from` (always older than the current one) to the current shape.
+    pub fn migrate<F>(mut self, migrate: F) -> Self
+    where
+        F: Fn(u32, Value) -> Result<Value, Error> + Send + Sync + 'static,
+    {
+        self.migrate = Some(Box::new(migrate));
+        self
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Bring a loaded document up to the current version; a document without a version is
+    /// taken to be current, i.e., fresh from the transform.
+    ///
+    /// Fails with [`Error::Verification`] for documents newer than the current version, or
+    /// older ones without a `migrate` hook to upgrade them.
+    pub fn upgrade(&self, doc: Value) -> Result<Value, Error> {
+        let from = match doc.get(&self.field) {
+            None => self.version,
+            Some(v) => v
+                .as_u64()
+                .and_then(|v| u32::try_from(v).ok())
+                .ok_or_else(|| Error::Verification(format!("invalid {}: {v}", self.field)))?,
+        };
+        let mut doc = match (from, &self.migrate) {
+            (from, _) if from > self.version => {
+                return Err(Error::Verification(format!(
+                    "document is version {from}, newer than version {}",
+                    self.version
+                )))
+            }
+            (from, _) if from == self.version => doc,
+            (from, Some(migrate)) => migrate(from, doc)?,
+            (from, None) => {
+                return Err(Error::Verification(format!(
+                    "no migration from version {from} to {}",
+                    self.version
+                )))
+            }
+        };
+        match doc.as_object_mut() {
+            Some(doc) => doc.insert(self.field.clone(), self.version.into()),
+            None => {
+                return Err(Error::Verification(format!(
+                    "cannot version a non-object document: {doc}"
+                )))
+            }
+        };
+        Ok(doc)
+    }
+}
+
+impl<O, S> Sink<O> for Versioned<S>
+where
+    O: Serialize + Sync + ?Sized,
+    S: Sink<Value>,
+{
+    async fn load(&self, output: &O) -> Result<Outcome, Error> {
+        // each element of an array is a document of its own
+        let output = match serde_json::to_value(output)? {
+            Value::Array(docs) => Value::Array(
+                docs.into_iter()
+                    .map(|doc| self.upgrade(doc))
+                    .collect::<Result<_, _>>()?,
+            ),
+            doc => self.upgrade(doc)?,
+        };
+        self.sink.load(&output).await
+    }
+}
@@ -0,0 +1,132 @@
+use super::error::Context;
+use super::EtlReport;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::future::Future;
+
+tokio::task_local! {
+    // the warnings of the run in progress
+    static WARNINGS: RefCell<Vec<Warning>>;
+}
+
+/// A recoverable anomaly in a run, e.g., a row skipped; the run carries on, and the warning is
+/// collected into its [`EtlReport::warnings`], so a pipe needn't choose between silently
+/// continuing and failing.
+///
+/// Raised from any stage with [`Pipe::warn()`], or [`warn()`] where there's no pipe to hand
+/// (e.g., in a sink):
+///
+/// ```rust,ignore
+/// async fn transform(&self, input: RawPrice) -> pipe_io::Result<Vec<Price>> {
+///     let mut prices = vec![];
+///     for (i, row) in input.rows.into_iter().enumerate() {
+///         match row.volume {
+///             Some(_) => prices.push(row.into()),
+///             None => self.warn(Warning::skipped("no volume").record(i)),
+///         }
+///     }
+///     Ok(prices)
+/// }
+/// ```
+///
+/// [`EtlReport::warnings`]: crate::EtlReport::warnings
+/// [`Pipe::warn()`]: crate::Pipe::warn
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Warning {
+    pub kind: Kind,
+    pub message: String,
+    /// Where the anomaly is, if anywhere in particular; e.g., a record's index.
+    pub context: Context,
+}
+
+/// What kind of anomaly a [`Warning`] is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    /// A missing value was filled in, e.g., with a default.
+    Filled,
+    /// A record was left out of the output.
+    Skipped,
+    /// A value was converted to another type, e.g., a numeric string to a number.
+    Coerced,
+    Other,
+}
+
+impl Warning {
+    pub fn new(kind: Kind, message: impl Into<String>) -> Self {
+        Warning {
+            kind,
+            message: message.into(),
+            context: Context::None,
+        }
+    }
+
+    pub fn filled(message: impl Into<String>) -> Self {
+        Self::new(Kind::Filled, message)
+    }
+
+    pub fn skipped(message: impl Into<String>) -> Self {
+        Self::new(Kind::Skipped, message)
+    }
+
+    pub fn coerced(message: impl Into<String>) -> Self {
+        Self::new(Kind::Coerced, message)
+    }
+
+    pub fn other(message: impl Into<String>) -> Self {
+        Self::new(Kind::Other, message)
+    }
+
+    /// Locate the warning at `context`.
+    pub fn context(mut self, context: Context) -> Self {
+        self.context = context;
+        self
+    }
+
+    /// Locate the warning at the record with index `index`.
+    pub fn record(self, index: usize) -> Self {
+        self.context(Context::Record(index))
+    }
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.context {
+            Context::None => write!(f, "{}", self.message),
+            ref context => write!(f, "{context}: {}", self.message),
+        }
+    }
+}
+
+/// Add `warning` to the report of the run in progress; outside of a run, it's logged to stderr.
+pub fn warn(warning: Warning) {
+    let mut warning = Some(warning);
+    let _ = WARNINGS.try_with(|warnings| warnings.borrow_mut().extend(warning.take()));
+    if let Some(warning) = warning {
+        eprintln!("warning: {warning}");
+    }
+}
+
+// How many warnings the run in progress has raised so far.
+#[cfg(feature = "smtp")]
+pub(crate) fn raised() -> usize {
+    WARNINGS
+        .try_with(|warnings| warnings.borrow().len())
+        .unwrap_or(0)
+}
+
+// Run `run`, collecting the warnings raised meanwhile into its report.
+pub(crate) async fn collect<E>(
+    run: impl Future<Output = Result<EtlReport, E>>,
+) -> Result<EtlReport, E> {
+    WARNINGS
+        .scope(RefCell::new(vec![]), async {
+            let result = run.await;
+            let warnings = WARNINGS.with(|warnings| warnings.take());
+            result.map(|mut report| {
+                report.warnings.extend(warnings);
+                report
+            })
+        })
+        .await
+}
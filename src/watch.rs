@@ -0,0 +1,302 @@
+//! Re-running a pipe whenever its source file changes; for local development loops, and files
+//! dropped in place by another process. Or, for a folder that many files are dropped into, see
+//! [`DropFolder`].
+//!
+//! ```rust,ignore
+//! let pipe = Pipe::<I, O>::builder()
+//!     .source(Source::endpoint("data/prices.json"))
+//!     .sink(sink::File::new("out/prices.json"))
+//!     .build()?;
+//!
+//! // runs straight away, then after every change; until the future is dropped
+//! pipe.watch(Duration::from_millis(200), |result| match result {
+//!     Ok(report) => eprintln!("loaded {} rows", report.rows_affected()),
+//!     Err(e) => eprintln!("run failed: {e}"),
+//! })
+//! .await?;
+//! ```
+
+use super::warning::{warn, Warning};
+use super::{Error, EtlReport, Input, Output, Pipe, Source, ETL};
+use chrono::{DateTime, Utc};
+use notify::{RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+impl<I, O> Pipe<I, O>
+where
+    I: Input,
+    O: Output,
+    Self: ETL<I, O>,
+{
+    /// Run the pipe, then run it again whenever its source file changes, handing every result to
+    /// `on_run`; changes less than `debounce` apart (e.g., a file written in several chunks) make
+    /// one run, once they've settled.
+    ///
+    /// A failed run doesn't stop the watch. Returns [`Error::Config`] if the source isn't a local
+    /// file, or [`Error::Watch`] if the file can't be watched.
+    pub async fn watch<F>(&self, debounce: Duration, mut on_run: F) -> Result<(), Error>
+    where
+        F: FnMut(Result<EtlReport, Error>),
+    {
+        let file = match &self.source {
+            Some(Source::Endpoint(path)) if !path.starts_with("http") => std::path::absolute(path)?,
+            _ => {
+                return Err(Error::Config(
+                    "only a pipe with a local file source can be watched".into(),
+                ))
+            }
+        };
+
+        // the directory is watched, rather than the file, to see it replaced (as editors do)
+        let (tx, mut changes) = mpsc::unbounded_channel();
+        let target = file.clone();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                if let Ok(event) = event {
+                    if changed(&event, &target) {
+                        let _ = tx.send(());
+                    }
+                }
+            })?;
+        let dir = file.parent().unwrap_or(Path::new("/"));
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
+        on_run(self.run().await);
+        while changes.recv().await.is_some() {
+            // wait for the changes to settle
+            while let Ok(Some(())) = tokio::time::timeout(debounce, changes.recv()).await {}
+            on_run(self.run().await);
+        }
+        Ok(())
+    }
+}
+
+// Does `event` write to (or replace) `file`?
+fn changed(event: &notify::Event, file: &Path) -> bool {
+    use notify::EventKind;
+    matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+        && event.paths.iter().any(|path| path == file)
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+// drop folder
+/////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// A directory that files are dropped into (e.g., by a partner's SFTP upload), each to be run
+/// through a pipe, then moved out of the way; see [`Pipe::ingest()`].
+///
+/// A file that loads is moved to `processed/`, and one that fails to `failed/` (both inside the
+/// folder, by default); either way with a sidecar report, `<file>.report.json`, as an [`Ingested`].
+/// A file named like one already moved there is renamed, e.g., `prices-1.json`, rather than
+/// replacing it.
+///
+/// ```rust,ignore
+/// let inbox = DropFolder::new("/srv/inbox").extension("json");
+/// pipe.ingest(&inbox, |ingested| eprintln!("{}: {:?}", ingested.file, ingested.error)).await?;
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct DropFolder {
+    pub dir: PathBuf,
+    pub processed: PathBuf,
+    pub failed: PathBuf,
+    pub extension: Option<String>,
+    pub debounce: Duration,
+}
+
+/// What became of one file of a [`DropFolder`]; written beside it as its sidecar report.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Ingested {
+    /// The file's name.
+    pub file: String,
+    /// Where the file was moved to; where it was, if it couldn't be.
+    pub moved_to: PathBuf,
+    /// When the file was run.
+    pub at: DateTime<Utc>,
+    /// What the sink reported; empty if the file failed.
+    pub report: EtlReport,
+    pub error: Option<String>,
+}
+
+impl DropFolder {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        let dir = dir.into();
+        DropFolder {
+            processed: dir.join("processed"),
+            failed: dir.join("failed"),
+            dir,
+            extension: None,
+            debounce: Duration::from_millis(500),
+        }
+    }
+
+    /// Move loaded files to `dir`, instead of `processed/`.
+    pub fn processed(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.processed = dir.into();
+        self
+    }
+
+    /// Move failed files to `dir`, instead of `failed/`.
+    pub fn failed(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.failed = dir.into();
+        self
+    }
+
+    /// Only take files with this extension (e.g., `json`); others are left where they are.
+    pub fn extension(mut self, extension: impl Into<String>) -> Self {
+        self.extension = Some(extension.into());
+        self
+    }
+
+    /// How long a new file must be left unchanged before it's taken, so it isn't taken while it's
+    /// still being written. Defaults to 500ms.
+    pub fn debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    // The files waiting in the folder, oldest name first.
+    fn waiting(&self) -> Result<Vec<PathBuf>, Error> {
+        let mut files = vec![];
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if self.takes(&path) {
+                files.push(path);
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    fn takes(&self, path: &Path) -> bool {
+        path.parent() == Some(self.dir.as_path())
+            && path.is_file()
+            && match &self.extension {
+                Some(extension) => path
+                    .extension()
+                    .is_some_and(|ext| ext == extension.as_str()),
+                None => true,
+            }
+    }
+}
+
+impl<I, O> Pipe<I, O>
+where
+    I: Input,
+    O: Output,
+    Self: ETL<I, O>,
+{
+    /// Run every file in `folder` through the pipe, in turn, then each new one as it arrives;
+    /// handing what became of each to `on_file`, until the future is dropped. The pipe's own
+    /// source isn't used.
+    ///
+    /// A failed file doesn't stop the rest; nor does one that can't be moved, which is left where
+    /// it is and not taken again, or whose report can't be written; each a [`Warning`]. Returns
+    /// [`Error::IO`] if the folder can't be listed, or [`Error::Watch`] if it can't be watched.
+    pub async fn ingest<F>(&self, folder: &DropFolder, mut on_file: F) -> Result<(), Error>
+    where
+        F: FnMut(&Ingested),
+    {
+        let dir = std::path::absolute(&folder.dir)?;
+        let folder = DropFolder {
+            dir,
+            ..folder.clone()
+        };
+        std::fs::create_dir_all(&folder.processed)?;
+        std::fs::create_dir_all(&folder.failed)?;
+
+        // watching starts before the first listing, so no file falls between the two
+        let (tx, mut arrivals) = mpsc::unbounded_channel();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                if let Ok(event) = event {
+                    let _ = tx.send(event);
+                }
+            })?;
+        watcher.watch(&folder.dir, RecursiveMode::NonRecursive)?;
+
+        // the files that couldn't be moved, so aren't taken again
+        let mut stuck = HashSet::new();
+        loop {
+            for file in folder.waiting()? {
+                if stuck.contains(&file) {
+                    continue;
+                }
+                let ingested = self.ingest_file(&folder, &file).await;
+                if ingested.moved_to == file {
+                    stuck.insert(file);
+                }
+                on_file(&ingested);
+            }
+            // wait for a new file, then for it to settle
+            if arrivals.recv().await.is_none() {
+                return Ok(());
+            }
+            while let Ok(Some(_)) = tokio::time::timeout(folder.debounce, arrivals.recv()).await {}
+        }
+    }
+
+    async fn ingest_file(&self, folder: &DropFolder, file: &Path) -> Ingested {
+        let at = self.clock.now();
+        let result = self.etl_many([file.to_string_lossy().into_owned()]).await;
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        let to = match result {
+            Ok(_) => &folder.processed,
+            Err(_) => &folder.failed,
+        };
+        let (report, mut error) = match result {
+            Ok(report) => (report, None),
+            Err(errors) => (EtlReport::new(), Some(Error::Many(errors).to_string())),
+        };
+
+        let mut moved_to = vacant(to, &name);
+        if let Err(e) = std::fs::rename(file, &moved_to) {
+            let message = format!("{name} couldn't be moved to {}: {e}", moved_to.display());
+            warn(Warning::other(&message));
+            error = Some(match error {
+                Some(error) => format!("{error}; {message}"),
+                None => message,
+            });
+            moved_to = file.to_path_buf();
+        }
+        let ingested = Ingested {
+            file: name.into_owned(),
+            moved_to,
+            at,
+            report,
+            error,
+        };
+        let moved_as = ingested.moved_to.file_name().unwrap_or_default();
+        let sidecar = to.join(format!("{}.report.json", moved_as.to_string_lossy()));
+        let written = serde_json::to_vec_pretty(&ingested)
+            .map_err(Error::from)
+            .and_then(|json| Ok(std::fs::write(&sidecar, json)?));
+        if let Err(e) = written {
+            warn(Warning::other(format!(
+                "the report of {} couldn't be written: {e}",
+                ingested.file
+            )));
+        }
+        ingested
+    }
+}
+
+// A path in `dir` for a file named `name`, that no file has yet: `name` itself, or else with a
+// number after its stem, e.g., `prices-1.json`.
+fn vacant(dir: &Path, name: &str) -> PathBuf {
+    let path = dir.join(name);
+    if !path.exists() {
+        return path;
+    }
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, format!(".{extension}")),
+        _ => (name, String::new()),
+    };
+    (1..)
+        .map(|n| dir.join(format!("{stem}-{n}{extension}")))
+        .find(|path| !path.exists())
+        .unwrap_or(path)
+}
@@ -0,0 +1,260 @@
+use super::Error;
+use futures::future::{BoxFuture, FutureExt};
+use futures::stream::{self, BoxStream, Stream, StreamExt};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// A record, with up to `behind` records before it and `ahead` after it; see [`iter()`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Windowed<T> {
+    /// The records before, oldest first; fewer than `behind` at the start.
+    pub before: Vec<T>,
+    pub current: T,
+    /// The records after, nearest first; fewer than `ahead` at the end.
+    pub after: Vec<T>,
+}
+
+impl<T> Windowed<T> {
+    /// The record just before, if any.
+    pub fn prev(&self) -> Option<&T> {
+        self.before.last()
+    }
+
+    /// The record just after, if any.
+    pub fn next(&self) -> Option<&T> {
+        self.after.first()
+    }
+}
+
+// Only `behind + 1 + ahead` records are held at a time.
+struct Windows<T> {
+    behind: usize,
+    ahead: usize,
+    history: VecDeque<T>,
+    pending: VecDeque<T>,
+}
+
+impl<T: Clone> Windows<T> {
+    fn new(behind: usize, ahead: usize) -> Self {
+        Windows {
+            behind,
+            ahead,
+            history: VecDeque::with_capacity(behind + 1),
+            pending: VecDeque::with_capacity(ahead + 1),
+        }
+    }
+
+    // the window of the oldest pending record, once it has enough records after it
+    fn push(&mut self, record: T) -> Option<Windowed<T>> {
+        self.pending.push_back(record);
+        (self.pending.len() > self.ahead)
+            .then(|| self.emit())
+            .flatten()
+    }
+
+    // the window of the oldest pending record, with what there is after it
+    fn emit(&mut self) -> Option<Windowed<T>> {
+        let current = self.pending.pop_front()?;
+        let windowed = Windowed {
+            before: self.history.iter().cloned().collect(),
+            after: self.pending.iter().take(self.ahead).cloned().collect(),
+            current: current.clone(),
+        };
+        if self.behind > 0 {
+            if self.history.len() == self.behind {
+                self.history.pop_front();
+            }
+            self.history.push_back(current);
+        }
+        Some(windowed)
+    }
+}
+
+/// Each of `records`, with up to `behind` records before it and `ahead` after it, e.g., to
+/// compute day-over-day changes in a transform:
+///
+/// ```rust,ignore
+/// let changes = window::iter(prices, 1, 0)
+///     .filter_map(|w| w.prev().map(|prev| w.current.close - prev.close))
+///     .collect::<Vec<_>>();
+/// ```
+///
+/// Records are read lazily, and only `behind + 1 + ahead` are held at a time.
+pub fn iter<T, R>(records: R, behind: usize, ahead: usize) -> impl Iterator<Item = Windowed<T>>
+where
+    T: Clone,
+    R: IntoIterator<Item = T>,
+{
+    let mut windows = Windows::new(behind, ahead);
+    let mut records = records.into_iter().fuse();
+    std::iter::from_fn(move || {
+        for record in records.by_ref() {
+            if let Some(windowed) = windows.push(record) {
+                return Some(windowed);
+            }
+        }
+        windows.emit()
+    })
+}
+
+/// [`iter()`], for a stream of records, e.g., a streaming [`Source`]'s; errors are passed straight through.
+///
+/// [`Source`]: crate::Source
+pub fn stream<T, S>(
+    records: S,
+    behind: usize,
+    ahead: usize,
+) -> BoxStream<'static, Result<Windowed<T>, Error>>
+where
+    T: Clone + Send + 'static,
+    S: Stream<Item = Result<T, Error>> + Send + 'static,
+{
+    let windows = Windows::new(behind, ahead);
+    stream::unfold(
+        (records.fuse().boxed(), windows),
+        |(mut records, mut windows)| async move {
+            while let Some(record) = records.next().await {
+                match record {
+                    Ok(record) => {
+                        if let Some(windowed) = windows.push(record) {
+                            return Some((Ok(windowed), (records, windows)));
+                        }
+                    }
+                    Err(e) => return Some((Err(e), (records, windows))),
+                }
+            }
+            let windowed = windows.emit()?;
+            Some((Ok(windowed), (records, windows)))
+        },
+    )
+    .boxed()
+}
+
+/// When [`batches()`] of a stream are handed on: once they have `records`, or once `wait` has
+/// passed since their first record; whichever is first. With neither, the whole stream is one
+/// batch.
+///
+/// ```rust,ignore
+/// // every 1000 records or 30 seconds
+/// let batch = Batch::new().records(1000).wait(Duration::from_secs(30));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Batch {
+    pub records: Option<usize>,
+    pub wait: Option<Duration>,
+}
+
+impl Batch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Hand on a batch once it has `records`; at least 1.
+    pub fn records(mut self, records: usize) -> Self {
+        self.records = Some(records.max(1));
+        self
+    }
+
+    /// Hand on a batch once `wait` has passed since its first record.
+    pub fn wait(mut self, wait: Duration) -> Self {
+        self.wait = Some(wait);
+        self
+    }
+}
+
+// the state of `batches()`
+struct Batches<T> {
+    records: BoxStream<'static, Result<T, Error>>,
+    shutdown: BoxFuture<'static, ()>,
+    batch: Batch,
+    pending: Vec<T>,
+    // when the pending batch is due, by its wait
+    due: Option<Instant>,
+    // an error, held back until the batch before it is handed on
+    error: Option<Error>,
+    done: bool,
+}
+
+enum Next<T> {
+    Record(Option<Result<T, Error>>),
+    Due,
+    Shutdown,
+}
+
+/// `records`, accumulated into batches by `batch`, e.g., for a sink to load many at once; see
+/// [`Source::batched()`].
+///
+/// Once `shutdown` completes, or the stream ends, the pending batch is handed on, and the stream
+/// ends; an error is passed straight through, after the batch pending before it.
+///
+/// [`Source::batched()`]: crate::Source::batched
+pub fn batches<T, S, F>(
+    records: S,
+    batch: Batch,
+    shutdown: F,
+) -> BoxStream<'static, Result<Vec<T>, Error>>
+where
+    T: Send + 'static,
+    S: Stream<Item = Result<T, Error>> + Send + 'static,
+    F: Future<Output = ()> + Send + 'static,
+{
+    let batches = Batches {
+        records: records.fuse().boxed(),
+        shutdown: shutdown.boxed(),
+        batch,
+        pending: vec![],
+        due: None,
+        error: None,
+        done: false,
+    };
+    stream::unfold(batches, |mut batches| async move {
+        if let Some(e) = batches.error.take() {
+            return Some((Err(e), batches));
+        }
+        while !batches.done {
+            let due = batches.due;
+            let next = tokio::select! {
+                biased;
+                _ = &mut batches.shutdown => Next::Shutdown,
+                _ = tokio::time::sleep_until(due.unwrap_or_else(Instant::now)), if due.is_some() => Next::Due,
+                record = batches.records.next() => Next::Record(record),
+            };
+            match next {
+                Next::Record(Some(Ok(record))) => {
+                    if batches.pending.is_empty() {
+                        batches.due = batches.batch.wait.map(|wait| Instant::now() + wait);
+                    }
+                    batches.pending.push(record);
+                    if batches.batch.records.is_some_and(|records| batches.pending.len() >= records) {
+                        return Some((Ok(batches.take()), batches));
+                    }
+                }
+                Next::Record(Some(Err(e))) => match batches.pending.is_empty() {
+                    true => return Some((Err(e), batches)),
+                    false => {
+                        batches.error = Some(e);
+                        return Some((Ok(batches.take()), batches));
+                    }
+                },
+                Next::Due => return Some((Ok(batches.take()), batches)),
+                Next::Record(None) | Next::Shutdown => {
+                    batches.done = true;
+                    if !batches.pending.is_empty() {
+                        return Some((Ok(batches.take()), batches));
+                    }
+                }
+            }
+        }
+        None
+    })
+    .boxed()
+}
+
+impl<T> Batches<T> {
+    fn take(&mut self) -> Vec<T> {
+        self.due = None;
+        std::mem::take(&mut self.pending)
+    }
+}
@@ -0,0 +1,149 @@
+use serde_json::Value;
+
+// header, query parameter and JSON field names (lowercase substrings) whose values are never logged
+const SECRETS: [&str; 7] = [
+    "authorization",
+    "password",
+    "secret",
+    "token",
+    "cookie",
+    "api-key",
+    "api_key",
+];
+
+/// Debug logging of HTTP requests & responses, written to stderr.
+///
+/// Credentials are redacted before anything is logged: passwords in URLs, sensitive headers
+/// (e.g., `Authorization`), and JSON fields with names like `password` or `token`.
+///
+/// ```rust,ignore
+/// let pipe = Pipe::<I, O>::builder().wire_log(WireLog::new().max_body(512)).build()?;
+/// let sink = sink::CouchDb::new(conn, "doc").wire_log(WireLog::new());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct WireLog {
+    /// Bodies are truncated to this many bytes.
+    pub max_body: usize,
+}
+
+impl Default for WireLog {
+    fn default() -> Self {
+        WireLog { max_body: 1024 }
+    }
+}
+
+impl WireLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_body(mut self, max_body: usize) -> Self {
+        self.max_body = max_body;
+        self
+    }
+
+    /// Log an outgoing request: method, URL, headers and body.
+    pub fn request(&self, request: &reqwest::Request) {
+        eprintln!("--> {} {}", request.method(), redact_url(request.url()));
+        self.headers(request.headers());
+        if let Some(body) = request.body().and_then(reqwest::Body::as_bytes) {
+            self.body(&String::from_utf8_lossy(body));
+        }
+    }
+
+    /// Log an incoming response's status & headers; its body is logged separately, with [`body()`].
+    ///
+    /// [`body()`]: WireLog::body
+    pub fn response(&self, response: &reqwest::Response) {
+        eprintln!("<-- {} {}", response.status(), redact_url(response.url()));
+        self.headers(response.headers());
+    }
+
+    /// Log a (request or response) body, redacted and truncated.
+    pub fn body(&self, body: &str) {
+        let body = redact_body(body);
+        match truncate(&body, self.max_body) {
+            head if head.len() < body.len() => {
+                eprintln!("    {head}... ({} bytes in total)", body.len())
+            }
+            _ => eprintln!("    {body}"),
+        }
+    }
+
+    fn headers(&self, headers: &reqwest::header::HeaderMap) {
+        for (name, value) in headers {
+            let value = match is_secret(name.as_str()) {
+                true => "***",
+                false => value.to_str().unwrap_or("<binary>"),
+            };
+            eprintln!("    {name}: {value}");
+        }
+    }
+}
+
+pub(crate) fn is_secret(name: &str) -> bool {
+    let name = name.to_lowercase();
+    SECRETS.iter().any(|secret| name.contains(secret))
+}
+
+/// The URL with its password and any secret query parameters replaced by `***`.
+pub fn redact_url(url: &reqwest::Url) -> String {
+    let mut url = url.clone();
+    if url.password().is_some() {
+        let _ = url.set_password(Some("***"));
+    }
+    if url.query_pairs().any(|(key, _)| is_secret(&key)) {
+        let pairs = url
+            .query_pairs()
+            .map(|(key, value)| match is_secret(&key) {
+                true => (key.into_owned(), "***".to_string()),
+                false => (key.into_owned(), value.into_owned()),
+            })
+            .collect::<Vec<_>>();
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    url.to_string()
+}
+
+// A proxy's URL, with its password replaced by `***`; all of it, if it isn't a URL of a host.
+pub(crate) fn redact_proxy(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(url) if url.has_host() => redact_url(&url),
+        _ => "***".to_string(),
+    }
+}
+
+/// The first `max` bytes of `body`, or fewer, so as not to split a character.
+pub fn truncate(body: &str, max: usize) -> &str {
+    let mut end = max.min(body.len());
+    while !body.is_char_boundary(end) {
+        end -= 1;
+    }
+    &body[..end]
+}
+
+/// The body with secret JSON fields replaced by `"***"`; non-JSON bodies are returned unchanged.
+pub fn redact_body(body: &str) -> String {
+    match serde_json::from_str::<Value>(body) {
+        Ok(mut json) => {
+            redact_value(&mut json);
+            json.to_string()
+        }
+        Err(_) => body.to_string(),
+    }
+}
+
+fn redact_value(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                match is_secret(key) {
+                    true => *value = Value::String("***".into()),
+                    false => redact_value(value),
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(redact_value),
+        _ => {}
+    }
+}
@@ -0,0 +1,63 @@
+// Top-level JSON arrays, decoded one element at a time.
+
+use futures::StreamExt;
+use pipe_io::{array, Error};
+use serde::Deserialize;
+use tokio::io::AsyncWriteExt;
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Price {
+    ticker: String,
+    close: f64,
+}
+
+// reads `json` back a few bytes at a time, so elements are split across reads
+fn trickle(json: &'static str) -> tokio::io::DuplexStream {
+    let (mut writer, reader) = tokio::io::duplex(4);
+    tokio::spawn(async move { writer.write_all(json.as_bytes()).await.unwrap() });
+    reader
+}
+
+#[tokio::test]
+async fn array_yields_each_element() {
+    let json = r#" [ {"ticker": "A,]", "close": 1.5}, {"ticker": "B\"[", "close": 2} ] "#;
+    let prices: Vec<Price> = array::from_reader(trickle(json))
+        .map(Result::unwrap)
+        .collect()
+        .await;
+    assert_eq!(
+        prices,
+        vec![
+            Price {
+                ticker: "A,]".into(),
+                close: 1.5
+            },
+            Price {
+                ticker: "B\"[".into(),
+                close: 2.0
+            },
+        ]
+    );
+
+    let empty: Vec<_> = array::from_reader::<Price, _>(trickle("[]"))
+        .collect()
+        .await;
+    assert!(empty.is_empty());
+}
+
+#[tokio::test]
+async fn array_rejects_malformed_input() {
+    for json in [
+        r#"{"ticker": "A"}"#,
+        r#"[{"ticker": "A", "close": 1},"#,
+        "[1,,2]",
+    ] {
+        let results: Vec<_> = array::from_reader::<Price, _>(trickle(json))
+            .collect()
+            .await;
+        assert!(
+            matches!(results.last(), Some(Err(Error::JSON(_)))),
+            "{json} should fail"
+        );
+    }
+}
@@ -0,0 +1,81 @@
+// Avro encoding, to files & framed for a schema registry; only with `--features avro`.
+#![cfg(feature = "avro")]
+
+use apache_avro::AvroSchema;
+use pipe_io::avro::{Avro, AvroFile, SchemaRegistry};
+use pipe_io::{Error, Outcome, Sink};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+mod common;
+
+#[derive(Serialize, Deserialize, AvroSchema, Debug, PartialEq)]
+struct Price {
+    ticker: String,
+    close: f64,
+}
+
+fn prices() -> Vec<Price> {
+    vec![
+        Price {
+            ticker: "NVDA".into(),
+            close: 120.5,
+        },
+        Price {
+            ticker: "AAPL".into(),
+            close: 210.0,
+        },
+    ]
+}
+
+#[tokio::test]
+async fn avro_file_sink_writes_a_container_file() {
+    let dir = std::env::temp_dir().join("pipe-io-test-avro");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("prices.avro");
+
+    let outcome = AvroFile::new(&path, Avro::of::<Price>())
+        .load(&prices())
+        .await
+        .unwrap();
+    let bytes = std::fs::read(&path).unwrap();
+    assert_eq!(
+        outcome,
+        Outcome::File {
+            bytes: bytes.len() as u64
+        }
+    );
+    let read: Vec<Price> = apache_avro::Reader::new(bytes.as_slice())
+        .unwrap()
+        .map(|value| apache_avro::from_value(&value.unwrap()).unwrap())
+        .collect();
+    assert_eq!(read, prices());
+
+    // records that don't match the schema fail, before the file is touched
+    let schema =
+        r#"{ "type": "record", "name": "Price", "fields": [{ "name": "close", "type": "int" }] }"#;
+    let result = AvroFile::new(&path, Avro::parse(schema).unwrap())
+        .load(&prices())
+        .await;
+    assert!(matches!(result, Err(Error::Avro(_))));
+    assert_eq!(std::fs::read(&path).unwrap(), bytes);
+}
+
+#[tokio::test]
+async fn schemas_are_registered_once_and_framed_with_their_id() {
+    let (url, mut requests) = common::serve(|_| common::Response::json(&json!({ "id": 7 }))).await;
+
+    let avro = Avro::of::<Price>();
+    let registry = SchemaRegistry::new(url);
+    assert_eq!(registry.id("prices-value", avro.schema()).await.unwrap(), 7);
+    assert_eq!(registry.id("prices-value", avro.schema()).await.unwrap(), 7);
+    let request = requests.recv().await.unwrap();
+    assert_eq!(request.method, "POST");
+    assert_eq!(request.target, "/subjects/prices-value/versions");
+    assert!(request.body.contains(r#"\"name\":\"Price\""#));
+    assert!(requests.try_recv().is_err());
+
+    let framed = avro.framed(7, &prices()[0]).unwrap();
+    assert_eq!(framed[..5], [0, 0, 0, 0, 7]);
+    assert_eq!(framed[5..], avro.datum(&prices()[0]).unwrap());
+}
@@ -0,0 +1,195 @@
+// Rows streamed to a local stand-in for the BigQuery API.
+#![cfg(feature = "bigquery")]
+
+use pipe_io::bigquery::{BigQuery, Field, Mode, Schema, Type};
+use pipe_io::{Error, Outcome, RetryPolicy, Sink};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+mod common;
+use common::{serve, Response};
+
+fn sink(url: &str) -> BigQuery {
+    BigQuery::new("project", "markets", "prices")
+        .endpoint(url)
+        .token("secret")
+        .run_id("run-1")
+        .retry(RetryPolicy::new(3).backoff(Duration::from_millis(1), Duration::from_millis(1)))
+}
+
+#[tokio::test]
+async fn rows_are_streamed_in_batches_and_retried_on_quota() {
+    // the first request hits the rate limit
+    let mut requests = 0;
+    let (url, mut received) = serve(move |_| {
+        requests += 1;
+        match requests {
+            1 => Response::status("403 Forbidden").body(
+                json!({ "error": { "message": "slow down", "errors": [{ "reason": "rateLimitExceeded" }] } })
+                    .to_string(),
+            ),
+            _ => Response::json(&json!({})),
+        }
+    })
+    .await;
+
+    let rows: Vec<Value> = (1..=3).map(|i| json!({ "id": i })).collect();
+    let batched = sink(&url).batch(2);
+    assert_eq!(
+        batched.load(&rows).await.unwrap(),
+        Outcome::Streamed { rows: 3 }
+    );
+
+    let requests: Vec<_> = std::iter::from_fn(|| received.try_recv().ok()).collect();
+    assert_eq!(requests.len(), 3);
+    assert_eq!(
+        requests[0].target,
+        "/projects/project/datasets/markets/tables/prices/insertAll"
+    );
+    assert_eq!(requests[0].header("authorization"), Some("Bearer secret"));
+    // the retry is the same request, with the same insert ids
+    assert_eq!(requests[0].json(), requests[1].json());
+    let batches: Vec<Value> = requests[1..].iter().map(|request| request.json()).collect();
+    assert_eq!(batches[0]["rows"][1]["json"], json!({ "id": 2 }));
+    assert_eq!(batches[1]["rows"].as_array().unwrap().len(), 1);
+
+    // the same rows, in the same run, have the same ids
+    let ids = |batch: &Value| batch["rows"][0]["insertId"].clone();
+    let (url, mut again) = serve(|_| Response::json(&json!({}))).await;
+    sink(&url).batch(2).load(&rows).await.unwrap();
+    assert_eq!(ids(&again.recv().await.unwrap().json()), ids(&batches[0]));
+}
+
+#[tokio::test]
+async fn rejected_rows_fail_the_load() {
+    let rejected = json!({
+        "insertErrors": [
+            { "index": 0, "errors": [{ "reason": "stopped", "message": "" }] },
+            { "index": 1, "errors": [{ "reason": "invalid", "message": "no such field: tiker" }] }
+        ]
+    });
+    let (url, mut received) = serve(move |_| Response::json(&rejected)).await;
+    let rows = json!([{ "ticker": "NVDA" }, { "tiker": "AAPL" }]);
+    let result = sink(&url).load(&rows).await;
+    assert!(matches!(
+        result,
+        Err(Error::Upstream(message)) if message == "rows rejected: row 1: no such field: tiker"
+    ));
+    // not retried
+    received.recv().await.unwrap();
+    assert!(received.try_recv().is_err());
+
+    let result = sink(&url).load(&json!([1, 2])).await;
+    assert!(matches!(result, Err(Error::Config(_))));
+}
+
+#[tokio::test]
+async fn a_rejected_batch_leaves_the_batches_before_it_inserted() {
+    let mut requests = 0;
+    let (url, mut received) = serve(move |_| {
+        requests += 1;
+        match requests {
+            1 => Response::json(&json!({})),
+            _ => Response::json(&json!({
+                "insertErrors": [{ "index": 0, "errors": [{ "reason": "invalid", "message": "bad" }] }]
+            })),
+        }
+    })
+    .await;
+    let rows: Vec<Value> = (1..=3).map(|i| json!({ "id": i })).collect();
+    assert!(sink(&url).batch(2).load(&rows).await.is_err());
+    let requests: Vec<_> = std::iter::from_fn(|| received.try_recv().ok()).collect();
+    assert_eq!(requests.len(), 2);
+
+    // retried, the first batch has the same ids, so isn't inserted twice
+    let (url, mut again) = serve(|_| Response::json(&json!({}))).await;
+    sink(&url).batch(2).load(&rows).await.unwrap();
+    assert_eq!(again.recv().await.unwrap().json(), requests[0].json());
+}
+
+#[tokio::test]
+async fn names_are_encoded_in_request_paths() {
+    let (url, mut received) = serve(|_| Response::json(&json!({}))).await;
+    let sink = BigQuery::new("my project", "markets/eu", "prices?")
+        .endpoint(&url)
+        .token("secret")
+        .create_missing();
+    sink.load(&json!([{ "ticker": "NVDA" }])).await.unwrap();
+    assert_eq!(
+        received.recv().await.unwrap().target,
+        "/projects/my%20project/datasets/markets%2Feu/tables"
+    );
+    assert_eq!(
+        received.recv().await.unwrap().target,
+        "/projects/my%20project/datasets/markets%2Feu/tables/prices%3F/insertAll"
+    );
+}
+
+#[tokio::test]
+async fn missing_tables_are_created_from_the_inferred_schema() {
+    let (url, mut received) = serve(|_| Response::json(&json!({}))).await;
+    let creating = sink(&url).create_missing();
+    creating.load(&json!([{ "ticker": "NVDA" }])).await.unwrap();
+    creating.load(&json!([{ "ticker": "AAPL" }])).await.unwrap();
+
+    let create = received.recv().await.unwrap();
+    assert_eq!(create.target, "/projects/project/datasets/markets/tables");
+    let create = create.json();
+    assert_eq!(create["tableReference"]["tableId"], "prices");
+    assert_eq!(
+        create["schema"],
+        json!({ "fields": [{ "name": "ticker", "type": "STRING", "mode": "NULLABLE" }] })
+    );
+    // only once
+    let inserts: Vec<_> = std::iter::from_fn(|| received.try_recv().ok()).collect();
+    assert_eq!(inserts.len(), 2);
+    assert!(inserts
+        .iter()
+        .all(|request| request.target.ends_with("/insertAll")));
+}
+
+#[test]
+fn schemas_are_inferred_from_records() {
+    let records = json!([
+        {
+            "id": 1,
+            "price": 10,
+            "at": "2024-06-01T09:30:00Z",
+            "on": "2024-06-01",
+            "tags": ["a"],
+            "venue": { "name": "NASDAQ", "open": true },
+            "note": null
+        },
+        { "id": 2, "price": 10.5, "at": "2024-06-01T09:31:00Z", "on": "soon", "tags": "b", "ask": 11 }
+    ]);
+    let field = |name: &str, kind, mode| Field {
+        name: name.into(),
+        kind,
+        mode,
+        fields: vec![],
+    };
+    assert_eq!(
+        Schema::infer(&records).unwrap(),
+        Schema {
+            fields: vec![
+                // in alphabetical order, though first seen in the second record
+                field("ask", Type::Int64, Mode::Nullable),
+                field("at", Type::Timestamp, Mode::Nullable),
+                field("id", Type::Int64, Mode::Nullable),
+                field("note", Type::Json, Mode::Nullable),
+                field("on", Type::String, Mode::Nullable),
+                field("price", Type::Float64, Mode::Nullable),
+                // an array in one record, but not another
+                field("tags", Type::Json, Mode::Nullable),
+                Field {
+                    fields: vec![
+                        field("name", Type::String, Mode::Nullable),
+                        field("open", Type::Bool, Mode::Nullable),
+                    ],
+                    ..field("venue", Type::Record, Mode::Nullable)
+                },
+            ]
+        }
+    );
+    assert!(Schema::infer(&json!(["a"])).is_err());
+}
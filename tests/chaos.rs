@@ -0,0 +1,83 @@
+// Injected failures; only with `--features chaos`.
+#![cfg(feature = "chaos")]
+
+use pipe_io::chaos::{Chaos, Fault, Faulty};
+use pipe_io::observer::Stage;
+use pipe_io::{pipeline, sink, Error, Outcome, Pipe, RetryPolicy, Sink, Source};
+use serde::Deserialize;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[derive(Deserialize, Debug)]
+struct Values {
+    values: Vec<i32>,
+}
+
+pipeline! {
+    Values -> Vec<i32> {
+        async fn transform(&self, input: Values) -> pipe_io::Result<Vec<i32>> {
+            Ok(input.values)
+        }
+    }
+}
+
+// every batch that reached the sink
+#[derive(Clone, Default)]
+struct Batches(Arc<Mutex<Vec<Vec<i32>>>>);
+
+impl Sink<[i32]> for Batches {
+    async fn load(&self, output: &[i32]) -> pipe_io::Result<Outcome> {
+        self.0.lock().unwrap().push(output.to_vec());
+        Ok(Outcome::Done)
+    }
+}
+
+fn input(name: &str) -> String {
+    let dir = std::env::temp_dir().join(format!("pipe-io-test-{name}"));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("input.json");
+    std::fs::write(&path, r#"{ "values": [1, 2, 3, 4, 5] }"#).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn injected_faults_exercise_retries_and_batches() {
+    let chaos = Chaos::new()
+        .fail(Stage::Extract, 1, Fault::Status(500))
+        .fail(Stage::Extract, 2, Fault::Timeout);
+    let batches = Batches::default();
+    let pipe = Pipe::<Values, Vec<i32>>::builder()
+        .source(Source::endpoint(input("chaos")))
+        .retry(RetryPolicy::new(3).backoff(Duration::ZERO, Duration::ZERO))
+        .timeout(Duration::from_secs(5))
+        .chaos(chaos.clone())
+        .sink(sink::Batched::new(
+            Faulty::new(batches.clone()).fail(2, Fault::Error("disk full".into())),
+            2,
+        ))
+        .build()
+        .unwrap();
+    pipe.run().await.unwrap();
+
+    // extraction succeeded on its 3rd attempt; the 1st load failed on its 2nd batch, & was retried
+    assert_eq!(chaos.attempts(Stage::Extract), 3);
+    assert_eq!(chaos.attempts(Stage::Load), 2);
+    assert_eq!(
+        *batches.0.lock().unwrap(),
+        [vec![1, 2], vec![5], vec![1, 2], vec![3, 4], vec![5]]
+    );
+
+    // out of attempts, the last injected failure is returned
+    let pipe = Pipe::<Values, Vec<i32>>::builder()
+        .source(Source::endpoint(input("chaos")))
+        .timeout(Duration::from_secs(5))
+        .chaos(Chaos::new().fail(Stage::Extract, 1, Fault::Timeout))
+        .sink(sink::Batched::new(batches.clone(), 5))
+        .build()
+        .unwrap();
+    let result = pipe.run().await;
+    assert!(matches!(
+        result,
+        Err(Error::Timeout { stage: Stage::Extract, timeout }) if timeout == Duration::from_secs(5)
+    ));
+}
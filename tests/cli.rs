@@ -0,0 +1,88 @@
+// The command-line interface to a runner's pipelines; only with `--features cli`.
+#![cfg(feature = "cli")]
+
+use pipe_io::cli::{execute, Args};
+use pipe_io::error::Context;
+use pipe_io::{sink, Error, EtlReport, Pipe, Runner, Source};
+use serde_json::Value;
+use std::time::Duration;
+
+fn dir() -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join("pipe-io-test-cli");
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn runner() -> Runner {
+    let dir = dir();
+    std::fs::write(dir.join("input.json"), r#"{ "values": [1] }"#).unwrap();
+    std::fs::write(dir.join("other.json"), r#"{ "values": [2] }"#).unwrap();
+    let copy = Pipe::<Value, Value>::builder()
+        .source(Source::endpoint(dir.join("input.json").to_str().unwrap()))
+        .sink(sink::File::new(dir.join("copy.json")))
+        .build()
+        .unwrap();
+    Runner::new().pipe("copy", copy, Duration::from_secs(60))
+}
+
+fn read(name: &str) -> Value {
+    serde_json::from_slice(&std::fs::read(dir().join(name)).unwrap()).unwrap()
+}
+
+#[test]
+fn cli_args_are_parsed() {
+    let args = Args::parse_from(["etl", "copy", "--source", "other.json", "--dry-run"]).unwrap();
+    assert_eq!(args.pipeline.as_deref(), Some("copy"));
+    assert_eq!(args.source.as_deref(), Some("other.json"));
+    assert!(args.dry_run);
+    assert_eq!(Args::parse_from(["etl"]).unwrap(), Args::default());
+
+    // overrides need a pipeline; a dry run has no sink
+    for args in [
+        vec!["etl", "--source", "other.json"],
+        vec!["etl", "copy", "--dry-run", "--sink", "out.json"],
+    ] {
+        assert!(matches!(Args::parse_from(args), Err(Error::Config(_))));
+    }
+}
+
+#[tokio::test]
+async fn cli_runs_pipelines_with_overrides() {
+    let dir = dir();
+    let _ = std::fs::remove_file(dir.join("copy.json"));
+
+    // a dry run prints the output, and loads nothing
+    let mut out = vec![];
+    let args = Args::parse_from(["etl", "copy", "--dry-run"]).unwrap();
+    execute(runner(), &args, &mut out).await.unwrap();
+    let printed: Value = serde_json::from_slice(&out).unwrap();
+    assert_eq!(printed["values"][0], 1);
+    assert!(!dir.join("copy.json").exists());
+
+    // another source, to the pipeline's sink
+    let other = dir.join("other.json");
+    let mut out = vec![];
+    let args = Args::parse_from(["etl", "copy", "--source", other.to_str().unwrap()]).unwrap();
+    execute(runner(), &args, &mut out).await.unwrap();
+    let report: EtlReport = serde_json::from_slice(&out).unwrap();
+    assert_eq!(report.loads(), 1);
+    assert_eq!(read("copy.json")["values"][0], 2);
+
+    // another sink
+    let elsewhere = dir.join("elsewhere.json");
+    let args = Args::parse_from(["etl", "copy", "--sink", elsewhere.to_str().unwrap()]).unwrap();
+    execute(runner(), &args, &mut vec![]).await.unwrap();
+    assert_eq!(read("elsewhere.json")["values"][0], 1);
+    assert_eq!(read("copy.json")["values"][0], 2);
+
+    // a plain run, recorded
+    let runner = runner();
+    runner.run_named("copy").await.unwrap();
+    assert_eq!(runner.statuses().get("copy").unwrap().runs, 1);
+    assert_eq!(read("copy.json")["values"][0], 1);
+
+    // failures are tagged with the pipeline
+    let args = Args::parse_from(["etl", "missing", "--dry-run"]).unwrap();
+    let errors = execute(runner, &args, &mut vec![]).await.unwrap_err();
+    assert_eq!(errors[0].context, Context::Pipeline("missing".into()));
+}
@@ -0,0 +1,41 @@
+// Parallel, nullable arrays zipped back into rows.
+
+use pipe_io::columns::{self, Column, Nulls};
+use pipe_io::Error;
+
+#[test]
+fn zip_applies_null_policies() {
+    let open = [Some(866.0), None, Some(123.0), None];
+    let volume = [Some(100), Some(200), None, Some(400)];
+    let dates = ["mon", "tue", "wed", "thu"];
+
+    let rows = columns::zip((
+        Column::new(&open).fill_forward(),
+        Column::new(&volume).or(0),
+        Column::dense(&dates),
+    ))
+    .unwrap();
+    assert_eq!(
+        rows,
+        vec![
+            (866.0, 100, "mon"),
+            (866.0, 200, "tue"),
+            (123.0, 0, "wed"),
+            (123.0, 400, "thu"),
+        ]
+    );
+
+    // skipped rows still advance the fill-forward value
+    let rows = columns::zip((
+        Column::new(&volume),
+        Column::new(&open).nulls(Nulls::FillForward),
+    ))
+    .unwrap();
+    assert_eq!(rows, vec![(100, 866.0), (200, 866.0), (400, 123.0)]);
+}
+
+#[test]
+fn zip_rejects_uneven_columns() {
+    let result = columns::zip((Column::dense(&[1, 2, 3]), Column::dense(&["a", "b"])));
+    assert!(matches!(result, Err(Error::Verification(_))));
+}
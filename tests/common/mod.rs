@@ -0,0 +1,155 @@
+// A local HTTP server, shared by the tests that stand one in for an API.
+#![allow(dead_code)]
+
+use serde_json::Value;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+/// A request to the server: its method & target, lowercased header names, and body.
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    pub target: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Request {
+    /// The value of header `name` (lowercase), if it was sent.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        let (_, value) = self.headers.iter().find(|(header, _)| header == name)?;
+        Some(value)
+    }
+
+    /// The target's path, without its query.
+    pub fn path(&self) -> &str {
+        self.target.split('?').next().unwrap_or_default()
+    }
+
+    /// The body, as JSON.
+    pub fn json(&self) -> Value {
+        serde_json::from_str(&self.body).unwrap()
+    }
+}
+
+/// The server's answer to a request.
+#[derive(Debug, Clone)]
+pub struct Response {
+    status: String,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl Response {
+    /// `200 OK`, with `body`.
+    pub fn ok(body: impl Into<String>) -> Self {
+        Response::status("200 OK").body(body)
+    }
+
+    /// `200 OK`, with `body` as `application/json`.
+    pub fn json(body: &Value) -> Self {
+        Response::ok(body.to_string()).header("content-type", "application/json")
+    }
+
+    /// An empty response of `status`, e.g., `"401 Unauthorized"`.
+    pub fn status(status: &str) -> Self {
+        Response {
+            status: status.to_string(),
+            headers: vec![],
+            body: String::new(),
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.headers.push((name.to_string(), value.into()));
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<String>) -> Self {
+        self.body = body.into();
+        self
+    }
+}
+
+/// Serve `respond`'s answer to each request, from a local port; returning the base URL, e.g.
+/// `http://127.0.0.1:1234`, and the requests as they arrive.
+pub async fn serve<F>(mut respond: F) -> (String, UnboundedReceiver<Request>)
+where
+    F: FnMut(&Request) -> Response + Send + 'static,
+{
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let Some(request) = read(&mut socket).await else {
+                continue;
+            };
+            let response = respond(&request);
+            // the test may have stopped listening
+            let _ = tx.send(request);
+            let mut head = format!("HTTP/1.1 {}\r\n", response.status);
+            for (name, value) in &response.headers {
+                head.push_str(&format!("{name}: {value}\r\n"));
+            }
+            head.push_str(&format!(
+                "content-length: {}\r\nconnection: close\r\n\r\n",
+                response.body.len()
+            ));
+            let _ = socket.write_all(head.as_bytes()).await;
+            let _ = socket.write_all(response.body.as_bytes()).await;
+        }
+    });
+    (url, rx)
+}
+
+/// Serve `respond`'s answer to each request, without keeping the requests.
+pub async fn serve_only<F>(respond: F) -> String
+where
+    F: FnMut(&Request) -> Response + Send + 'static,
+{
+    serve(respond).await.0
+}
+
+// the headers, then as much body as they announce; `None` if the client hung up first
+async fn read(socket: &mut tokio::net::TcpStream) -> Option<Request> {
+    let mut buf = vec![];
+    let mut chunk = vec![0; 64 * 1024];
+    let end = loop {
+        let n = socket.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(end) = buf.windows(4).position(|window| window == b"\r\n\r\n") {
+            break end;
+        }
+    };
+    let head = String::from_utf8_lossy(&buf[..end]).to_string();
+    let mut lines = head.lines();
+    let mut line = lines.next()?.split(' ');
+    let method = line.next()?.to_string();
+    let target = line.next()?.to_string();
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+        .collect();
+    let length = headers
+        .iter()
+        .find(|(name, _)| name == "content-length")
+        .and_then(|(_, length)| length.parse::<usize>().ok())
+        .unwrap_or(0);
+    while buf.len() < end + 4 + length {
+        let n = socket.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    Some(Request {
+        method,
+        target,
+        headers,
+        body: String::from_utf8_lossy(&buf[end + 4..]).to_string(),
+    })
+}
@@ -0,0 +1,64 @@
+// Per-environment profiles.
+
+use pipe_io::config::{Limits, Profiles};
+use pipe_io::Error;
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Config {
+    endpoint: String,
+    conn: String,
+    #[serde(default)]
+    limits: Limits,
+}
+
+fn profiles() -> Profiles {
+    Profiles::from_value(json!({
+        "default": { "endpoint": "https://example.com/prices.json", "limits": { "retries": 3 } },
+        "profiles": {
+            "dev": { "conn": "host=localhost" },
+            "prod": { "conn": "host=db.internal", "limits": { "requests_per_second": 2 } },
+            "staging": { "endpoint": "https://staging.example.com/prices.json" }
+        }
+    }))
+    .unwrap()
+}
+
+#[test]
+fn profiles_merge_over_the_defaults() {
+    let profiles = profiles();
+    assert_eq!(
+        profiles.names().collect::<Vec<_>>(),
+        ["dev", "prod", "staging"]
+    );
+
+    let prod: Config = profiles.get("prod").unwrap();
+    assert_eq!(
+        prod,
+        Config {
+            endpoint: "https://example.com/prices.json".into(),
+            conn: "host=db.internal".into(),
+            limits: Limits {
+                retries: Some(3),
+                requests_per_second: Some(2),
+                timeout_secs: None
+            },
+        }
+    );
+    assert!(matches!(
+        profiles.get::<Config>("test"),
+        Err(Error::Config(_))
+    ));
+}
+
+#[test]
+fn validate_reports_incomplete_profiles() {
+    // staging has no `conn`
+    let errors = profiles().validate::<Config>().unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0]
+        .error
+        .to_string()
+        .contains("profile `staging` is incomplete"));
+}
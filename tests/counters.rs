@@ -0,0 +1,70 @@
+// Stage counters; only with `--features bench`. One pipe per binary, as the counters are global.
+#![cfg(feature = "bench")]
+
+use pipe_io::{counters, pipeline, sink, Error, Pipe, RetryPolicy, Source};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+#[derive(Deserialize, Debug)]
+struct Names {
+    names: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Count(usize);
+
+static FAILED: AtomicBool = AtomicBool::new(false);
+
+pipeline! {
+    Names -> Count {
+        async fn extract(&self, path: &str) -> pipe_io::Result<Names> {
+            // fail the first attempt, to count a retry
+            if !FAILED.swap(true, Ordering::SeqCst) {
+                return Err(Error::Other(anyhow::anyhow!("flaky endpoint")));
+            }
+            pipe_io::default::extract(path).await
+        }
+
+        async fn transform(&self, input: Names) -> pipe_io::Result<Count> {
+            Ok(Count(input.names.len()))
+        }
+    }
+}
+
+#[tokio::test]
+async fn counters_count_every_stage_attempt() {
+    let dir = std::env::temp_dir().join("pipe-io-test-counters");
+    std::fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("names.json");
+    std::fs::write(&input, r#"{ "names": ["a", "b"] }"#).unwrap();
+
+    let pipe = Pipe::<Names, Count>::builder()
+        .source(Source::endpoint(input.to_str().unwrap()))
+        .retry(RetryPolicy::new(2).backoff(Duration::from_millis(1), Duration::from_millis(1)))
+        .sink(sink::File::new(dir.join("count.json")))
+        .build()
+        .unwrap();
+    counters::reset();
+    pipe.run().await.unwrap();
+
+    let counters = counters::snapshot();
+    assert_eq!(counters.extract.completed, 1);
+    assert_eq!(counters.extract.failed, 1);
+    assert_eq!(counters.retries, 1);
+    assert_eq!(counters.transform.attempts(), 1);
+    assert_eq!(counters.enrich.attempts(), 0);
+    assert_eq!(counters.load.completed, 1);
+    assert!(counters.extract.elapsed > Duration::ZERO);
+}
+
+#[test]
+fn means_are_over_every_attempt() {
+    // more attempts than fit in the `u32` a `Duration` divides by
+    let counter = counters::Counter {
+        completed: 1 << 33,
+        failed: 0,
+        elapsed: Duration::from_secs(1 << 33),
+    };
+    assert_eq!(counter.mean(), Duration::from_secs(1));
+}
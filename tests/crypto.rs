@@ -0,0 +1,65 @@
+// Field encryption; only with `--features crypto`.
+#![cfg(feature = "crypto")]
+
+use pipe_io::crypto::{Cipher, Encrypted, Key};
+use pipe_io::{sink, Error, Sink};
+use serde_json::{json, Value};
+
+const KEY: [u8; 32] = [7; 32];
+
+#[test]
+fn fields_round_trip_and_fail_under_another_key() {
+    let cipher = Cipher::new(&KEY);
+    let encrypted = cipher.encrypt(&json!({ "street": "1 Main St" })).unwrap();
+    assert!(encrypted.starts_with("enc:v1:"));
+    // a fresh nonce every time
+    assert_ne!(
+        encrypted,
+        cipher.encrypt(&json!({ "street": "1 Main St" })).unwrap()
+    );
+    assert_eq!(
+        cipher.decrypt(&encrypted).unwrap(),
+        json!({ "street": "1 Main St" })
+    );
+
+    assert!(matches!(
+        Cipher::new(&[8; 32]).decrypt(&encrypted),
+        Err(Error::Crypto(_))
+    ));
+    assert!(matches!(cipher.decrypt("plain"), Err(Error::Crypto(_))));
+}
+
+#[tokio::test]
+async fn encrypted_sink_encrypts_configured_fields() {
+    let path = std::env::temp_dir().join("pipe-io-test-crypto.json");
+    let sink = Encrypted::new(
+        sink::File::new(&path),
+        Key::kms(|| async { Ok::<_, Error>(KEY) }),
+    )
+    .field("email")
+    .unwrap()
+    .field("address.street")
+    .unwrap();
+
+    let records = json!([
+        { "id": 1, "email": "a@example.com", "address": { "street": "1 Main St", "city": "Leeds" } },
+        { "id": 2, "email": null },
+    ]);
+    sink.load(&records).await.unwrap();
+
+    let mut loaded: Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    assert_eq!(loaded[0]["id"], 1);
+    assert_eq!(loaded[0]["address"]["city"], "Leeds");
+    assert!(loaded[0]["email"].as_str().unwrap().starts_with("enc:v1:"));
+    assert!(loaded[0]["address"]["street"]
+        .as_str()
+        .unwrap()
+        .starts_with("enc:v1:"));
+    assert_eq!(loaded[1]["email"], Value::Null);
+
+    let cipher = Cipher::new(&KEY);
+    for record in loaded.as_array_mut().unwrap() {
+        cipher.decrypt_fields(record, sink.fields()).unwrap();
+    }
+    assert_eq!(loaded, records);
+}
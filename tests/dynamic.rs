@@ -0,0 +1,122 @@
+// Pipes on plain JSON, reshaped by rules from config.
+
+use pipe_io::dynamic::Path;
+use pipe_io::{sink, DynamicPipe, Error, Pipe, Rules, Source};
+use serde_json::{json, Value};
+
+fn rules() -> Rules {
+    Rules::from_value(json!({
+        "records": "chart.result",
+        "rules": [
+            { "op": "filter", "field": "meta.price", "cmp": "gt", "value": 100 },
+            { "op": "map", "from": "meta.symbol", "to": "symbol" },
+            { "op": "rename", "from": "meta.price", "to": "price" },
+            { "op": "set", "field": "source", "value": "yahoo" },
+            { "op": "select", "fields": ["symbol", "price", "source", "missing"] }
+        ]
+    }))
+    .unwrap()
+}
+
+fn chart() -> Value {
+    json!({
+        "chart": {
+            "result": [
+                { "meta": { "symbol": "NVDA", "price": 120.5 } },
+                { "meta": { "symbol": "INTC", "price": 30.1 } },
+                { "meta": { "symbol": "AAPL", "price": 210 } }
+            ]
+        }
+    })
+}
+
+#[test]
+fn rules_reshape_and_filter_records() {
+    assert_eq!(
+        rules().apply(chart()).unwrap(),
+        json!([
+            { "symbol": "NVDA", "price": 120.5, "source": "yahoo" },
+            { "symbol": "AAPL", "price": 210, "source": "yahoo" }
+        ])
+    );
+
+    // a single record still comes out as an array
+    let drop = Rules::from_value(json!({
+        "rules": [{ "op": "drop", "fields": ["b", "c[0]"] }]
+    }))
+    .unwrap();
+    assert_eq!(
+        drop.apply(json!({ "a": 1, "b": 2, "c": [3, 4] })).unwrap(),
+        json!([{ "a": 1, "c": [4] }])
+    );
+
+    assert!(matches!(
+        rules().apply(json!({ "chart": {} })),
+        Err(Error::Config(_))
+    ));
+}
+
+#[test]
+fn invalid_paths_are_rejected_when_read() {
+    for path in ["", "a..b", "a[x]", "a[1", "a[0]b"] {
+        assert!(Path::parse(path).is_err(), "{path:?} should be invalid");
+    }
+    let path = Path::parse("chart.result[0].meta").unwrap();
+    assert_eq!(
+        path.get(&chart()),
+        Some(&chart()["chart"]["result"][0]["meta"])
+    );
+
+    let result = Rules::from_value(json!({
+        "rules": [{ "op": "map", "from": "a[", "to": "b" }]
+    }));
+    assert!(matches!(result, Err(Error::Config(_))));
+}
+
+#[test]
+fn rules_declare_the_lineage_of_their_fields() {
+    let lineage = serde_json::to_value(rules().lineage()).unwrap();
+    assert_eq!(
+        lineage,
+        json!([
+            { "field": "symbol", "from": ["meta.symbol"] },
+            { "field": "price", "from": ["meta.price"] },
+            { "field": "source", "from": [] },
+            { "field": "missing", "from": ["missing"] }
+        ])
+    );
+
+    // fields passed through aren't declared, but renames are followed
+    let rules = Rules::from_value(json!({
+        "rules": [
+            { "op": "rename", "from": "a", "to": "b" },
+            { "op": "map", "from": "b", "to": "c" },
+            { "op": "drop", "fields": ["b"] }
+        ]
+    }))
+    .unwrap();
+    let lineage = rules.lineage();
+    assert_eq!(lineage.fields.len(), 1);
+    assert_eq!(lineage.of("c").unwrap().from, ["a"]);
+}
+
+#[tokio::test]
+async fn dynamic_pipe_applies_rules_before_loading() {
+    let dir = std::env::temp_dir().join("pipe-io-test-dynamic");
+    std::fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("chart.json");
+    let output = dir.join("prices.json");
+    std::fs::write(&input, chart().to_string()).unwrap();
+
+    let pipe: DynamicPipe = Pipe::builder()
+        .source(Source::endpoint(input.to_str().unwrap()))
+        .rules(rules())
+        .sink(sink::File::new(&output))
+        .build()
+        .unwrap();
+    pipe.run().await.unwrap();
+
+    let loaded: Value = serde_json::from_str(&std::fs::read_to_string(&output).unwrap()).unwrap();
+    assert_eq!(loaded.as_array().map(Vec::len), Some(2));
+    assert_eq!(loaded[1]["symbol"], "AAPL");
+}
@@ -0,0 +1,29 @@
+// URLs built with `Endpoint`; nothing is requested.
+
+use pipe_io::{Endpoint, Error, Source};
+
+#[test]
+fn endpoint_encodes_vars_and_params() {
+    let url = Endpoint::new("https://example.com/v1/{ticker}/prices")
+        .var("ticker", "BRK/B")
+        .param("type", "open,close")
+        .param_iter([("from", 1), ("to", 2)])
+        .param("q", "a&b c")
+        .url()
+        .unwrap();
+    assert_eq!(
+        url.as_str(),
+        "https://example.com/v1/BRK%2FB/prices?type=open%2Cclose&from=1&to=2&q=a%26b+c"
+    );
+
+    let source: Source<()> = Endpoint::new("https://example.com").try_into().unwrap();
+    assert_eq!(source.describe(), "https://example.com/");
+}
+
+#[test]
+fn endpoint_rejects_unfilled_placeholders() {
+    let result = Endpoint::new("https://example.com/{ticker}/{period}")
+        .var("ticker", "NVDA")
+        .url();
+    assert!(matches!(result, Err(Error::Config(msg)) if msg.contains("{period}")));
+}
@@ -0,0 +1,103 @@
+// Transforms checked against golden files, in a temporary fixtures directory.
+
+use pipe_io::fixtures::{Fixtures, Verdict};
+use pipe_io::{pipeline, Pipe};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Debug)]
+struct Quotes {
+    symbol: String,
+    closes: Vec<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Close {
+    symbol: String,
+    close: f64,
+}
+
+pipeline! {
+    Quotes -> Vec<Close> {
+        async fn transform(&self, input: Quotes) -> pipe_io::Result<Vec<Close>> {
+            Ok(input
+                .closes
+                .into_iter()
+                .map(|close| Close { symbol: input.symbol.clone(), close })
+                .collect())
+        }
+    }
+}
+
+fn fixtures() -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join("pipe-io-test-fixtures");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("nested")).unwrap();
+    std::fs::write(
+        dir.join("nvda.json"),
+        r#"{ "symbol": "NVDA", "closes": [120.5, 121] }"#,
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("nested/aapl.json"),
+        r#"{ "symbol": "AAPL", "closes": [210] }"#,
+    )
+    .unwrap();
+    dir
+}
+
+#[tokio::test]
+async fn outputs_are_blessed_then_compared_with_golden_files() {
+    let dir = fixtures();
+    let pipe = Pipe::<Quotes, Vec<Close>>::new();
+
+    // nothing to compare with yet
+    let checked = Fixtures::new(&dir).bless(false).check(&pipe).await;
+    assert!(!checked.is_ok());
+    assert!(checked
+        .fixtures
+        .iter()
+        .all(|(_, verdict)| *verdict == Verdict::Missing));
+    assert!(checked.to_string().ends_with(
+        "2 of 2 fixtures failed; rerun with PIPE_IO_BLESS=1 to write the outputs as golden files"
+    ));
+
+    let checked = Fixtures::new(&dir).bless(true).check(&pipe).await;
+    assert!(checked.is_ok());
+    assert_eq!(
+        checked
+            .fixtures
+            .iter()
+            .map(|(path, _)| path.strip_prefix(&dir).unwrap().to_str().unwrap())
+            .collect::<Vec<_>>(),
+        ["nested/aapl.json", "nvda.json"]
+    );
+    let golden = std::fs::read_to_string(dir.join("nvda.golden.json")).unwrap();
+    assert!(golden.contains(r#""close": 121.0"#));
+
+    // golden files match however they're formatted
+    std::fs::write(
+        dir.join("nested/aapl.golden.json"),
+        r#"[{"symbol":"AAPL","close":210.0}]"#,
+    )
+    .unwrap();
+    Fixtures::new(&dir).bless(false).check(&pipe).await.assert();
+
+    // a regression is shown as a diff
+    std::fs::write(
+        dir.join("nvda.json"),
+        r#"{ "symbol": "NVDA", "closes": [120.5, 122] }"#,
+    )
+    .unwrap();
+    let checked = Fixtures::new(&dir).bless(false).check(&pipe).await;
+    let Verdict::Differs(diff) = &checked.fixtures[1].1 else {
+        panic!("expected a diff, not {:?}", checked.fixtures[1].1);
+    };
+    assert!(diff.contains(r#"-     "close": 121.0"#));
+    assert!(diff.contains(r#"+     "close": 122.0"#));
+    assert!(diff.contains(r#"      "symbol": "NVDA""#));
+
+    // & a payload that no longer decodes, as its error
+    std::fs::write(dir.join("nvda.json"), r#"{ "symbol": "NVDA" }"#).unwrap();
+    let checked = Fixtures::new(&dir).bless(true).check(&pipe).await;
+    assert!(matches!(&checked.fixtures[1].1, Verdict::Failed(e) if e.contains("closes")));
+}
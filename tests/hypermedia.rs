@@ -0,0 +1,143 @@
+// Paginated JSON:API & HAL documents, flattened into structs; from local files.
+
+use pipe_io::hypermedia::Hypermedia;
+use pipe_io::passthrough::Value;
+use pipe_io::{Error, Pipe};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Ticket {
+    id: String,
+    subject: String,
+    assignee: Option<String>,
+    tags: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Order {
+    id: u32,
+    customer: Customer,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Customer {
+    name: String,
+}
+
+// write `pages` to files, each linking to the next with `link`; returning the first's path
+fn pages(name: &str, pages: Vec<Value>, link: impl Fn(&str) -> Value) -> String {
+    let dir = std::env::temp_dir().join(format!("pipe-io-test-hypermedia-{name}"));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = |i: usize| dir.join(format!("{i}.json")).to_str().unwrap().to_string();
+    let count = pages.len();
+    for (i, mut page) in pages.into_iter().enumerate() {
+        if i + 1 < count {
+            page.as_object_mut()
+                .unwrap()
+                .extend(link(&path(i + 1)).as_object().cloned().unwrap());
+        }
+        std::fs::write(path(i), page.to_string()).unwrap();
+    }
+    path(0)
+}
+
+#[tokio::test]
+async fn json_api_resources_are_flattened_across_pages() {
+    let ticket = |id: &str, assignee: Value| {
+        json!({
+            "id": id,
+            "type": "tickets",
+            "attributes": { "subject": format!("ticket {id}") },
+            "relationships": {
+                "assignee": { "data": assignee },
+                "tags": { "data": [{ "id": "urgent", "type": "tags" }] }
+            }
+        })
+    };
+    let first = pages(
+        "json-api",
+        vec![
+            json!({ "data": [ticket("1", json!({ "id": "7", "type": "people" }))] }),
+            json!({ "data": [ticket("2", Value::Null)], "links": { "next": null } }),
+        ],
+        |next| json!({ "links": { "next": { "href": next } } }),
+    );
+
+    let pipe = Pipe::<Value, Value>::new();
+    let tickets: Vec<Ticket> = Hypermedia::JsonApi.extract(&pipe, &first).await.unwrap();
+    assert_eq!(
+        tickets,
+        [
+            Ticket {
+                id: "1".into(),
+                subject: "ticket 1".into(),
+                assignee: Some("7".into()),
+                tags: vec!["urgent".into()],
+            },
+            Ticket {
+                id: "2".into(),
+                subject: "ticket 2".into(),
+                assignee: None,
+                tags: vec!["urgent".into()],
+            }
+        ]
+    );
+
+    let failed = json!({ "errors": [{ "status": "403" }] });
+    assert!(matches!(
+        Hypermedia::JsonApi.resources(failed),
+        Err(Error::Upstream(errors)) if errors.contains("403")
+    ));
+}
+
+#[tokio::test]
+async fn hal_resources_are_flattened_across_pages() {
+    let order = |id: u32| {
+        json!({
+            "id": id,
+            "_links": { "self": { "href": format!("/orders/{id}") } },
+            "_embedded": { "customer": { "name": format!("customer {id}"), "_links": {} } }
+        })
+    };
+    let first = pages(
+        "hal",
+        vec![
+            json!({ "_embedded": { "orders": [order(1), order(2)] } }),
+            json!({ "_embedded": { "orders": [order(3)] } }),
+        ],
+        |next| json!({ "_links": { "next": { "href": next } } }),
+    );
+
+    let pipe = Pipe::<Value, Value>::new();
+    let orders: Vec<Order> = Hypermedia::hal().extract(&pipe, &first).await.unwrap();
+    assert_eq!(orders.iter().map(|o| o.id).collect::<Vec<_>>(), [1, 2, 3]);
+    assert_eq!(orders[2].customer.name, "customer 3");
+
+    // a resource that doesn't fit is located
+    let result: Result<Vec<Ticket>, _> = Hypermedia::hal().extract(&pipe, &first).await;
+    assert!(matches!(result, Err(Error::Decode { path, .. }) if path.starts_with("[0]")));
+
+    // several rels need naming
+    let page = json!({ "_embedded": { "orders": [order(1)], "refunds": [] } });
+    assert!(matches!(
+        Hypermedia::hal().resources(page.clone()),
+        Err(Error::Config(_))
+    ));
+    let rel = Hypermedia::Hal {
+        rel: Some("refunds".into()),
+    };
+    assert_eq!(rel.resources(page).unwrap(), Vec::<Value>::new());
+}
+
+#[test]
+fn next_links_are_strings_or_hrefs() {
+    let page = json!({ "links": { "next": "/tickets?page=2" } });
+    assert_eq!(
+        Hypermedia::JsonApi.next(&page).as_deref(),
+        Some("/tickets?page=2")
+    );
+    let page = json!({ "_links": { "next": { "href": "?page=2" } } });
+    assert_eq!(Hypermedia::hal().next(&page).as_deref(), Some("?page=2"));
+    assert_eq!(Hypermedia::hal().next(&json!({ "_links": {} })), None);
+}
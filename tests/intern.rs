@@ -0,0 +1,52 @@
+// Repeated strings decoded as shared copies.
+
+use pipe_io::intern::{self, Interned, Interner};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::Arc;
+
+#[derive(Deserialize, Serialize)]
+struct Fact {
+    value: f64,
+    unit: Interned,
+    form: Option<Interned>,
+    #[serde(with = "pipe_io::intern")]
+    period: Arc<str>,
+}
+
+#[test]
+fn repeated_strings_are_decoded_as_one_copy() {
+    let facts = json!([
+        { "value": 1.0, "unit": "USD", "form": "10-K", "period": "FY" },
+        { "value": 2.0, "unit": "USD", "form": null, "period": "FY" }
+    ]);
+    // from text, as borrowed & escaped strings alike
+    let text = facts.to_string().replacen("\"USD\"", "\"U\\u0053D\"", 1);
+    let facts: Vec<Fact> = serde_json::from_str(&text).unwrap();
+    assert_eq!(facts[0].unit, "USD");
+    assert!(facts[0].unit.ptr_eq(&facts[1].unit));
+    assert!(Arc::ptr_eq(&facts[0].period, &facts[1].period));
+    assert!(facts[1].form.is_none());
+    assert!(Interned::new("USD").ptr_eq(&facts[1].unit));
+
+    assert_eq!(
+        serde_json::to_value(&facts[0]).unwrap(),
+        json!({ "value": 1.0, "unit": "USD", "form": "10-K", "period": "FY" })
+    );
+    assert!(intern::global().len() >= 3);
+    assert!(serde_json::from_value::<Interned>(json!(1)).is_err());
+}
+
+#[test]
+fn unused_strings_are_pruned() {
+    let interner = Interner::new();
+    let kept = interner.intern("EUR");
+    assert!(Arc::ptr_eq(&kept, &interner.intern("EUR")));
+    interner.intern("GBP");
+    assert_eq!(interner.len(), 2);
+    assert_eq!(interner.prune(), 1);
+    assert_eq!(interner.len(), 1);
+    drop(kept);
+    assert_eq!(interner.prune(), 1);
+    assert!(interner.is_empty());
+}
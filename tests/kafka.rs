@@ -0,0 +1,63 @@
+// A Kafka source, against the broker at `KAFKA_BROKERS` (default `localhost:9092`); only with
+// `--features kafka`.
+#![cfg(feature = "kafka")]
+
+use futures::StreamExt;
+use pipe_io::kafka::KafkaSource;
+use pipe_io::Source;
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::util::Timeout;
+use rdkafka::ClientConfig;
+use serde::Deserialize;
+use std::time::Duration;
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Price {
+    id: u32,
+}
+
+fn brokers() -> String {
+    std::env::var("KAFKA_BROKERS").unwrap_or_else(|_| "localhost:9092".into())
+}
+
+async fn produce(topic: &str, payloads: &[&str]) {
+    let producer: FutureProducer = ClientConfig::new()
+        .set("bootstrap.servers", brokers())
+        .create()
+        .unwrap();
+    for payload in payloads {
+        let record = FutureRecord::<(), _>::to(topic).payload(*payload);
+        producer.send(record, Timeout::Never).await.unwrap();
+    }
+}
+
+// the next input of a fresh consumer in `group`, having loaded & committed it
+async fn next(topic: &str, group: &str) -> Price {
+    let source = KafkaSource::new(&brokers(), group, &[topic])
+        .unwrap()
+        .commit_every(1)
+        .into_source::<Price>();
+    let Source::Stream { stream, checkpoint } = source else {
+        panic!("expected a streaming source");
+    };
+    let mut stream = stream.lock().await.take().unwrap();
+    let input = tokio::time::timeout(Duration::from_secs(30), stream.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    checkpoint.unwrap().loaded().await.unwrap();
+    input
+}
+
+#[tokio::test]
+async fn undecodable_messages_are_skipped_and_committed_past() {
+    let topic = format!("prices-{}", std::process::id());
+    let group = format!("{topic}-loader");
+    produce(&topic, &["not json", r#"{"id": 1}"#]).await;
+    assert_eq!(next(&topic, &group).await, Price { id: 1 });
+
+    // the group resumes after both, rather than at the undecodable one
+    produce(&topic, &[r#"{"id": 2}"#]).await;
+    assert_eq!(next(&topic, &group).await, Price { id: 2 });
+}
@@ -0,0 +1,103 @@
+// Code generated by the macros, checked against the hand-written equivalent.
+
+use pipe_io::lineage::Lineage;
+use pipe_io::{pipeline, Error, Pipe, Transform, ETL};
+use serde::{Deserialize, Serialize};
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+// #[derive(Transform)]
+/////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Deserialize, Debug)]
+struct RawPrice {
+    chart: Chart,
+}
+
+#[derive(Deserialize, Debug)]
+struct Chart {
+    result: Vec<ChartResult>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ChartResult {
+    meta: Meta,
+}
+
+#[derive(Deserialize, Debug)]
+struct Meta {
+    currency: String,
+    symbol: String,
+}
+
+#[derive(Transform, Serialize, Deserialize, Debug, PartialEq)]
+#[etl(input = RawPrice)]
+struct Listing {
+    #[etl(from = "chart.result[0].meta.currency")]
+    currency: String,
+    #[etl(from = "chart.result[0].meta.symbol")]
+    ticker: String,
+}
+
+#[tokio::test]
+async fn derive_transform_plucks_paths() {
+    let raw: RawPrice = serde_json::from_str(
+        r#"{ "chart": { "result": [{ "meta": { "currency": "USD", "symbol": "NVDA" } }] } }"#,
+    )
+    .unwrap();
+    let listing = Pipe::<RawPrice, Listing>::new()
+        .transform(raw)
+        .await
+        .unwrap();
+    assert_eq!(
+        listing,
+        Listing {
+            currency: "USD".into(),
+            ticker: "NVDA".into(),
+        }
+    );
+
+    let lineage = Pipe::<RawPrice, Listing>::new().lineage();
+    assert_eq!(
+        lineage,
+        Lineage::new()
+            .field("currency", ["chart.result[0].meta.currency"])
+            .field("ticker", ["chart.result[0].meta.symbol"])
+    );
+
+    let empty: RawPrice = serde_json::from_str(r#"{ "chart": { "result": [] } }"#).unwrap();
+    let result = Pipe::<RawPrice, Listing>::new().transform(empty).await;
+    assert!(matches!(result, Err(Error::Missing(path)) if path == "chart.result[0]"));
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+// pipeline! { extend ... }
+/////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Deserialize, Debug)]
+struct Celsius(f64);
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Fahrenheit(f64);
+
+pipeline! {
+    Celsius -> Fahrenheit {
+        async fn transform(&self, input: Celsius) -> pipe_io::Result<Fahrenheit> {
+            Ok(Fahrenheit(input.0 * 9.0 / 5.0 + 32.0))
+        }
+    }
+
+    extend Celsius -> Fahrenheit {
+        async fn extract(&self, path: &str) -> pipe_io::Result<Celsius> {
+            Ok(Celsius(path.parse().map_err(anyhow::Error::from)?))
+        }
+    }
+}
+
+#[tokio::test]
+async fn extend_merges_into_one_pipeline() {
+    let output = Pipe::<Celsius, Fahrenheit>::new()
+        .extran("100")
+        .await
+        .unwrap();
+    assert_eq!(output, Fahrenheit(212.0));
+}
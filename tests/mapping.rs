@@ -0,0 +1,95 @@
+// A transform's fields, matched over samples: by name, by value & by declared lineage.
+
+use pipe_io::lineage::Lineage;
+use pipe_io::mapping::Mapping;
+use pipe_io::{pipeline, Error, Pipe};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+#[derive(Deserialize, Debug)]
+struct RawQuote {
+    meta: Meta,
+    #[serde(rename = "regularMarketPrice")]
+    price: f64,
+    volume: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct Meta {
+    symbol: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Quote {
+    ticker: String,
+    price: f64,
+    volume_k: f64,
+    source: String,
+}
+
+pipeline! {
+    RawQuote -> Quote {
+        async fn transform(&self, input: RawQuote) -> pipe_io::Result<Quote> {
+            Ok(Quote {
+                ticker: input.meta.symbol,
+                price: input.price,
+                volume_k: input.volume as f64 / 1000.0,
+                source: "yahoo".into(),
+            })
+        }
+    }
+}
+
+#[tokio::test]
+async fn unused_and_unsourced_fields_are_reported() {
+    let samples = || {
+        [
+            json!({
+                "meta": { "symbol": "NVDA", "currency": "USD" },
+                "regularMarketPrice": 120.5,
+                "volume": 2500,
+                "splits": []
+            }),
+            json!({
+                "meta": { "symbol": "AAPL", "currency": "USD" },
+                "regularMarketPrice": 210,
+                "volume": 900
+            }),
+        ]
+    };
+    let pipe = Pipe::<RawQuote, Quote>::new();
+    let mapping = Mapping::of(&pipe, samples()).await.unwrap();
+    assert_eq!(
+        mapping.inputs,
+        [
+            "meta.currency",
+            "meta.symbol",
+            "regularMarketPrice",
+            "splits",
+            "volume"
+        ]
+    );
+    assert_eq!(mapping.outputs, ["price", "source", "ticker", "volume_k"]);
+    // the ticker & price are matched by value, though renamed
+    assert_eq!(mapping.unused, ["meta.currency", "splits", "volume"]);
+    assert_eq!(mapping.unsourced, ["source", "volume_k"]);
+    assert_eq!(
+        mapping.to_string(),
+        "unused input fields: meta.currency, splits, volume\n\
+         output fields without a source: source, volume_k"
+    );
+
+    // declared lineage sources a derived field
+    let pipe = Pipe::<RawQuote, Quote>::builder()
+        .lineage(Lineage::new().derived("volume_k", ["volume"], "in thousands"))
+        .build()
+        .unwrap();
+    let mapping = Mapping::of(&pipe, samples()).await.unwrap();
+    assert_eq!(mapping.unused, ["meta.currency", "splits"]);
+    assert_eq!(mapping.unsourced, ["source"]);
+    assert!(!mapping.is_complete());
+
+    // a sample that isn't an input
+    let result = Mapping::of(&pipe, [json!({ "meta": {} })]).await;
+    assert!(matches!(result, Err(Error::JSON(_))));
+}
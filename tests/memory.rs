@@ -0,0 +1,160 @@
+// The sizes of a measured pipe's data, and chunked loads over its soft limit.
+
+use pipe_io::memory::{self, Counting, Memory};
+use pipe_io::observer::{Event, Stage};
+use pipe_io::passthrough::Value;
+use pipe_io::{pipeline, Outcome, Pipe, Sink, Source};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+#[derive(Clone, Default)]
+struct Loads(Arc<Mutex<Vec<Value>>>);
+
+impl Sink<Value> for Loads {
+    async fn load(&self, output: &Value) -> pipe_io::Result<Outcome> {
+        self.0.lock().unwrap().push(output.clone());
+        Ok(Outcome::Done)
+    }
+}
+
+// a transform that allocates 8 MiB, frees it, then waits for `RESUME`; having signalled `HELD`
+static HELD: Notify = Notify::const_new();
+static RESUME: Notify = Notify::const_new();
+const HELD_BYTES: u64 = 8 << 20;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Held(u8);
+
+pipeline! {
+    Held -> Held {
+        async fn transform(&self, input: Held) -> pipe_io::Result<Held> {
+            drop(std::hint::black_box(vec![1u8; HELD_BYTES as usize]));
+            HELD.notify_one();
+            RESUME.notified().await;
+            Ok(input)
+        }
+    }
+}
+
+struct Discard;
+
+impl Sink<Held> for Discard {
+    async fn load(&self, _: &Held) -> pipe_io::Result<Outcome> {
+        Ok(Outcome::Done)
+    }
+}
+
+fn input(name: &str, payload: &Value) -> (String, u64) {
+    let dir = std::env::temp_dir().join("pipe-io-test-memory");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    let payload = serde_json::to_vec_pretty(payload).unwrap();
+    std::fs::write(&path, &payload).unwrap();
+    (path.to_str().unwrap().to_string(), payload.len() as u64)
+}
+
+#[tokio::test]
+async fn measured_runs_report_sizes_and_chunk_over_the_limit() {
+    let records: Vec<Value> = (0..10).map(|i| json!({ "id": i, "name": "x" })).collect();
+    let (path, fetched) = input("records.json", &json!(records));
+    let loads = Loads::default();
+    let sized = Arc::new(Mutex::new(vec![]));
+    let pipe = Pipe::<Value, Value>::builder()
+        .source(Source::endpoint(&path))
+        .memory(Memory::new().soft_limit(60))
+        .observer({
+            let sized = sized.clone();
+            move |event: &Event| {
+                if let Event::Sized { stage, bytes } = event {
+                    sized.lock().unwrap().push((*stage, *bytes));
+                }
+            }
+        })
+        .sink(loads.clone())
+        .build()
+        .unwrap();
+
+    let report = pipe.run().await.unwrap();
+    let transformed = serde_json::to_vec(&records).unwrap().len() as u64;
+    let sizes = report.sizes.unwrap();
+    assert_eq!((sizes.fetched, sizes.transformed), (fetched, transformed));
+    assert_eq!((sizes.largest, sizes.chunked), (transformed, 1));
+    assert!(memory::allocated().is_some());
+    for stage in [Stage::Extract, Stage::Transform, Stage::Load] {
+        assert!(sizes.peaks[&stage] > 0, "{stage}");
+    }
+    assert_eq!(
+        *sized.lock().unwrap(),
+        [(Stage::Extract, fetched), (Stage::Transform, transformed)]
+    );
+
+    // loaded in chunks of at most 60 bytes, in order
+    let loads = loads.0.lock().unwrap();
+    assert!(matches!(&report.outcomes[0], Outcome::Batches(chunks) if chunks.len() == loads.len()));
+    assert!(loads.len() > 1);
+    assert!(loads
+        .iter()
+        .all(|chunk| serde_json::to_vec(chunk).unwrap().len() <= 60));
+    let loaded: Vec<Value> = loads
+        .iter()
+        .flat_map(|chunk| chunk.as_array().unwrap().clone())
+        .collect();
+    assert_eq!(loaded, records);
+}
+
+#[tokio::test]
+async fn outputs_that_are_not_arrays_are_loaded_whole() {
+    let (path, _) = input("object.json", &json!({ "name": "a long enough name" }));
+    let loads = Loads::default();
+    let pipe = Pipe::<Value, Value>::builder()
+        .source(Source::endpoint(&path))
+        .memory(Memory::new().soft_limit(10))
+        .sink(loads.clone())
+        .build()
+        .unwrap();
+
+    let report = pipe.run().await.unwrap();
+    assert_eq!(report.outcomes, [Outcome::Done]);
+    assert_eq!(report.warnings.len(), 1);
+    assert_eq!(report.sizes.unwrap().chunked, 0);
+
+    // unmeasured, there are no sizes
+    let pipe = Pipe::<Value, Value>::builder()
+        .source(Source::endpoint(&path))
+        .sink(loads)
+        .build()
+        .unwrap();
+    assert_eq!(pipe.run().await.unwrap().sizes, None);
+}
+
+#[tokio::test]
+async fn a_stage_peak_is_measured_from_its_own_start() {
+    let (held, _) = input("held.json", &json!(1));
+    let (records, _) = input("others.json", &json!([1, 2, 3]));
+    let held = Pipe::<Held, Held>::builder()
+        .source(Source::endpoint(&held))
+        .memory(Memory::new())
+        .sink(Discard)
+        .build()
+        .unwrap();
+    let other = Pipe::<Value, Value>::builder()
+        .source(Source::endpoint(&records))
+        .memory(Memory::new())
+        .sink(Loads::default())
+        .build()
+        .unwrap();
+
+    // another pipe's stages start & finish within the held transform, after its peak
+    let (report, _) = tokio::join!(held.run(), async {
+        HELD.notified().await;
+        other.run().await.unwrap();
+        RESUME.notify_one();
+    });
+    let peaks = report.unwrap().sizes.unwrap().peaks;
+    assert!(peaks[&Stage::Transform] >= HELD_BYTES);
+}
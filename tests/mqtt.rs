@@ -0,0 +1,185 @@
+// MQTT sources & sinks, against a minimal local broker; only with `--features mqtt`.
+#![cfg(feature = "mqtt")]
+
+use pipe_io::core::*;
+use pipe_io::mqtt::{MqttSink, MqttSource, QoS};
+use pipe_io::{Error, Outcome, Sink};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct Reading {
+    id: u16,
+}
+
+pipeline! {
+    Reading -> Reading {
+        async fn transform(&self, input: Reading) -> pipe_io::Result<Reading> {
+            Ok(input)
+        }
+    }
+}
+
+// what the broker received: a packet's type (the high nibble of its first byte), and its body
+type Received = (u8, Vec<u8>);
+
+// A broker at the returned port: accepting one client at a time; publishing `payloads` at QoS 1
+// to whoever subscribes (with packet ids 1, 2, ...); and passing on every packet it receives.
+async fn broker(payloads: Vec<&'static str>) -> (u16, UnboundedReceiver<Received>) {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (tx, rx) = unbounded_channel();
+    tokio::spawn(async move {
+        while let Ok((socket, _)) = listener.accept().await {
+            session(socket, &payloads, &tx).await;
+        }
+    });
+    (port, rx)
+}
+
+async fn session(mut socket: TcpStream, payloads: &[&str], received: &UnboundedSender<Received>) {
+    while let Some((kind, body)) = packet(&mut socket).await {
+        let _ = received.send((kind, body.clone()));
+        match kind {
+            // CONNECT: CONNACK
+            1 => socket.write_all(&[0x20, 2, 0, 0]).await.unwrap(),
+            // PUBLISH at QoS 1: PUBACK
+            3 => {
+                let topic = u16::from_be_bytes([body[0], body[1]]) as usize;
+                let pkid = &body[2 + topic..4 + topic];
+                socket
+                    .write_all(&[0x40, 2, pkid[0], pkid[1]])
+                    .await
+                    .unwrap();
+            }
+            // SUBSCRIBE: SUBACK, then the messages
+            8 => {
+                socket
+                    .write_all(&[0x90, 3, body[0], body[1], 1])
+                    .await
+                    .unwrap();
+                for (pkid, payload) in (1u16..).zip(payloads) {
+                    let mut publish = vec![0, 1, b't'];
+                    publish.extend(pkid.to_be_bytes());
+                    publish.extend(payload.as_bytes());
+                    socket
+                        .write_all(&[0x32, publish.len() as u8])
+                        .await
+                        .unwrap();
+                    socket.write_all(&publish).await.unwrap();
+                }
+            }
+            // PINGREQ: PINGRESP
+            12 => socket.write_all(&[0xd0, 0]).await.unwrap(),
+            // DISCONNECT
+            14 => return,
+            _ => {}
+        }
+    }
+}
+
+// the next packet's type & body; `None` once the client hangs up
+async fn packet(socket: &mut TcpStream) -> Option<Received> {
+    let first = socket.read_u8().await.ok()?;
+    let (mut length, mut shift) = (0usize, 0);
+    loop {
+        let byte = socket.read_u8().await.ok()?;
+        length |= ((byte & 0x7f) as usize) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    let mut body = vec![0; length];
+    socket.read_exact(&mut body).await.ok()?;
+    Some((first >> 4, body))
+}
+
+// the next packet of type `kind` the broker received
+async fn next(received: &mut UnboundedReceiver<Received>, kind: u8) -> Vec<u8> {
+    tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let (received, body) = received.recv().await.unwrap();
+            if received == kind {
+                return body;
+            }
+        }
+    })
+    .await
+    .unwrap()
+}
+
+// slow to load, so an acknowledgement sent on receipt would arrive before the load
+#[derive(Clone, Default)]
+struct Slow(Arc<Mutex<Vec<Reading>>>);
+
+impl Sink<Reading> for Slow {
+    async fn load(&self, output: &Reading) -> pipe_io::Result<Outcome> {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        self.0.lock().unwrap().push(output.clone());
+        Ok(Outcome::Done)
+    }
+}
+
+#[tokio::test]
+async fn messages_are_acknowledged_once_loaded() {
+    let (port, mut received) = broker(vec![r#"{"id": 1}"#, r#"{"id": 2}"#]).await;
+    let loads = Slow::default();
+    let source = MqttSource::new("127.0.0.1", port, "loader")
+        .subscribe("t", QoS::AtLeastOnce)
+        .into_source::<Reading>();
+    let pipe = Pipe::<Reading, Reading>::builder()
+        .source(source)
+        .sink(loads.clone())
+        .build()
+        .unwrap();
+    let run = tokio::spawn(async move { pipe.run().await.map(|_| ()) });
+
+    // each sent as the next message is polled for: after its own load, if not the next one's
+    for pkid in 1u16..=2 {
+        assert_eq!(next(&mut received, 4).await, pkid.to_be_bytes());
+        assert!(loads.0.lock().unwrap().len() >= pkid as usize);
+    }
+    assert_eq!(
+        *loads.0.lock().unwrap(),
+        [Reading { id: 1 }, Reading { id: 2 }]
+    );
+    run.abort();
+}
+
+#[tokio::test]
+async fn the_source_gives_up_reconnecting() {
+    // a port nothing listens on
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    drop(listener);
+
+    let source = MqttSource::new("127.0.0.1", port, "loader")
+        .subscribe("t", QoS::AtLeastOnce)
+        .reconnect_delay(Duration::from_millis(10))
+        .max_reconnects(2)
+        .into_source::<Reading>();
+    let pipe = Pipe::<Reading, Reading>::builder()
+        .source(source)
+        .sink(Slow::default())
+        .build()
+        .unwrap();
+    let run = tokio::time::timeout(Duration::from_secs(10), pipe.run());
+    assert!(matches!(run.await.unwrap(), Err(Error::Mqtt(_))));
+}
+
+#[tokio::test]
+async fn the_sink_disconnects_once_dropped() {
+    let (port, mut received) = broker(vec![]).await;
+    let sink = MqttSink::new("127.0.0.1", port, "publisher", "t").qos(QoS::AtLeastOnce);
+    sink.load(&Reading { id: 1 }).await.unwrap();
+    let publish = next(&mut received, 3).await;
+    assert!(publish.ends_with(br#"{"id":1}"#));
+
+    drop(sink);
+    next(&mut received, 14).await;
+}
@@ -0,0 +1,178 @@
+// Nested records normalized into keyed parent & child tables, and loaded one sink per table.
+
+use pipe_io::normalize::{Normalize, Row, Sinks, Tables};
+use pipe_io::{Error, Outcome, Sink};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+// each load, by the sink it reached
+type Loaded = Arc<Mutex<Vec<(&'static str, Vec<Row>)>>>;
+
+#[derive(Clone, Default)]
+struct Loads(Loaded, &'static str);
+
+impl Sink<Vec<Row>> for Loads {
+    async fn load(&self, output: &Vec<Row>) -> pipe_io::Result<Outcome> {
+        if self.1 == "broken" {
+            return Err(Error::Upstream("down".into()));
+        }
+        self.0.lock().unwrap().push((self.1, output.clone()));
+        Ok(Outcome::Done)
+    }
+}
+
+fn rows(value: Value) -> Vec<Row> {
+    serde_json::from_value(value).unwrap()
+}
+
+fn quotes() -> Value {
+    json!([
+        {
+            "symbol": "NVDA",
+            "meta": { "currency": "USD", "exchange": { "name": "NASDAQ" } },
+            "prices": [{ "close": 120.5, "trades": [{ "size": 10 }] }, { "close": 121.0 }],
+            "tags": ["chips", "ai"],
+            "grid": [[1, 2], [3, 4]]
+        },
+        { "symbol": "AAPL", "meta": { "currency": "USD" }, "prices": [{ "close": 210.0 }] }
+    ])
+}
+
+#[test]
+fn arrays_are_exploded_into_keyed_child_tables() {
+    let tables = Normalize::new("quotes").apply(&quotes()).unwrap();
+    assert_eq!(
+        tables.rows("quotes"),
+        rows(json!([
+            {
+                "_id": 1,
+                "symbol": "NVDA",
+                "meta_currency": "USD",
+                "meta_exchange_name": "NASDAQ",
+                "grid": [[1, 2], [3, 4]]
+            },
+            { "_id": 2, "symbol": "AAPL", "meta_currency": "USD" }
+        ]))
+    );
+    assert_eq!(
+        tables.rows("quotes_prices"),
+        rows(json!([
+            { "_id": 1, "quotes_id": 1, "close": 120.5 },
+            { "_id": 2, "quotes_id": 1, "close": 121.0 },
+            { "_id": 3, "quotes_id": 2, "close": 210.0 }
+        ]))
+    );
+    assert_eq!(
+        tables.rows("quotes_prices_trades"),
+        rows(json!([{ "_id": 1, "quotes_prices_id": 1, "size": 10 }]))
+    );
+    assert_eq!(
+        tables.rows("quotes_tags"),
+        rows(json!([
+            { "_id": 1, "quotes_id": 1, "value": "chips" },
+            { "_id": 2, "quotes_id": 1, "value": "ai" }
+        ]))
+    );
+    assert_eq!(tables.tables.len(), 4);
+}
+
+#[test]
+fn named_tables_keys_and_separators_are_configurable() {
+    let tables = Normalize::new("quotes")
+        .id("key")
+        .separator("__")
+        .table("meta", "venues")
+        .table("prices", "prices")
+        .apply(&quotes()[1])
+        .unwrap();
+    assert_eq!(
+        tables.rows("quotes"),
+        rows(json!([{ "key": 1, "symbol": "AAPL" }]))
+    );
+    assert_eq!(
+        tables.rows("venues"),
+        rows(json!([{ "key": 1, "quotes__key": 1, "currency": "USD" }]))
+    );
+    assert_eq!(
+        tables.rows("prices"),
+        rows(json!([{ "key": 1, "quotes__key": 1, "close": 210.0 }]))
+    );
+
+    let result = Normalize::new("quotes").apply(&json!([{ "symbol": "NVDA" }, 1]));
+    assert!(matches!(result, Err(Error::Config(message)) if message == "record 1 isn't an object"));
+}
+
+#[test]
+fn fields_named_as_other_columns_are_rejected() {
+    let normalize = Normalize::new("quotes");
+    let rejected = |value: Value| match normalize.apply(&value) {
+        Err(Error::Config(message)) => message,
+        result => panic!("expected a config error, got {result:?}"),
+    };
+    assert_eq!(
+        rejected(json!({ "_id": "NVDA" })),
+        "`_id` would overwrite column `_id` of table `quotes`"
+    );
+    assert_eq!(
+        rejected(json!({ "prices": [{ "quotes_id": 7 }] })),
+        "`prices.quotes_id` would overwrite column `quotes_id` of table `quotes_prices`"
+    );
+    assert_eq!(
+        rejected(json!({ "meta": { "currency": "USD" }, "meta_currency": "EUR" })),
+        "`meta_currency` would overwrite column `meta_currency` of table `quotes`"
+    );
+    // unless they're keyed, or joined, differently
+    assert!(normalize.id("key").apply(&json!({ "_id": "NVDA" })).is_ok());
+    let joined = Normalize::new("quotes").separator(".");
+    assert!(joined
+        .apply(&json!({ "meta": { "currency": "USD" }, "meta_currency": "EUR" }))
+        .is_ok());
+}
+
+#[tokio::test]
+async fn tables_are_loaded_to_their_sinks_in_order() {
+    let tables = Normalize::new("quotes").apply(&quotes()[1]).unwrap();
+    let loads = Loads::default();
+    let sinks = Sinks::new()
+        .table("quotes", Loads(loads.0.clone(), "quotes"))
+        .table("quotes_prices", Loads(loads.0.clone(), "prices"))
+        .table("quotes_tags", Loads(loads.0.clone(), "tags"));
+    let outcome = sinks.load(&tables).await.unwrap();
+    assert_eq!(
+        outcome,
+        Outcome::Routes(
+            [
+                ("quotes".to_string(), Outcome::Done),
+                ("quotes_prices".to_string(), Outcome::Done)
+            ]
+            .into()
+        )
+    );
+    let loaded = loads.0.lock().unwrap().clone();
+    assert_eq!(
+        loaded.iter().map(|(sink, _)| *sink).collect::<Vec<_>>(),
+        ["quotes", "prices"]
+    );
+    assert_eq!(loaded[1].1, tables.rows("quotes_prices"));
+
+    // a table with nowhere to go fails before anything is loaded
+    let result = Sinks::new()
+        .table("quotes", Loads(loads.0.clone(), "quotes"))
+        .load(&tables)
+        .await;
+    assert!(matches!(result, Err(Error::Config(message)) if message.contains("quotes_prices")));
+    assert_eq!(loads.0.lock().unwrap().len(), 2);
+
+    // and a failing table stops the load, named as its route
+    let result = Sinks::new()
+        .table("quotes_prices", Loads(loads.0.clone(), "broken"))
+        .table("quotes", Loads(loads.0.clone(), "quotes"))
+        .load(&tables)
+        .await;
+    let Err(Error::Many(errors)) = result else {
+        panic!("expected the failed table");
+    };
+    assert!(errors.to_string().contains("quotes_prices"));
+    assert_eq!(loads.0.lock().unwrap().len(), 2);
+    assert_eq!(Tables::default().rows("quotes"), &[] as &[Row]);
+}
@@ -0,0 +1,101 @@
+// Notifications of runs, through a mock mailer; only with `--features smtp`.
+#![cfg(feature = "smtp")]
+
+use futures::future::BoxFuture;
+use pipe_io::notify::{Format, Mail, Mailer, Notify};
+use pipe_io::{Error, Outcome, Pipe, Sink, Source};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+// keeps the mails it's sent, or fails to send them
+#[derive(Clone, Default)]
+struct Outbox {
+    sent: Arc<Mutex<Vec<Mail>>>,
+    failing: bool,
+}
+
+impl Mailer for Outbox {
+    fn send(&self, mail: Mail) -> BoxFuture<'_, pipe_io::Result<()>> {
+        Box::pin(async move {
+            if self.failing {
+                return Err(Error::Other(anyhow::anyhow!("relay down")));
+            }
+            self.sent.lock().unwrap().push(mail);
+            Ok(())
+        })
+    }
+}
+
+struct Loads {
+    failing: bool,
+}
+
+impl Sink<Value> for Loads {
+    async fn load(&self, _: &Value) -> pipe_io::Result<Outcome> {
+        match self.failing {
+            true => Err(Error::Other(anyhow::anyhow!("sink down"))),
+            false => Ok(Outcome::Streamed { rows: 2 }),
+        }
+    }
+}
+
+fn pipe(outbox: &Outbox, failing: bool) -> Pipe<Value, Value> {
+    Pipe::<Value, Value>::builder()
+        .source(Source::inline(
+            json!([{ "id": 1, "name": "a,b" }, { "id": 2 }]),
+        ))
+        .sink(Loads { failing })
+        .notify(
+            Notify::new(outbox.clone())
+                .name("prices")
+                .attach(Format::Csv, 1024),
+        )
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn a_successful_run_is_notified_once_with_its_output() {
+    let outbox = Outbox::default();
+    let report = pipe(&outbox, false).run().await.unwrap();
+    assert!(report.warnings.is_empty());
+
+    let sent = outbox.sent.lock().unwrap();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].subject, "prices: loaded 2 records");
+    assert!(sent[0].body.contains("2 rows affected"));
+    let attachment = sent[0].attachment.as_ref().unwrap();
+    assert_eq!(attachment.filename, "prices.csv");
+    assert_eq!(
+        String::from_utf8_lossy(&attachment.content),
+        "id,name\r\n1,\"a,b\"\r\n2,\r\n"
+    );
+}
+
+#[tokio::test]
+async fn a_failed_run_is_notified_and_still_fails() {
+    let outbox = Outbox::default();
+    let error = pipe(&outbox, true).run().await.unwrap_err();
+    assert!(error.to_string().contains("sink down"));
+
+    let sent = outbox.sent.lock().unwrap();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].subject, "prices: run failed");
+    assert!(sent[0].body.contains("sink down"));
+}
+
+#[tokio::test]
+async fn a_notification_that_cant_be_sent_is_a_warning() {
+    let outbox = Outbox {
+        failing: true,
+        ..Outbox::default()
+    };
+    let report = pipe(&outbox, false).run().await.unwrap();
+    assert_eq!(report.loads(), 1);
+    assert_eq!(report.warnings.len(), 1);
+    assert!(report.warnings[0].to_string().contains("relay down"));
+
+    // nor does it replace the error of a failed run
+    let error = pipe(&outbox, true).run().await.unwrap_err();
+    assert!(error.to_string().contains("sink down"));
+}
@@ -0,0 +1,102 @@
+// Client-credentials tokens, from a local token endpoint; only with `--features oauth2`.
+#![cfg(feature = "oauth2")]
+
+use pipe_io::oauth2::OAuth2;
+use pipe_io::Pipe;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+mod common;
+use common::{serve, Response};
+
+// a token endpoint, issuing `token-1`, `token-2`, ... each valid for `expires_in` seconds
+async fn tokens(
+    expires_in: u64,
+) -> (
+    String,
+    tokio::sync::mpsc::UnboundedReceiver<common::Request>,
+) {
+    let issued = AtomicU32::new(0);
+    serve(move |_| {
+        let n = issued.fetch_add(1, Ordering::SeqCst) + 1;
+        Response::json(&json!({
+            "access_token": format!("token-{n}"),
+            "token_type": "Bearer",
+            "expires_in": expires_in,
+        }))
+    })
+    .await
+}
+
+#[tokio::test]
+async fn tokens_are_fetched_once_and_shared() {
+    let (url, mut requests) = tokens(3600).await;
+    let oauth2 = OAuth2::new(format!("{url}/token"), "client", "secret").scope("read");
+    let client = reqwest::Client::new();
+    assert_eq!(oauth2.bearer(&client).await.unwrap(), "token-1");
+    assert_eq!(oauth2.bearer(&client).await.unwrap(), "token-1");
+
+    let request = requests.recv().await.unwrap();
+    assert_eq!(
+        (request.method.as_str(), request.target.as_str()),
+        ("POST", "/token")
+    );
+    assert_eq!(
+        request.body,
+        "grant_type=client_credentials&client_id=client&client_secret=secret&scope=read"
+    );
+    assert!(requests.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn tokens_are_refreshed_within_their_expiry_skew() {
+    // expiring within the default minute of skew, so never fresh
+    let (url, _requests) = tokens(30).await;
+    let oauth2 = OAuth2::new(format!("{url}/token"), "client", "secret");
+    let client = reqwest::Client::new();
+    assert_eq!(oauth2.bearer(&client).await.unwrap(), "token-1");
+    assert_eq!(oauth2.bearer(&client).await.unwrap(), "token-2");
+
+    // but outside of a smaller one
+    let oauth2 = oauth2.skew(Duration::from_secs(10));
+    assert_eq!(oauth2.bearer(&client).await.unwrap(), "token-2");
+    assert_eq!(oauth2.bearer(&client).await.unwrap(), "token-2");
+
+    // until it's invalidated
+    oauth2.invalidate().await;
+    assert_eq!(oauth2.bearer(&client).await.unwrap(), "token-3");
+}
+
+#[tokio::test]
+async fn a_revoked_token_is_replaced_and_the_request_retried() {
+    let (token_url, mut issued) = tokens(3600).await;
+    // an API that has revoked the first token
+    let attempts = Arc::new(AtomicU32::new(0));
+    let counted = attempts.clone();
+    let (url, _requests) = serve(move |request| {
+        counted.fetch_add(1, Ordering::SeqCst);
+        match request.header("authorization") {
+            Some("Bearer token-2") => Response::json(&json!([1, 2])),
+            _ => Response::status("401 Unauthorized"),
+        }
+    })
+    .await;
+
+    let pipe = Pipe::<Value, Value>::builder()
+        .oauth2(OAuth2::new(
+            format!("{token_url}/token"),
+            "client",
+            "secret",
+        ))
+        .build()
+        .unwrap();
+    let prices = pipe
+        .extract_default(&format!("{url}/prices"))
+        .await
+        .unwrap();
+    assert_eq!(prices, json!([1, 2]));
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    assert_eq!(std::iter::from_fn(|| issued.try_recv().ok()).count(), 2);
+}
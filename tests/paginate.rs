@@ -0,0 +1,95 @@
+// Cursor-paginated SaaS APIs, each paged by a preset; from a local server.
+
+use pipe_io::paginate::{Cursor, Paginator, PRESETS};
+use pipe_io::passthrough::Value;
+use pipe_io::{Error, Pipe};
+use serde::Deserialize;
+use serde_json::json;
+
+mod common;
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Contact {
+    id: u32,
+}
+
+async fn ids(paginator: Paginator, url: &str) -> Vec<u32> {
+    let pipe = Pipe::<Value, Value>::new();
+    let contacts: Vec<Contact> = paginator.extract(&pipe, url).await.unwrap();
+    contacts.into_iter().map(|contact| contact.id).collect()
+}
+
+#[tokio::test]
+async fn presets_follow_their_cursors() {
+    let url = common::serve_only(|request| {
+        common::Response::json(&match request.target.as_str() {
+            "/hubspot?archived=false" => json!({
+                "results": [{ "id": 1 }, { "id": 2 }],
+                "paging": { "next": { "after": "2" } }
+            }),
+            "/hubspot?archived=false&after=2" => json!({ "results": [{ "id": 3 }] }),
+            "/stripe" => json!({ "data": [{ "id": 1 }], "has_more": true }),
+            "/stripe?starting_after=1" => json!({ "data": [{ "id": 2 }], "has_more": false }),
+            "/offset?offset=0&limit=2" => json!({ "data": [{ "id": 1 }, { "id": 2 }] }),
+            // fewer than the limit, as if capped, but not the last
+            "/offset?offset=2&limit=2" => json!({ "data": [{ "id": 3 }] }),
+            "/offset?offset=3&limit=2" => json!({ "data": [{ "id": 4 }] }),
+            "/offset?offset=4&limit=2" => json!({ "data": [] }),
+            "/salesforce" => json!({
+                "records": [{ "id": 1 }],
+                "nextRecordsUrl": "/salesforce/query-2"
+            }),
+            "/salesforce/query-2" => json!({ "records": [{ "id": 2 }], "done": true }),
+            // a token that never changes
+            "/google" | "/google?pageToken=same" => json!({
+                "items": [{ "id": 1 }],
+                "nextPageToken": "same"
+            }),
+            _ => json!({ "error": request.target }),
+        })
+    })
+    .await;
+
+    let hubspot = Paginator::preset("hubspot").unwrap();
+    assert_eq!(
+        ids(hubspot, &format!("{url}/hubspot?archived=false")).await,
+        [1, 2, 3]
+    );
+    let stripe: Paginator = "has_more".parse().unwrap();
+    assert_eq!(ids(stripe, &format!("{url}/stripe")).await, [1, 2]);
+    let offset = Paginator::preset("offset_limit").unwrap().size(2);
+    assert_eq!(ids(offset, &format!("{url}/offset")).await, [1, 2, 3, 4]);
+    let salesforce = Paginator::preset("salesforce").unwrap();
+    assert_eq!(ids(salesforce, &format!("{url}/salesforce")).await, [1, 2]);
+
+    // a repeated page ends the extract, as does the most pages
+    let google = Paginator::preset("next_page_token").unwrap();
+    assert_eq!(ids(google.clone(), &format!("{url}/google")).await, [1, 1]);
+    assert_eq!(
+        ids(google.max_pages(1), &format!("{url}/google")).await,
+        [1]
+    );
+
+    // a page without its items
+    let pipe = Pipe::<Value, Value>::new();
+    let result: Result<Vec<Contact>, _> = Paginator::preset("hubspot")
+        .unwrap()
+        .extract(&pipe, &format!("{url}/missing"))
+        .await;
+    assert!(matches!(result, Err(Error::Missing(path)) if path == "results"));
+}
+
+#[test]
+fn presets_are_selected_by_name() {
+    for name in PRESETS {
+        assert!(Paginator::preset(name).is_ok(), "{name}");
+    }
+    assert!(matches!(
+        "graphql".parse::<Paginator>(),
+        Err(Error::Config(message)) if message.contains("offset_limit")
+    ));
+
+    let custom = Paginator::new("", Cursor::token("meta.cursor", "cursor"));
+    let page = json!([{ "id": 1 }]);
+    assert_eq!(custom.page_items(&page).unwrap(), [json!({ "id": 1 })]);
+}
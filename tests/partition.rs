@@ -0,0 +1,80 @@
+// Local NDJSON & CSV files, decoded in parallel partitions.
+
+use futures::StreamExt;
+use pipe_io::partition::Partitioned;
+use pipe_io::Error;
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Price {
+    ticker: String,
+    close: f64,
+}
+
+fn file(name: &str, contents: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join("pipe-io-test-partition");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[tokio::test]
+async fn partitions_split_on_record_boundaries() {
+    let lines: String = (0..500)
+        .map(|i| format!("{{\"ticker\": \"T{i}\", \"close\": {i}}}\n\n"))
+        .collect();
+    let path = file("prices.ndjson", &lines);
+
+    // partitions much smaller than, and not aligned to, the records
+    let ordered: Vec<Price> = Partitioned::json_lines(&path)
+        .chunk_size(37)
+        .threads(4)
+        .into_stream()
+        .await
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+        .await;
+    let expected: Vec<f64> = (0..500).map(f64::from).collect();
+    let closes: Vec<f64> = ordered.iter().map(|price| price.close).collect();
+    assert_eq!(closes, expected);
+    assert_eq!(ordered[7].ticker, "T7");
+
+    let unordered: Vec<Price> = Partitioned::json_lines(&path)
+        .chunk_size(1000)
+        .ordered(false)
+        .into_stream()
+        .await
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+        .await;
+    let mut closes: Vec<f64> = unordered.iter().map(|price| price.close).collect();
+    closes.sort_by(f64::total_cmp);
+    assert_eq!(closes, expected);
+}
+
+#[tokio::test]
+async fn csv_partitions_share_the_header() {
+    let mut csv = String::from("close;ticker\r\n");
+    for i in 0..100 {
+        csv.push_str(&format!("{i}.5;T{i}\r\n"));
+    }
+    csv.push_str("bad;T100\r\n101;T101");
+    let path = file("prices.csv", &csv);
+
+    let prices: Vec<Result<Price, Error>> = Partitioned::csv(&path)
+        .delimiter(b';')
+        .chunk_size(20)
+        .into_stream()
+        .await
+        .unwrap()
+        .collect()
+        .await;
+    assert_eq!(prices.len(), 102);
+    let first = prices[0].as_ref().unwrap();
+    assert_eq!((first.ticker.as_str(), first.close), ("T0", 0.5));
+    assert!(matches!(prices[100], Err(Error::Csv(_))));
+    assert_eq!(prices[101].as_ref().unwrap().ticker, "T101");
+}
@@ -0,0 +1,102 @@
+// Plugin sinks & sources, as trait objects chosen by name; only with `--features dyn`.
+#![cfg(feature = "dyn")]
+
+use pipe_io::plugin::{async_trait, Registry, Sink, Source};
+use pipe_io::{Error, Outcome, Pipe, Runner};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// what a plugin saw: the outputs loaded, and the inputs acknowledged
+#[derive(Clone, Default)]
+struct Seen {
+    loaded: Arc<Mutex<Vec<Value>>>,
+    acknowledged: Arc<Mutex<usize>>,
+    flushed: Arc<Mutex<bool>>,
+}
+
+struct Memory(Seen);
+
+#[async_trait]
+impl Sink<Value> for Memory {
+    async fn load(&self, output: &Value) -> pipe_io::Result<Outcome> {
+        self.0.loaded.lock().unwrap().push(output.clone());
+        Ok(Outcome::Done)
+    }
+}
+
+struct Values {
+    values: std::vec::IntoIter<Value>,
+    seen: Seen,
+}
+
+#[async_trait]
+impl Source<Value> for Values {
+    async fn next(&mut self) -> Option<pipe_io::Result<Value>> {
+        self.values.next().map(Ok)
+    }
+
+    async fn loaded(&mut self) -> pipe_io::Result<()> {
+        *self.seen.acknowledged.lock().unwrap() += 1;
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> pipe_io::Result<()> {
+        *self.seen.flushed.lock().unwrap() = true;
+        Ok(())
+    }
+}
+
+fn registry(seen: &Seen) -> Registry<Value, Value> {
+    let (sink, source) = (seen.clone(), seen.clone());
+    Registry::new()
+        .register_sink("memory", move |_| Ok(Box::new(Memory(sink.clone()))))
+        .register_source("values", move |settings| {
+            let values = settings["values"]
+                .as_array()
+                .ok_or_else(|| Error::Missing("values".into()))?;
+            Ok(Box::new(Values {
+                values: values.clone().into_iter(),
+                seen: source.clone(),
+            }))
+        })
+}
+
+#[tokio::test]
+async fn plugins_are_chosen_by_name_and_run() {
+    let seen = Seen::default();
+    let registry = registry(&seen);
+    assert_eq!(registry.sinks().collect::<Vec<_>>(), ["memory"]);
+    let pipe = Pipe::<Value, Value>::builder()
+        .source(
+            registry
+                .source("values", &json!({ "values": [1, 2, 3] }))
+                .unwrap(),
+        )
+        .sink(registry.sink("memory", &Value::Null).unwrap())
+        .build()
+        .unwrap();
+    let runner = Runner::new().pipe("plugins", pipe, Duration::from_secs(60));
+    runner.run_once().await.unwrap();
+
+    assert_eq!(*seen.loaded.lock().unwrap(), [json!(1), json!(2), json!(3)]);
+    assert_eq!(*seen.acknowledged.lock().unwrap(), 3);
+    assert!(*seen.flushed.lock().unwrap());
+    assert_eq!(runner.statuses().get("plugins").unwrap().runs, 1);
+
+    // each run from a source of its own, made from the same settings
+    runner.run_once().await.unwrap();
+    assert_eq!(seen.loaded.lock().unwrap().len(), 6);
+    assert_eq!(*seen.acknowledged.lock().unwrap(), 6);
+    assert_eq!(runner.statuses().get("plugins").unwrap().runs, 2);
+
+    // unregistered, or misconfigured
+    assert!(matches!(
+        registry.sink("bucket", &Value::Null),
+        Err(Error::Config(message)) if message.contains("memory")
+    ));
+    assert!(matches!(
+        registry.source("values", &json!({})),
+        Err(Error::Missing(_))
+    ));
+}
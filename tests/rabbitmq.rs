@@ -0,0 +1,175 @@
+// A RabbitMQ sink, against a minimal local broker; only with `--features rabbitmq`.
+#![cfg(feature = "rabbitmq")]
+
+use pipe_io::rabbitmq::RabbitMqSink;
+use pipe_io::{Error, Outcome, Sink};
+use serde::Serialize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+#[derive(Serialize)]
+struct Price {
+    id: u16,
+}
+
+// A broker at the returned URI: confirming every message it's published, and passing on their
+// bodies; but hanging up on a connection once it has confirmed `per_connection` of them, as if
+// it had restarted. The counter is of the connections it's accepted.
+async fn broker(per_connection: usize) -> (String, UnboundedReceiver<String>, Arc<AtomicUsize>) {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let uri = format!("amqp://{}/%2f", listener.local_addr().unwrap());
+    let (tx, rx) = unbounded_channel();
+    let connections = Arc::new(AtomicUsize::new(0));
+    let accepted = connections.clone();
+    tokio::spawn(async move {
+        while let Ok((socket, _)) = listener.accept().await {
+            accepted.fetch_add(1, Ordering::SeqCst);
+            let tx = tx.clone();
+            tokio::spawn(async move { session(socket, per_connection, &tx).await });
+        }
+    });
+    (uri, rx, connections)
+}
+
+async fn session(
+    mut socket: TcpStream,
+    per_connection: usize,
+    published: &UnboundedSender<String>,
+) {
+    let mut header = [0; 8];
+    if socket.read_exact(&mut header).await.is_err() {
+        return;
+    }
+    // connection.start: version 0-9, no server properties, PLAIN, en_US
+    let mut start = vec![0, 9, 0, 0, 0, 0];
+    start.extend(long_string("PLAIN"));
+    start.extend(long_string("en_US"));
+    method(&mut socket, 0, (10, 10), &start).await;
+
+    let (mut confirmed, mut body, mut size) = (0u64, vec![], None);
+    while let Some((kind, channel, payload)) = frame(&mut socket).await {
+        match kind {
+            1 => match (class_method(&payload), channel) {
+                // connection.start-ok: connection.tune, with no heartbeats
+                ((10, 11), _) => method(&mut socket, 0, (10, 30), &[0, 0, 0, 2, 0, 0, 0, 0]).await,
+                // connection.open: connection.open-ok
+                ((10, 40), _) => method(&mut socket, 0, (10, 41), &[0]).await,
+                // channel.open: channel.open-ok
+                ((20, 10), channel) => method(&mut socket, channel, (20, 11), &[0; 4]).await,
+                // confirm.select: confirm.select-ok
+                ((85, 10), channel) => method(&mut socket, channel, (85, 11), &[]).await,
+                // channel.close & connection.close: their close-oks
+                ((20, 40), channel) => method(&mut socket, channel, (20, 41), &[]).await,
+                ((10, 50), _) => {
+                    method(&mut socket, 0, (10, 51), &[]).await;
+                    return;
+                }
+                _ => {}
+            },
+            // a message's content header, with its body's size
+            2 => size = Some(u64::from_be_bytes(payload[4..12].try_into().unwrap()) as usize),
+            // its body, then basic.ack
+            3 => {
+                body.extend(payload);
+                if Some(body.len()) == size {
+                    let _ = published.send(String::from_utf8(std::mem::take(&mut body)).unwrap());
+                    confirmed += 1;
+                    let mut ack = confirmed.to_be_bytes().to_vec();
+                    ack.push(0);
+                    method(&mut socket, channel, (60, 80), &ack).await;
+                    if confirmed as usize == per_connection {
+                        return;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn long_string(value: &str) -> Vec<u8> {
+    let mut bytes = (value.len() as u32).to_be_bytes().to_vec();
+    bytes.extend(value.as_bytes());
+    bytes
+}
+
+fn class_method(payload: &[u8]) -> (u16, u16) {
+    (
+        u16::from_be_bytes([payload[0], payload[1]]),
+        u16::from_be_bytes([payload[2], payload[3]]),
+    )
+}
+
+async fn method(socket: &mut TcpStream, channel: u16, (class, method): (u16, u16), args: &[u8]) {
+    let mut payload = class.to_be_bytes().to_vec();
+    payload.extend(method.to_be_bytes());
+    payload.extend(args);
+    let mut frame = vec![1];
+    frame.extend(channel.to_be_bytes());
+    frame.extend((payload.len() as u32).to_be_bytes());
+    frame.extend(payload);
+    frame.push(0xce);
+    let _ = socket.write_all(&frame).await;
+}
+
+// the next frame's type, channel & payload; `None` once the client hangs up
+async fn frame(socket: &mut TcpStream) -> Option<(u8, u16, Vec<u8>)> {
+    let mut header = [0; 7];
+    socket.read_exact(&mut header).await.ok()?;
+    let size = u32::from_be_bytes(header[3..7].try_into().unwrap()) as usize;
+    let mut payload = vec![0; size + 1];
+    socket.read_exact(&mut payload).await.ok()?;
+    payload.pop();
+    Some((
+        header[0],
+        u16::from_be_bytes([header[1], header[2]]),
+        payload,
+    ))
+}
+
+fn prices(ids: std::ops::Range<u16>) -> Vec<Price> {
+    ids.map(|id| Price { id }).collect()
+}
+
+#[tokio::test]
+async fn every_record_is_published_and_confirmed() {
+    let (uri, mut published, connections) = broker(usize::MAX).await;
+    let sink = RabbitMqSink::new(uri, "", "prices");
+    let outcome = sink.load(&prices(1..3)).await.unwrap();
+    assert_eq!(outcome, Outcome::Published { messages: 2 });
+    assert_eq!(published.recv().await.unwrap(), r#"{"id":1}"#);
+    assert_eq!(published.recv().await.unwrap(), r#"{"id":2}"#);
+
+    // on the same connection
+    sink.load(&prices(3..4)).await.unwrap();
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn a_lost_connection_is_reopened_by_the_next_load() {
+    let (uri, mut published, connections) = broker(2).await;
+    let sink = RabbitMqSink::new(uri, "", "prices");
+    sink.load(&prices(1..3)).await.unwrap();
+
+    // the broker has hung up, whether or not the sink has noticed yet
+    let outcome = sink.load(&prices(3..4)).await.unwrap();
+    assert_eq!(outcome, Outcome::Published { messages: 1 });
+    assert_eq!(connections.load(Ordering::SeqCst), 2);
+    let bodies: Vec<String> = std::iter::from_fn(|| published.try_recv().ok()).collect();
+    assert_eq!(bodies, [r#"{"id":1}"#, r#"{"id":2}"#, r#"{"id":3}"#]);
+}
+
+#[tokio::test]
+async fn a_broker_that_cant_be_reached_fails_the_load() {
+    // a port nothing listens on
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let uri = format!("amqp://{}/%2f", listener.local_addr().unwrap());
+    drop(listener);
+
+    let sink = RabbitMqSink::new(uri, "", "prices");
+    let error = sink.load(&prices(1..2)).await.unwrap_err();
+    assert!(matches!(error, Error::RabbitMq(_)));
+}
@@ -0,0 +1,976 @@
+// Sinks that only need the local filesystem.
+
+use pipe_io::audit::{AuditRecord, Audited};
+use pipe_io::clock::Fixed;
+use pipe_io::db::couchdb;
+use pipe_io::fs;
+use pipe_io::health::{Breaker, Circuit, Health};
+use pipe_io::observer::Event;
+use pipe_io::retry::{self, Hint};
+use pipe_io::sink::{Collision, Dialect, Quoting, RawSink};
+use pipe_io::throttle::Throttled;
+use pipe_io::{sink, CouchOutcome, Error, Outcome, RetryPolicy, Sink, Staged, Tabular, Versioned};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+mod common;
+use common::{serve, Response};
+
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("pipe-io-test-{name}"));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+// staging
+/////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[tokio::test]
+async fn staged_file_publishes_or_leaves_destination_untouched() {
+    let dir = temp_dir("staged");
+    let path = dir.join("prices.json");
+    std::fs::write(&path, r#"{"old": true}"#).unwrap();
+
+    // a failing check discards the staged file, and keeps the old output
+    let failing = Staged::new(sink::File::new(&path))
+        .check(|_: &Value| Err(Error::Verification("rejected".into())));
+    let result = failing.load(&json!({ "new": true })).await;
+    assert!(matches!(result, Err(Error::Verification(_))));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), r#"{"old": true}"#);
+    assert!(!dir.join("prices.json.staging").exists());
+
+    // a passing load replaces the output
+    let passing = Staged::new(sink::File::new(&path));
+    passing.load(&json!({ "new": true })).await.unwrap();
+    let published: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(published, json!({ "new": true }));
+}
+
+#[tokio::test]
+async fn staged_couchdb_sink_copies_the_staging_document_over_the_destination() {
+    // a CouchDB holding `doc.staging` once it's been PUT
+    let staged = Arc::new(Mutex::new(false));
+    let stored = staged.clone();
+    let (url, mut requests) = serve(move |request| {
+        let mut staged = stored.lock().unwrap();
+        match (request.method.as_str(), request.target.as_str()) {
+            ("PUT", "/doc.staging") => {
+                *staged = true;
+                Response::status("201 Created")
+                    .body(r#"{ "ok": true, "id": "doc.staging", "rev": "1-a" }"#)
+            }
+            ("GET", "/doc.staging") if *staged => {
+                Response::ok(r#"{ "_id": "doc.staging", "_rev": "1-a" }"#)
+            }
+            ("COPY", "/doc.staging") => {
+                Response::status("201 Created").body(r#"{ "ok": true, "id": "doc", "rev": "1-b" }"#)
+            }
+            ("DELETE", "/doc.staging?rev=1-a") => {
+                *staged = false;
+                Response::ok("")
+            }
+            _ => Response::status("404 Not Found"),
+        }
+    })
+    .await;
+    let outcome = Staged::new(sink::CouchDb::new(&url, "doc"))
+        .load(&json!({ "ticker": "NVDA" }))
+        .await
+        .unwrap();
+    // the destination's, not the staging document's
+    let Outcome::Couch(doc) = outcome else {
+        panic!("expected a CouchDB outcome, got {outcome:?}");
+    };
+    assert_eq!(
+        (doc.id.as_str(), doc.rev.as_str(), doc.created),
+        ("doc", "1-b", true)
+    );
+    let requests: Vec<_> = std::iter::from_fn(|| requests.try_recv().ok())
+        .filter(|request| request.method != "GET")
+        .collect();
+    let methods: Vec<_> = requests
+        .iter()
+        .map(|request| request.method.as_str())
+        .collect();
+    assert_eq!(methods, ["PUT", "COPY", "DELETE"]);
+    assert_eq!(requests[1].header("destination"), Some("doc"));
+    assert!(!*staged.lock().unwrap());
+
+    // a failed write is an error, not a panic
+    let (url, _requests) = serve(|request| match request.method.as_str() {
+        "PUT" => Response::status("500 Internal Server Error"),
+        _ => Response::status("404 Not Found"),
+    })
+    .await;
+    let result = Staged::new(sink::CouchDb::new(&url, "doc"))
+        .load(&json!({ "ticker": "NVDA" }))
+        .await;
+    assert!(result.is_err());
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+// csv
+/////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Serialize, Tabular)]
+struct Row {
+    symbol: &'static str,
+    note: &'static str,
+    close: f64,
+}
+
+#[tokio::test]
+async fn csv_sink_escapes_and_appends() {
+    let dir = temp_dir("csv");
+    let path = dir.join("prices.csv");
+    let rows = vec![
+        Row {
+            symbol: "NVDA",
+            note: "split; 10:1",
+            close: 1.5,
+        },
+        Row {
+            symbol: "AAPL",
+            note: "said \"hold\"",
+            close: 2.0,
+        },
+    ];
+
+    let url = format!("file://{}", path.display());
+    let sink = sink::Csv::from_url(&url).unwrap().dialect(Dialect {
+        delimiter: ';',
+        bom: true,
+        ..Default::default()
+    });
+    sink.load(&rows).await.unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "\u{feff}symbol;note;close\nNVDA;\"split; 10:1\";1.5\nAAPL;\"said \"\"hold\"\"\";2.0\n"
+    );
+
+    // appending adds rows alone; no second header, nor byte order mark
+    let sink = sink.append(true);
+    sink.load(&rows[..1].iter().collect::<Vec<_>>())
+        .await
+        .unwrap();
+    let csv = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(csv.matches("symbol").count(), 1);
+    assert!(csv.ends_with("2.0\nNVDA;\"split; 10:1\";1.5\n"));
+
+    let sink = sink::Csv::new(&path).dialect(Dialect {
+        quoting: Quoting::Always,
+        header: false,
+        ..Default::default()
+    });
+    sink.load(&rows[..1].iter().collect::<Vec<_>>())
+        .await
+        .unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "\"NVDA\",\"split; 10:1\",\"1.5\"\n"
+    );
+
+    assert!(matches!(
+        sink::Csv::from_url("https://example.com/prices.csv"),
+        Err(Error::Config(_))
+    ));
+}
+
+// scalar fields, of any type; the nested one skipped
+#[derive(Serialize, Tabular)]
+struct Quote<P> {
+    symbol: String,
+    date: chrono::NaiveDate,
+    price: Option<P>,
+    #[serde(skip)]
+    #[allow(dead_code)]
+    history: Vec<P>,
+}
+
+#[tokio::test]
+async fn csv_sink_takes_tabular_records() {
+    let path = temp_dir("tabular").join("quotes.csv");
+    let quotes = vec![Quote {
+        symbol: "NVDA".into(),
+        date: chrono::NaiveDate::from_ymd_opt(2024, 6, 3).unwrap(),
+        price: Some(120.5),
+        history: vec![118.0],
+    }];
+    sink::Csv::new(&path).load(&quotes).await.unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "symbol,date,price\nNVDA,2024-06-03,120.5\n"
+    );
+
+    sink::Csv::new(&path)
+        .load(&vec![("AAPL", 210.0, None::<&str>)])
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "AAPL,210.0,\n");
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+// hive
+/////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+fn gunzip(path: &std::path::Path) -> String {
+    let mut ndjson = String::new();
+    let file = std::fs::File::open(path).unwrap();
+    std::io::Read::read_to_string(&mut flate2::read::GzDecoder::new(file), &mut ndjson).unwrap();
+    ndjson
+}
+
+#[tokio::test]
+async fn hive_sink_writes_a_part_per_partition_and_load() {
+    let dir = temp_dir("hive");
+    let sink = sink::Hive::new(&dir).date("dt", "time").key("symbol");
+    let prices = serde_json::json!([
+        { "symbol": "NVDA", "time": "2024-06-01T14:30:00Z", "close": 120.5 },
+        { "symbol": "AAPL", "time": "2024-06-01T14:30:00Z", "close": 210.0 },
+        { "symbol": "NVDA", "time": 1717286400, "close": 121.0 },
+        { "symbol": "BRK/B", "time": "2024-06-02", "close": 400.0 },
+        { "symbol": null, "time": "2024-06-02", "close": 1.0 }
+    ]);
+    let Outcome::Routes(parts) = sink.load(&prices).await.unwrap() else {
+        panic!("expected a route per partition");
+    };
+    assert_eq!(
+        parts.keys().collect::<Vec<_>>(),
+        [
+            "dt=2024-06-01/symbol=AAPL/part-0001.ndjson.gz",
+            "dt=2024-06-01/symbol=NVDA/part-0001.ndjson.gz",
+            "dt=2024-06-02/symbol=BRK%2FB/part-0001.ndjson.gz",
+            "dt=2024-06-02/symbol=NVDA/part-0001.ndjson.gz",
+            "dt=2024-06-02/symbol=__HIVE_DEFAULT_PARTITION__/part-0001.ndjson.gz",
+        ]
+    );
+    // partitioned by key, the symbol is only in the path
+    assert_eq!(
+        gunzip(&dir.join("dt=2024-06-01/symbol=NVDA/part-0001.ndjson.gz")),
+        "{\"close\":120.5,\"time\":\"2024-06-01T14:30:00Z\"}\n"
+    );
+
+    // a later load adds the next part
+    let price = serde_json::json!({ "symbol": "NVDA", "time": "2024-06-01", "close": 122.0 });
+    let outcome = sink.load(&price).await.unwrap();
+    let part = "dt=2024-06-01/symbol=NVDA/part-0002.ndjson.gz";
+    assert!(matches!(outcome, Outcome::Routes(parts) if parts.contains_key(part)));
+    assert!(gunzip(&dir.join(part)).contains("122.0"));
+
+    // after the highest part, rather than the number of parts, if there's a gap
+    let partition = dir.join("dt=2024-06-01/symbol=NVDA");
+    std::fs::rename(
+        partition.join("part-0002.ndjson.gz"),
+        partition.join("part-0005.ndjson.gz"),
+    )
+    .unwrap();
+    let outcome = sink.load(&price).await.unwrap();
+    let part = "dt=2024-06-01/symbol=NVDA/part-0006.ndjson.gz";
+    assert!(matches!(outcome, Outcome::Routes(parts) if parts.contains_key(part)));
+
+    // and loads at once each take a part of their own
+    let (first, second) = tokio::join!(sink.load(&price), sink.load(&price));
+    let (Outcome::Routes(first), Outcome::Routes(second)) = (first.unwrap(), second.unwrap())
+    else {
+        panic!("expected a route per partition");
+    };
+    assert_ne!(first.keys().next(), second.keys().next());
+    assert_eq!(std::fs::read_dir(&partition).unwrap().count(), 5);
+
+    assert!(matches!(sink.load(&[1, 2]).await, Err(Error::Config(_))));
+    let late = serde_json::json!({ "symbol": "NVDA", "time": "soon" });
+    assert!(matches!(sink.load(&late).await, Err(Error::Time(_))));
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+// serialized
+/////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[tokio::test]
+async fn custom_serializer_replaces_json() {
+    let dir = temp_dir("serialized");
+    let path = dir.join("prices.csv");
+
+    let sink = sink::File::new(&path).with_serializer(|rows: &Vec<(String, f64)>| {
+        rows.iter()
+            .map(|(ticker, price)| format!("\"{ticker}\",{price}\n"))
+            .collect::<String>()
+            .into_bytes()
+    });
+    sink.load(&vec![("NVDA".into(), 120.5), ("AAPL".into(), 210.0)])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "\"NVDA\",120.5\n\"AAPL\",210\n"
+    );
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+// http
+/////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[tokio::test]
+async fn http_sink_sends_idempotency_keys_once() {
+    let (url, mut requests) = serve(|_| Response::ok("")).await;
+    let url = format!("{url}/records");
+    let sink = sink::Http::new(&url).run_id("run-1");
+    let record = json!({ "ticker": "NVDA" });
+
+    sink.load(&record).await.unwrap();
+    sink.load(&record).await.unwrap();
+    sink.load(&json!({ "ticker": "AAPL" })).await.unwrap();
+
+    let key = sink.idempotency_key(&serde_json::to_vec(&record).unwrap());
+    let first = requests.recv().await.unwrap();
+    assert_eq!(first.header("idempotency-key"), Some(key.as_str()));
+    // the replayed record was skipped; the next request is the new record
+    let second = requests.recv().await.unwrap();
+    assert!(second
+        .header("idempotency-key")
+        .is_some_and(|other| other != key));
+    assert!(requests.try_recv().is_err());
+
+    // the same run id derives the same key
+    let again = sink::Http::new(&url).run_id("run-1");
+    assert_eq!(
+        again.idempotency_key(&serde_json::to_vec(&record).unwrap()),
+        key
+    );
+}
+
+#[tokio::test]
+async fn http_sink_keys_by_the_runs_id() {
+    let (url, mut requests) = serve(|_| Response::ok("")).await;
+    let url = format!("{url}/records");
+    let pipe = pipe_io::Pipe::<Value, Value>::builder()
+        .source(pipe_io::Source::inline(json!({ "ticker": "NVDA" })))
+        .sink(sink::Http::new(&url))
+        .clock(Arc::new(Fixed::new(
+            "2024-06-01T00:00:00Z".parse().unwrap(),
+        )))
+        .build()
+        .unwrap();
+    pipe.run().await.unwrap();
+
+    let run = sink::Http::new(&url).run_id("run-00000000-0000-0000-0000-000000000001");
+    let key = run.idempotency_key(br#"{"ticker":"NVDA"}"#);
+    let request = requests.recv().await.unwrap();
+    assert_eq!(request.header("idempotency-key"), Some(key.as_str()));
+}
+
+#[tokio::test]
+async fn couchdb_sink_retries_conflicting_writes() {
+    // every PUT conflicts with a concurrent writer, until `conflicts` run out
+    let couch = |mut conflicts: u32| {
+        move |request: &common::Request| match request.method == "PUT" {
+            true if conflicts > 0 => {
+                conflicts -= 1;
+                Response::status("409 Conflict")
+            }
+            true => {
+                Response::status("201 Created").body(r#"{ "ok": true, "id": "doc", "rev": "3-c" }"#)
+            }
+            false => Response::ok(r#"{ "_id": "doc", "_rev": "2-b" }"#),
+        }
+    };
+
+    let (url, mut requests) = serve(couch(2)).await;
+    let sink = sink::CouchDb::new(&url, "doc");
+    let outcome = sink.load(&json!({ "ticker": "NVDA" })).await.unwrap();
+    assert_eq!(
+        outcome,
+        Outcome::Couch(CouchOutcome {
+            id: "doc".into(),
+            rev: "3-c".into(),
+            created: false,
+        })
+    );
+    let mut puts = 0;
+    while let Ok(request) = requests.try_recv() {
+        if request.method == "PUT" {
+            puts += 1;
+            assert!(request.body.contains(r#""_rev":"2-b""#));
+        }
+    }
+    assert_eq!(puts, 3);
+
+    let (url, _requests) = serve(couch(u32::MAX)).await;
+    let sink = sink::CouchDb::new(&url, "doc").conflict_retries(1);
+    let result = sink.load(&json!({ "ticker": "NVDA" })).await;
+    assert!(matches!(result, Err(Error::Conflict { attempts: 2, .. })));
+}
+
+#[tokio::test]
+async fn couchdb_loads_fail_on_a_server_error() {
+    // the GET of the current revision fails, or else the PUT after it
+    for failing in ["GET", "PUT"] {
+        let url = common::serve_only(move |request| match request.method == failing {
+            true => Response::status("500 Internal Server Error"),
+            false => Response::status("404 Not Found"),
+        })
+        .await;
+        let doc = json!({ "ticker": "NVDA" });
+        let wire_log = pipe_io::WireLog::new();
+        let results = [
+            couchdb::insert_doc(&doc, &url, "doc").await,
+            couchdb::insert_doc_logged(&doc, &url, "doc", Some(&wire_log)).await,
+            pipe_io::default::load(doc, &url, "doc").await,
+        ];
+        for result in results {
+            assert!(matches!(result, Err(Error::HTTP(_))), "{failing}");
+        }
+    }
+}
+
+#[tokio::test]
+async fn couchdb_sink_pushes_back_with_its_retry_after() {
+    let (url, _requests) = serve(|request| match request.method == "PUT" {
+        true => Response::status("429 Too Many Requests").header("retry-after", "7"),
+        false => Response::status("404 Not Found"),
+    })
+    .await;
+    let sink = sink::CouchDb::new(&url, "doc");
+    let error = sink.load(&json!({ "ticker": "NVDA" })).await.unwrap_err();
+    assert!(matches!(
+        error,
+        Error::Backpressure {
+            retry_after: Some(delay),
+            ..
+        } if delay == Duration::from_secs(7)
+    ));
+
+    // which the retry policy waits, instead of its backoff; but no longer than its longest delay
+    let retry = RetryPolicy::new(3).backoff(Duration::from_millis(10), Duration::from_secs(10));
+    assert_eq!(retry.delay_after(1, &error), Duration::from_secs(7));
+    assert_eq!(Hint::of(&error), Hint::After(Duration::from_secs(7)));
+    let retry = retry.backoff(Duration::from_millis(10), Duration::from_secs(1));
+    assert_eq!(retry.delay_after(1, &error), Duration::from_secs(1));
+}
+
+// the delay of a `Retry-After` date, as of the load
+#[derive(Clone, Default)]
+struct RetryAfter(Arc<Mutex<Option<Duration>>>);
+
+impl Sink<Value> for RetryAfter {
+    async fn load(&self, _: &Value) -> pipe_io::Result<Outcome> {
+        *self.0.lock().unwrap() = retry::retry_after("Wed, 21 Oct 2015 07:28:00 GMT");
+        Ok(Outcome::Done)
+    }
+}
+
+#[tokio::test]
+async fn retry_after_dates_are_as_of_the_pipes_clock() {
+    let sink = RetryAfter::default();
+    let pipe = pipe_io::Pipe::<Value, Value>::builder()
+        .source(pipe_io::Source::inline(json!({})))
+        .sink(sink.clone())
+        .clock(Arc::new(Fixed::new(
+            "2015-10-21T07:27:00Z".parse().unwrap(),
+        )))
+        .build()
+        .unwrap();
+    pipe.run().await.unwrap();
+    assert_eq!(*sink.0.lock().unwrap(), Some(Duration::from_secs(60)));
+}
+
+#[test]
+fn retries_follow_the_hints_of_their_failures() {
+    let retry = RetryPolicy::new(3).backoff(Duration::from_millis(10), Duration::from_secs(1));
+    let overloaded = Error::Backpressure {
+        message: "busy".into(),
+        retry_after: None,
+    };
+    assert_eq!(Hint::of(&overloaded), Hint::Overloaded);
+    assert_eq!(retry.delay_after(1, &overloaded), Duration::from_secs(1));
+    let open = Error::CircuitOpen {
+        retry_in: Duration::from_millis(250),
+    };
+    assert_eq!(retry.delay_after(1, &open), Duration::from_millis(250));
+    let other = Error::Upstream("down".into());
+    assert_eq!(Hint::of(&other), Hint::Backoff);
+    assert_eq!(retry.delay_after(2, &other), Duration::from_millis(20));
+
+    // a batch waits for the slowest of its failures
+    let mut errors = pipe_io::error::Errors::new();
+    errors.push(pipe_io::error::Context::Record(0), other);
+    errors.push(pipe_io::error::Context::Record(1), open);
+    assert_eq!(
+        retry.delay_after(1, &Error::Many(errors)),
+        Duration::from_millis(250)
+    );
+
+    assert_eq!(retry::retry_after(" 120 "), Some(Duration::from_secs(120)));
+    assert_eq!(
+        retry::retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+        Some(Duration::ZERO)
+    );
+    assert_eq!(retry::retry_after("soon"), None);
+}
+
+#[tokio::test]
+async fn couchdb_sink_splits_outputs_into_chunks() {
+    // `doc` is the manifest of a previous, longer load
+    let (url, mut requests) = serve(|request| {
+        let id = request.path().trim_start_matches('/');
+        match request.method.as_str() {
+            "GET" if id == "doc" => Response::json(
+                &json!({ "_id": "doc", "_rev": "1-a", "chunks": ["doc-0001", "doc-0002", "doc-0003"], "records": 5 }),
+            ),
+            "GET" => Response::json(&json!({ "_id": id, "_rev": "1-a" })),
+            "PUT" => Response::status("201 Created")
+                .body(json!({ "ok": true, "id": id, "rev": "2-b" }).to_string()),
+            _ => Response::ok(""),
+        }
+    })
+    .await;
+    let sink = sink::CouchDb::new(&url, "doc").layout(couchdb::Layout::Chunked { records: 2 });
+    let outcome = sink.load(&json!([1, 2, 3])).await.unwrap();
+
+    let ids: Vec<_> = outcome
+        .flatten()
+        .into_iter()
+        .map(|outcome| match outcome {
+            Outcome::Couch(doc) => doc.id.clone(),
+            other => panic!("expected a document, got {other:?}"),
+        })
+        .collect();
+    assert_eq!(ids, ["doc-0001", "doc-0002", "doc"]);
+
+    let mut puts = vec![];
+    let mut deletes = vec![];
+    while let Ok(request) = requests.try_recv() {
+        match request.method.as_str() {
+            "PUT" => puts.push(request.body),
+            "DELETE" => deletes.push(request.target),
+            _ => {}
+        }
+    }
+    assert!(puts[0].contains(r#""records":[1,2]"#));
+    assert!(puts[1].contains(r#""records":[3]"#));
+    assert!(puts[2].contains(r#""chunks":["doc-0001","doc-0002"]"#));
+    // the chunk left over from the longer load is removed
+    assert_eq!(deletes, ["/doc-0003?rev=1-a"]);
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+// collision
+/////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[tokio::test]
+async fn versioned_file_sink_keeps_every_load() {
+    let dir = temp_dir("versions");
+    let clock = Arc::new(
+        Fixed::new("2024-06-01T12:00:00Z".parse().unwrap()).step(Duration::from_secs(60 * 60)),
+    );
+    let sink = sink::File::new(dir.join("prices.json")).collision(Collision::Versioned(clock));
+
+    sink.load(&json!({ "close": 1 })).await.unwrap();
+    sink.load(&json!({ "close": 2 })).await.unwrap();
+
+    let read = |name: &str| -> Value {
+        serde_json::from_slice(&std::fs::read(dir.join(name)).unwrap()).unwrap()
+    };
+    assert_eq!(
+        read("prices@2024-06-01T12%3A00%3A00Z.json"),
+        json!({ "close": 1 })
+    );
+    assert_eq!(
+        read("prices@2024-06-01T13%3A00%3A00Z.json"),
+        json!({ "close": 2 })
+    );
+    assert_eq!(read("prices@latest.json"), json!({ "close": 2 }));
+    assert!(!dir.join("prices.json").exists());
+}
+
+#[test]
+fn file_names_are_escaped_for_every_platform() {
+    assert_eq!(fs::file_name("prices@latest"), "prices@latest");
+    assert_eq!(
+        fs::file_name("eu/prices@2024-06-01T12:00:00Z"),
+        "eu%2Fprices@2024-06-01T12%3A00%3A00Z"
+    );
+    assert_eq!(fs::file_name(r#"a\b|c?*"<>"#), "a%5Cb%7Cc%3F%2A%22%3C%3E");
+    // escapes are escaped too, so distinct names stay distinct
+    assert_eq!(fs::file_name("100%"), "100%25");
+    assert_eq!(fs::file_name("nul.json"), "%6Eul.json");
+    assert_eq!(fs::file_name("console"), "console");
+    assert_eq!(fs::file_name("trailing. "), "trailing.%20");
+    assert_eq!(fs::file_name("tab\t"), "tab%09");
+
+    // files are replaced whole, through a temporary file that's gone once written
+    let dir = temp_dir("atomic");
+    let path = dir.join("prices.json");
+    fs::write_atomic(&path, b"[1]").unwrap();
+    fs::write_atomic(&path, b"[1, 2]").unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"[1, 2]");
+    let names: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    assert_eq!(names, ["prices.json"]);
+}
+
+#[tokio::test]
+async fn versioned_couchdb_sink_writes_the_version_then_the_alias() {
+    let (url, mut requests) = serve(|request| match request.method.as_str() {
+        "PUT" => Response::status("201 Created").body(
+            json!({ "ok": true, "id": request.target.trim_start_matches('/'), "rev": "1-a" })
+                .to_string(),
+        ),
+        _ => Response::status("404 Not Found"),
+    })
+    .await;
+    let clock = Arc::new(Fixed::new("2024-06-01T12:00:00Z".parse().unwrap()));
+    let sink = sink::CouchDb::new(&url, "doc").collision(Collision::Versioned(clock));
+    let outcome = sink.load(&json!({ "close": 1 })).await.unwrap();
+
+    let Outcome::Couch(doc) = outcome else {
+        panic!("expected a document, got {outcome:?}");
+    };
+    assert_eq!(doc.id, "doc@2024-06-01T12:00:00Z");
+    let mut puts = vec![];
+    while let Ok(request) = requests.try_recv() {
+        if request.method == "PUT" {
+            puts.push(request.target);
+        }
+    }
+    assert_eq!(puts, ["/doc@2024-06-01T12:00:00Z", "/doc@latest"]);
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+// health
+/////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// a sink that is either up or down, counting the loads & checks that reached it
+#[derive(Default)]
+struct Flaky {
+    down: AtomicBool,
+    loads: AtomicU32,
+    checks: AtomicU32,
+}
+
+impl Sink<Value> for Flaky {
+    async fn load(&self, _: &Value) -> pipe_io::Result<Outcome> {
+        self.loads.fetch_add(1, Ordering::SeqCst);
+        self.check().await?;
+        Ok(Outcome::Done)
+    }
+}
+
+impl Health for Flaky {
+    async fn check(&self) -> pipe_io::Result<()> {
+        self.checks.fetch_add(1, Ordering::SeqCst);
+        // as a connection would, letting other loads in meanwhile
+        tokio::task::yield_now().await;
+        match self.down.load(Ordering::SeqCst) {
+            true => Err(Error::Other(anyhow::anyhow!("connection refused"))),
+            false => Ok(()),
+        }
+    }
+}
+
+#[tokio::test]
+async fn breaker_opens_then_recovers() {
+    let events = Arc::new(Mutex::new(vec![]));
+    let seen = events.clone();
+    let breaker = Breaker::new(Flaky::default(), 2, Duration::from_millis(20)).observer(
+        move |event: &Event| {
+            if let Event::Circuit { circuit } = event {
+                seen.lock().unwrap().push(*circuit);
+            }
+        },
+    );
+    let record = json!({});
+
+    breaker.probe().await.unwrap();
+    let flaky = breaker.sink();
+    flaky.down.store(true, Ordering::SeqCst);
+    assert!(breaker.load(&record).await.is_err());
+    assert!(breaker.load(&record).await.is_err());
+    assert_eq!(breaker.circuit(), Circuit::Open);
+
+    // open: fails fast, without reaching the sink
+    let result = breaker.load(&record).await;
+    assert!(matches!(result, Err(Error::CircuitOpen { .. })));
+    assert_eq!(flaky.loads.load(Ordering::SeqCst), 2);
+
+    // after the cooldown, a healthy probe closes the circuit again
+    flaky.down.store(false, Ordering::SeqCst);
+    tokio::time::sleep(Duration::from_millis(30)).await;
+    breaker.load(&record).await.unwrap();
+    assert_eq!(
+        *events.lock().unwrap(),
+        vec![Circuit::Open, Circuit::HalfOpen, Circuit::Closed]
+    );
+}
+
+#[tokio::test]
+async fn half_open_breaker_lets_one_probe_through() {
+    let breaker = Breaker::new(Flaky::default(), 1, Duration::from_millis(20));
+    let record = json!({});
+    let flaky = breaker.sink();
+    flaky.down.store(true, Ordering::SeqCst);
+    assert!(breaker.load(&record).await.is_err());
+    assert_eq!(breaker.circuit(), Circuit::Open);
+
+    // after the cooldown, concurrent loads: the first probes, the other fails fast meanwhile
+    flaky.down.store(false, Ordering::SeqCst);
+    tokio::time::sleep(Duration::from_millis(30)).await;
+    let checks = flaky.checks.load(Ordering::SeqCst);
+    let (probe, other) = tokio::join!(breaker.load(&record), breaker.load(&record));
+    probe.unwrap();
+    assert!(matches!(other, Err(Error::CircuitOpen { .. })));
+    // the probe's health check, then the load's own
+    assert_eq!(flaky.checks.load(Ordering::SeqCst), checks + 2);
+    assert_eq!(breaker.circuit(), Circuit::Closed);
+    breaker.load(&record).await.unwrap();
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+// audit
+/////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Default)]
+struct Records(Mutex<Vec<AuditRecord>>);
+
+impl Sink<AuditRecord> for Records {
+    async fn load(&self, record: &AuditRecord) -> pipe_io::Result<Outcome> {
+        self.0.lock().unwrap().push(record.clone());
+        Ok(Outcome::Done)
+    }
+}
+
+#[tokio::test]
+async fn audited_sink_records_every_load() {
+    let dir = temp_dir("audited");
+    let store = Arc::new(Records::default());
+    let output = json!([{ "ticker": "NVDA" }, { "ticker": "AAPL" }]);
+
+    let audited = Audited::new(sink::File::new(dir.join("prices.json")), store.clone())
+        .user("etl-bot")
+        .name("prices")
+        .clock(Arc::new(
+            Fixed::new("2024-06-01T00:00:00Z".parse().unwrap()).step(Duration::from_secs(1)),
+        ))
+        .source("https://example.com/prices");
+    audited.load(&output).await.unwrap();
+
+    // a failed load is still audited, with its error
+    let failing = Audited::new(
+        sink::File::new(dir.join("missing/prices.json")),
+        store.clone(),
+    );
+    assert!(failing.load(&output).await.is_err());
+
+    let records = store.0.lock().unwrap();
+    assert_eq!(records.len(), 2);
+    assert_eq!(
+        (records[0].user.as_str(), records[0].sink.as_str()),
+        ("etl-bot", "prices")
+    );
+    assert_eq!(
+        records[0].source.as_deref(),
+        Some("https://example.com/prices")
+    );
+    assert_eq!(records[0].records, 2);
+    assert_eq!(
+        (
+            records[0].started_at.as_str(),
+            records[0].finished_at.as_str()
+        ),
+        ("2024-06-01T00:00:00+00:00", "2024-06-01T00:00:01+00:00")
+    );
+    assert_eq!(records[0].error, None);
+    assert_eq!(records[1].content_hash, records[0].content_hash);
+    assert!(records[1].error.is_some());
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Default)]
+struct Docs(Mutex<Vec<Value>>);
+
+impl Sink<Value> for Docs {
+    async fn load(&self, docs: &Value) -> pipe_io::Result<Outcome> {
+        self.0.lock().unwrap().push(docs.clone());
+        Ok(Outcome::Done)
+    }
+}
+
+#[tokio::test]
+async fn versioned_sink_stamps_and_migrates_documents() {
+    let docs = Arc::new(Docs::default());
+    let versioned = Versioned::new(docs.clone(), 2).migrate(|from, mut doc| {
+        assert_eq!(from, 1);
+        doc["symbol"] = doc["ticker"].take();
+        doc.as_object_mut().unwrap().remove("ticker");
+        Ok(doc)
+    });
+
+    // fresh output, alongside a document loaded by version 1
+    let output = json!([{ "symbol": "NVDA" }, { "ticker": "AAPL", "_version": 1 }]);
+    versioned.load(&output).await.unwrap();
+    assert_eq!(
+        docs.0.lock().unwrap()[0],
+        json!([{ "symbol": "NVDA", "_version": 2 }, { "symbol": "AAPL", "_version": 2 }])
+    );
+
+    let newer = versioned.upgrade(json!({ "symbol": "NVDA", "_version": 3 }));
+    assert!(matches!(newer, Err(Error::Verification(_))));
+    let unmigrated = Versioned::new(docs.clone(), 2).upgrade(json!({ "_version": 1 }));
+    assert!(matches!(unmigrated, Err(Error::Verification(_))));
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+// throttle
+/////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// a slow sink, tracking the most loads in flight at once
+#[derive(Default)]
+struct Slow {
+    loading: AtomicU32,
+    most: AtomicU32,
+}
+
+impl Sink<Value> for Slow {
+    async fn load(&self, _: &Value) -> pipe_io::Result<Outcome> {
+        let loading = self.loading.fetch_add(1, Ordering::SeqCst) + 1;
+        self.most.fetch_max(loading, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        self.loading.fetch_sub(1, Ordering::SeqCst);
+        Ok(Outcome::Done)
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn throttled_sink_limits_records_and_concurrency() {
+    let docs = Arc::new(Docs::default());
+    let sink = Throttled::new(docs.clone()).records_per_second(10);
+    let started = tokio::time::Instant::now();
+    sink.load(&json!([1, 2, 3, 4, 5])).await.unwrap();
+    assert_eq!(started.elapsed(), Duration::ZERO);
+    // the first 5 records take half a second, at 10/s
+    sink.load(&json!({ "one": "record" })).await.unwrap();
+    assert_eq!(started.elapsed(), Duration::from_millis(500));
+    sink.load(&json!([])).await.unwrap();
+    assert_eq!(started.elapsed(), Duration::from_millis(600));
+    assert_eq!(docs.0.lock().unwrap().len(), 3);
+
+    let slow = Arc::new(Slow::default());
+    let sink = Throttled::new(slow.clone()).max_concurrent(2);
+    let output = json!({});
+    futures::future::try_join_all((0..5).map(|_| sink.load(&output)))
+        .await
+        .unwrap();
+    assert_eq!(slow.most.load(Ordering::SeqCst), 2);
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+// batched
+/////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// the serialized size of every batch loaded
+#[derive(Default)]
+struct Sizes(Mutex<Vec<(usize, usize)>>);
+
+impl Sink<[Value]> for Sizes {
+    async fn load(&self, batch: &[Value]) -> pipe_io::Result<Outcome> {
+        let bytes = serde_json::to_vec(batch).unwrap().len();
+        self.0.lock().unwrap().push((batch.len(), bytes));
+        Ok(Outcome::Done)
+    }
+}
+
+#[tokio::test]
+async fn batched_sink_sizes_batches_by_bytes() {
+    // records of 10 bytes each, but one of 30
+    let record = |n: usize| json!("x".repeat(n - 2));
+    let mut output: Vec<Value> = (0..7).map(|_| record(10)).collect();
+    output.insert(3, record(30));
+
+    let sizes = Arc::new(Sizes::default());
+    sink::Batched::by_bytes(sizes.clone(), 35)
+        .load(&output)
+        .await
+        .unwrap();
+    // `[` + 3 records & their commas + `]` is 34 bytes; the large record goes alone
+    let batches = sizes.0.lock().unwrap().clone();
+    assert_eq!(batches, vec![(3, 34), (1, 32), (3, 34), (1, 12)]);
+    assert!(batches.iter().all(|(_, bytes)| *bytes <= 35));
+
+    // with a record limit too
+    let sizes = Arc::new(Sizes::default());
+    sink::Batched::new(sizes.clone(), 2)
+        .max_bytes(1000)
+        .load(&output)
+        .await
+        .unwrap();
+    let counts: Vec<_> = sizes.0.lock().unwrap().iter().map(|(n, _)| *n).collect();
+    assert_eq!(counts, vec![2, 2, 2, 2]);
+}
+
+/////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+// router
+/////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[tokio::test]
+async fn router_loads_each_record_to_its_route() {
+    let dir = temp_dir("router");
+    let route = |price: &Value| match price["close"].as_f64() {
+        Some(close) if close > 0.0 => "valid",
+        Some(_) => "quarantine",
+        None => "unknown",
+    };
+    let router = sink::Router::new(route)
+        .route("valid", sink::File::new(dir.join("valid.json")))
+        .route("quarantine", sink::File::new(dir.join("quarantine.json")))
+        .route("unused", sink::File::new(dir.join("unused.json")));
+
+    let output = vec![
+        json!({ "close": 1.5 }),
+        json!({ "close": -1.0 }),
+        json!({ "close": 2.5 }),
+    ];
+    let outcome = router.load(&output).await.unwrap();
+    let Outcome::Routes(routes) = outcome else {
+        panic!("not routed: {outcome:?}");
+    };
+    assert_eq!(routes.keys().collect::<Vec<_>>(), ["quarantine", "valid"]);
+    let valid: Value =
+        serde_json::from_slice(&std::fs::read(dir.join("valid.json")).unwrap()).unwrap();
+    assert_eq!(valid, json!([{ "close": 1.5 }, { "close": 2.5 }]));
+    assert!(!dir.join("unused.json").exists());
+
+    // a record without a route fails the load, before any is loaded
+    let result = router.load(&vec![json!({ "close": 3.0 }), json!({})]).await;
+    assert!(matches!(result, Err(Error::Config(message)) if message.contains("`unknown`")));
+    let valid: Value =
+        serde_json::from_slice(&std::fs::read(dir.join("valid.json")).unwrap()).unwrap();
+    assert_eq!(valid.as_array().unwrap().len(), 2);
+
+    // a failing route doesn't stop the rest
+    let router = sink::Router::new(route)
+        .route(
+            "quarantine",
+            sink::File::new(dir.join("missing").join("quarantine.json")),
+        )
+        .route("valid", sink::File::new(dir.join("valid.json")));
+    let Err(Error::Many(errors)) = router.load(&output).await else {
+        panic!("the quarantine route should fail");
+    };
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].context,
+        pipe_io::error::Context::Route("quarantine".into())
+    );
+    let valid: Value =
+        serde_json::from_slice(&std::fs::read(dir.join("valid.json")).unwrap()).unwrap();
+    assert_eq!(valid.as_array().unwrap().len(), 2);
+}
@@ -0,0 +1,88 @@
+// One pipe definition, instantiated per symbol, with its vars read back by the transform.
+
+use pipe_io::template::{Template, Vars};
+use pipe_io::{pipeline, sink, Endpoint, Error, Pipe, Runner, Source, ETL};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Deserialize, Debug)]
+struct RawPrice {
+    close: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Price {
+    symbol: String,
+    close: f64,
+}
+
+pipeline! {
+    RawPrice -> Price {
+        async fn transform(&self, input: RawPrice) -> pipe_io::Result<Price> {
+            let symbol = self.vars().require("symbol")?.to_string();
+            Ok(Price { symbol, close: input.close })
+        }
+    }
+}
+
+fn dir() -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join("pipe-io-test-template");
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn prices() -> Template<RawPrice, Price> {
+    Template::new(|vars: &Vars| {
+        let dir = dir();
+        let input = dir.join(vars.fill("{symbol}.json")?);
+        let output = dir.join(vars.fill("prices-{symbol}.json")?);
+        Ok(Pipe::<RawPrice, Price>::builder()
+            .source(Source::endpoint(input.to_str().unwrap_or_default()))
+            .sink(sink::File::new(output)))
+    })
+}
+
+#[tokio::test]
+async fn one_template_runs_per_symbol() {
+    for (symbol, close) in [("NVDA", 120.5), ("AAPL", 210.0)] {
+        let input = format!(r#"{{ "close": {close} }}"#);
+        std::fs::write(dir().join(format!("{symbol}.json")), input).unwrap();
+    }
+    let pipes = prices().each("symbol", ["NVDA", "AAPL"]).unwrap();
+    assert_eq!(pipes["NVDA"].vars().get("symbol"), Some("NVDA"));
+
+    let runner = pipes
+        .into_iter()
+        .fold(Runner::new(), |runner, (symbol, pipe)| {
+            runner.pipe(symbol, pipe, Duration::from_secs(60))
+        });
+    runner.run_once().await.unwrap();
+    for (symbol, close) in [("NVDA", 120.5), ("AAPL", 210.0)] {
+        let output = std::fs::read(dir().join(format!("prices-{symbol}.json"))).unwrap();
+        let price: Price = serde_json::from_slice(&output).unwrap();
+        let symbol = symbol.to_string();
+        assert_eq!(price, Price { symbol, close });
+    }
+
+    // a pipe built without vars has none for its transform
+    let pipe = Pipe::<RawPrice, Price>::new();
+    let result = pipe.transform(RawPrice { close: 1.0 }).await;
+    assert!(matches!(result, Err(Error::Missing(path)) if path == "vars.symbol"));
+}
+
+#[test]
+fn vars_fill_placeholders() {
+    let vars = Vars::new().set("symbol", "BRK.B").set("year", 2024);
+    assert_eq!(
+        vars.fill("prices-{symbol}-{year}").unwrap(),
+        "prices-BRK.B-2024"
+    );
+    assert_eq!(vars.fill("no {placeholder").unwrap(), "no {placeholder");
+    assert!(matches!(vars.fill("{month}"), Err(Error::Config(_))));
+
+    let endpoint = Endpoint::new("https://example.com/prices/{symbol}/{year}").vars(&vars);
+    assert_eq!(
+        endpoint.url().unwrap().as_str(),
+        "https://example.com/prices/BRK.B/2024"
+    );
+}
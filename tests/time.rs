@@ -0,0 +1,110 @@
+// Date & time parsing, and the timezone policy.
+
+use chrono::NaiveDate;
+use pipe_io::core::*;
+use pipe_io::time::{self, Timezone};
+use pipe_io::{Error, Source};
+
+#[test]
+fn parses_epochs_and_iso8601() {
+    let time = time::epoch_seconds(1_717_200_000).unwrap();
+    assert_eq!(time.to_rfc3339(), "2024-06-01T00:00:00+00:00");
+    assert_eq!(
+        time::epoch_millis(1_717_200_000_500)
+            .unwrap()
+            .timestamp_subsec_millis(),
+        500
+    );
+    assert!(matches!(time::epoch_seconds(i64::MAX), Err(Error::Time(_))));
+
+    for (s, expected) in [
+        ("2024-06-01T09:30:00-04:00", "2024-06-01T09:30:00-04:00"),
+        ("2024-06-01T09:30:00.250", "2024-06-01T09:30:00.250+00:00"),
+        ("2024-06-01", "2024-06-01T00:00:00+00:00"),
+    ] {
+        assert_eq!(time::iso8601(s).unwrap().to_rfc3339(), expected);
+    }
+    assert!(matches!(time::iso8601("01/06/2024"), Err(Error::Time(_))));
+}
+
+#[test]
+fn exchange_dates_follow_the_timezone_policy() {
+    // midnight in UTC is still the day before in New York (UTC-4)
+    let (seconds, new_york) = (1_717_286_400, -4 * 3600);
+    assert_eq!(
+        time::exchange_date(seconds, new_york).unwrap(),
+        NaiveDate::from_ymd_opt(2024, 6, 1).unwrap()
+    );
+
+    let local = Timezone::Preserve.exchange(seconds, new_york).unwrap();
+    assert_eq!(local.to_rfc3339(), "2024-06-01T20:00:00-04:00");
+    let utc = Timezone::Utc.exchange(seconds, new_york).unwrap();
+    assert_eq!(utc.to_rfc3339(), "2024-06-02T00:00:00+00:00");
+    assert_eq!(local, utc);
+}
+
+#[test]
+fn deserializes_epoch_dates() {
+    #[derive(serde::Deserialize)]
+    struct Chart {
+        #[serde(deserialize_with = "time::de::epoch_dates")]
+        timestamp: Vec<NaiveDate>,
+    }
+    let chart: Chart =
+        serde_json::from_str(r#"{ "timestamp": [1717200000, 1717286400] }"#).unwrap();
+    assert_eq!(
+        chart.timestamp,
+        [
+            NaiveDate::from_ymd_opt(2024, 6, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 6, 2).unwrap()
+        ]
+    );
+    assert!(serde_json::from_str::<Chart>(r#"{ "timestamp": ["today"] }"#).is_err());
+}
+
+#[test]
+fn series_dates_follow_the_timezone_policy() {
+    // a bar at 20:00 in New York is already the next day in UTC
+    let (seconds, new_york) = ([1_717_286_400], -4 * 3600);
+    let (local, utc) = (
+        NaiveDate::from_ymd_opt(2024, 6, 1).unwrap(),
+        NaiveDate::from_ymd_opt(2024, 6, 2).unwrap(),
+    );
+    assert_eq!(
+        Timezone::Preserve.dates(&seconds, new_york).unwrap(),
+        [local]
+    );
+    assert_eq!(Timezone::Utc.dates(&seconds, new_york).unwrap(), [utc]);
+}
+
+#[derive(serde::Deserialize)]
+struct Stamped {
+    #[serde(deserialize_with = "time::de::iso8601")]
+    at: chrono::DateTime<chrono::FixedOffset>,
+}
+
+pipeline! {
+    Stamped -> String {
+        async fn transform(&self, input: Stamped) -> pipe_io::Result<String> {
+            Ok(format!("{} {:?}", input.at.to_rfc3339(), time::timezone()))
+        }
+    }
+}
+
+#[tokio::test]
+async fn pipes_decode_and_transform_with_their_policy() {
+    let payload = serde_json::json!({ "at": "2024-06-01T09:30:00-04:00" });
+    for (timezone, expected) in [
+        (Timezone::Utc, "2024-06-01T13:30:00+00:00 Utc"),
+        (Timezone::Preserve, "2024-06-01T09:30:00-04:00 Preserve"),
+    ] {
+        let pipe = Pipe::<Stamped, String>::builder()
+            .source(Source::inline(payload.clone()))
+            .timezone(timezone)
+            .build()
+            .unwrap();
+        assert_eq!(pipe.preview(None).await.unwrap(), expected);
+    }
+    // outside of a pipe, the default
+    assert_eq!(time::timezone(), Timezone::Utc);
+}
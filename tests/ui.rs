@@ -0,0 +1,8 @@
+// Compile-time diagnostics: each case in tests/ui fails to compile with the errors in the `.stderr`
+// next to it; regenerate those with `TRYBUILD=overwrite cargo test --test ui`.
+
+#[test]
+fn diagnostics() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,15 @@
+use pipe_io::{sink, Sink};
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct Price {
+    symbol: String,
+    close: f64,
+}
+
+async fn load(prices: Vec<Price>) {
+    let sink = sink::Csv::new("prices.csv");
+    sink.load(&prices).await.unwrap();
+}
+
+fn main() {}
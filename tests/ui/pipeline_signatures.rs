@@ -0,0 +1,32 @@
+use pipe_io::core::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+struct RawPrice {
+    close: f64,
+}
+
+#[derive(Serialize)]
+struct Price {
+    close: f64,
+}
+
+#[derive(Serialize)]
+struct Volume(u64);
+
+// each pipeline's first mistake is reported, at once
+pipeline! {
+    RawPrice -> Price {
+        async fn transform(&self) -> pipe_io::Result<Price> {
+            Ok(Price { close: 0.0 })
+        }
+    }
+
+    RawPrice -> Volume {
+        async fn transform(input: RawPrice) -> pipe_io::Result<Volume> {
+            Ok(Volume(input.close as u64))
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,26 @@
+use pipe_io::core::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+struct RawPrice {
+    close: f64,
+}
+
+#[derive(Serialize)]
+struct Price {
+    close: f64,
+}
+
+pipeline! {
+    RawPrice -> Price {
+        async fn transform(&self, input: RawPrice) -> pipe_io::Result<Price> {
+            Ok(Price { close: input.close })
+        }
+
+        async fn transfrom(&self, input: RawPrice) -> pipe_io::Result<Price> {
+            Ok(Price { close: input.close })
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,22 @@
+use pipe_io::core::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+struct RawPrice {
+    close: f64,
+}
+
+#[derive(Serialize)]
+struct Price {
+    close: f64,
+}
+
+pipeline! {
+    RawPrice -> Price {
+        async fn extract(&self, path: &str) -> pipe_io::Result<RawPrice> {
+            pipe_io::default::extract(path).await
+        }
+    }
+}
+
+fn main() {}
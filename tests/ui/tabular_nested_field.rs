@@ -0,0 +1,18 @@
+use pipe_io::Tabular;
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct Quote {
+    bid: f64,
+    ask: f64,
+}
+
+#[derive(Serialize, Tabular)]
+struct Price {
+    symbol: String,
+    close: Option<f64>,
+    history: Vec<f64>,
+    quote: Quote,
+}
+
+fn main() {}
@@ -0,0 +1,174 @@
+// Re-running on source changes; only with `--features watch`.
+#![cfg(feature = "watch")]
+
+use pipe_io::watch::{DropFolder, Ingested};
+use pipe_io::{pipeline, sink, Error, Pipe, Source};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+#[derive(Deserialize, Debug)]
+struct Values {
+    values: Vec<i32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Total(i32);
+
+pipeline! {
+    Values -> Total {
+        async fn transform(&self, input: Values) -> pipe_io::Result<Total> {
+            Ok(Total(input.values.iter().sum()))
+        }
+    }
+}
+
+// the next of `events`, within a few seconds
+async fn next<T>(events: &mut UnboundedReceiver<T>) -> T {
+    let event = tokio::time::timeout(Duration::from_secs(5), events.recv()).await;
+    event.expect("an event").expect("still watching")
+}
+
+#[tokio::test]
+async fn watched_pipe_reruns_once_per_settled_change() {
+    let dir = std::env::temp_dir().join("pipe-io-test-watch");
+    std::fs::create_dir_all(&dir).unwrap();
+    let (input, output) = (dir.join("input.json"), dir.join("output.json"));
+    std::fs::write(&input, r#"{ "values": [1, 2] }"#).unwrap();
+
+    let pipe = Pipe::<Values, Total>::builder()
+        .source(Source::endpoint(input.to_str().unwrap()))
+        .sink(sink::File::new(&output))
+        .build()
+        .unwrap();
+    let (tx, mut runs) = tokio::sync::mpsc::unbounded_channel();
+    let read = {
+        let output = output.clone();
+        move || serde_json::from_slice::<Total>(&std::fs::read(&output).unwrap()).unwrap()
+    };
+    let watch = tokio::spawn(async move {
+        pipe.watch(Duration::from_millis(100), |result| {
+            tx.send(result.map(|_| read())).unwrap();
+        })
+        .await
+    });
+
+    // the first run is straight away
+    assert_eq!(next(&mut runs).await.unwrap(), Total(3));
+
+    // several writes in quick succession make one run
+    for values in ["[1, 2, 3]", "[1, 2, 3, 4]"] {
+        std::fs::write(&input, format!(r#"{{ "values": {values} }}"#)).unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(next(&mut runs).await.unwrap(), Total(10));
+    assert!(
+        tokio::time::timeout(Duration::from_millis(300), runs.recv())
+            .await
+            .is_err()
+    );
+    watch.abort();
+
+    let pipe = Pipe::<Values, Total>::builder()
+        .source(Source::endpoint("https://example.com/values.json"))
+        .build()
+        .unwrap();
+    let result = pipe.watch(Duration::ZERO, |_| {}).await;
+    assert!(matches!(result, Err(Error::Config(_))));
+}
+
+#[tokio::test]
+async fn drop_folder_moves_each_file_with_a_report() {
+    let inbox = std::env::temp_dir().join("pipe-io-test-inbox");
+    let _ = std::fs::remove_dir_all(&inbox);
+    std::fs::create_dir_all(&inbox).unwrap();
+    std::fs::write(inbox.join("a.json"), r#"{ "values": [1, 2] }"#).unwrap();
+    std::fs::write(inbox.join("b.json"), r#"{ "values": "none" }"#).unwrap();
+    std::fs::write(inbox.join("notes.txt"), "not for the pipe").unwrap();
+
+    let output = inbox.join("processed").join("total.json");
+    let pipe = Pipe::<Values, Total>::builder()
+        .sink(sink::File::new(&output))
+        .build()
+        .unwrap();
+    let folder = DropFolder::new(&inbox)
+        .extension("json")
+        .debounce(Duration::from_millis(50));
+    let (tx, mut files) = tokio::sync::mpsc::unbounded_channel();
+    let ingest = tokio::spawn(async move {
+        pipe.ingest(&folder, |ingested| tx.send(ingested.clone()).unwrap())
+            .await
+    });
+
+    // the files already waiting, in order
+    let a = next(&mut files).await;
+    assert_eq!((a.file.as_str(), a.error.is_none()), ("a.json", true));
+    assert_eq!(a.moved_to, inbox.join("processed").join("a.json"));
+    let b = next(&mut files).await;
+    assert!(b.error.as_deref().unwrap().contains("values"));
+    assert!(inbox.join("failed").join("b.json").exists());
+    let sidecar: Ingested = serde_json::from_slice(
+        &std::fs::read(inbox.join("failed").join("b.json.report.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(sidecar, b);
+
+    // then every new one
+    std::fs::write(inbox.join("c.json"), r#"{ "values": [4, 5] }"#).unwrap();
+    let c = next(&mut files).await;
+    assert_eq!((c.file.as_str(), c.error.is_none()), ("c.json", true));
+    let total: Total = serde_json::from_slice(&std::fs::read(&output).unwrap()).unwrap();
+    assert_eq!(total, Total(9));
+    assert!(inbox.join("notes.txt").exists());
+    ingest.abort();
+}
+
+#[tokio::test]
+async fn drop_folder_keeps_going_past_a_file_it_cant_move() {
+    let inbox = std::env::temp_dir().join("pipe-io-test-inbox-stuck");
+    let _ = std::fs::remove_dir_all(&inbox);
+    let processed = inbox.join("processed");
+    std::fs::create_dir_all(&processed).unwrap();
+    // one of the same name, already processed
+    std::fs::write(processed.join("a.json"), "an earlier a").unwrap();
+    std::fs::write(inbox.join("a.json"), r#"{ "values": [1, 2] }"#).unwrap();
+
+    let pipe = Pipe::<Values, Total>::builder()
+        .sink(sink::File::new(inbox.join("total.txt")))
+        .build()
+        .unwrap();
+    let folder = DropFolder::new(&inbox)
+        .extension("json")
+        .debounce(Duration::from_millis(50));
+    let (tx, mut files) = tokio::sync::mpsc::unbounded_channel();
+    let ingest = tokio::spawn(async move {
+        pipe.ingest(&folder, |ingested| tx.send(ingested.clone()).unwrap())
+            .await
+    });
+
+    // renamed, rather than replacing the earlier one
+    let a = next(&mut files).await;
+    assert_eq!(a.moved_to, processed.join("a-1.json"));
+    assert!(processed.join("a-1.json.report.json").exists());
+    assert_eq!(
+        std::fs::read_to_string(processed.join("a.json")).unwrap(),
+        "an earlier a"
+    );
+
+    // nowhere to move it to: left where it is, and the folder still ingested
+    std::fs::remove_dir_all(&processed).unwrap();
+    std::fs::write(inbox.join("b.json"), r#"{ "values": [3] }"#).unwrap();
+    let b = next(&mut files).await;
+    assert!(b.error.as_deref().unwrap().contains("couldn't be moved"));
+    assert_eq!(b.moved_to, inbox.join("b.json"));
+    assert!(inbox.join("b.json").exists());
+
+    // nor is it taken again
+    std::fs::create_dir_all(&processed).unwrap();
+    std::fs::write(inbox.join("c.json"), r#"{ "values": [4] }"#).unwrap();
+    let c = next(&mut files).await;
+    assert_eq!((c.file.as_str(), c.error.is_none()), ("c.json", true));
+    assert!(processed.join("c.json").exists());
+    assert!(!ingest.is_finished());
+    ingest.abort();
+}
@@ -0,0 +1,176 @@
+// Records with their neighbours, from iterators & streams; and streams in batches.
+
+use futures::StreamExt;
+use pipe_io::window::{self, Batch, Windowed};
+use pipe_io::{pipeline, Error, Outcome, Pipe, Sink, Source};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[test]
+fn iter_gives_each_record_its_neighbours() {
+    let windows = window::iter([1, 2, 3, 4], 2, 1).collect::<Vec<_>>();
+    assert_eq!(
+        windows,
+        vec![
+            Windowed {
+                before: vec![],
+                current: 1,
+                after: vec![2]
+            },
+            Windowed {
+                before: vec![1],
+                current: 2,
+                after: vec![3]
+            },
+            Windowed {
+                before: vec![1, 2],
+                current: 3,
+                after: vec![4]
+            },
+            Windowed {
+                before: vec![2, 3],
+                current: 4,
+                after: vec![]
+            },
+        ]
+    );
+
+    // day-over-day changes
+    let changes = window::iter([10.0, 12.0, 9.0], 1, 0)
+        .filter_map(|w| w.prev().map(|prev| w.current - prev))
+        .collect::<Vec<_>>();
+    assert_eq!(changes, vec![2.0, -3.0]);
+    assert_eq!(window::iter(Vec::<i32>::new(), 1, 1).count(), 0);
+}
+
+#[tokio::test]
+async fn stream_passes_errors_through() {
+    let records = futures::stream::iter(vec![Ok(1), Ok(2), Err(Error::Missing("3".into())), Ok(4)]);
+    let windows = window::stream(records, 0, 1).collect::<Vec<_>>().await;
+    let ok = |current, after: Vec<i32>| Windowed {
+        before: vec![],
+        current,
+        after,
+    };
+
+    assert_eq!(windows.len(), 4);
+    assert_eq!(windows[0].as_ref().unwrap(), &ok(1, vec![2]));
+    assert!(matches!(windows[1], Err(Error::Missing(_))));
+    assert_eq!(windows[2].as_ref().unwrap(), &ok(2, vec![4]));
+    assert_eq!(windows[3].as_ref().unwrap(), &ok(4, vec![]));
+}
+
+// a stream of what's sent on the channel, until it's closed
+fn channel() -> (
+    tokio::sync::mpsc::UnboundedSender<pipe_io::Result<i32>>,
+    impl futures::Stream<Item = pipe_io::Result<i32>>,
+) {
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    let stream = futures::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|record| (record, rx))
+    });
+    (tx, stream)
+}
+
+#[tokio::test(start_paused = true)]
+async fn batches_are_handed_on_by_count_or_wait() {
+    let (tx, records) = channel();
+    let batch = Batch::new().records(3).wait(Duration::from_secs(30));
+    let mut batches = window::batches(records, batch, std::future::pending());
+
+    for record in 1..=4 {
+        tx.send(Ok(record)).unwrap();
+    }
+    assert_eq!(batches.next().await.unwrap().unwrap(), [1, 2, 3]);
+    // the 4th waits 30s from when it arrived
+    let started = tokio::time::Instant::now();
+    assert_eq!(batches.next().await.unwrap().unwrap(), [4]);
+    assert_eq!(started.elapsed(), Duration::from_secs(30));
+
+    // an error comes after the batch before it
+    tx.send(Ok(5)).unwrap();
+    tx.send(Err(Error::Missing("6".into()))).unwrap();
+    assert_eq!(batches.next().await.unwrap().unwrap(), [5]);
+    assert!(matches!(batches.next().await, Some(Err(Error::Missing(_)))));
+
+    // the end of the stream flushes what's pending
+    tx.send(Ok(7)).unwrap();
+    drop(tx);
+    assert_eq!(batches.next().await.unwrap().unwrap(), [7]);
+    assert!(batches.next().await.is_none());
+}
+
+#[derive(Serialize, Deserialize)]
+struct Batched(Vec<i32>);
+
+#[derive(Clone, Default)]
+struct Loads(Arc<Mutex<Vec<Vec<i32>>>>);
+
+impl Sink<Batched> for Loads {
+    async fn load(&self, output: &Batched) -> pipe_io::Result<Outcome> {
+        self.0.lock().unwrap().push(output.0.clone());
+        Ok(Outcome::Done)
+    }
+}
+
+pipeline! {
+    Vec<i32> -> Batched {
+        async fn transform(&self, input: Vec<i32>) -> pipe_io::Result<Batched> {
+            Ok(Batched(input))
+        }
+    }
+}
+
+#[tokio::test]
+async fn batched_source_loads_batches_and_flushes_on_shutdown() {
+    let (tx, records) = channel();
+    let (stop, shutdown) = tokio::sync::oneshot::channel::<()>();
+    let source = Source::stream(records)
+        .batched_until(Batch::new().records(2), async {
+            let _ = shutdown.await;
+        })
+        .unwrap();
+    let loads = Loads::default();
+    let pipe = Pipe::<Vec<i32>, Batched>::builder()
+        .source(source)
+        .sink(loads.clone())
+        .build()
+        .unwrap();
+
+    for record in 1..=3 {
+        tx.send(Ok(record)).unwrap();
+    }
+    let run = tokio::spawn(async move { pipe.run().await.map(|report| report.loads()) });
+    while loads.0.lock().unwrap().is_empty() {
+        tokio::task::yield_now().await;
+    }
+    // the channel is still open, but the pending 3 is loaded on shutdown
+    stop.send(()).unwrap();
+    assert_eq!(run.await.unwrap().unwrap(), 2);
+    assert_eq!(*loads.0.lock().unwrap(), [vec![1, 2], vec![3]]);
+
+    let result = Source::<i32>::endpoint("prices.json").batched(Batch::new());
+    assert!(matches!(result, Err(Error::Config(_))));
+}
+
+#[tokio::test]
+async fn batched_streams_are_batched_afresh_every_run() {
+    let source =
+        Source::streams(|| Ok(Source::stream(futures::stream::iter([Ok(1), Ok(2), Ok(3)]))))
+            .batched(Batch::new().records(2))
+            .unwrap();
+    let loads = Loads::default();
+    let pipe = Pipe::<Vec<i32>, Batched>::builder()
+        .source(source)
+        .sink(loads.clone())
+        .build()
+        .unwrap();
+
+    pipe.run().await.unwrap();
+    pipe.run().await.unwrap();
+    assert_eq!(
+        *loads.0.lock().unwrap(),
+        [vec![1, 2], vec![3], vec![1, 2], vec![3]]
+    );
+}